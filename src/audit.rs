@@ -0,0 +1,183 @@
+use ropey::Rope;
+
+/// lines longer than this many characters are reported
+pub const LONG_LINE: usize = 500;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Category {
+    MixedLineEndings,
+    TrailingWhitespace,
+    TabsAfterSpaces,
+    InvisibleCharacters,
+    LongLines,
+}
+
+impl Category {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Category::MixedLineEndings => "Mixed line endings",
+            Category::TrailingWhitespace => "Trailing whitespace",
+            Category::TabsAfterSpaces => "Tabs after spaces",
+            Category::InvisibleCharacters => "Zero-width and bidi characters",
+            Category::LongLines => "Long lines",
+        }
+    }
+}
+
+/// an anomaly at a 0-based (line_number, column)
+#[derive(Debug, Eq, PartialEq)]
+pub struct Finding {
+    pub category: Category,
+    pub line_number: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// characters that render as nothing or reorder the text around them
+fn is_invisible(c: char) -> bool {
+    matches!(c,
+        '\u{200B}'..='\u{200F}'
+        | '\u{202A}'..='\u{202E}'
+        | '\u{2060}'..='\u{2064}'
+        | '\u{2066}'..='\u{2069}'
+        | '\u{FEFF}')
+}
+
+pub fn audit(content: &Rope) -> Vec<Finding> {
+    let mut findings = vec![];
+    let mut crlf = vec![];
+    let mut lf = vec![];
+    for (line_number, line) in content.lines().enumerate() {
+        let line = String::from(line);
+        let text = if let Some(text) = line.strip_suffix("\r\n") {
+            crlf.push(line_number);
+            text
+        } else if let Some(text) = line.strip_suffix('\n') {
+            lf.push(line_number);
+            text
+        } else {
+            line.as_str()
+        };
+        let mut push = |category, column, message: String| {
+            findings.push(Finding {
+                category,
+                line_number,
+                column,
+                message,
+            })
+        };
+
+        let trimmed = text.trim_end();
+        if trimmed.len() < text.len() {
+            push(
+                Category::TrailingWhitespace,
+                trimmed.chars().count(),
+                format!(
+                    "{} trailing character(s)",
+                    text.chars().count() - trimmed.chars().count()
+                ),
+            );
+        }
+        let indent: String = text
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+        if let Some(column) = indent.find(" \t") {
+            push(
+                Category::TabsAfterSpaces,
+                column + 1,
+                String::from("tab follows a space in indentation"),
+            );
+        }
+        for (column, c) in text.chars().enumerate().filter(|(_, c)| is_invisible(*c)) {
+            push(
+                Category::InvisibleCharacters,
+                column,
+                format!("U+{:04X}", c as u32),
+            );
+        }
+        let n = text.chars().count();
+        if n > LONG_LINE {
+            push(Category::LongLines, LONG_LINE, format!("{} characters", n));
+        }
+    }
+
+    // the less common line ending is the anomaly
+    if !crlf.is_empty() && !lf.is_empty() {
+        let (lines, ending) = if crlf.len() <= lf.len() {
+            (crlf, "CRLF")
+        } else {
+            (lf, "LF")
+        };
+        for line_number in lines {
+            let column = content.line(line_number).len_chars().saturating_sub(1);
+            findings.push(Finding {
+                category: Category::MixedLineEndings,
+                line_number,
+                column,
+                message: format!("{} line ending", ending),
+            });
+        }
+    }
+    findings.sort_by_key(|f| (f.category, f.line_number, f.column));
+    findings
+}
+
+/// report grouped by category, one `name:line:column: message` entry per finding
+pub fn format_report(name: &str, findings: &[Finding]) -> String {
+    if findings.is_empty() {
+        return format!("# Audit of {}\n\nNo anomalies found\n", name);
+    }
+    let mut report = format!("# Audit of {}\n", name);
+    let mut category = None;
+    for finding in findings {
+        if category != Some(finding.category) {
+            category = Some(finding.category);
+            report.push_str(&format!("\n## {}\n\n", finding.category.title()));
+        }
+        report.push_str(&format!(
+            "{}:{}:{}: {}\n",
+            name,
+            finding.line_number + 1,
+            finding.column + 1,
+            finding.message
+        ));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audit_anomalies() {
+        let content = Rope::from("a \r\n \tb\nc\u{200B}d\ne\n");
+        let findings = audit(&content);
+        let found: Vec<(Category, usize, usize)> = findings
+            .iter()
+            .map(|f| (f.category, f.line_number, f.column))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Category::MixedLineEndings, 0, 3),
+                (Category::TrailingWhitespace, 0, 1),
+                (Category::TabsAfterSpaces, 1, 1),
+                (Category::InvisibleCharacters, 2, 1),
+            ]
+        );
+        assert!(audit(&Rope::from("clean\n")).is_empty());
+        let long = Rope::from("x".repeat(LONG_LINE + 1));
+        assert_eq!(audit(&long)[0].category, Category::LongLines);
+    }
+
+    #[test]
+    fn report() {
+        let findings = audit(&Rope::from("a \n"));
+        assert_eq!(
+            format_report("main.rs", &findings),
+            "# Audit of main.rs\n\n## Trailing whitespace\n\nmain.rs:1:2: 1 trailing character(s)\n"
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Values built in a thread while the editor starts, waited for on first use
+//!
+//! Loading syntect's syntaxes and themes takes most of the startup time, so they are built
+//! alongside the terminal setup and only block whoever needs them first.
+
+use std::cell::{Cell, OnceCell};
+use std::ops::{Deref, DerefMut};
+use std::panic;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+type Build<T> = JoinHandle<(T, Duration)>;
+
+pub struct Background<T> {
+    value: OnceCell<T>,
+    thread: Cell<Option<Build<T>>>,
+    /// time taken to build the value, then waited for it on first use
+    timing: Cell<Option<(Duration, Duration)>>,
+}
+
+impl<T: Send + 'static> Background<T> {
+    /// starts building the value in a thread
+    pub fn spawn(build: fn() -> T) -> Self {
+        let thread = thread::spawn(move || {
+            let start = Instant::now();
+            let value = build();
+            (value, start.elapsed())
+        });
+        Self {
+            value: OnceCell::new(),
+            thread: Cell::new(Some(thread)),
+            timing: Cell::new(None),
+        }
+    }
+}
+
+impl<T> Background<T> {
+    /// a value already built
+    pub fn ready(value: T) -> Self {
+        Self {
+            value: OnceCell::from(value),
+            thread: Cell::new(None),
+            timing: Cell::new(None),
+        }
+    }
+
+    /// the value, waiting for the thread building it the first time
+    pub fn get(&self) -> &T {
+        self.value.get_or_init(|| {
+            let waiting = Instant::now();
+            let thread = self.thread.take().expect("a value or a thread building it");
+            let (value, built) = thread
+                .join()
+                .unwrap_or_else(|err| panic::resume_unwind(err));
+            self.timing.set(Some((built, waiting.elapsed())));
+            value
+        })
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.get();
+        self.value.get_mut().expect("a value once got")
+    }
+
+    /// (built in, waited for) once used, when it was built in a thread
+    pub fn timing(&self) -> Option<(Duration, Duration)> {
+        self.timing.get()
+    }
+}
+
+impl<T> Deref for Background<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<T> DerefMut for Background<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.get_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wait_on_first_use() {
+        let mut numbers = Background::spawn(|| {
+            thread::sleep(Duration::from_millis(20));
+            vec![1, 2]
+        });
+        assert_eq!(numbers.timing(), None);
+        numbers.push(3);
+        assert_eq!(*numbers, vec![1, 2, 3]);
+        let (built, _) = numbers.timing().unwrap();
+        assert!(built >= Duration::from_millis(20));
+        let ready = Background::ready("now");
+        assert_eq!(*ready, "now");
+        assert_eq!(ready.timing(), None);
+    }
+}
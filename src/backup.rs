@@ -0,0 +1,162 @@
+//! Copies of a file's previous content written before each save, restored with `restore_backup`
+//!
+//! With `"backup": true` the copy is `file~`, next to the file. With a directory, e.g.
+//! `"backup": "~/.local/share/ted/backup"`, every save keeps a copy named after the file's path,
+//! `%` and `/` escaped as `%25` and `%2F`, and the time of the save to the nanosecond. The
+//! directory keeps the latest `MAX_BACKUPS` of each file.
+
+use crate::recovery;
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// backups of a file kept in a backup directory, the oldest removed first
+pub const MAX_BACKUPS: usize = 20;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Backup {
+    #[default]
+    Off,
+    /// `file~` next to the file
+    Beside,
+    /// timestamped copies in a directory
+    Dir(PathBuf),
+}
+
+impl Backup {
+    /// reads a boolean or a directory, where `~/` is the home directory
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(true) => Some(Self::Beside),
+            Value::Bool(false) => Some(Self::Off),
+            Value::String(dir) => {
+                let dir = match (dir.strip_prefix("~/"), env::var_os("HOME")) {
+                    (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+                    _ => PathBuf::from(dir),
+                };
+                Some(Self::Dir(dir))
+            }
+            _ => None,
+        }
+    }
+
+    /// where the backup of file goes when saved at time, None when off
+    pub fn path_of(&self, file: &Path, time: SystemTime) -> Option<PathBuf> {
+        match self {
+            Self::Off => None,
+            Self::Beside => {
+                let mut name = file.as_os_str().to_owned();
+                name.push("~");
+                Some(PathBuf::from(name))
+            }
+            Self::Dir(dir) => {
+                let time = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+                let stamp = format!("{}.{:09}", time.as_secs(), time.subsec_nanos());
+                Some(dir.join(format!("{}.{}", prefix(file), stamp)))
+            }
+        }
+    }
+
+    /// copies file to its backup before it gets overwritten, returns the copy if any
+    pub fn write(&self, file: &Path) -> io::Result<Option<PathBuf>> {
+        let backup = match self.path_of(file, SystemTime::now()) {
+            Some(backup) if file.is_file() => backup,
+            _ => return Ok(None),
+        };
+        if let Some(dir) = backup.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::copy(file, &backup)?;
+        let backups = self.all(file);
+        for old in &backups[..backups.len().saturating_sub(MAX_BACKUPS)] {
+            fs::remove_file(old)?;
+        }
+        Ok(Some(backup))
+    }
+
+    /// the most recent backup of file
+    pub fn latest(&self, file: &Path) -> Option<PathBuf> {
+        match self {
+            Self::Off => None,
+            Self::Beside => self.path_of(file, UNIX_EPOCH).filter(|path| path.is_file()),
+            Self::Dir(_) => self.all(file).pop(),
+        }
+    }
+
+    /// the backups of file in the backup directory, the oldest first
+    fn all(&self, file: &Path) -> Vec<PathBuf> {
+        let dir = match self {
+            Self::Dir(dir) => dir,
+            _ => return vec![],
+        };
+        let prefix = format!("{}.", prefix(file));
+        let mut backups: Vec<((u64, u32), PathBuf)> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                Some((parse_stamp(name.strip_prefix(&prefix)?)?, entry.path()))
+            })
+            .collect();
+        backups.sort();
+        backups.into_iter().map(|(_, path)| path).collect()
+    }
+}
+
+/// seconds and nanoseconds of a backup's time, backups of older versions having no nanoseconds
+fn parse_stamp(stamp: &str) -> Option<(u64, u32)> {
+    match stamp.split_once('.') {
+        Some((seconds, nanos)) => Some((seconds.parse().ok()?, nanos.parse().ok()?)),
+        None => Some((stamp.parse().ok()?, 0)),
+    }
+}
+
+/// name of the backups of file, its absolute path when it exists
+fn prefix(file: &Path) -> String {
+    let path = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+    recovery::file_name(&path.to_string_lossy())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn write_and_restore() {
+        let dir = env::temp_dir().join(format!("ted-backup-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("main.rs");
+        assert_eq!(Backup::Beside.write(&file).unwrap(), None);
+        fs::write(&file, "old\n").unwrap();
+        let beside = Backup::Beside.write(&file).unwrap().unwrap();
+        assert_eq!(beside, dir.join("main.rs~"));
+        assert_eq!(Backup::Beside.latest(&file), Some(beside));
+        let backups = Backup::Dir(dir.join("backup"));
+        let time = UNIX_EPOCH + Duration::from_secs(1000);
+        let older = backups.path_of(&file, time).unwrap();
+        let name = older.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("%2F") && name.ends_with("%2Fmain.rs.1000.000000000"));
+        let newer = backups.write(&file).unwrap().unwrap();
+        fs::write(&older, "older\n").unwrap();
+        assert_eq!(backups.latest(&file), Some(newer));
+        assert_eq!(
+            fs::read_to_string(backups.latest(&file).unwrap()).unwrap(),
+            "old\n"
+        );
+        // saves within a second each keep a backup, up to MAX_BACKUPS
+        for _ in 0..MAX_BACKUPS + 5 {
+            backups.write(&file).unwrap();
+        }
+        let all = backups.all(&file);
+        assert_eq!(all.len(), MAX_BACKUPS);
+        assert!(!all.contains(&older));
+        assert_eq!(Backup::Off.write(&file).unwrap(), None);
+        assert_eq!(Backup::from_value(&Value::Bool(true)), Some(Backup::Beside));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
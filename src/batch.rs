@@ -0,0 +1,149 @@
+//! `ted --batch script.ted file`: edits a file without a terminal and prints the result
+//!
+//! Each line of the script runs a command, like a plugin script does, or types keys when it
+//! starts with `keys`, in vim's notation. `screen` prints to stderr the 80x24 screen as ted
+//! would draw it at that point:
+//!
+//! ```text
+//! # comment out the first and third lines
+//! keys I// <Esc>
+//! goto_line 3
+//! keys I// <Esc>
+//! screen
+//! ```
+
+use crate::cli::Args;
+use crate::editor::Ted;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use ted::Config;
+
+/// key named between angle brackets, e.g. `Esc` or `C-w`
+fn named_key(name: &str) -> Option<KeyEvent> {
+    let control = |c: char| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+    let code = match name.to_lowercase().as_str() {
+        "esc" => KeyCode::Esc,
+        "cr" | "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "bs" => KeyCode::Backspace,
+        "del" => KeyCode::Delete,
+        "space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        name => {
+            let mut chars = name.strip_prefix("c-")?.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(control(c)),
+                _ => None,
+            };
+        }
+    };
+    Some(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+/// keys typed by e.g. `ihello<Esc>:w<CR>`, where `<lt>` is a literal `<`
+pub fn parse_keys(notation: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut keys = vec![];
+    let mut rest = notation;
+    while let Some(c) = rest.chars().next() {
+        let named = rest
+            .strip_prefix('<')
+            .and_then(|tail| tail.split_once('>'))
+            .filter(|(name, _)| !name.is_empty() && !name.contains('<'));
+        if let Some((name, tail)) = named {
+            keys.push(named_key(name).ok_or_else(|| format!("unknown key <{}>", name))?);
+            rest = tail;
+        } else {
+            keys.push(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Ok(keys)
+}
+
+/// runs the script on the files, then writes the focused buffer to --output or stdout
+pub fn run(script: &Path, args: Args, config: Config) -> io::Result<()> {
+    let text = fs::read_to_string(script)?;
+    let mut ted = Ted::headless(config)?;
+    for file in args.files {
+        ted.file_open_at(file.path, file.line, file.column, args.readonly);
+    }
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim_start();
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let quit = match line.strip_prefix("keys ") {
+            Some(notation) => {
+                let keys = parse_keys(notation).map_err(|err| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{}:{}: {}", script.display(), line_number + 1, err),
+                    )
+                })?;
+                keys.into_iter().any(|key| ted.handle_key(key))
+            }
+            None if line.trim_end() == "screen" => {
+                for row in ted.screen()? {
+                    eprintln!("{}", row.trim_end());
+                }
+                false
+            }
+            None => ted.run_batch_line(line),
+        };
+        if quit {
+            break;
+        }
+    }
+    if !ted.errors().is_empty() {
+        for error in ted.errors() {
+            eprintln!("ted: {}", error);
+        }
+        process::exit(1);
+    }
+    let text = ted.focused_text();
+    match args.output {
+        Some(path) => fs::write(path, text),
+        None => io::stdout().write_all(text.as_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(
+            parse_keys("ia<lt><Esc><C-w><CR>").unwrap(),
+            vec![
+                key(KeyCode::Char('i')),
+                key(KeyCode::Char('a')),
+                key(KeyCode::Char('<')),
+                key(KeyCode::Esc),
+                KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL),
+                key(KeyCode::Enter),
+            ]
+        );
+        assert_eq!(
+            parse_keys("a<b <>").unwrap(),
+            "a<b <>"
+                .chars()
+                .map(|c| key(KeyCode::Char(c)))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(parse_keys("<Nope>").unwrap_err(), "unknown key <Nope>");
+    }
+}
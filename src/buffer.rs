@@ -0,0 +1,3272 @@
+use crate::audit::{audit, Finding};
+use crate::cached_highlighter::CachedHighlighter;
+use crate::command::Commands;
+use crate::completion::{abbreviation_before, is_word_char};
+use crate::config::Whitespace;
+use crate::count::Counts;
+use crate::detect;
+use crate::diff::{diff_lines, format_diff, merge_with_markers, pick_hunk};
+use crate::editorconfig::{self, Settings};
+use crate::expand;
+use crate::fold::{all_folds, fold_around};
+use crate::format;
+use crate::git::LineChange;
+use crate::grapheme::{next_boundary, prev_boundary};
+use crate::help;
+use crate::hex;
+use crate::keymap::Keymap;
+use crate::lint::{lint, LintRules, Warning};
+use crate::lsp::Diagnostic;
+use crate::modeline::{self, Setting};
+use crate::options::BufferOptions;
+use crate::path;
+use crate::prose;
+use crate::save;
+use crate::search::{self, Search};
+use crate::snippet::{self, Placeholders};
+use crate::undo::{self, Step, UndoTree};
+use crate::wrap;
+use crate::Config;
+use ropey::Rope;
+use serde_json::Value;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+use syntect::parsing::SyntaxReference;
+
+/// the id of the next buffer created
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Buffer {
+    /// tells buffers apart for the session, unlike their names which change
+    id: usize,
+    pub name: String,
+    pub mode: InputMode,
+    /// refuses edits and saves
+    pub readonly: bool,
+    window: Range<usize>,
+    /// number of visible lines fitting in the view
+    height: usize,
+    columns: Range<usize>,
+    file: Option<BackendFile>,
+    content: Rope,
+    cursor: usize, // 0..content.len_chars()
+    last_col: usize,
+    selection: Option<Selection>,
+    config: Rc<Config>,
+    highlighter: Option<CachedHighlighter>,
+    snapshots: Vec<Snapshot>,
+    /// the states the content went through, to undo and redo
+    undo: UndoTree,
+    /// the content changed since the last undo state was recorded
+    edited: bool,
+    /// (selection, cursor) before each expand_selection, and the range it selected
+    expansions: Vec<(Option<Selection>, usize, Range<usize>)>,
+    /// problems reported by a language server
+    diagnostics: Vec<Diagnostic>,
+    /// lines changed since the last commit, None outside of a git repository
+    git_changes: Option<Vec<(usize, LineChange)>>,
+    /// closed folds, each hiding the lines after its first one
+    folds: Vec<Range<usize>>,
+    /// number of lines after the last edit, to shift the folds below the next one
+    line_count: usize,
+    /// indentation and save settings from .editorconfig files
+    editorconfig: Settings,
+    /// settings of this buffer only, over the global config
+    pub options: BufferOptions,
+    /// other paths the file is written to on every save
+    pub copies: Vec<PathBuf>,
+    /// tab stops of the snippet being filled in
+    snippet: Option<Placeholders>,
+    /// positions of the closing characters auto_pairs inserted, typed over rather than doubled
+    closers: Vec<usize>,
+    /// matches of the last search, highlighted until cleared
+    search: Option<Search>,
+    /// last edit since the matches of the search were found
+    search_edited: Option<Instant>,
+    /// matches of a replacement being typed, with what they would become
+    replacements: Vec<(Range<usize>, String)>,
+    /// the file is binary, shown and saved as a hex dump
+    hex: bool,
+    /// the view was drawn before the highlighter caught up with it
+    highlight_pending: bool,
+    /// edited since it was opened or saved
+    dirty: bool,
+    /// directory set with `cd`, as given by path::directory_of
+    working_dir: Option<String>,
+    /// named with `buffer_rename`, kept over the name of its file
+    renamed: bool,
+    /// language set by name, never guessed again
+    language_set: bool,
+    /// the home buffer
+    help: bool,
+}
+
+pub struct BackendFile {
+    path: String,
+    modified: SystemTime,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum InputMode {
+    Normal,
+    Insert,
+}
+
+/// immutable copy of a buffer's content at some point in time
+pub struct Snapshot {
+    pub content: Rope,
+    pub taken: SystemTime,
+}
+
+impl Snapshot {
+    /// HH:MM:SS (UTC) at which the snapshot was taken
+    pub fn label(&self) -> String {
+        undo::clock(self.taken)
+    }
+}
+
+/// the spaces and tabs starting a line
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// text moved to indent, keeping the relative indentation of its lines, the first one left
+/// as is with skip_first and blank ones untouched
+fn reindent(text: &str, indent: &str, skip_first: bool) -> String {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let moved = &lines[(skip_first as usize).min(lines.len())..];
+    let blank = |line: &str| line.trim().is_empty();
+    let base = match moved
+        .iter()
+        .filter(|line| !blank(line))
+        .map(|line| indentation(line))
+        .min_by_key(|i| i.chars().count())
+    {
+        Some(base) => base.chars().count(),
+        None => return text.to_string(),
+    };
+    let mut reindented = lines[..lines.len() - moved.len()].concat();
+    for line in moved {
+        if blank(line) {
+            reindented.push_str(line);
+        } else {
+            let outdented: String = line.chars().skip(base).collect();
+            reindented.push_str(indent);
+            reindented.push_str(&outdented);
+        }
+    }
+    reindented
+}
+
+/// unified diff between two named contents
+fn diff_ropes((old_name, old): (&str, &Rope), (new_name, new): (&str, &Rope)) -> String {
+    let old: Vec<String> = old.lines().map(String::from).collect();
+    let new: Vec<String> = new.lines().map(String::from).collect();
+    let old: Vec<&str> = old.iter().map(String::as_str).collect();
+    let new: Vec<&str> = new.iter().map(String::as_str).collect();
+    format_diff(old_name, new_name, &diff_lines(&old, &new))
+}
+
+#[derive(Clone, Copy)]
+pub enum Selection {
+    Lines(usize),
+    Chars(usize),
+}
+
+pub type HighlightedLine = (String, Vec<(syntect::highlighting::Style, Range<usize>)>);
+pub enum Lines {
+    Highlighted(Vec<HighlightedLine>),
+    Plain(Vec<String>),
+}
+
+/// text of a file's bytes, or their hex dump
+fn decode(bytes: Vec<u8>, hex: bool) -> io::Result<String> {
+    if hex {
+        Ok(hex::dump(&bytes))
+    } else {
+        String::from_utf8(bytes).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+}
+
+impl Buffer {
+    /// Basic in-memory buffer
+    pub fn new(content: String, name: String, config: Rc<Config>) -> Self {
+        let content = Rope::from(content);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            mode: InputMode::Normal,
+            readonly: false,
+            undo: UndoTree::new(content.clone()),
+            edited: false,
+            expansions: Vec::default(),
+            line_count: content.len_lines(),
+            content,
+            highlighter: None,
+            config,
+            cursor: 0,
+            last_col: 0,
+            name,
+            file: None,
+            selection: None,
+            window: 0..1,
+            height: 1,
+            columns: 0..1,
+            snapshots: Vec::default(),
+            diagnostics: Vec::default(),
+            git_changes: None,
+            folds: Vec::default(),
+            editorconfig: Settings::default(),
+            options: BufferOptions::default(),
+            copies: Vec::default(),
+            snippet: None,
+            closers: Vec::default(),
+            search: None,
+            search_edited: None,
+            replacements: Vec::default(),
+            hex: false,
+            highlight_pending: false,
+            dirty: false,
+            working_dir: None,
+            renamed: false,
+            language_set: false,
+            help: false,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Home buffer with help
+    pub fn home<T>(config: Rc<Config>, commands: &Commands<T>, keymap: &Keymap) -> Self {
+        let text = help::document(commands, keymap);
+        let mut buffer = Buffer::new(text, String::from("Buffer #1"), config);
+        buffer.set_language("Markdown");
+        buffer.help = true;
+        buffer
+    }
+
+    /// whether it is the home buffer, drawn by the help widget
+    pub fn is_help(&self) -> bool {
+        self.help
+    }
+
+    /// lists the commands and chords as bound now, unless the home buffer was edited
+    pub fn refresh_help(&mut self, text: &str) {
+        if self.help && !self.dirty && self.get_text() != text {
+            self.content = Rope::from(text);
+            self.invalidate_from(0);
+            self.dirty = false;
+            self.move_cursor(self.cursor);
+        }
+    }
+
+    /// Buffer with a backend file to save to
+    pub fn from_file(path: &str, config: Rc<Config>) -> io::Result<Self> {
+        let p = Path::new(&path);
+        let name = if let Some(stem) = p.file_stem() {
+            stem.to_string_lossy().to_string()
+        } else {
+            String::from("nameless file")
+        };
+        let epoch = SystemTime::UNIX_EPOCH;
+        let (bytes, modified) = if p.exists() {
+            let attr = std::fs::metadata(path)?;
+            (std::fs::read(path)?, attr.modified()?)
+        } else {
+            (vec![], epoch)
+        };
+        let hex = hex::is_binary(&bytes);
+        let mut buffer = Buffer::new(decode(bytes, hex)?, name, config.clone());
+        buffer.file = Some(BackendFile {
+            path: path.to_string(),
+            modified,
+        });
+        buffer.hex = hex;
+        buffer.copies = config.copies_of(p);
+        if hex {
+            return Ok(buffer);
+        }
+        buffer.editorconfig = editorconfig::resolve(p);
+        let from_ext = buffer
+            .file
+            .as_ref()
+            .and_then(|file| Path::new(&file.path).extension())
+            .and_then(|e| e.to_str())
+            .and_then(|extension| config.syntax_set.find_syntax_by_extension(extension));
+        let from_line = buffer.content.get_line(0).and_then(|line| {
+            config
+                .syntax_set
+                .find_syntax_by_first_line(&line.to_string())
+        });
+        if let Some(syntax) = from_line.or(from_ext).cloned() {
+            let theme = config
+                .theme_set
+                .themes
+                .get(config.default_theme.borrow().as_str())
+                .cloned()
+                .unwrap_or_default();
+            buffer.highlighter = Some(CachedHighlighter::new(syntax, theme, config.clone()));
+        }
+        if config.modelines.get() {
+            buffer.apply_modelines();
+        }
+        Ok(buffer)
+    }
+
+    /// applies the settings of the modelines in the first and last lines
+    pub fn apply_modelines(&mut self) {
+        let len = self.content.len_lines();
+        let lines: Vec<String> = (0..len.min(modeline::LINES))
+            .chain(len.saturating_sub(modeline::LINES).max(modeline::LINES)..len)
+            .map(|n| self.content.line(n).to_string())
+            .collect();
+        for setting in modeline::parse(&lines) {
+            match setting {
+                Setting::Language(language) => {
+                    let syntax = self
+                        .config
+                        .syntax_set
+                        .find_syntax_by_name(&language)
+                        .or_else(|| self.config.syntax_set.find_syntax_by_token(&language))
+                        .cloned();
+                    if let Some(syntax) = syntax {
+                        self.set_syntax(syntax);
+                        self.language_set = true;
+                    }
+                }
+                Setting::Theme(name) => {
+                    self.set_theme(&name);
+                }
+                Setting::Option(name, value) => {
+                    let _ = self.options.set(name, &value);
+                }
+            }
+        }
+    }
+
+    /// counts of the selection, or of the whole buffer
+    pub fn count(&self) -> Counts {
+        let len = self.content.len_chars();
+        let range = self.get_selection_range().unwrap_or(0..len);
+        Counts::of(self.content.slice(range.start.min(len)..range.end.min(len)))
+    }
+
+    /// whether the buffer is a hex dump of a binary file
+    pub fn is_hex(&self) -> bool {
+        self.hex
+    }
+
+    /// offset of the byte under the cursor of a hex dump
+    pub fn hex_offset(&self) -> Option<usize> {
+        if !self.hex {
+            return None;
+        }
+        let (_, line_number, column_number) = self.get_cursor();
+        hex::offset_at(line_number, column_number)
+    }
+
+    /// moves to the byte at offset of a hex dump
+    pub fn goto_offset(&mut self, offset: usize) {
+        let (line_number, column_number) = hex::position_of(offset);
+        self.goto(line_number, column_number);
+    }
+
+    /// directory files are opened, searched and formatted from: the one set with `cd`,
+    /// or the file's, empty for the editor's
+    pub fn working_dir(&self) -> String {
+        self.working_dir
+            .clone()
+            .or_else(|| self.get_path().map(path::directory_of))
+            .unwrap_or_default()
+    }
+
+    /// the directory set with `cd`, if any
+    pub fn get_working_dir(&self) -> Option<&str> {
+        self.working_dir.as_deref()
+    }
+
+    /// changes the working directory relative to the current one, returns the new one
+    pub fn change_dir(&mut self, target: &str) -> io::Result<String> {
+        let dir = path::change_dir(&self.working_dir(), target).ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("No directory {}", target.trim()),
+            )
+        })?;
+        self.working_dir = Some(dir.clone());
+        Ok(dir)
+    }
+
+    /// gives the buffer a name of its own, instead of its file's
+    pub fn rename(&mut self, name: String) {
+        self.name = name;
+        self.renamed = true;
+    }
+
+    pub fn is_renamed(&self) -> bool {
+        self.renamed
+    }
+
+    pub fn get_path(&self) -> Option<&str> {
+        self.file.as_ref().map(|file| file.path.as_str())
+    }
+
+    pub fn set_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics = diagnostics;
+    }
+
+    pub fn get_diagnostics(&self) -> &Vec<Diagnostic> {
+        &self.diagnostics
+    }
+
+    pub fn set_git_changes(&mut self, changes: Option<Vec<(usize, LineChange)>>) {
+        self.git_changes = changes;
+    }
+
+    pub fn get_git_changes(&self) -> Option<&Vec<(usize, LineChange)>> {
+        self.git_changes.as_ref()
+    }
+
+    /// the .editorconfig settings, with the indentation of the buffer's options
+    pub fn get_editorconfig(&self) -> Settings {
+        self.options.indentation(&self.editorconfig)
+    }
+
+    /// invisible characters rendered in this buffer
+    pub fn whitespace(&self) -> Whitespace {
+        self.options.whitespace(&self.config)
+    }
+
+    /// removes whitespace at the end of lines, keeping the cursor in place
+    pub fn trim_trailing_whitespace(&mut self) {
+        if self.readonly {
+            return;
+        }
+        let text = self.content.to_string();
+        let trimmed: String = text
+            .split_inclusive('\n')
+            .map(|line| {
+                let content = line.trim_end_matches(&['\r', '\n'][..]);
+                format!("{}{}", content.trim_end(), &line[content.len()..])
+            })
+            .collect();
+        if trimmed != text {
+            let (_, line_number, column_number) = self.get_cursor();
+            self.content = Rope::from(trimmed);
+            self.invalidate_from(0);
+            self.goto(line_number, column_number);
+        }
+    }
+
+    /// columns left of the text for markers
+    pub fn gutter_width(&self) -> usize {
+        self.git_changes.is_some() as usize + self.number_width()
+    }
+
+    /// columns of the line numbers and the space after them, 0 when they are hidden
+    pub fn number_width(&self) -> usize {
+        if !self.config.line_numbers.get() && !self.config.relative_numbers.get() {
+            return 0;
+        }
+        self.content.len_lines().to_string().len().max(3) + 1
+    }
+
+    /// the number of a line in the gutter, its distance to the cursor's with relative_numbers
+    pub fn line_label(&self, line_number: usize) -> String {
+        let (_, cursor_line, _) = self.get_cursor();
+        match self.config.relative_numbers.get() {
+            true if line_number != cursor_line || !self.config.line_numbers.get() => {
+                line_number.abs_diff(cursor_line).to_string()
+            }
+            _ => (line_number + 1).to_string(),
+        }
+    }
+
+    /// returns (warnings, wether they should block saving)
+    pub fn lint(&self) -> (Vec<Warning>, bool) {
+        let rules = LintRules::for_path(self.get_path().map(Path::new));
+        (lint(&self.content, &rules), rules.block)
+    }
+
+    /// moves the backend file on disk, renaming the buffer after it
+    pub fn rename_backend_file(&mut self, path: &str) -> io::Result<()> {
+        let file = self
+            .file
+            .as_mut()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "No backend file"))?;
+        if Path::new(path).exists() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                "Destination already exists",
+            ));
+        }
+        if Path::new(&file.path).exists() {
+            std::fs::rename(&file.path, path)?;
+        }
+        file.path = path.to_string();
+        if let Some(stem) = Path::new(path).file_stem().filter(|_| !self.renamed) {
+            self.name = stem.to_string_lossy().to_string();
+        }
+        Ok(())
+    }
+
+    /// removes the backend file from disk, leaving a scratch buffer
+    pub fn delete_backend_file(&mut self) -> io::Result<()> {
+        let file = self
+            .file
+            .as_ref()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "No backend file"))?;
+        std::fs::remove_file(&file.path)?;
+        self.file = None;
+        self.name = format!("{} (deleted)", self.name);
+        Ok(())
+    }
+
+    /// returns invisible anomalies: mixed EOLs, trailing whitespace, bidi characters...
+    pub fn audit(&self) -> Vec<Finding> {
+        audit(&self.content)
+    }
+
+    pub fn overwrite_backend_file(&mut self) -> io::Result<()> {
+        self.write_backend_file(false, false)
+    }
+
+    /// the content as written to disk, with the file's line endings or parsed from a hex dump
+    pub fn file_bytes(&self) -> io::Result<Vec<u8>> {
+        let text = self.content.to_string();
+        Ok(match self.editorconfig.convert_line_endings(&text) {
+            _ if self.hex => hex::parse(&text)?,
+            Some(text) => text.into_bytes(),
+            None => text.into_bytes(),
+        })
+    }
+
+    /// adds the content at the end of another file
+    pub fn append_to(&self, path: &Path) -> io::Result<()> {
+        save::append(path, &self.file_bytes()?)
+    }
+
+    /// adds paths to the copies written on every save, or goes back to the configured ones
+    /// without paths
+    pub fn tee(&mut self, paths: Vec<PathBuf>) {
+        if paths.is_empty() {
+            self.copies = match self.get_path() {
+                Some(path) => self.config.copies_of(Path::new(path)),
+                None => vec![],
+            };
+            return;
+        }
+        self.copies.extend(paths);
+        self.copies.sort();
+        self.copies.dedup();
+    }
+
+    /// writes the content to each of the copies, returning those that failed
+    pub fn write_copies(&self) -> io::Result<Vec<(PathBuf, Error)>> {
+        let bytes = self.file_bytes()?;
+        Ok(self
+            .copies
+            .iter()
+            .filter_map(|path| {
+                save::write(path, &bytes)
+                    .err()
+                    .map(|err| (path.clone(), err))
+            })
+            .collect())
+    }
+
+    /// the directory of the backend file, when it doesn't exist yet
+    pub fn missing_dir(&self) -> Option<&Path> {
+        let dir = Path::new(self.get_path()?).parent()?;
+        Some(dir).filter(|dir| !dir.as_os_str().is_empty() && !dir.is_dir())
+    }
+
+    /// saves even if the file was modified since opened
+    pub fn force_overwrite_backend_file(&mut self) -> io::Result<()> {
+        self.write_backend_file(true, false)
+    }
+
+    /// saves through the `elevate` command, for files the user may not write
+    pub fn overwrite_backend_file_as_root(&mut self) -> io::Result<()> {
+        self.write_backend_file(false, true)
+    }
+
+    /// wether the file changed on disk since it was opened or saved
+    pub fn is_modified_on_disk(&self) -> bool {
+        self.file
+            .as_ref()
+            .and_then(|file| std::fs::metadata(&file.path).ok().map(|attr| (file, attr)))
+            .and_then(|(file, attr)| attr.modified().ok().map(|m| file.modified < m))
+            .unwrap_or(false)
+    }
+
+    /// reads the file from disk, returns it along with its modification time
+    fn read_backend_file(&self) -> io::Result<(String, SystemTime)> {
+        let file = self
+            .file
+            .as_ref()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "No backend file"))?;
+        let content = decode(std::fs::read(&file.path)?, self.hex)?;
+        Ok((content, std::fs::metadata(&file.path)?.modified()?))
+    }
+
+    fn set_content(&mut self, content: &str, modified: SystemTime) {
+        self.content = Rope::from(content);
+        if let Some(file) = self.file.as_mut() {
+            file.modified = modified;
+        }
+        self.remove_selection();
+        self.invalidate_from(0);
+        self.dirty = false;
+        self.move_cursor(self.cursor);
+    }
+
+    /// takes the content of the file's latest backup, still to be saved, returns the backup
+    pub fn restore_backup(&mut self) -> io::Result<PathBuf> {
+        let path = self
+            .get_path()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "No backend file"))?;
+        let backup = self
+            .config
+            .backup
+            .borrow()
+            .latest(Path::new(path))
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "No backup"))?;
+        let content = decode(std::fs::read(&backup)?, self.hex)?;
+        self.recover(&content);
+        Ok(backup)
+    }
+
+    /// takes the text of a buffer recovered after a crash, still to be saved
+    pub fn recover(&mut self, text: &str) {
+        self.content = Rope::from(text);
+        self.remove_selection();
+        self.invalidate_from(0);
+        self.move_cursor(self.cursor);
+    }
+
+    /// wether it was edited since it was opened or saved
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// discards the buffer's content for the file's
+    pub fn reload_backend_file(&mut self) -> io::Result<()> {
+        let (content, modified) = self.read_backend_file()?;
+        self.set_content(&content, modified);
+        Ok(())
+    }
+
+    /// wraps each difference with the file in conflict markers, returns their number
+    pub fn merge_backend_file(&mut self) -> io::Result<usize> {
+        if self.readonly {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "Buffer is read-only",
+            ));
+        }
+        let (content, modified) = self.read_backend_file()?;
+        let (merged, hunks) = merge_with_markers(&self.content.to_string(), &content, "disk");
+        self.set_content(&merged, modified);
+        self.dirty = hunks > 0;
+        Ok(hunks)
+    }
+
+    /// keeps one side of the conflict hunk under the cursor, returns wether there was one
+    pub fn pick_hunk(&mut self, mine: bool) -> bool {
+        if self.readonly {
+            return false;
+        }
+        let text = self.content.to_string();
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        let (_, line_number, _) = self.get_cursor();
+        if let Some((range, picked)) = pick_hunk(&lines, line_number, mine) {
+            let start = self.content.line_to_char(range.start);
+            let end = self
+                .content
+                .line_to_char(range.end.min(self.content.len_lines()));
+            self.content.remove(start..end);
+            self.content.insert(start, &picked);
+            self.invalidate_from(range.start);
+            self.move_cursor(start);
+            return true;
+        }
+        false
+    }
+
+    fn write_backend_file(&mut self, force: bool, elevated: bool) -> io::Result<()> {
+        if self.readonly {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "Buffer is read-only",
+            ));
+        }
+        if !force && self.is_modified_on_disk() {
+            return Err(Error::other("File modified since opened"));
+        }
+        if self.editorconfig.trim_trailing_whitespace == Some(true) && self.file.is_some() {
+            self.trim_trailing_whitespace();
+        }
+        let bytes = self.file_bytes()?;
+        if let Some(file) = &mut self.file {
+            let backup = self.config.backup.borrow().write(Path::new(&file.path));
+            // the backup of a file we may not write likely fails too
+            if !elevated {
+                backup?;
+            }
+            match elevated {
+                true => save::write_elevated(
+                    &self.config.elevate.borrow(),
+                    Path::new(&file.path),
+                    &bytes,
+                )?,
+                false => save::write(Path::new(&file.path), &bytes)?,
+            }
+            file.modified = SystemTime::now();
+            self.dirty = false;
+            Ok(())
+        } else {
+            // TODO: ask for a file name to save
+            Err(Error::new(ErrorKind::NotFound, "No backend file"))
+        }
+    }
+
+    /// stores a copy of the current content, returns its index
+    pub fn snapshot(&mut self) -> usize {
+        self.snapshots.push(Snapshot {
+            content: self.content.clone(),
+            taken: SystemTime::now(),
+        });
+        self.snapshots.len() - 1
+    }
+
+    pub fn get_snapshots(&self) -> &Vec<Snapshot> {
+        &self.snapshots
+    }
+
+    /// diff from the given snapshot to the live content
+    pub fn diff_snapshot(&self, index: usize) -> Option<String> {
+        self.snapshots.get(index).map(|snapshot| {
+            let old_name = format!("{} @ {}", self.name, snapshot.label());
+            diff_ropes((&old_name, &snapshot.content), (&self.name, &self.content))
+        })
+    }
+
+    /// records the content as a new undo state when it changed since the last one
+    pub fn checkpoint(&mut self) {
+        if self.edited {
+            self.undo.record(self.content.clone(), self.cursor);
+            self.edited = false;
+        }
+    }
+
+    /// goes back n states, returns how many were undone
+    pub fn undo(&mut self, n: usize) -> usize {
+        if self.readonly {
+            return 0;
+        }
+        self.checkpoint();
+        let mut undone = 0;
+        while let Some((state, cursor)) = self.undo.undo() {
+            let content = state.content.clone();
+            self.set_state(content, cursor);
+            undone += 1;
+            if undone == n {
+                break;
+            }
+        }
+        undone
+    }
+
+    /// goes forward n states along the branch last visited, returns how many were redone
+    pub fn redo(&mut self, n: usize) -> usize {
+        if self.readonly {
+            return 0;
+        }
+        self.checkpoint();
+        let mut redone = 0;
+        while let Some(state) = self.undo.redo() {
+            let (content, cursor) = (state.content.clone(), state.cursor);
+            self.set_state(content, cursor);
+            redone += 1;
+            if redone == n {
+                break;
+            }
+        }
+        redone
+    }
+
+    /// goes to any state of the undo tree, in another branch or not
+    pub fn undo_jump(&mut self, index: usize) -> bool {
+        if self.readonly {
+            return false;
+        }
+        self.checkpoint();
+        match self.undo.jump(index) {
+            Some(state) => {
+                let (content, cursor) = (state.content.clone(), state.cursor);
+                self.set_state(content, cursor);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// goes back through the undo states in the order they were recorded, whatever their branch,
+    /// returns the state reached unless it is the current one
+    pub fn earlier(&mut self, step: Step) -> Option<usize> {
+        self.checkpoint();
+        let target = self.undo.earlier(step);
+        self.undo_to(target)
+    }
+
+    /// goes forward through the undo states in the order they were recorded
+    pub fn later(&mut self, step: Step) -> Option<usize> {
+        self.checkpoint();
+        let target = self.undo.later(step);
+        self.undo_to(target)
+    }
+
+    fn undo_to(&mut self, index: usize) -> Option<usize> {
+        Some(index).filter(|&i| i != self.undo.index() && self.undo_jump(i))
+    }
+
+    /// the undo tree as JSON, see `UndoTree::to_json`
+    pub fn undo_history(&mut self, limit: usize) -> Value {
+        self.checkpoint();
+        self.undo.to_json(limit)
+    }
+
+    /// replaces the undo tree with one saved for the current content, returns wether it was
+    pub fn load_undo_history(&mut self, history: &Value) -> bool {
+        match UndoTree::from_json(history, self.content.clone()) {
+            Some(tree) => {
+                self.undo = tree;
+                self.edited = false;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// a line per state of the undo tree, see `UndoTree::render`
+    pub fn undo_tree(&mut self) -> Vec<String> {
+        self.checkpoint();
+        self.undo.render()
+    }
+
+    /// diff from the live content to a state of the undo tree
+    pub fn diff_undo_state(&self, index: usize) -> Option<String> {
+        let state = self.undo.get(index)?;
+        let new_name = format!("{} @ #{}", self.name, index);
+        Some(diff_ropes(
+            (&self.name, &self.content),
+            (&new_name, &state.content),
+        ))
+    }
+
+    fn set_state(&mut self, content: Rope, cursor: usize) {
+        self.content = content;
+        self.remove_selection();
+        self.invalidate_from(0);
+        self.edited = false;
+        self.move_cursor(cursor);
+    }
+
+    /// replaces the content with the given snapshot's
+    pub fn restore_snapshot(&mut self, index: usize) -> bool {
+        if self.readonly {
+            return false;
+        }
+        if let Some(snapshot) = self.snapshots.get(index) {
+            self.content = snapshot.content.clone();
+            self.remove_selection();
+            self.invalidate_from(0);
+            self.move_cursor(self.cursor);
+            return true;
+        }
+        false
+    }
+
+    /// returns a non-empty line
+    pub fn get_line(&self, line_number: usize) -> Option<String> {
+        if let Some(line) = self.content.get_line(line_number) {
+            if line.len_chars() > 0 {
+                return Some(String::from(line));
+            }
+        }
+        None
+    }
+
+    pub fn get_text(&self) -> String {
+        self.content.to_string()
+    }
+
+    pub fn get_lines(&self, range: Range<usize>) -> Option<String> {
+        self.content
+            .get_lines_at(range.start)
+            .map(|lines| lines.take(range.len()).map(String::from).collect())
+    }
+
+    pub fn get_current_line(&self) -> Option<String> {
+        self.get_line(self.content.char_to_line(self.cursor))
+    }
+
+    pub fn set_language(&mut self, language: &str) -> bool {
+        if let Some(syntax) = self.config.syntax_set.find_syntax_by_name(language) {
+            self.set_syntax(syntax.clone());
+            self.language_set = true;
+            return true;
+        }
+        false
+    }
+
+    fn set_syntax(&mut self, syntax: SyntaxReference) {
+        self.highlighter = Some(CachedHighlighter::new(
+            syntax,
+            self.config
+                .theme_set
+                .themes
+                .get(self.config.default_theme.borrow().as_str())
+                .cloned()
+                .unwrap_or_default(),
+            self.config.clone(),
+        ));
+    }
+
+    /// highlights the buffer as the language its text looks like, returns its name
+    pub fn detect_language(&mut self) -> Option<String> {
+        let text = self.get_lines(0..detect::LINES).unwrap_or_default();
+        let syntax = detect::guess(&self.config.syntax_set, &text)?.clone();
+        let name = syntax.name.clone();
+        let current = self.highlighter.as_ref().map(|cached| &cached.syntax.name);
+        if current != Some(&name) {
+            self.set_syntax(syntax);
+        }
+        Some(name)
+    }
+
+    /// guesses the language again of a scratch buffer whose language wasn't set by name
+    fn auto_detect_language(&mut self) {
+        if self.file.is_none() && !self.language_set && !self.readonly {
+            self.detect_language();
+        }
+    }
+
+    pub fn set_theme(&mut self, name: &str) -> bool {
+        if let Some(cached) = self.highlighter.as_mut() {
+            if let Some(theme) = self.config.theme_set.themes.get(name).cloned() {
+                cached.set_theme(theme);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// lines within view, plain until the highlighter catches up with them
+    pub fn get_visible_lines(&mut self) -> Lines {
+        let hidden: Vec<bool> = self.window.clone().map(|n| self.is_hidden(n)).collect();
+        let (window, content) = (self.window.clone(), &self.content);
+        let highlighted = self
+            .highlighter
+            .as_mut()
+            .and_then(|cached| cached.try_highlighted_lines(content, window.clone()));
+        self.highlight_pending = self.highlighter.is_some() && highlighted.is_none();
+        let lines = match highlighted {
+            Some(lines) => Lines::Highlighted(lines),
+            None => self.get_plain_lines(window),
+        };
+        match lines {
+            Lines::Highlighted(lines) => Lines::Highlighted(
+                lines
+                    .into_iter()
+                    .zip(&hidden)
+                    .filter(|(_, hidden)| !**hidden)
+                    .map(|(line, _)| line)
+                    .collect(),
+            ),
+            Lines::Plain(lines) => Lines::Plain(
+                lines
+                    .into_iter()
+                    .zip(&hidden)
+                    .filter(|(_, hidden)| !**hidden)
+                    .map(|(line, _)| line)
+                    .collect(),
+            ),
+        }
+    }
+
+    /// line numbers within view, without the ones hidden by folds
+    pub fn visible_line_numbers(&self) -> Vec<usize> {
+        self.window
+            .clone()
+            .filter(|n| !self.is_hidden(*n))
+            .collect()
+    }
+
+    /// row of the view at which the given line is drawn, its first one when wrapped, if it is
+    pub fn screen_row(&self, line_number: usize) -> Option<usize> {
+        if self.window.contains(&line_number) && !self.is_hidden(line_number) {
+            Some(self.rows(self.window.start..line_number))
+        } else {
+            None
+        }
+    }
+
+    /// whether long lines are drawn on several rows rather than cut to the view's columns
+    pub fn is_wrapped(&self) -> bool {
+        self.config.soft_wrap.get()
+    }
+
+    /// char ranges of the rows a line is drawn on: the view's columns when lines aren't wrapped
+    pub fn line_rows(&self, line_number: usize) -> Vec<Range<usize>> {
+        match (self.is_wrapped(), self.get_line(line_number)) {
+            (true, Some(line)) => {
+                let line = line.trim_end_matches(&['\r', '\n'][..]);
+                wrap::rows(line, self.columns.len())
+            }
+            _ => vec![self.columns.clone()],
+        }
+    }
+
+    /// number of rows a line is drawn on
+    fn row_count(&self, line_number: usize) -> usize {
+        match self.is_wrapped() {
+            true => self.line_rows(line_number).len(),
+            false => 1,
+        }
+    }
+
+    /// each row of the view: the line drawn on it and the chars of the line it shows
+    pub fn screen_rows(&self) -> Vec<(usize, Range<usize>)> {
+        self.visible_line_numbers()
+            .into_iter()
+            .flat_map(|n| self.line_rows(n).into_iter().map(move |row| (n, row)))
+            .take(self.height)
+            .collect()
+    }
+
+    /// x of a column within the row of line drawing it
+    fn row_x(&self, line: &str, row: &Range<usize>, column: usize) -> usize {
+        match self.is_wrapped() {
+            true => wrap::width_of(line, row.start..column),
+            false => column - row.start,
+        }
+    }
+
+    /// row and x within the view at which the char at line_number and column is drawn, if it is
+    pub fn screen_position(&self, line_number: usize, column: usize) -> Option<(usize, usize)> {
+        let first = self.screen_row(line_number)?;
+        let rows = self.line_rows(line_number);
+        let line = self.get_line(line_number).unwrap_or_default();
+        let i = match self.is_wrapped() {
+            true => wrap::row_of(&rows, column),
+            false if self.columns.contains(&column) => 0,
+            false => return None,
+        };
+        let x = self.row_x(&line, &rows[i], column);
+        Some((first + i, x)).filter(|(y, _)| *y < self.height)
+    }
+
+    /// the first line of the outermost closed fold hiding line_number, or line_number itself
+    fn visible_line(&self, line_number: usize) -> usize {
+        self.folds
+            .iter()
+            .filter(|fold| fold.start < line_number && line_number < fold.end)
+            .map(|fold| fold.start)
+            .min()
+            .unwrap_or(line_number)
+    }
+
+    pub fn is_hidden(&self, line_number: usize) -> bool {
+        self.visible_line(line_number) != line_number
+    }
+
+    /// number of rows of the visible lines within the range
+    fn rows(&self, lines: Range<usize>) -> usize {
+        lines
+            .filter(|n| !self.is_hidden(*n))
+            .map(|n| self.row_count(n))
+            .sum()
+    }
+
+    /// the visible line drawn at most n rows above the given one
+    fn rows_above(&self, line_number: usize, n: usize) -> usize {
+        let mut line_number = self.visible_line(line_number);
+        let mut rows = 0;
+        while line_number > 0 {
+            let above = self.visible_line(line_number - 1);
+            rows += self.row_count(above);
+            if rows > n {
+                break;
+            }
+            line_number = above;
+        }
+        line_number
+    }
+
+    /// the visible line n visible lines above the given one
+    fn lines_above(&self, line_number: usize, n: usize) -> usize {
+        let mut line_number = self.visible_line(line_number);
+        for _ in 0..n {
+            if line_number == 0 {
+                break;
+            }
+            line_number = self.visible_line(line_number - 1);
+        }
+        line_number
+    }
+
+    /// the visible line n visible lines below the given one, may be past the content
+    pub fn lines_below(&self, line_number: usize, n: usize) -> usize {
+        let mut line_number = self.visible_line(line_number);
+        for _ in 0..n {
+            line_number += 1;
+            while self.is_hidden(line_number) {
+                line_number += 1;
+            }
+        }
+        line_number
+    }
+
+    /// the closed fold whose first line is the given one
+    pub fn closed_fold_at(&self, line_number: usize) -> Option<&Range<usize>> {
+        self.folds.iter().find(|fold| fold.start == line_number)
+    }
+
+    /// opens the closed fold at the cursor, returns whether there was one
+    pub fn open_fold(&mut self) -> bool {
+        let (_, line_number, _) = self.get_cursor();
+        let n = self.folds.len();
+        self.folds.retain(|fold| fold.start != line_number);
+        self.refold();
+        self.folds.len() < n
+    }
+
+    /// closes the indentation block around the cursor
+    pub fn close_fold(&mut self) {
+        let (_, line_number, _) = self.get_cursor();
+        if let Some(fold) = fold_around(&self.content, line_number) {
+            if !self.folds.contains(&fold) {
+                self.folds.push(fold);
+            }
+        }
+        self.refold();
+    }
+
+    /// opens the closed fold at the cursor, or closes the block around it
+    pub fn toggle_fold(&mut self) {
+        if !self.open_fold() {
+            self.close_fold();
+        }
+    }
+
+    pub fn open_all_folds(&mut self) {
+        self.folds.clear();
+        self.refold();
+    }
+
+    pub fn close_all_folds(&mut self) {
+        self.folds = all_folds(&self.content);
+        self.refold();
+    }
+
+    /// fits the view and the cursor to the folds after they changed
+    fn refold(&mut self) {
+        self.scroll_to(self.window.start);
+        self.move_cursor(self.cursor);
+    }
+
+    /// forgets highlighting from the given line on after an edit, the folds around it too while
+    /// the ones below move by the number of lines added or removed
+    fn invalidate_from(&mut self, line_number: usize) {
+        self.dirty = true;
+        self.edited = true;
+        if let Some(cached) = self.highlighter.as_mut() {
+            cached.invalidate_from(line_number)
+        }
+        if line_number == 0 {
+            self.auto_detect_language();
+        }
+        let delta = self.content.len_lines() as isize - self.line_count as isize;
+        self.line_count = self.content.len_lines();
+        self.folds = std::mem::take(&mut self.folds)
+            .into_iter()
+            .filter_map(|fold| match fold.start > line_number {
+                true => {
+                    let start = fold.start as isize + delta;
+                    let end = fold.end as isize + delta;
+                    (start > line_number as isize).then_some(start as usize..end as usize)
+                }
+                false => Some(fold).filter(|fold| fold.end <= line_number),
+            })
+            .collect();
+        if self.search.is_some() {
+            self.search_edited = Some(Instant::now());
+        }
+    }
+
+    /// returns highlighted lines within the given range
+    pub fn get_highlighted_lines(&mut self, range: Range<usize>) -> Lines {
+        if let Some(cached) = self.highlighter.as_mut() {
+            Lines::Highlighted(cached.get_highlighted_lines(&self.content, range))
+        } else {
+            self.get_plain_lines(range)
+        }
+    }
+
+    fn get_plain_lines(&self, range: Range<usize>) -> Lines {
+        Lines::Plain(
+            self.content
+                .get_lines_at(range.start)
+                .map(|lines| lines.take(range.len()).map(String::from).collect())
+                .unwrap_or_default(),
+        )
+    }
+
+    /// whether the last drawn view is still being highlighted
+    pub fn is_highlight_pending(&self) -> bool {
+        self.highlight_pending
+    }
+
+    pub fn len_lines(&self) -> usize {
+        self.content.len_lines()
+    }
+
+    pub fn get_search(&self) -> Option<&Search> {
+        self.search.as_ref()
+    }
+
+    /// finds the matches of the search again once the text was left alone for delay since
+    /// it was edited, rather than at every key typed. returns whether it did
+    pub fn refresh_search(&mut self, delay: Duration) -> bool {
+        match (self.search.as_mut(), self.search_edited) {
+            (Some(search), Some(edited)) if edited.elapsed() >= delay => {
+                search.update(&self.content.to_string());
+                self.search_edited = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// highlights the occurrences of pattern, matched with the configured search
+    /// options, without moving
+    pub fn set_search(&mut self, pattern: &str) -> io::Result<()> {
+        let options = self.config.search.get();
+        self.search = Some(Search::new(pattern, options, &self.content.to_string())?);
+        self.search_edited = None;
+        Ok(())
+    }
+
+    /// highlights the occurrences of pattern and moves to the first one from the cursor,
+    /// returns its (1-based index, number of matches)
+    pub fn search(&mut self, pattern: &str) -> io::Result<Option<(usize, usize)>> {
+        self.set_search(pattern)?;
+        Ok(self.goto_match(true, true))
+    }
+
+    /// moves to the next (or previous) match of the last search, as in search
+    pub fn search_next(&mut self, forward: bool) -> Option<(usize, usize)> {
+        self.goto_match(forward, false)
+    }
+
+    fn goto_match(&mut self, forward: bool, inclusive: bool) -> Option<(usize, usize)> {
+        self.refresh_search(Duration::ZERO);
+        let search = self.search.as_ref()?;
+        let i = search.next_from(self.cursor, forward, inclusive)?;
+        let (start, n) = (search.matches[i].start, search.matches.len());
+        let (line_number, _) = self.coord_from_pos(start);
+        self.folds.retain(|fold| !fold.contains(&line_number));
+        self.move_cursor(start);
+        Some((i + 1, n))
+    }
+
+    pub fn clear_search(&mut self) {
+        self.search = None;
+    }
+
+    /// the matches of pattern, within the selection if any, with what template turns them
+    /// into, see search::replacements
+    fn find_replacements(
+        &self,
+        pattern: &str,
+        template: &str,
+        lines: Range<usize>,
+    ) -> io::Result<Vec<(Range<usize>, String)>> {
+        let last = self.content.len_lines();
+        let start = self.content.line_to_char(lines.start.min(last));
+        let end = self.content.line_to_char(lines.end.min(last));
+        let text = self.content.slice(start..end).to_string();
+        let options = self.config.search.get();
+        let mut found = search::replacements(&text, pattern, options, template)?;
+        for (m, _) in found.iter_mut() {
+            *m = m.start + start..m.end + start;
+        }
+        if let Some(selection) = self.get_selection_range() {
+            found.retain(|(m, _)| selection.start <= m.start && m.end <= selection.end);
+        }
+        Ok(found)
+    }
+
+    /// the number of matches replace_matches would replace
+    pub fn count_replacements(&self, pattern: &str) -> io::Result<usize> {
+        let lines = 0..self.content.len_lines();
+        Ok(self.find_replacements(pattern, "", lines)?.len())
+    }
+
+    /// shows what replacing pattern by template would do to the lines in view, returns the
+    /// number of matches shown
+    pub fn preview_replace(&mut self, pattern: &str, template: &str) -> io::Result<usize> {
+        self.replacements = self.find_replacements(pattern, template, self.window.clone())?;
+        Ok(self.replacements.len())
+    }
+
+    pub fn clear_replace_preview(&mut self) {
+        self.replacements.clear();
+    }
+
+    /// the matches previewed by preview_replace with their replacements
+    pub fn get_replacements(&self) -> &[(Range<usize>, String)] {
+        &self.replacements
+    }
+
+    /// replaces the matches of pattern, within the selection if any, by template as
+    /// previewed, returns the number of replacements
+    pub fn replace_matches(&mut self, pattern: &str, template: &str) -> io::Result<usize> {
+        self.replacements.clear();
+        if self.readonly {
+            return Ok(0);
+        }
+        let found = self.find_replacements(pattern, template, 0..self.content.len_lines())?;
+        for (m, text) in found.iter().rev() {
+            self.content.remove(m.clone());
+            self.content.insert(m.start, text);
+        }
+        if let Some((first, _)) = found.first() {
+            self.remove_selection();
+            self.invalidate_from(self.content.char_to_line(first.start));
+            self.move_cursor(first.start);
+        }
+        Ok(found.len())
+    }
+
+    /// replaces every occurrence of pattern, returns the number of replacements
+    pub fn replace_all(&mut self, pattern: &str, replacement: &str) -> usize {
+        if pattern.is_empty() || self.readonly {
+            return 0;
+        }
+        let text = self.content.to_string();
+        let n = text.matches(pattern).count();
+        if n > 0 {
+            self.content = Rope::from(text.replace(pattern, replacement));
+            self.remove_selection();
+            self.invalidate_from(0);
+            self.move_cursor(self.cursor);
+        }
+        n
+    }
+
+    /// moves the view to start at the given line, keeping its height
+    fn scroll_to(&mut self, start: usize) {
+        let start = self.visible_line(start);
+        if self.height == 0 {
+            self.window = start..start;
+            return;
+        }
+        let mut end = start;
+        let mut rows = self.row_count(start);
+        while rows < self.height {
+            end = self.lines_below(end, 1);
+            rows += self.row_count(end);
+        }
+        self.window = start..end + 1;
+    }
+
+    /// scrolls so the cursor's line is at the middle of the view
+    pub fn scroll_cursor_center(&mut self) {
+        let (_, line_number, _) = self.get_cursor();
+        self.scroll_to(self.rows_above(line_number, self.height / 2));
+    }
+
+    /// scrolls so the cursor's line is at the top of the view, past the scrolloff
+    pub fn scroll_cursor_top(&mut self) {
+        let (_, line_number, _) = self.get_cursor();
+        self.scroll_to(self.rows_above(line_number, self.options.scrolloff(&self.config)));
+    }
+
+    /// scrolls so the cursor's line is at the bottom of the view, before the scrolloff
+    pub fn scroll_cursor_bottom(&mut self) {
+        let (_, line_number, _) = self.get_cursor();
+        let rows = self
+            .height
+            .saturating_sub(self.options.scrolloff(&self.config) + self.row_count(line_number));
+        self.scroll_to(self.rows_above(line_number, rows));
+    }
+
+    /// moves the cursor to the given line, keeping its column when possible
+    fn move_cursor_to_line(&mut self, line_number: usize) {
+        let (_, current_line_number, _) = self.get_cursor();
+        if line_number > current_line_number {
+            self.move_cursor_down(self.rows(current_line_number..line_number));
+        } else {
+            self.move_cursor_up(self.rows(line_number..current_line_number));
+        }
+    }
+
+    /// scrolls the view by n lines (up if negative), the cursor only moves to stay in view
+    pub fn scroll_lines(&mut self, n: isize) {
+        let last_start = self.visible_line(self.content.len_lines().saturating_sub(1));
+        let start = if n < 0 {
+            self.lines_above(self.window.start, n.unsigned_abs())
+        } else {
+            self.lines_below(self.window.start, n as usize)
+                .min(last_start)
+        };
+        self.scroll_to(start);
+        let margin = self
+            .options
+            .scrolloff(&self.config)
+            .min(self.height.saturating_sub(1) / 2);
+        let top = if self.window.start > 0 {
+            self.lines_below(self.window.start, margin)
+        } else {
+            0
+        };
+        let bottom = self
+            .lines_above(self.window.end.saturating_sub(1), margin)
+            .max(top);
+        let (_, line_number, _) = self.get_cursor();
+        self.move_cursor_to_line(line_number.clamp(top, bottom));
+    }
+
+    /// scrolls the view and the cursor by half a page n times (up if negative)
+    pub fn scroll_half_pages(&mut self, n: isize) {
+        let rows = (self.height / 2) as isize * n;
+        let (_, line_number, _) = self.get_cursor();
+        let target = if rows < 0 {
+            self.lines_above(line_number, rows.unsigned_abs())
+        } else {
+            self.lines_below(line_number, rows as usize)
+        };
+        self.scroll_lines(rows);
+        self.move_cursor_to_line(target);
+    }
+
+    /// fits the view to height rows, scrolling back when it grew past the end of the content
+    /// and scrolling to keep the cursor in view
+    pub fn resize_window(&mut self, height: usize) {
+        let changed = height != self.height;
+        let mut start = self.window.start;
+        if height > self.height {
+            let last = self.content.len_lines().saturating_sub(1);
+            start = start.min(self.rows_above(last, height.saturating_sub(self.row_count(last))));
+        }
+        self.height = height;
+        self.scroll_to(start);
+        let line_number = self.content.char_to_line(self.cursor);
+        if changed || !self.window.contains(&line_number) {
+            let last_col = self.last_col;
+            self.move_cursor(self.cursor);
+            self.last_col = last_col;
+        }
+    }
+
+    /// the max_line_length of the buffer's language, or of every language
+    pub fn max_line_length(&self) -> Option<usize> {
+        let lengths = self.config.max_line_length.borrow();
+        self.highlighter
+            .as_ref()
+            .and_then(|cached| lengths.get(&cached.syntax.name))
+            .or_else(|| lengths.get("*"))
+            .copied()
+    }
+
+    /// "All", "Top", "Bot" or the percentage of lines above the view, like vim's ruler
+    pub fn scroll_percentage(&self) -> String {
+        let lines = self.content.len_lines();
+        match (self.window.start == 0, self.window.end >= lines) {
+            (true, true) => String::from("All"),
+            (true, false) => String::from("Top"),
+            (false, true) => String::from("Bot"),
+            (false, false) => format!("{}%", self.window.start * 100 / (lines - self.window.len())),
+        }
+    }
+
+    /// the title format with {name}, {path} (or the name) and {dirty} replaced
+    pub fn title(&self, format: &str) -> String {
+        format
+            .replace("{name}", &self.name)
+            .replace("{path}", self.get_path().unwrap_or(&self.name))
+            .replace("{dirty}", if self.dirty { " [+]" } else { "" })
+    }
+
+    /// returns the [first_line_number, last_line_number) within view, folded lines included
+    pub fn get_window(&self) -> &Range<usize> {
+        &self.window
+    }
+
+    /// fits the view to width columns, scrolling back left when it grew
+    pub fn resize_columns(&mut self, width: usize) {
+        let width = width.max(1);
+        let mut start = self.columns.start;
+        if width > self.columns.len() {
+            let (_, column_number) = self.coord_from_pos(self.cursor);
+            start = start.min((column_number + 1).saturating_sub(width));
+        }
+        self.columns = start..start + width;
+        self.scroll_columns();
+    }
+
+    /// returns the [first_column, last_column) within view
+    pub fn get_columns(&self) -> &Range<usize> {
+        &self.columns
+    }
+
+    /// shifts the visible columns so the cursor's column stays in view, wrapped lines being
+    /// always in view from their start
+    fn scroll_columns(&mut self) {
+        let (_, column_number) = self.coord_from_pos(self.cursor);
+        let width = self.columns.end - self.columns.start;
+        if self.is_wrapped() {
+            self.columns = 0..width;
+            return;
+        }
+        if column_number < self.columns.start {
+            self.columns = column_number..column_number + width;
+        }
+        if column_number >= self.columns.end {
+            let start = column_number + 1 - width;
+            self.columns = start..start + width;
+        }
+    }
+
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    /// swaps the config, re-resolving the syntax in the new syntax set
+    pub fn set_config(&mut self, config: Rc<Config>) {
+        if let Some(cached) = self.highlighter.take() {
+            self.highlighter = config
+                .syntax_set
+                .find_syntax_by_name(&cached.syntax.name)
+                .map(|syntax| CachedHighlighter::new(syntax.clone(), cached.theme, config.clone()));
+        }
+        self.config = config;
+    }
+
+    pub fn get_highlighter(&self) -> &Option<CachedHighlighter> {
+        &self.highlighter
+    }
+
+    /// returns (line_number, column_number) within self.window
+    pub fn coord_from_pos(&self, pos: usize) -> (usize, usize) {
+        let line_number = self.content.char_to_line(pos);
+        let beginning_of_line = self.content.line_to_char(line_number);
+        (line_number, pos.saturating_sub(beginning_of_line))
+    }
+
+    /// returns (cursor, line_number, column_number)
+    pub fn get_cursor(&self) -> (usize, usize, usize) {
+        let (line_number, column_number) = self.coord_from_pos(self.cursor);
+        (self.cursor, line_number, column_number)
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        if self.readonly {
+            return;
+        }
+        self.content.insert_char(self.cursor, c);
+        let pos = self.placeholders_inserted(self.cursor, 1);
+        let line_number = self.content.char_to_line(pos);
+        self.invalidate_from(line_number);
+        self.move_cursor(pos + 1);
+    }
+
+    /// the part of a word left of the cursor
+    pub fn word_before_cursor(&self) -> String {
+        let (_, line_number, column_number) = self.get_cursor();
+        let line: Vec<char> = self
+            .get_line(line_number)
+            .unwrap_or_default()
+            .chars()
+            .take(column_number)
+            .collect();
+        let start = line
+            .iter()
+            .rposition(|c| !is_word_char(*c))
+            .map_or(0, |i| i + 1);
+        line[start..].iter().collect()
+    }
+
+    /// the word the cursor is on, or empty
+    pub fn word_under_cursor(&self) -> String {
+        let (_, line_number, column_number) = self.get_cursor();
+        let line: Vec<char> = self
+            .get_line(line_number)
+            .unwrap_or_default()
+            .chars()
+            .collect();
+        let column_number = column_number.min(line.len());
+        let start = line[..column_number]
+            .iter()
+            .rposition(|c| !is_word_char(*c))
+            .map_or(0, |i| i + 1);
+        let end = line[column_number..]
+            .iter()
+            .position(|c| !is_word_char(*c))
+            .map_or(line.len(), |i| column_number + i);
+        line[start..end].iter().collect()
+    }
+
+    /// replaces the word left of the cursor with the given one
+    pub fn complete_word(&mut self, word: &str) {
+        if self.readonly {
+            return;
+        }
+        let start = self.cursor - self.word_before_cursor().chars().count();
+        self.content.remove(start..self.cursor);
+        let start = self.placeholders_removed(start..self.cursor);
+        self.content.insert(start, word);
+        let start = self.placeholders_inserted(start, word.chars().count());
+        self.invalidate_from(self.content.char_to_line(start));
+        self.move_cursor(start + word.chars().count());
+    }
+
+    /// replaces the word before the cursor with the snippet it triggers for the
+    /// buffer's language, returns whether there was one
+    pub fn expand_snippet(&mut self) -> bool {
+        let word = self.word_before_cursor();
+        if self.readonly || word.is_empty() {
+            return false;
+        }
+        let language = self
+            .highlighter
+            .as_ref()
+            .map_or("Plain Text", |cached| cached.syntax.name.as_str());
+        let body = self
+            .config
+            .snippets
+            .borrow()
+            .get(language)
+            .and_then(|snippets| snippets.get(&word))
+            .cloned();
+        let body = match body {
+            Some(body) => body,
+            None => return false,
+        };
+        let (_, line_number, _) = self.get_cursor();
+        let bol = self.content.line_to_char(line_number);
+        let indent: String = self
+            .content
+            .slice(bol..self.cursor)
+            .chars()
+            .take_while(|&c| c == ' ' || c == '\t')
+            .collect();
+        let expansion = snippet::expand(&body, &indent);
+        let start = self.cursor - word.chars().count();
+        self.snippet = None;
+        self.content.remove(start..self.cursor);
+        self.content.insert(start, &expansion.text);
+        self.invalidate_from(line_number);
+        self.snippet = Some(Placeholders::new(expansion.stops, start));
+        self.jump_to_placeholder();
+        true
+    }
+
+    /// moves to the end of the current placeholder, the snippet ends at the last one
+    fn jump_to_placeholder(&mut self) {
+        if let Some(placeholders) = &self.snippet {
+            let last = placeholders.is_last();
+            if let Some(range) = placeholders.current().cloned() {
+                self.move_cursor(range.end);
+            }
+            if last {
+                self.snippet = None;
+            }
+        }
+    }
+
+    /// moves to the next placeholder of the snippet, returns whether one is being filled in
+    pub fn next_placeholder(&mut self) -> bool {
+        match self.snippet.as_mut() {
+            Some(placeholders) => placeholders.next(),
+            None => return false,
+        }
+        self.jump_to_placeholder();
+        true
+    }
+
+    /// moves to the previous placeholder of the snippet
+    pub fn prev_placeholder(&mut self) {
+        if let Some(placeholders) = self.snippet.as_mut() {
+            placeholders.prev();
+            self.jump_to_placeholder();
+        }
+    }
+
+    /// removes the default text of a placeholder that was just reached, so typing replaces it
+    fn clear_placeholder(&mut self) -> bool {
+        let range = match &self.snippet {
+            Some(placeholders) if !placeholders.typed => placeholders.current().cloned(),
+            _ => None,
+        };
+        match range {
+            Some(range) if !range.is_empty() && range.end == self.cursor => {
+                self.delete_range(range);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// shifts the placeholders and auto-paired closers after an insertion, returns pos
+    /// shifted by mirrored edits
+    fn placeholders_inserted(&mut self, pos: usize, n: usize) -> usize {
+        for closer in self.closers.iter_mut().filter(|closer| **closer >= pos) {
+            *closer += n;
+        }
+        match self.snippet.as_mut() {
+            Some(placeholders) => placeholders.inserted(pos, n),
+            None => return pos,
+        }
+        self.mirror_placeholder(pos)
+    }
+
+    /// shifts the placeholders and auto-paired closers after a removal, returns its start
+    /// shifted by mirrored edits
+    fn placeholders_removed(&mut self, range: Range<usize>) -> usize {
+        self.closers.retain(|closer| !range.contains(closer));
+        for closer in self
+            .closers
+            .iter_mut()
+            .filter(|closer| **closer >= range.end)
+        {
+            *closer -= range.len();
+        }
+        match self.snippet.as_mut() {
+            Some(placeholders) => placeholders.removed(range.clone()),
+            None => return range.start,
+        }
+        self.mirror_placeholder(range.start)
+    }
+
+    /// copies the text of the current placeholder to its mirrors,
+    /// returns pos shifted by the replacements made before it
+    fn mirror_placeholder(&mut self, mut pos: usize) -> usize {
+        let current = match self.snippet.as_ref().and_then(|p| p.current().cloned()) {
+            Some(current) => current,
+            None => return pos,
+        };
+        let text = self.content.slice(current).to_string();
+        let n = text.chars().count();
+        let count = self.snippet.as_ref().map_or(0, |p| p.mirrors().len());
+        for i in 0..count {
+            // earlier replacements shift the later mirrors
+            let mirror = match self
+                .snippet
+                .as_ref()
+                .and_then(|p| p.mirrors().get(i).cloned())
+            {
+                Some(mirror) => mirror,
+                None => break,
+            };
+            if self.content.slice(mirror.clone()) == text {
+                continue;
+            }
+            self.content.remove(mirror.clone());
+            self.content.insert(mirror.start, &text);
+            if let Some(placeholders) = self.snippet.as_mut() {
+                placeholders.removed(mirror.clone());
+                placeholders.inserted(mirror.start, n);
+            }
+            if mirror.start < pos {
+                pos = pos + n - mirror.len();
+            }
+            if mirror.start < self.cursor {
+                self.cursor = self.cursor + n - mirror.len();
+            }
+            self.invalidate_from(self.content.char_to_line(mirror.start));
+        }
+        pos
+    }
+
+    /// replaces an abbreviation right before the cursor with its expansion
+    fn expand_abbreviation(&mut self) {
+        let (_, line_number, _) = self.get_cursor();
+        let bol = self.content.line_to_char(line_number);
+        let before = self.content.slice(bol..self.cursor).to_string();
+        let abbreviations = self.config.abbreviations.borrow();
+        let (from, to) = match abbreviation_before(&before, &abbreviations) {
+            Some((from, to)) => (from.chars().count(), to.to_string()),
+            None => return,
+        };
+        drop(abbreviations);
+        let start = self.cursor - from;
+        self.content.remove(start..self.cursor);
+        let start = self.placeholders_removed(start..self.cursor);
+        self.content.insert(start, &to);
+        let start = self.placeholders_inserted(start, to.chars().count());
+        self.invalidate_from(line_number);
+        self.move_cursor(start + to.chars().count());
+    }
+
+    /// inserts a tab, or spaces up to the next indentation stop per .editorconfig
+    pub fn insert_indent(&mut self) {
+        let (_, _, column_number) = self.get_cursor();
+        for c in self.get_editorconfig().indent(column_number).chars() {
+            self.insert_char(c);
+        }
+    }
+
+    /// inserts a newline, keeping the current line's indentation with auto_indent
+    pub fn insert_newline(&mut self) {
+        let indent: String =
+            if self.options.auto_indent(&self.config) && !self.config.paste_mode.get() {
+                let line_number = self.content.char_to_line(self.cursor);
+                let bol = self.content.line_to_char(line_number);
+                self.content
+                    .slice(bol..self.cursor)
+                    .chars()
+                    .take_while(|&c| c == ' ' || c == '\t')
+                    .collect()
+            } else {
+                String::default()
+            };
+        self.insert_char('\n');
+        for c in indent.chars() {
+            self.insert_char(c);
+        }
+    }
+
+    /// inserts a typed character, handling brackets and quotes with auto_pairs
+    pub fn type_char(&mut self, c: char) {
+        if self.readonly {
+            return;
+        }
+        self.clear_placeholder();
+        if !is_word_char(c) && !self.config.paste_mode.get() {
+            self.expand_abbreviation();
+        }
+        if !self.options.auto_pairs(&self.config) || self.config.paste_mode.get() {
+            return self.insert_char(c);
+        }
+        let next = self.content.get_char(self.cursor);
+        if next == Some(c) && self.closers.contains(&self.cursor) {
+            // type over the closing character inserted earlier
+            let cursor = self.cursor;
+            self.closers.retain(|&closer| closer != cursor);
+            return self.move_cursor(self.cursor + 1);
+        }
+        let prev = self
+            .cursor
+            .checked_sub(1)
+            .and_then(|pos| self.content.get_char(pos));
+        let closing = match c {
+            '(' => Some(')'),
+            '[' => Some(']'),
+            '{' => Some('}'),
+            // an apostrophe or a closing quote after a word
+            '"' | '\'' if matches!(prev, Some(prev) if is_word_char(prev)) => None,
+            '"' | '\'' => Some(c),
+            _ => None,
+        };
+        self.insert_char(c);
+        // opening right before a word wraps it by hand
+        let before_word = matches!(next, Some(next) if is_word_char(next));
+        if let Some(closing) = closing.filter(|_| !before_word) {
+            self.content.insert_char(self.cursor, closing);
+            self.cursor = self.placeholders_inserted(self.cursor, 1);
+            self.closers.push(self.cursor);
+        }
+    }
+
+    pub fn prepend_newline(&mut self) {
+        if self.readonly {
+            return;
+        }
+        let current_line_number = self.content.char_to_line(self.cursor);
+        let bol = self.content.line_to_char(current_line_number);
+        self.content.insert_char(bol, '\n');
+        self.invalidate_from(current_line_number);
+        if self.cursor != bol {
+            self.move_cursor_up(1);
+        }
+    }
+
+    pub fn append_newline(&mut self) {
+        if self.readonly {
+            return;
+        }
+        let current_line_number = self.content.char_to_line(self.cursor);
+        let eol = self.end_of_line(current_line_number);
+        self.content.insert_char(eol, '\n');
+        self.invalidate_from(current_line_number);
+        self.move_cursor_down(1);
+    }
+
+    pub fn insert_mode(&mut self) {
+        if !self.readonly {
+            self.mode = InputMode::Insert;
+        }
+    }
+
+    pub fn normal_mode(&mut self) {
+        if let InputMode::Insert = self.mode {
+            self.mode = InputMode::Normal;
+            self.snippet = None;
+            self.closers.clear();
+            self.move_cursor(
+                self.cursor
+                    .min(self.end_of_line(self.content.char_to_line(self.cursor))),
+            );
+        }
+    }
+
+    pub fn select_chars(&mut self) {
+        self.selection = Some(Selection::Chars(self.cursor));
+    }
+
+    pub fn select_lines(&mut self) {
+        let line_number = self.content.char_to_line(self.cursor);
+        self.selection = Some(Selection::Lines(line_number));
+    }
+
+    pub fn remove_selection(&mut self) {
+        self.selection = None;
+    }
+
+    pub fn get_selection(&self) -> Option<String> {
+        self.get_selection_range()
+            .and_then(|selection| self.content.get_slice(selection))
+            .map(String::from)
+    }
+
+    /// get the range of selected character position
+    pub fn get_selection_range(&self) -> Option<Range<usize>> {
+        match self.selection {
+            Some(Selection::Chars(pos)) => {
+                // the grapheme under the last position is selected whole
+                let last = pos.max(self.cursor);
+                Some(pos.min(self.cursor)..next_boundary(&self.content, last).max(last + 1))
+            }
+            Some(Selection::Lines(line_number)) => {
+                let current_line_number = self.content.char_to_line(self.cursor);
+                let lower = self
+                    .content
+                    .line_to_char(line_number.min(current_line_number));
+                // a closed fold is selected as a whole
+                let upper = self
+                    .content
+                    .line_to_char(self.lines_below(line_number.max(current_line_number), 1));
+                Some(lower..upper)
+            }
+            _ => None,
+        }
+    }
+
+    /// (row, columns) of the selected part of each row within view, relative to the view
+    pub fn get_selection_spans(&self) -> Option<Vec<(u16, Range<u16>)>> {
+        let range = self.get_selection_range()?;
+        let rows = self.screen_rows();
+        let spans = rows
+            .iter()
+            .enumerate()
+            .filter_map(|(y, (line_number, row))| {
+                let line = self.get_line(*line_number)?;
+                let bol = self.content.line_to_char(*line_number);
+                // the row holding the line break takes it, drawn as one more column
+                let last = rows.get(y + 1).map(|(next, _)| next) != Some(line_number);
+                let len = line.chars().count();
+                let end = match last {
+                    true => len.min(self.columns.len() + row.start),
+                    false => row.end,
+                };
+                let start = range.start.saturating_sub(bol).max(row.start);
+                let end = range.end.saturating_sub(bol).min(end).max(start);
+                let x = |column| match column > row.end {
+                    true => self.row_x(&line, row, row.end) + column - row.end,
+                    false => self.row_x(&line, row, column),
+                };
+                let columns = x(start) as u16..x(end) as u16;
+                Some((y as u16, columns)).filter(|(_, columns)| !columns.is_empty())
+            })
+            .collect();
+        Some(spans)
+    }
+
+    pub fn move_cursor_bol(&mut self) {
+        let current_line = self.content.char_to_line(self.cursor);
+        let dest_cursor = self.content.line_to_char(current_line);
+        if dest_cursor != self.cursor {
+            self.move_cursor(dest_cursor);
+        }
+    }
+
+    pub fn move_cursor_eol(&mut self) {
+        let current_line = self.content.char_to_line(self.cursor);
+        let dest_cursor = self.end_of_line(current_line);
+        if dest_cursor != self.cursor {
+            self.move_cursor(dest_cursor);
+        }
+    }
+
+    /// moves n graphemes left, staying on the line
+    pub fn move_cursor_left(&mut self, n: usize) {
+        let line_number = self.content.char_to_line(self.cursor);
+        let bol = self.content.line_to_char(line_number);
+        let mut dest_cursor = self.cursor;
+        for _ in 0..n {
+            dest_cursor = prev_boundary(&self.content, dest_cursor).max(bol);
+        }
+        if dest_cursor != self.cursor {
+            self.move_cursor(dest_cursor);
+        }
+    }
+
+    /// moves n graphemes right, staying on the line
+    pub fn move_cursor_right(&mut self, n: usize) {
+        let line_number = self.content.char_to_line(self.cursor);
+        let eol = self.end_of_line(line_number);
+        let mut dest_cursor = self.cursor;
+        for _ in 0..n {
+            dest_cursor = next_boundary(&self.content, dest_cursor).min(eol);
+        }
+        if dest_cursor != self.cursor {
+            self.move_cursor(dest_cursor);
+        }
+    }
+
+    /// moves to the start of the n-th next word, past punctuation and line breaks
+    pub fn move_word_forward(&mut self, n: usize) {
+        let len = self.content.len_chars();
+        let mut dest_cursor = self.cursor;
+        for _ in 0..n {
+            while dest_cursor < len && is_word_char(self.content.char(dest_cursor)) {
+                dest_cursor += 1;
+            }
+            while dest_cursor < len && !is_word_char(self.content.char(dest_cursor)) {
+                dest_cursor += 1;
+            }
+        }
+        self.move_cursor(dest_cursor);
+    }
+
+    /// moves to the start of the n-th previous word
+    pub fn move_word_backward(&mut self, n: usize) {
+        let mut dest_cursor = self.cursor;
+        for _ in 0..n {
+            while dest_cursor > 0 && !is_word_char(self.content.char(dest_cursor - 1)) {
+                dest_cursor -= 1;
+            }
+            while dest_cursor > 0 && is_word_char(self.content.char(dest_cursor - 1)) {
+                dest_cursor -= 1;
+            }
+        }
+        self.move_cursor(dest_cursor);
+    }
+
+    /// moves to the start of the n-th next sentence, `)`
+    pub fn move_sentence_forward(&mut self, n: usize) {
+        self.move_cursor(prose::next_sentence(&self.content, self.cursor, n));
+    }
+
+    /// moves to the start of the sentence, or of the previous one when already there, `(`
+    pub fn move_sentence_backward(&mut self, n: usize) {
+        self.move_cursor(prose::previous_sentence(&self.content, self.cursor, n));
+    }
+
+    /// moves to the blank line after the n-th next paragraph, `}`
+    pub fn move_paragraph_forward(&mut self, n: usize) {
+        let line_number = self.content.char_to_line(self.cursor);
+        self.goto(prose::next_paragraph(&self.content, line_number, n), 0);
+    }
+
+    /// moves to the blank line before the n-th previous paragraph, `{`
+    pub fn move_paragraph_backward(&mut self, n: usize) {
+        let line_number = self.content.char_to_line(self.cursor);
+        self.goto(prose::previous_paragraph(&self.content, line_number, n), 0);
+    }
+
+    /// grows the selection, or selects from the cursor, to the unit of text around it, see
+    /// `expand::enclosing`
+    pub fn expand_selection(&mut self) -> bool {
+        let range = self
+            .get_selection_range()
+            .unwrap_or(self.cursor..self.cursor);
+        let expanded = match expand::enclosing(&self.content, range) {
+            Some(expanded) => expanded,
+            None => return false,
+        };
+        self.expansions
+            .push((self.selection, self.cursor, expanded.clone()));
+        self.selection = Some(Selection::Chars(expanded.start));
+        self.move_cursor(prev_boundary(&self.content, expanded.end));
+        true
+    }
+
+    /// goes back to the selection before the last expand_selection, unless it changed since
+    pub fn shrink_selection(&mut self) -> bool {
+        match self.expansions.pop() {
+            Some((selection, cursor, expanded))
+                if self.get_selection_range().as_ref() == Some(&expanded) =>
+            {
+                self.selection = selection;
+                self.move_cursor(cursor);
+                true
+            }
+            _ => {
+                self.expansions.clear();
+                false
+            }
+        }
+    }
+
+    /// selects the lines of the paragraph under the cursor and the n - 1 next ones, for `d`
+    /// and `c` to act on
+    pub fn select_paragraph(&mut self, n: usize) {
+        let line_number = self.content.char_to_line(self.cursor);
+        let lines = prose::paragraph(&self.content, line_number, n);
+        self.goto(lines.start, 0);
+        self.select_lines();
+        self.goto(lines.end.saturating_sub(1), 0);
+    }
+
+    /// will return last char position if line_number >= self.content.len_lines()
+    ///
+    /// In normal mode, the cursor stays on the line's last grapheme rather than past it.
+    fn end_of_line(&self, line_number: usize) -> usize {
+        let off_one = (self.mode != InputMode::Insert) as usize;
+        if let Some(line) = self.get_line(line_number) {
+            let beginning_of_line = self.content.line_to_char(line_number);
+            let eol = beginning_of_line + line.replace("\n", "").chars().count();
+            if off_one == 0 || eol == beginning_of_line {
+                eol
+            } else {
+                prev_boundary(&self.content, eol).max(beginning_of_line)
+            }
+        } else {
+            self.content.len_chars().saturating_sub(1 + off_one)
+        }
+    }
+
+    pub fn move_cursor_up(&mut self, n: usize) {
+        let current_line_number = self.content.char_to_line(self.cursor);
+        let current_line_offset = self.cursor - self.content.line_to_char(current_line_number);
+        let dest_line_number = self.lines_above(current_line_number, n);
+        let dest_cursor =
+            self.content.line_to_char(dest_line_number) + current_line_offset.max(self.last_col);
+        self.move_cursor(dest_cursor.min(self.end_of_line(dest_line_number)));
+    }
+
+    pub fn move_cursor_down(&mut self, n: usize) {
+        let current_line_number = self.content.char_to_line(self.cursor);
+        let current_line_offset = self.cursor - self.content.line_to_char(current_line_number);
+        let dest_line_number = self
+            .content
+            .len_lines()
+            .min(self.lines_below(current_line_number, n));
+        // find the furthest visible line that's non-empty
+        for line_number in (current_line_number..=dest_line_number).rev() {
+            if !self.is_hidden(line_number) && self.get_line(line_number).is_some() {
+                let dest_cursor =
+                    self.content.line_to_char(line_number) + current_line_offset.max(self.last_col);
+                self.move_cursor(dest_cursor.min(self.end_of_line(line_number)));
+                return;
+            }
+        }
+    }
+
+    pub fn move_cursor(&mut self, cursor: usize) {
+        let cursor = cursor.clamp(0, self.content.len_chars().saturating_sub(1));
+        // the cursor rests on the first line of a closed fold, never within
+        let line_number = self.content.char_to_line(cursor);
+        let dest_line_number = self.visible_line(line_number);
+        let cursor = if dest_line_number == line_number {
+            cursor
+        } else {
+            self.content.line_to_char(dest_line_number)
+        };
+        // keep scrolloff lines of context around the cursor when there are some
+        let height = self.height;
+        let margin = self
+            .options
+            .scrolloff(&self.config)
+            .min(height.saturating_sub(1) / 2);
+        if dest_line_number < self.window.start
+            || self.rows(self.window.start..dest_line_number) < margin
+        {
+            self.scroll_to(self.rows_above(dest_line_number, margin));
+        }
+        let dest_rows = self.row_count(dest_line_number);
+        if self.rows(self.window.start..dest_line_number) + dest_rows + margin > height {
+            let last = self.content.len_lines().saturating_sub(1);
+            let last_start = self.rows_above(last, height.saturating_sub(self.row_count(last)));
+            let start = self
+                .rows_above(dest_line_number, height.saturating_sub(dest_rows + margin))
+                .min(last_start)
+                .max(self.rows_above(dest_line_number, height.saturating_sub(dest_rows)));
+            self.scroll_to(start.max(self.window.start));
+        }
+        self.last_col = cursor - self.content.line_to_char(dest_line_number);
+        self.cursor = cursor;
+        self.scroll_columns();
+    }
+
+    /// moves to the given 0-based line and column, clamped to the content
+    pub fn goto(&mut self, line_number: usize, column_number: usize) {
+        let line_number = line_number.min(self.content.len_lines().saturating_sub(1));
+        let bol = self.content.line_to_char(line_number);
+        self.move_cursor((bol + column_number).min(self.end_of_line(line_number)));
+    }
+
+    /// moves to the given 0-based column of the current line
+    pub fn goto_column(&mut self, column_number: usize) {
+        let (_, line_number, _) = self.get_cursor();
+        self.goto(line_number, column_number);
+    }
+
+    /// the cursor's row among the rows of its line, the line's text and its rows
+    fn cursor_row(&self) -> (usize, String, Vec<Range<usize>>) {
+        let (_, line_number, column_number) = self.get_cursor();
+        let rows = self.line_rows(line_number);
+        let line = self.get_line(line_number).unwrap_or_default();
+        (wrap::row_of(&rows, column_number), line, rows)
+    }
+
+    /// moves to the first column in view of the cursor's screen row, `g0`
+    pub fn move_cursor_view_start(&mut self) {
+        let (row, _, rows) = self.cursor_row();
+        self.goto_column(rows[row].start);
+    }
+
+    /// moves to the last column in view of the cursor's screen row, or the line's end when
+    /// shorter, `g$`
+    pub fn move_cursor_view_end(&mut self) {
+        let (row, _, rows) = self.cursor_row();
+        self.goto_column(rows[row].end.saturating_sub(1).max(rows[row].start));
+    }
+
+    /// moves n screen rows down, `gj`, keeping the cursor's x on screen: lines wrapped on
+    /// several rows are moved through a row at a time
+    pub fn move_cursor_row_down(&mut self, n: usize) {
+        if !self.is_wrapped() {
+            return self.move_cursor_down(n);
+        }
+        let (_, mut line_number, column_number) = self.get_cursor();
+        let (mut row, mut line, mut rows) = self.cursor_row();
+        let x = wrap::width_of(&line, rows[row].start..column_number);
+        for _ in 0..n {
+            if row + 1 < rows.len() {
+                row += 1;
+                continue;
+            }
+            let below = self.lines_below(line_number, 1);
+            if below >= self.content.len_lines() || self.get_line(below).is_none() {
+                break;
+            }
+            line_number = below;
+            line = self.get_line(below).unwrap_or_default();
+            rows = self.line_rows(below);
+            row = 0;
+        }
+        self.goto(line_number, wrap::column_at(&line, &rows[row], x));
+    }
+
+    /// moves n screen rows up, `gk`
+    pub fn move_cursor_row_up(&mut self, n: usize) {
+        if !self.is_wrapped() {
+            return self.move_cursor_up(n);
+        }
+        let (_, mut line_number, column_number) = self.get_cursor();
+        let (mut row, mut line, mut rows) = self.cursor_row();
+        let x = wrap::width_of(&line, rows[row].start..column_number);
+        for _ in 0..n {
+            if row > 0 {
+                row -= 1;
+                continue;
+            }
+            if line_number == 0 {
+                break;
+            }
+            line_number = self.lines_above(line_number, 1);
+            line = self.get_line(line_number).unwrap_or_default();
+            rows = self.line_rows(line_number);
+            row = rows.len() - 1;
+        }
+        self.goto(line_number, wrap::column_at(&line, &rows[row], x));
+    }
+
+    /// moves a screenful of columns left, staying on the current line
+    pub fn page_left(&mut self, n: usize) {
+        let (_, _, column_number) = self.get_cursor();
+        let width = self.columns.end - self.columns.start;
+        self.goto_column(column_number.saturating_sub(width * n));
+    }
+
+    /// moves a screenful of columns right, staying on the current line
+    pub fn page_right(&mut self, n: usize) {
+        let (_, _, column_number) = self.get_cursor();
+        let width = self.columns.end - self.columns.start;
+        self.goto_column(column_number + width * n);
+    }
+
+    pub fn page_up(&mut self, n: usize) {
+        self.move_cursor_up((self.height / 2) * n);
+    }
+
+    pub fn page_down(&mut self, n: usize) {
+        self.move_cursor_down((self.height / 2) * n);
+    }
+
+    /// pipes the selection (or the whole buffer) through a formatter command,
+    /// keeping the cursor on the same line and column when possible
+    pub fn format_with(&mut self, command: &str) -> io::Result<()> {
+        if self.readonly {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "Buffer is read-only",
+            ));
+        }
+        let range = self
+            .get_selection_range()
+            .unwrap_or(0..self.content.len_chars());
+        let range = range.start..range.end.min(self.content.len_chars());
+        let input = self.content.slice(range.clone()).to_string();
+        let output = format::run(command, &input, &self.working_dir())?;
+        let (_, line_number, column_number) = self.get_cursor();
+        self.content.remove(range.clone());
+        self.content.insert(range.start, &output);
+        self.remove_selection();
+        self.invalidate_from(self.content.char_to_line(range.start));
+        self.goto(line_number, column_number);
+        Ok(())
+    }
+
+    /// returns the deleted text
+    fn delete_range(&mut self, range: Range<usize>) -> String {
+        if self.readonly {
+            return String::default();
+        }
+        let text = self.content.slice(range.clone()).to_string();
+        self.content.remove(range.clone());
+        let start = self.placeholders_removed(range);
+        let last_line_number = self.content.len_lines().saturating_sub(2);
+        let line_number = self.content.char_to_line(start).min(last_line_number);
+        self.invalidate_from(line_number);
+        self.move_cursor(start);
+        text
+    }
+
+    /// delete up to n lines from the current line, returns them
+    pub fn delete_lines(&mut self, n: usize) -> String {
+        let current_line_number = self.content.char_to_line(self.cursor);
+        let start = self.content.line_to_char(current_line_number);
+        // a closed fold counts as a single line
+        let end_line_number = self
+            .content
+            .len_lines()
+            .min(self.lines_below(current_line_number, n));
+        let end = self.content.line_to_char(end_line_number);
+        let range = self.get_selection_range().unwrap_or(start..end);
+        self.remove_selection();
+        self.delete_range(range)
+    }
+
+    /// delete up to n characters from the current line, returns them
+    pub fn delete_chars(&mut self, n: usize) -> String {
+        if self.content.len_chars() == 0 {
+            return String::default();
+        }
+        let current_line_number = self.content.char_to_line(self.cursor);
+        let last = next_boundary(&self.content, self.end_of_line(current_line_number));
+        let mut end = self.cursor;
+        for _ in 0..n {
+            end = next_boundary(&self.content, end).min(last);
+        }
+        let range = self.get_selection_range().unwrap_or(self.cursor..end);
+        self.remove_selection();
+        self.delete_range(range)
+    }
+
+    /// deletes the grapheme under the cursor, joining the next line at the end of one
+    pub fn delete_char(&mut self) {
+        let end = next_boundary(&self.content, self.cursor);
+        let range = self.get_selection_range().unwrap_or(self.cursor..end);
+        self.remove_selection();
+        self.delete_range(range);
+    }
+
+    pub fn back_delete_char(&mut self) {
+        if self.clear_placeholder() {
+            return;
+        }
+        if self.cursor > 0 {
+            self.move_cursor(prev_boundary(&self.content, self.cursor));
+            self.delete_chars(1);
+        }
+    }
+
+    /// paste given text n times at given position, returns where it was pasted
+    fn paste(&mut self, pos: usize, n: usize, text: &str) -> Range<usize> {
+        if text.is_empty() || self.readonly {
+            return pos..pos;
+        }
+
+        for _ in 0..n {
+            self.content.insert(pos, text);
+        }
+        let line_number = self.content.char_to_line(pos);
+        self.invalidate_from(line_number);
+        if line_number > 0 {
+            self.auto_detect_language();
+        }
+        pos..pos + n * text.chars().count()
+    }
+
+    /// pastes text from the kill ring n times: whole lines under the current line, or else under
+    /// the cursor, after it when on the last character of a line, reindented per paste_reindent
+    pub fn paste_kill(&mut self, n: usize, text: &str, lines: bool) -> Range<usize> {
+        if lines {
+            let line_number = self.content.char_to_line(self.cursor);
+            let pos = self.content.line_to_char(line_number + 1);
+            let text = self.reindented(pos..pos, text, true);
+            return self.paste_lines(n, &text);
+        }
+        let line_number = self.content.char_to_line(self.cursor);
+        let at_eol = self.mode == InputMode::Normal
+            && self.cursor == self.end_of_line(line_number)
+            && matches!(self.content.get_char(self.cursor), Some(c) if c != '\n');
+        let pos = match at_eol {
+            true => next_boundary(&self.content, self.cursor),
+            false => self.cursor,
+        };
+        let text = self.reindented(pos..pos, text, false);
+        self.paste(pos, n, &text)
+    }
+
+    /// text from the kill ring as pasted in place of range, see paste_kill: whole lines take the
+    /// deeper indentation of the non-blank lines around, the lines after the first of other texts
+    /// the indentation of the line they start on
+    pub fn reindented(&self, range: Range<usize>, text: &str, lines: bool) -> String {
+        if !self.config.paste_reindent.get() {
+            return text.to_string();
+        }
+        let indent_of = |n: usize| {
+            let line = self.get_line(n).unwrap_or_default();
+            Some(indentation(&line).to_string()).filter(|_| !line.trim().is_empty())
+        };
+        let indent = match lines {
+            true => {
+                let previous = self.content.char_to_line(range.start.saturating_sub(1));
+                let next = self.content.char_to_line(range.end);
+                let above = (0..=previous).rev().find_map(indent_of);
+                let below = (next..self.content.len_lines()).find_map(indent_of);
+                above
+                    .into_iter()
+                    .chain(below)
+                    .max_by_key(|i| i.chars().count())
+            }
+            false => {
+                let line = self.get_line(self.content.char_to_line(range.start));
+                Some(indentation(&line.unwrap_or_default()).to_string())
+            }
+        };
+        reindent(text, &indent.unwrap_or_default(), !lines)
+    }
+
+    /// paste given text n times under cursor
+    pub fn paste_chars(&mut self, n: usize, text: &str) -> Range<usize> {
+        self.paste(self.cursor, n, text)
+    }
+
+    /// paste given text n times under current line
+    pub fn paste_lines(&mut self, n: usize, text: &str) -> Range<usize> {
+        if self.readonly {
+            return self.cursor..self.cursor;
+        }
+        let line_number = self.content.char_to_line(self.cursor);
+        let mut pos = self.content.line_to_char(line_number + 1);
+        if let Some(line) = self.get_line(line_number) {
+            if !line.ends_with('\n') {
+                self.content.insert(pos, "\n");
+                pos += 1;
+            }
+        }
+        self.paste(pos, n, text)
+    }
+
+    /// text between two char indices, None when out of bounds
+    pub fn get_range(&self, range: Range<usize>) -> Option<String> {
+        self.content.get_slice(range).map(|slice| slice.to_string())
+    }
+
+    /// replaces the text between two char indices, returns where the new text is
+    pub fn replace_range(&mut self, range: Range<usize>, text: &str) -> Range<usize> {
+        if self.readonly || range.end > self.content.len_chars() {
+            return range;
+        }
+        self.content.remove(range.clone());
+        self.content.insert(range.start, text);
+        self.invalidate_from(self.content.char_to_line(range.start));
+        self.move_cursor(range.start);
+        range.start..range.start + text.chars().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    thread_local! {
+        static CONFIG: Rc<Config> = Rc::new(Config::builtin());
+    }
+
+    fn init() -> Rc<Config> {
+        CONFIG.with(Rc::clone)
+    }
+
+    #[test]
+    fn end_of_line() {
+        let config = init();
+        // empty line defaults to first char, even if there's none
+        let buffer = Buffer::new(String::from(""), String::from(""), config.clone());
+        assert_eq!(buffer.end_of_line(0), 0);
+        let buffer = Buffer::new(String::from("\n"), String::from(""), config.clone());
+        assert_eq!(buffer.end_of_line(0), 0);
+        let buffer = Buffer::new(String::from("a\n"), String::from(""), config.clone());
+        assert_eq!(buffer.end_of_line(0), 0);
+        let buffer = Buffer::new(String::from("a\nbb\n"), String::from(""), config.clone());
+        assert_eq!(buffer.end_of_line(1), 3);
+        let buffer = Buffer::new(String::from("a\nbb"), String::from(""), config.clone());
+        assert_eq!(buffer.end_of_line(1), 3);
+        // out of bound returns last pos
+        let buffer = Buffer::new(String::from("a\nbb\n"), String::from(""), config.clone());
+        assert_eq!(buffer.end_of_line(2), 3);
+        let buffer = Buffer::new(String::from("a\nbb\n"), String::from(""), config);
+        assert_eq!(buffer.end_of_line(3), 3);
+    }
+
+    #[test]
+    fn get_line() {
+        let config = init();
+
+        let buffer = Buffer::new(String::from(""), String::from(""), config.clone());
+        assert_eq!(buffer.get_line(0), None);
+
+        let buffer = Buffer::new(String::from("\n"), String::from(""), config.clone());
+        assert_eq!(buffer.get_line(0), Some(String::from("\n")));
+        assert_eq!(buffer.get_line(1), None);
+
+        let buffer = Buffer::new(String::from("a\n\n"), String::from(""), config);
+        assert_eq!(buffer.get_line(0), Some(String::from("a\n")));
+        assert_eq!(buffer.get_line(1), Some(String::from("\n")));
+        assert_eq!(buffer.get_line(2), None);
+    }
+
+    #[test]
+    fn delete_line_out_of_bounds() {
+        let config = init();
+        let mut buffer = Buffer::new(String::from(""), String::from(""), config);
+        buffer.delete_lines(1000);
+        assert_eq!(buffer.get_line(0), None);
+    }
+
+    #[test]
+    fn expand_and_shrink_selection() {
+        let config = init();
+        let mut buffer = Buffer::new(String::from("f(ab, c)\n"), String::from(""), config);
+        buffer.goto(0, 3);
+        assert!(buffer.expand_selection());
+        assert_eq!(buffer.get_selection(), Some(String::from("ab")));
+        assert!(buffer.expand_selection());
+        assert_eq!(buffer.get_selection(), Some(String::from("ab, c")));
+        assert!(buffer.shrink_selection());
+        assert_eq!(buffer.get_selection(), Some(String::from("ab")));
+        assert!(buffer.shrink_selection());
+        assert_eq!(buffer.get_selection(), None);
+        assert_eq!(buffer.get_cursor().0, 3);
+        assert!(!buffer.shrink_selection());
+        buffer.expand_selection();
+        buffer.select_lines();
+        assert!(!buffer.shrink_selection());
+    }
+
+    #[test]
+    fn snapshot_restore() {
+        let config = init();
+        let mut buffer = Buffer::new(String::from("a\nb\n"), String::from(""), config);
+        let index = buffer.snapshot();
+        buffer.delete_lines(1);
+        assert_eq!(buffer.get_line(0), Some(String::from("b\n")));
+        assert!(buffer.diff_snapshot(index).unwrap().contains("-a\n"));
+        assert!(buffer.restore_snapshot(index));
+        assert_eq!(buffer.get_line(0), Some(String::from("a\n")));
+        assert!(!buffer.restore_snapshot(index + 1));
+    }
+
+    #[test]
+    fn undo_redo() {
+        let config = init();
+        let mut buffer = Buffer::new(String::from("a\nb\n"), String::from(""), config);
+        assert_eq!(buffer.undo(1), 0);
+        buffer.delete_lines(1);
+        buffer.checkpoint();
+        buffer.insert_char('c');
+        assert_eq!(buffer.undo(1), 1);
+        assert_eq!(buffer.get_text(), "b\n");
+        assert_eq!(buffer.undo(5), 1);
+        assert_eq!(buffer.get_text(), "a\nb\n");
+        assert_eq!(buffer.redo(1), 1);
+        buffer.insert_char('d');
+        assert_eq!(buffer.undo_tree().len(), 4);
+        assert!(buffer.diff_undo_state(2).unwrap().contains("+cb\n"));
+        assert!(buffer.undo_jump(2));
+        assert_eq!(buffer.get_text(), "cb\n");
+        assert_eq!(buffer.undo(1), 1);
+        assert_eq!(buffer.redo(1), 1);
+        assert_eq!(buffer.get_text(), "cb\n");
+        assert!(!buffer.undo_jump(4));
+    }
+
+    #[test]
+    fn goto() {
+        let config = init();
+        let mut buffer = Buffer::new(String::from("a\nbcd\n"), String::from(""), config);
+        buffer.goto(1, 1);
+        assert_eq!(buffer.get_cursor(), (3, 1, 1));
+        buffer.goto(1, 100);
+        assert_eq!(buffer.get_cursor(), (4, 1, 2));
+        buffer.goto(100, 0);
+        assert_eq!(buffer.get_cursor().1, 1);
+    }
+
+    #[test]
+    fn format_with() {
+        let config = init();
+        let mut buffer = Buffer::new(String::from("b\na\nc\n"), String::from(""), config);
+        buffer.goto(1, 0);
+        buffer.format_with("sort").unwrap();
+        assert_eq!(buffer.get_line(0), Some(String::from("a\n")));
+        assert_eq!(buffer.get_cursor().1, 1);
+        buffer.select_lines();
+        buffer.format_with("tr a-z A-Z").unwrap();
+        assert_eq!(buffer.get_line(1), Some(String::from("B\n")));
+        assert_eq!(buffer.get_line(2), Some(String::from("c\n")));
+        assert!(buffer.format_with("false").is_err());
+    }
+
+    #[test]
+    fn complete_word() {
+        let config = init();
+        let mut buffer = Buffer::new(String::from("a.fo\n"), String::from(""), config);
+        buffer.insert_mode();
+        buffer.move_cursor_eol();
+        assert_eq!(buffer.word_before_cursor(), "fo");
+        buffer.complete_word("foobar");
+        assert_eq!(buffer.get_line(0), Some(String::from("a.foobar\n")));
+        assert_eq!(buffer.get_cursor(), (8, 0, 8));
+    }
+
+    #[test]
+    fn expand_snippet() {
+        let config = Rc::new(Config::builtin());
+        let mut snippets = HashMap::new();
+        snippets.insert(
+            String::from("for"),
+            String::from("for ${1:i} in $2 {\n\t$1\n}"),
+        );
+        config
+            .snippets
+            .borrow_mut()
+            .insert(String::from("Plain Text"), snippets);
+        let mut buffer = Buffer::new(String::from("  for\n"), String::from(""), config);
+        buffer.insert_mode();
+        buffer.move_cursor_eol();
+        assert!(buffer.expand_snippet());
+        assert_eq!(buffer.get_cursor(), (7, 0, 7));
+        buffer.type_char('n');
+        buffer.type_char('x');
+        assert!(buffer.next_placeholder());
+        buffer.type_char('v');
+        assert_eq!(buffer.get_text(), "  for nx in v {\n  \tnx\n  }\n");
+        assert!(buffer.next_placeholder());
+        assert_eq!(buffer.get_cursor().1, 2);
+        assert!(!buffer.next_placeholder());
+    }
+
+    #[test]
+    #[cfg(feature = "bundled-assets")]
+    fn auto_detect_language() {
+        let config = init();
+        let language = |buffer: &Buffer| {
+            let cached = buffer.highlighter.as_ref();
+            cached.map(|cached| cached.syntax.name.clone())
+        };
+        let mut buffer = Buffer::new(String::default(), String::from(""), config);
+        buffer.paste_chars(1, "fn main() {\n    let mut x = 1;\n}\n");
+        assert_eq!(language(&buffer).as_deref(), Some("Rust"));
+        buffer.goto(0, 0);
+        buffer.paste_chars(1, "#!/usr/bin/env python3\n");
+        assert_eq!(language(&buffer).as_deref(), Some("Python"));
+        buffer.set_language("Markdown");
+        buffer.paste_chars(1, "{}\n");
+        assert_eq!(language(&buffer).as_deref(), Some("Markdown"));
+    }
+
+    #[test]
+    #[cfg(feature = "bundled-assets")]
+    fn max_line_length_by_language() {
+        let config = Rc::new(Config::builtin());
+        let mut buffer = Buffer::new(String::default(), String::from(""), config.clone());
+        assert_eq!(buffer.max_line_length(), None);
+        let mut lengths = config.max_line_length.borrow_mut();
+        lengths.insert(String::from("*"), 80);
+        lengths.insert(String::from("Rust"), 100);
+        drop(lengths);
+        assert_eq!(buffer.max_line_length(), Some(80));
+        buffer.set_language("Rust");
+        assert_eq!(buffer.max_line_length(), Some(100));
+    }
+
+    #[test]
+    fn pick_hunk() {
+        let config = init();
+        let text = "a\n<<<<<<< mine\nb\n=======\nB\n>>>>>>> disk\nc\n";
+        let mut buffer = Buffer::new(String::from(text), String::from(""), config);
+        assert!(!buffer.pick_hunk(true));
+        buffer.goto(3, 0);
+        assert!(buffer.pick_hunk(false));
+        assert_eq!(buffer.get_text(), "a\nB\nc\n");
+        assert_eq!(buffer.get_cursor().1, 1);
+    }
+
+    #[test]
+    fn rename_and_delete_backend_file() {
+        let config = init();
+        let dir = std::env::temp_dir().join(format!("ted-rename-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (old, new) = (dir.join("old.txt"), dir.join("new.txt"));
+        std::fs::write(&old, "a\n").unwrap();
+        let nested = dir.join("a/b/new.txt");
+        let new_file = Buffer::from_file(nested.to_str().unwrap(), config.clone()).unwrap();
+        assert_eq!(new_file.missing_dir(), Some(dir.join("a/b").as_path()));
+        let mut buffer = Buffer::from_file(old.to_str().unwrap(), config).unwrap();
+        assert_eq!(buffer.missing_dir(), None);
+        assert!(!buffer.is_dirty());
+        buffer.insert_char('b');
+        assert!(buffer.is_dirty());
+        buffer.overwrite_backend_file().unwrap();
+        assert!(!buffer.is_dirty());
+        buffer.rename_backend_file(new.to_str().unwrap()).unwrap();
+        assert!(!old.exists() && new.exists());
+        assert_eq!(
+            (buffer.name.as_str(), buffer.get_path()),
+            ("new", new.to_str())
+        );
+        buffer.delete_backend_file().unwrap();
+        assert!(!new.exists());
+        assert_eq!(buffer.get_path(), None);
+        assert!(buffer.delete_backend_file().is_err());
+        std::fs::remove_dir(dir).unwrap();
+    }
+
+    #[test]
+    fn save_copies() {
+        let config = init();
+        let dir = std::env::temp_dir().join(format!("ted-copies-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("deploy")).unwrap();
+        let destinations = vec![
+            format!("{}/deploy/", dir.display()),
+            format!("{}/missing/copy.txt", dir.display()),
+        ];
+        let pattern = format!("*/ted-copies-{}/*.txt", std::process::id());
+        config
+            .save_copies
+            .borrow_mut()
+            .insert(pattern, destinations);
+        let path = dir.join("main.txt");
+        let mut buffer = Buffer::from_file(path.to_str().unwrap(), config).unwrap();
+        assert_eq!(buffer.copies.len(), 2);
+        buffer.insert_char('a');
+        buffer.overwrite_backend_file().unwrap();
+        let failed = buffer.write_copies().unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, dir.join("missing/copy.txt"));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("deploy/main.txt")).unwrap(),
+            "a"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn tee_copies() {
+        let config = Rc::new(Config::builtin());
+        let dir = std::env::temp_dir().join(format!("ted-tee-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pattern = format!("*/ted-tee-{}/*.txt", std::process::id());
+        let deploy = dir.join("deploy.txt");
+        config
+            .save_copies
+            .borrow_mut()
+            .insert(pattern, vec![deploy.display().to_string()]);
+        let path = dir.join("main.txt");
+        let mut buffer = Buffer::from_file(path.to_str().unwrap(), config).unwrap();
+        // tee adds to the configured copies, without them twice
+        buffer.tee(vec![dir.join("b.txt"), deploy.clone()]);
+        buffer.tee(vec![dir.join("a.txt")]);
+        assert_eq!(
+            buffer.copies,
+            vec![dir.join("a.txt"), dir.join("b.txt"), deploy.clone()]
+        );
+        buffer.tee(vec![]);
+        assert_eq!(buffer.copies, vec![deploy]);
+        buffer.insert_char('a');
+        buffer.append_to(&dir.join("log")).unwrap();
+        buffer.append_to(&dir.join("log")).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("log")).unwrap(), "aa");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn scrolloff() {
+        let config = Rc::new(Config::builtin());
+        config.scrolloff.set(2);
+        let text = "x\n".repeat(20);
+        let mut buffer = Buffer::new(text, String::from(""), config);
+        buffer.resize_window(6);
+        buffer.move_cursor_down(3);
+        assert_eq!(buffer.get_window(), &(0..6));
+        buffer.move_cursor_down(1);
+        assert_eq!(buffer.get_window(), &(1..7));
+        buffer.move_cursor_down(100);
+        assert_eq!(buffer.get_window(), &(15..21));
+        buffer.move_cursor_up(2);
+        assert_eq!(buffer.get_window(), &(15..21));
+        buffer.move_cursor_up(1);
+        assert_eq!(buffer.get_window(), &(14..20));
+        buffer.scroll_cursor_center();
+        assert_eq!(buffer.get_window(), &(13..19));
+        buffer.scroll_cursor_top();
+        assert_eq!(buffer.get_window(), &(14..20));
+        buffer.move_cursor_up(1);
+        buffer.scroll_cursor_bottom();
+        assert_eq!(buffer.get_window(), &(12..18));
+    }
+
+    #[test]
+    fn line_numbers() {
+        let config = Rc::new(Config::builtin());
+        let text = "x\n".repeat(2000);
+        let mut buffer = Buffer::new(text, String::from(""), config.clone());
+        assert_eq!(buffer.gutter_width(), 0);
+        config.line_numbers.set(true);
+        assert_eq!(buffer.gutter_width(), 5);
+        buffer.move_cursor_down(4);
+        assert_eq!(buffer.line_label(1), "2");
+        config.relative_numbers.set(true);
+        assert_eq!(buffer.line_label(1), "3");
+        assert_eq!(buffer.line_label(4), "5");
+        config.line_numbers.set(false);
+        assert_eq!(buffer.line_label(4), "0");
+        assert_eq!(buffer.line_label(6), "2");
+    }
+
+    #[test]
+    fn scroll_lines() {
+        let config = Rc::new(Config::builtin());
+        config.scrolloff.set(1);
+        let text = "x\n".repeat(20);
+        let mut buffer = Buffer::new(text, String::from(""), config);
+        buffer.resize_window(6);
+        buffer.move_cursor_down(2);
+        buffer.scroll_lines(1);
+        assert_eq!((buffer.get_window(), buffer.get_cursor().1), (&(1..7), 2));
+        buffer.scroll_lines(2);
+        assert_eq!((buffer.get_window(), buffer.get_cursor().1), (&(3..9), 4));
+        buffer.scroll_lines(-3);
+        assert_eq!((buffer.get_window(), buffer.get_cursor().1), (&(0..6), 4));
+        buffer.scroll_half_pages(2);
+        assert_eq!((buffer.get_window(), buffer.get_cursor().1), (&(6..12), 10));
+        buffer.scroll_half_pages(-1);
+        assert_eq!((buffer.get_window(), buffer.get_cursor().1), (&(3..9), 7));
+    }
+
+    #[test]
+    fn folds() {
+        let config = Rc::new(Config::builtin());
+        config.scrolloff.set(0);
+        let text = String::from("a\n  b\n  c\n  d\ne\nf\n");
+        let mut buffer = Buffer::new(text, String::from(""), config);
+        buffer.resize_window(4);
+        buffer.goto(2, 2);
+        buffer.close_fold();
+        assert_eq!(buffer.get_cursor().1, 0);
+        assert_eq!(buffer.get_window(), &(0..7));
+        assert_eq!(buffer.visible_line_numbers(), vec![0, 4, 5, 6]);
+        buffer.move_cursor_down(1);
+        assert_eq!(buffer.get_cursor().1, 4);
+        assert_eq!(buffer.screen_row(4), Some(1));
+        buffer.move_cursor_up(1);
+        assert_eq!(buffer.lines_below(0, 1), 4);
+        buffer.toggle_fold();
+        assert_eq!(buffer.get_window(), &(0..4));
+        buffer.close_all_folds();
+        buffer.delete_lines(1);
+        assert_eq!(buffer.get_line(0), Some(String::from("e\n")));
+    }
+
+    #[test]
+    fn folds_follow_edits() {
+        let config = Rc::new(Config::builtin());
+        let text = String::from("x\na\n  b\n  c\ne\n");
+        let mut buffer = Buffer::new(text, String::from(""), config);
+        let hidden = |buffer: &Buffer| {
+            let lines = 0..buffer.content.len_lines();
+            lines.filter(|n| buffer.is_hidden(*n)).collect::<Vec<_>>()
+        };
+        buffer.goto(2, 0);
+        buffer.close_fold();
+        assert_eq!(hidden(&buffer), vec![2, 3]);
+        buffer.goto(0, 1);
+        buffer.insert_newline();
+        assert_eq!(hidden(&buffer), vec![3, 4]);
+        buffer.goto(0, 0);
+        buffer.delete_lines(1);
+        assert_eq!(hidden(&buffer), vec![2, 3]);
+        // an edit within the fold opens it
+        buffer.goto(2, 0);
+        buffer.delete_lines(1);
+        assert_eq!(hidden(&buffer), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn page_columns() {
+        let config = init();
+        let mut buffer = Buffer::new("x".repeat(25), String::from(""), config);
+        buffer.resize_columns(10);
+        buffer.page_right(1);
+        assert_eq!(buffer.get_cursor().2, 10);
+        assert_eq!(buffer.get_columns(), &(1..11));
+        buffer.page_right(2);
+        assert_eq!(buffer.get_cursor().2, 24);
+        assert_eq!(buffer.get_columns(), &(15..25));
+        buffer.page_left(1);
+        buffer.goto_column(3);
+        assert_eq!(buffer.get_columns(), &(3..13));
+    }
+
+    #[test]
+    fn auto_indent_and_pairs() {
+        let config = Rc::new(Config::builtin());
+        config.auto_pairs.set(true);
+        let mut buffer = Buffer::new(String::from("  a\n"), String::from(""), config.clone());
+        buffer.insert_mode();
+        buffer.move_cursor_eol();
+        buffer.insert_newline();
+        assert_eq!(buffer.get_line(1), Some(String::from("  \n")));
+        buffer.type_char('(');
+        buffer.type_char(')');
+        buffer.type_char('x');
+        assert_eq!(buffer.get_line(1), Some(String::from("  ()x\n")));
+        for c in "don't (\"a".chars() {
+            buffer.type_char(c);
+        }
+        buffer.type_char('"');
+        buffer.type_char(')');
+        assert_eq!(
+            buffer.get_line(1),
+            Some(String::from("  ()xdon't (\"a\")\n"))
+        );
+        // only the closers inserted by typing an opening are typed over
+        buffer.move_cursor(buffer.get_cursor().0 - 1);
+        buffer.type_char(')');
+        // nothing is paired before a word
+        buffer.move_cursor(buffer.get_cursor().0 - 3);
+        buffer.type_char('[');
+        assert_eq!(
+            buffer.get_line(1),
+            Some(String::from("  ()xdon't (\"[a\"))\n"))
+        );
+
+        config.paste_mode.set(true);
+        buffer.move_cursor_eol();
+        buffer.insert_newline();
+        buffer.type_char('(');
+        assert_eq!(buffer.get_line(2), Some(String::from("(\n")));
+    }
+
+    #[test]
+    fn delete_char_out_of_bounds() {
+        let config = init();
+        let mut buffer = Buffer::new(String::from(""), String::from(""), config);
+        buffer.delete_chars(1000);
+        buffer.delete_char();
+    }
+
+    #[test]
+    fn move_word() {
+        let config = init();
+        let mut buffer = Buffer::new(
+            String::from("foo.bar(baz)\n  qux"),
+            String::from(""),
+            config,
+        );
+        buffer.move_word_forward(1);
+        assert_eq!(buffer.get_cursor().0, 4);
+        buffer.move_word_forward(2);
+        assert_eq!(buffer.get_cursor(), (15, 1, 2));
+        buffer.move_word_forward(1);
+        assert_eq!(buffer.get_cursor().0, 17);
+        buffer.move_word_backward(2);
+        assert_eq!(buffer.get_cursor().0, 8);
+        buffer.move_word_backward(10);
+        assert_eq!(buffer.get_cursor().0, 0);
+    }
+
+    #[test]
+    fn paste_and_replace() {
+        let config = init();
+        let mut buffer = Buffer::new(String::from("ab\n"), String::from(""), config);
+        buffer.move_cursor(1);
+        let range = buffer.paste_chars(2, "xy");
+        assert_eq!(range, 1..5);
+        assert_eq!(buffer.get_range(range.clone()).unwrap(), "xyxy");
+        assert_eq!(buffer.replace_range(range, "é"), 1..2);
+        assert_eq!(buffer.get_text(), "aéb\n");
+        assert_eq!(buffer.delete_lines(1), "aéb\n");
+        assert_eq!(buffer.get_range(0..10), None);
+    }
+
+    #[test]
+    fn preview_and_replace() {
+        let config = Rc::new(Config::builtin());
+        let mut buffer = Buffer::new(String::from("ab ab\nab\n"), String::from(""), config);
+        // only the lines in view are previewed
+        assert_eq!(buffer.count_replacements("ab").unwrap(), 3);
+        assert_eq!(buffer.preview_replace("ab", "x").unwrap(), 2);
+        buffer.resize_window(2);
+        assert_eq!(buffer.preview_replace("ab", "x").unwrap(), 3);
+        assert_eq!(buffer.get_replacements()[2], (6..8, String::from("x")));
+        buffer.move_cursor(3);
+        buffer.select_chars();
+        buffer.move_cursor_down(1);
+        assert_eq!(buffer.replace_matches("ab", "x").unwrap(), 2);
+        assert_eq!(buffer.get_text(), "ab x\nx\n");
+        assert!(buffer.get_replacements().is_empty());
+    }
+
+    #[test]
+    fn paste_reindented() {
+        let config = Rc::new(Config::builtin());
+        let text = "fn f() {\n    a();\n}\n";
+        let mut buffer = Buffer::new(String::from(text), String::from(""), config.clone());
+        let range = buffer.paste_kill(1, "  if x {\n    b();\n  }\n", true);
+        assert_eq!(
+            buffer.get_range(range).unwrap(),
+            "    if x {\n      b();\n    }\n"
+        );
+        buffer.move_cursor_down(4);
+        buffer.move_cursor_eol();
+        buffer.paste_kill(1, "x", false);
+        assert_eq!(buffer.get_line(4), Some(String::from("    a();x\n")));
+        buffer.move_cursor(0);
+        let range = buffer.paste_kill(1, "g(\n\n        y)", false);
+        assert_eq!(buffer.get_range(range).unwrap(), "g(\n\ny)");
+        config.paste_reindent.set(false);
+        buffer.move_cursor_down(1);
+        let range = buffer.paste_kill(1, "  z\n", true);
+        assert_eq!(buffer.get_range(range).unwrap(), "  z\n");
+    }
+
+    #[test]
+    fn delete_char() {
+        let config = init();
+        let mut buffer = Buffer::new(String::from("ab\ncd"), String::from(""), config);
+        buffer.insert_mode();
+        buffer.goto(0, 2);
+        buffer.delete_char();
+        assert_eq!(buffer.get_text(), "abcd");
+        buffer.delete_char();
+        assert_eq!(buffer.get_text(), "abd");
+        buffer.readonly = true;
+        buffer.delete_char();
+        assert_eq!(buffer.get_text(), "abd");
+    }
+
+    #[test]
+    fn search() {
+        let config = init();
+        let mut buffer = Buffer::new(String::from("ab\nb ab\n"), String::from(""), config);
+        assert_eq!(buffer.search("ab").unwrap(), Some((1, 2)));
+        assert_eq!(buffer.search_next(true), Some((2, 2)));
+        assert_eq!(buffer.get_cursor(), (5, 1, 2));
+        assert_eq!(buffer.search_next(true), Some((1, 2)));
+        assert_eq!(buffer.search_next(false), Some((2, 2)));
+        buffer.move_cursor(0);
+        buffer.insert_char('a');
+        buffer.insert_char('b');
+        assert_eq!(buffer.get_search().map(|s| s.matches.len()), Some(2));
+        assert!(!buffer.refresh_search(Duration::from_secs(60)));
+        assert!(buffer.refresh_search(Duration::ZERO));
+        assert_eq!(buffer.get_search().map(|s| s.matches.len()), Some(3));
+        buffer.clear_search();
+        assert_eq!(buffer.search_next(true), None);
+        assert_eq!(buffer.search("x").unwrap(), None);
+        buffer.set_search("b").unwrap();
+        assert_eq!(buffer.get_cursor().0, 2);
+        assert_eq!(buffer.search_next(false), Some((1, 4)));
+    }
+
+    #[test]
+    fn scroll_percentage() {
+        let config = init();
+        let mut buffer = Buffer::new(String::from("a\nb"), String::from(""), config.clone());
+        buffer.resize_window(10);
+        assert_eq!(buffer.scroll_percentage(), "All");
+        let mut buffer = Buffer::new("a\n".repeat(100), String::from(""), config);
+        buffer.resize_window(10);
+        assert_eq!(buffer.scroll_percentage(), "Top");
+        buffer.scroll_lines(45);
+        assert_eq!(buffer.scroll_percentage(), "49%");
+        buffer.scroll_lines(1000);
+        assert_eq!(buffer.scroll_percentage(), "Bot");
+    }
+
+    #[test]
+    fn resize() {
+        let config = Rc::new(Config::builtin());
+        config.scrolloff.set(0);
+        let mut buffer = Buffer::new("x\n".repeat(20), String::from(""), config.clone());
+        buffer.resize_window(10);
+        buffer.goto(15, 0);
+        assert_eq!(buffer.get_window(), &(6..16));
+        buffer.resize_window(5);
+        assert_eq!(buffer.get_window(), &(11..16));
+        buffer.resize_window(10);
+        assert_eq!(buffer.get_window(), &(11..21));
+        buffer.resize_window(30);
+        assert_eq!(buffer.get_window(), &(0..30));
+        buffer.resize_window(2);
+        assert_eq!(buffer.get_window(), &(14..16));
+        assert_eq!(buffer.get_cursor().1, 15);
+        let mut buffer = Buffer::new("a".repeat(50), String::from(""), config);
+        buffer.resize_columns(10);
+        buffer.goto(0, 30);
+        assert_eq!(buffer.get_columns(), &(21..31));
+        buffer.resize_columns(5);
+        assert_eq!(buffer.get_columns(), &(26..31));
+        buffer.resize_columns(20);
+        assert_eq!(buffer.get_columns(), &(11..31));
+        buffer.resize_columns(40);
+        assert_eq!(buffer.get_columns(), &(0..40));
+        buffer.move_cursor_view_end();
+        assert_eq!(buffer.get_cursor().2, 39);
+        buffer.resize_columns(10);
+        buffer.move_cursor_view_start();
+        assert_eq!(buffer.get_cursor().2, 30);
+    }
+
+    #[test]
+    fn soft_wrap() {
+        let config = Rc::new(Config::builtin());
+        config.soft_wrap.set(true);
+        let text = "one two three four five\nsix\n";
+        let mut buffer = Buffer::new(text.to_string(), String::default(), config);
+        buffer.resize_columns(8);
+        buffer.resize_window(10);
+        assert_eq!(buffer.line_rows(0), vec![0..8, 8..14, 14..19, 19..23]);
+        assert_eq!(buffer.screen_rows()[4], (1, 0..3));
+        assert_eq!(buffer.screen_position(0, 13), Some((1, 5)));
+        assert_eq!(buffer.screen_position(1, 2), Some((4, 2)));
+        // gj and gk move a row at a time, keeping the cursor's x
+        buffer.goto(0, 5);
+        buffer.move_cursor_row_down(1);
+        assert_eq!(buffer.get_cursor().2, 13);
+        buffer.move_cursor_row_down(2);
+        assert_eq!(buffer.get_cursor().2, 22);
+        buffer.move_cursor_row_down(1);
+        assert_eq!(buffer.get_cursor(), (26, 1, 2));
+        buffer.move_cursor_row_up(1);
+        assert_eq!(buffer.get_cursor().2, 21);
+        buffer.move_cursor_view_start();
+        assert_eq!(buffer.get_cursor().2, 19);
+        buffer.move_cursor_view_end();
+        assert_eq!(buffer.get_cursor().2, 22);
+        buffer.move_cursor_row_up(3);
+        assert_eq!(buffer.get_cursor().2, 3);
+        buffer.move_cursor_row_up(1);
+        assert_eq!(buffer.get_cursor().2, 3);
+        // the view scrolls by rows
+        buffer.resize_window(3);
+        buffer.goto(1, 0);
+        assert_eq!(buffer.get_window(), &(1..4));
+        buffer.goto(0, 0);
+        assert_eq!(buffer.get_window(), &(0..1));
+    }
+
+    #[test]
+    fn title() {
+        let config = init();
+        let mut buffer = Buffer::new(String::default(), String::from("notes"), config);
+        assert_eq!(buffer.title("ted — {name}{dirty}"), "ted — notes");
+        buffer.insert_char('a');
+        assert_eq!(buffer.title("{path}{dirty} - ted"), "notes [+] - ted");
+    }
+
+    #[test]
+    fn selection_spans() {
+        let config = init();
+        let mut buffer = Buffer::new(String::from("éa\nbcd\nef\n"), String::from(""), config);
+        buffer.resize_window(10);
+        buffer.resize_columns(80);
+        assert_eq!(buffer.get_selection_spans(), None);
+        buffer.move_cursor(1);
+        buffer.select_chars();
+        buffer.move_cursor(5);
+        assert_eq!(
+            buffer.get_selection_spans(),
+            Some(vec![(0, 1..3), (1, 0..3)])
+        );
+        buffer.resize_columns(2);
+        buffer.move_cursor(8);
+        assert_eq!(buffer.get_columns(), &(1..3));
+        assert_eq!(
+            buffer.get_selection_spans(),
+            Some(vec![(0, 0..2), (1, 0..2), (2, 0..1)])
+        );
+    }
+
+    #[test]
+    fn edit_graphemes() {
+        let config = init();
+        let text = "a👍🏽e\u{301}👨\u{200d}👩\u{200d}👧\n";
+        let mut buffer = Buffer::new(String::from(text), String::from(""), config);
+        buffer.move_cursor_right(2);
+        assert_eq!(buffer.get_cursor().0, 3);
+        buffer.move_cursor_right(5);
+        assert_eq!(buffer.get_cursor().0, 5);
+        buffer.move_cursor_left(1);
+        assert_eq!(buffer.get_cursor().0, 3);
+        buffer.select_chars();
+        assert_eq!(buffer.get_selection().as_deref(), Some("e\u{301}"));
+        buffer.remove_selection();
+        buffer.delete_chars(1);
+        assert_eq!(buffer.content.to_string(), "a👍🏽👨\u{200d}👩\u{200d}👧\n");
+        buffer.insert_mode();
+        buffer.back_delete_char();
+        assert_eq!(buffer.content.to_string(), "a👨\u{200d}👩\u{200d}👧\n");
+        buffer.move_cursor_right(1);
+        buffer.back_delete_char();
+        assert_eq!(buffer.content.to_string(), "a\n");
+    }
+}
@@ -0,0 +1,183 @@
+use crate::buffer::Buffer;
+use crate::command::Commands;
+use crate::keymap::Keymap;
+use crate::path;
+use crate::Config;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::rc::Rc;
+
+pub struct Buffers {
+    buffers: VecDeque<Buffer>,
+}
+
+impl Buffers {
+    /// singleton of the home buffer
+    pub fn home<T>(config: Rc<Config>, commands: &Commands<T>, keymap: &Keymap) -> Self {
+        Self {
+            buffers: VecDeque::from(vec![Buffer::home(config, commands, keymap)]),
+        }
+    }
+
+    /// lists the commands and chords as bound now in the home buffer
+    pub fn refresh_help(&mut self, text: &str) {
+        for buffer in self.buffers.iter_mut() {
+            buffer.refresh_help(text);
+        }
+    }
+
+    pub fn focused(&self) -> &Buffer {
+        self.buffers.front().unwrap()
+    }
+
+    pub fn focused_mut(&mut self) -> &mut Buffer {
+        self.buffers.front_mut().unwrap()
+    }
+
+    pub fn cycle_prev(&mut self) {
+        if let Some(buffer) = self.buffers.pop_front() {
+            self.buffers.push_back(buffer);
+        }
+    }
+
+    pub fn cycle_next(&mut self) {
+        if let Some(buffer) = self.buffers.pop_back() {
+            self.buffers.push_front(buffer);
+        }
+    }
+
+    /// brings the buffer at given index (as in names()) to the front
+    pub fn focus(&mut self, index: usize) {
+        if index < self.buffers.len() {
+            self.buffers.rotate_left(index);
+        }
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.buffers
+            .iter()
+            .map(|buffer| buffer.name.clone())
+            .collect()
+    }
+
+    pub fn position_by_name(&self, name: &str) -> Option<usize> {
+        self.buffers.iter().position(|buffer| buffer.name == name)
+    }
+
+    /// contents of every buffer, the focused one first
+    pub fn texts(&self) -> Vec<String> {
+        self.buffers.iter().map(Buffer::get_text).collect()
+    }
+
+    /// (absolute path, or name without a backend file, text) of the buffers with unsaved
+    /// changes
+    pub fn dirty(&self) -> Vec<(String, String)> {
+        self.buffers
+            .iter()
+            .filter(|buffer| buffer.is_dirty())
+            .map(|buffer| {
+                let name = match buffer.get_path() {
+                    Some(path) => Path::new(path)
+                        .canonicalize()
+                        .map(|path| path.to_string_lossy().to_string())
+                        .unwrap_or_else(|_| path.to_string()),
+                    None => buffer.name.clone(),
+                };
+                (name, buffer.get_text())
+            })
+            .collect()
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<&Buffer> {
+        self.buffers.iter().find(|buffer| buffer.name == name)
+    }
+
+    /// index (as in names()) of the buffer backed by the given file
+    pub fn position_by_path(&self, path: &Path) -> Option<usize> {
+        let path = path.canonicalize().ok()?;
+        self.buffers.iter().position(|buffer| {
+            buffer
+                .get_path()
+                .and_then(|p| Path::new(p).canonicalize().ok())
+                .as_ref()
+                == Some(&path)
+        })
+    }
+
+    pub fn position_by_id(&self, id: usize) -> Option<usize> {
+        self.buffers.iter().position(|buffer| buffer.id() == id)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Buffer> {
+        self.buffers.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Buffer> {
+        self.buffers.get_mut(index)
+    }
+
+    pub fn new_buffer(&mut self, buffer: Buffer) {
+        self.buffers.push_front(buffer);
+        self.disambiguate();
+    }
+
+    /// names buffers after their file's stem, with parent directories when several files share
+    /// it, except those renamed
+    pub fn disambiguate(&mut self) {
+        let paths: Vec<&str> = self
+            .buffers
+            .iter()
+            .filter(|buffer| !buffer.is_renamed())
+            .filter_map(Buffer::get_path)
+            .collect();
+        let names = path::unique_names(&paths);
+        let files = self
+            .buffers
+            .iter_mut()
+            .filter(|buffer| buffer.get_path().is_some() && !buffer.is_renamed());
+        for (buffer, name) in files.zip(names) {
+            buffer.name = name;
+        }
+    }
+
+    /// name and path of each buffer, as in names(), for pickers
+    pub fn labels(&self) -> Vec<String> {
+        self.buffers
+            .iter()
+            .map(|buffer| match buffer.get_path() {
+                Some(path) => format!("{}  {}", buffer.name, path),
+                None => buffer.name.clone(),
+            })
+            .collect()
+    }
+
+    /// fits every buffer to the view, not only the focused one drawn next
+    pub fn resize(&mut self, height: usize, width: usize) {
+        for buffer in self.buffers.iter_mut() {
+            buffer.resize_window(height);
+            let gutter = buffer.gutter_width();
+            buffer.resize_columns(width.saturating_sub(gutter));
+        }
+    }
+
+    pub fn set_config(&mut self, config: Rc<Config>) {
+        for buffer in self.buffers.iter_mut() {
+            buffer.set_config(config.clone());
+        }
+    }
+
+    pub fn set_theme(&mut self, name: &str) {
+        for buffer in self.buffers.iter_mut() {
+            buffer.set_theme(name);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// never true, the home buffer at least is open
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+}
@@ -0,0 +1,168 @@
+use crate::Config;
+use ropey::Rope;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::rc::Rc;
+use syntect::{highlighting::*, parsing::*};
+
+#[cfg(debug_assertions)]
+const STEP: usize = 100;
+#[cfg(not(debug_assertions))]
+const STEP: usize = 1000;
+
+type State = (ParseState, HighlightState);
+
+type Line = (String, Vec<(Style, Range<usize>)>);
+
+pub struct CachedHighlighter {
+    pub syntax: SyntaxReference,
+    pub theme: Theme,
+    highlighted_lines: Vec<Line>,
+    config: Rc<Config>,
+    /// (line_number => states) before parsing the line
+    cache: BTreeMap<usize, State>,
+    /// state before parsing the line after the highlighted ones, to resume from
+    frontier: Option<(usize, State)>,
+}
+
+impl CachedHighlighter {
+    pub fn new(syntax: SyntaxReference, theme: Theme, config: Rc<Config>) -> Self {
+        CachedHighlighter {
+            syntax,
+            config,
+            theme,
+            highlighted_lines: Vec::default(),
+            cache: BTreeMap::default(),
+            frontier: None,
+        }
+    }
+
+    /// returns (line_number, state), from where the last highlighting stopped if still valid
+    fn latest_state(&mut self) -> (usize, State) {
+        if let Some(frontier) = self.frontier.take() {
+            return frontier;
+        }
+        if let Some((&k, state)) = self.cache.iter().next_back() {
+            return (k, state.clone());
+        }
+        let highlighter = Highlighter::new(&self.theme);
+        let parse_state = ParseState::new(&self.syntax);
+        let highlight_state = HighlightState::new(&highlighter, ScopeStack::new());
+        let state = (parse_state, highlight_state);
+        self.cache.insert(0, state.clone());
+        (0, state)
+    }
+
+    /// must be called when content changes
+    pub fn invalidate_from(&mut self, line_number: usize) {
+        self.highlighted_lines.truncate(line_number);
+        self.cache.retain(|k, _| k < &line_number);
+        if matches!(self.frontier, Some((k, _)) if k > line_number) {
+            self.frontier = None;
+        }
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.invalidate_from(0);
+    }
+
+    /// the range clamped to the highlightable lines: the empty line after a final newline is
+    /// never highlighted, views reaching it are served from the cache rather than highlighting
+    /// from the last state on every draw
+    fn clamp(content: &Rope, range: Range<usize>) -> Range<usize> {
+        let len = content.len_chars();
+        let last_empty = len == 0 || content.char(len - 1) == '\n';
+        let end = range.end.min(content.len_lines() - last_empty as usize);
+        range.start.min(end)..end
+    }
+
+    /// highlights the lines up to end, at most budget of them, returns whether it got there
+    ///
+    /// Lines are read from the first one to highlight on, and highlighting resumes from the
+    /// last line reached, so scrolling down only highlights the lines coming into view.
+    fn highlight_until(&mut self, content: &Rope, end: usize, budget: Option<usize>) -> bool {
+        if self.highlighted_lines.len() >= end {
+            return true;
+        }
+        // get latest good state from cache
+        let (line_number, (mut parse_state, mut highlight_state)) = self.latest_state();
+        self.highlighted_lines.truncate(line_number);
+        let highlighter = Highlighter::new(&self.theme);
+        let stop = budget.map_or(end, |budget| end.min(line_number + budget));
+
+        // work on content
+        let lines = (line_number..stop)
+            .zip(content.lines_at(line_number))
+            .filter(|(_, s)| s.len_chars() != 0);
+        for (i, line) in lines {
+            if i % STEP == 0 {
+                let state = (parse_state.clone(), highlight_state.clone());
+                self.cache.insert(i, state);
+            }
+            let s = String::from(line);
+            let changes = parse_state.parse_line(&s, &self.config.syntax_set);
+            let ranges: Vec<(Style, Range<usize>)> =
+                RangedHighlightIterator::new(&mut highlight_state, &changes, &s, &highlighter)
+                    .map(|(style, _, r)| (style, r))
+                    .collect();
+            self.highlighted_lines.push((s, ranges))
+        }
+        self.frontier = Some((self.highlighted_lines.len(), (parse_state, highlight_state)));
+        stop == end
+    }
+
+    /// returns up to range.len() lines
+    pub fn get_highlighted_lines(&mut self, content: &Rope, range: Range<usize>) -> Vec<Line> {
+        let range = Self::clamp(content, range);
+        self.highlight_until(content, range.end, None);
+        self.highlighted_lines[range].to_vec()
+    }
+
+    /// like get_highlighted_lines, but highlights STEP lines at most, None until the range is
+    /// reached so long files are highlighted over several frames
+    pub fn try_highlighted_lines(
+        &mut self,
+        content: &Rope,
+        range: Range<usize>,
+    ) -> Option<Vec<Line>> {
+        let range = Self::clamp(content, range);
+        if self.highlight_until(content, range.end, Some(STEP)) {
+            Some(self.highlighted_lines[range].to_vec())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(all(test, feature = "bundled-assets"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_over_several_calls() {
+        let config = Rc::new(Config::builtin());
+        let syntax = config.syntax_set.find_syntax_by_extension("rs").cloned();
+        let mut cached = CachedHighlighter::new(syntax.unwrap(), Theme::default(), config);
+        let content = Rope::from("let a = 1;\n".repeat(2 * STEP + 5));
+        let range = 2 * STEP..2 * STEP + 10;
+        assert!(cached
+            .try_highlighted_lines(&content, range.clone())
+            .is_none());
+        assert!(cached
+            .try_highlighted_lines(&content, range.clone())
+            .is_none());
+        let lines = cached.try_highlighted_lines(&content, range).unwrap();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0].0, "let a = 1;\n");
+        assert_eq!(cached.get_highlighted_lines(&content, 0..2).len(), 2);
+        assert_eq!(
+            cached.frontier.as_ref().map(|(k, _)| *k),
+            Some(2 * STEP + 5)
+        );
+        cached.invalidate_from(STEP + 3);
+        assert_eq!(cached.highlighted_lines.len(), STEP + 3);
+        let (line_number, _) = cached.latest_state();
+        assert_eq!(line_number, STEP);
+    }
+}
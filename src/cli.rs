@@ -0,0 +1,178 @@
+use std::path::{Path, PathBuf};
+
+pub const USAGE: &str = "usage:
+    ted [options] [+line] [file[:line[:column]]...]
+    ted --batch <script> [--output <path>] <file>
+    ted highlight <file> [--theme <name>] [--html]
+    ted replace <pattern> <replacement> <file...>
+
+options:
+    --readonly         open files read-only
+    --theme <name>     theme to use for this session
+    --config <path>    config file to use instead of ~/.config/ted/config.json
+    --batch <script>   run a script of commands and keys on the file without a terminal
+    --output <path>    where --batch writes the result instead of stdout
+    --profile-startup  report how long each startup phase took, on exit
+    --version          print the version and exit
+    --help             print this message and exit";
+
+/// a file to open, with an optional 1-based position
+#[derive(Debug, Eq, PartialEq)]
+pub struct FileArg {
+    pub path: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Args {
+    pub files: Vec<FileArg>,
+    pub readonly: bool,
+    pub theme: Option<String>,
+    pub config: Option<PathBuf>,
+    pub version: bool,
+    pub batch: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub profile_startup: bool,
+}
+
+/// splits "path:line[:column]" unless a file with that exact name exists
+fn split_location(arg: &str) -> FileArg {
+    let mut path = arg;
+    let mut numbers = vec![];
+    if !Path::new(arg).exists() {
+        while numbers.len() < 2 {
+            match path.rsplit_once(':') {
+                Some((head, tail)) if !head.is_empty() => match tail.parse::<usize>() {
+                    Ok(n) => {
+                        numbers.insert(0, n);
+                        path = head;
+                    }
+                    Err(_) => break,
+                },
+                _ => break,
+            }
+        }
+    }
+    FileArg {
+        path: path.to_string(),
+        line: numbers.first().copied(),
+        column: numbers.get(1).copied(),
+    }
+}
+
+impl Args {
+    /// parses arguments (without the program name)
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut line = None;
+        let mut only_files = false;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} expects a value", name))
+            };
+            if only_files {
+                let mut file = split_location(arg);
+                file.line = line.take().or(file.line);
+                parsed.files.push(file);
+            } else if arg == "--" {
+                only_files = true;
+            } else if arg == "--readonly" {
+                parsed.readonly = true;
+            } else if arg == "--profile-startup" {
+                parsed.profile_startup = true;
+            } else if arg == "--version" {
+                parsed.version = true;
+            } else if arg == "--theme" {
+                parsed.theme = Some(value(arg)?);
+            } else if arg == "--config" {
+                parsed.config = Some(PathBuf::from(value(arg)?));
+            } else if arg == "--batch" {
+                parsed.batch = Some(PathBuf::from(value(arg)?));
+            } else if arg == "--output" {
+                parsed.output = Some(PathBuf::from(value(arg)?));
+            } else if arg.starts_with('-') && arg.len() > 1 {
+                return Err(format!("unknown option {}", arg));
+            } else if let Some(n) = arg.strip_prefix('+') {
+                line = Some(
+                    n.parse()
+                        .map_err(|_| format!("invalid line number: {}", arg))?,
+                );
+            } else {
+                let mut file = split_location(arg);
+                file.line = line.take().or(file.line);
+                parsed.files.push(file);
+            }
+        }
+        if line.is_some() {
+            return Err("+N must be followed by a file".to_string());
+        }
+        if parsed.output.is_some() && parsed.batch.is_none() {
+            return Err("--output requires --batch".to_string());
+        }
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, line: Option<usize>, column: Option<usize>) -> FileArg {
+        FileArg {
+            path: path.to_string(),
+            line,
+            column,
+        }
+    }
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn locations() {
+        assert_eq!(
+            parse(&["a.rs", "b.rs:12", "c.rs:12:5", "+7", "d.rs"]).unwrap(),
+            Args {
+                files: vec![
+                    file("a.rs", None, None),
+                    file("b.rs", Some(12), None),
+                    file("c.rs", Some(12), Some(5)),
+                    file("d.rs", Some(7), None),
+                ],
+                ..Args::default()
+            }
+        );
+        assert_eq!(
+            parse(&["a:b:12"]).unwrap().files,
+            vec![file("a:b", Some(12), None)]
+        );
+        assert_eq!(parse(&["a:"]).unwrap().files, vec![file("a:", None, None)]);
+        assert!(parse(&["+x", "a"]).is_err());
+        assert!(parse(&["a", "+1"]).is_err());
+    }
+
+    #[test]
+    fn flags() {
+        let args = parse(&["--readonly", "--theme", "ted", "--config", "c.json", "a"]).unwrap();
+        assert!(args.readonly);
+        assert_eq!(args.theme, Some(String::from("ted")));
+        assert_eq!(args.config, Some(PathBuf::from("c.json")));
+        assert_eq!(args.files, vec![file("a", None, None)]);
+        assert_eq!(
+            parse(&["--", "--readonly"]).unwrap().files,
+            vec![file("--readonly", None, None)]
+        );
+        let args = parse(&["--batch", "s.ted", "--output", "b", "a"]).unwrap();
+        assert_eq!(args.batch, Some(PathBuf::from("s.ted")));
+        assert_eq!(args.output, Some(PathBuf::from("b")));
+        assert!(parse(&["--profile-startup"]).unwrap().profile_startup);
+        assert!(parse(&["--output", "b", "a"]).is_err());
+        assert!(parse(&["--theme"]).is_err());
+        assert!(parse(&["--nope"]).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use std::env;
+
+/// a color the terminal can display
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Color {
+    Rgb(u8, u8, u8),
+    /// 256 color palette entry
+    Indexed(u8),
+    /// one of the 16 basic colors, in the usual black, red, ..., bright white order
+    Ansi(u8),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+/// the 16 basic colors with their usual xterm values
+const ANSI16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// steps of the 6x6x6 color cube of 256 color terminals
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+fn nearest_cube_step(v: u8) -> usize {
+    (0..CUBE.len())
+        .min_by_key(|&i| (CUBE[i] as i32 - v as i32).abs())
+        .unwrap_or(0)
+}
+
+/// (r, g, b) of a `#rrggbb` color
+pub fn parse_hex(text: &str) -> Option<(u8, u8, u8)> {
+    let digits = text.strip_prefix('#')?;
+    if digits.len() != 6 {
+        return None;
+    }
+    let component = |i: usize| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok();
+    Some((component(0)?, component(2)?, component(4)?))
+}
+
+impl ColorDepth {
+    /// guesses what the terminal supports from COLORTERM and TERM
+    pub fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        Self::guess(&colorterm, &term)
+    }
+
+    /// 256 colors unless COLORTERM tells of more, or TERM of a console known to have 16
+    fn guess(colorterm: &str, term: &str) -> Self {
+        match (colorterm, term) {
+            ("truecolor" | "24bit", _) => ColorDepth::TrueColor,
+            (_, "linux" | "vt100" | "vt220" | "ansi") => ColorDepth::Ansi16,
+            _ => ColorDepth::Ansi256,
+        }
+    }
+
+    /// "auto", "truecolor", "256" or "16"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Self::detect()),
+            "truecolor" | "24bit" => Some(ColorDepth::TrueColor),
+            "256" => Some(ColorDepth::Ansi256),
+            "16" => Some(ColorDepth::Ansi16),
+            _ => None,
+        }
+    }
+
+    /// quantizes a theme color to what the terminal can display
+    pub fn convert(self, r: u8, g: u8, b: u8) -> Color {
+        match self {
+            ColorDepth::TrueColor => Color::Rgb(r, g, b),
+            ColorDepth::Ansi256 => {
+                let (ri, gi, bi) = (
+                    nearest_cube_step(r),
+                    nearest_cube_step(g),
+                    nearest_cube_step(b),
+                );
+                let cube = (CUBE[ri], CUBE[gi], CUBE[bi]);
+                // grayscale ramp 232..=255 goes from 8 to 238 by steps of 10
+                let average = (r as u32 + g as u32 + b as u32) / 3;
+                let gray_index = (average.saturating_sub(3) / 10).min(23) as u8;
+                let gray_value = 8 + 10 * gray_index;
+                if distance((r, g, b), (gray_value, gray_value, gray_value))
+                    < distance((r, g, b), cube)
+                {
+                    Color::Indexed(232 + gray_index)
+                } else {
+                    Color::Indexed(16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8)
+                }
+            }
+            ColorDepth::Ansi16 => Color::Ansi(
+                (0..ANSI16.len())
+                    .min_by_key(|&i| distance(ANSI16[i], (r, g, b)))
+                    .unwrap_or(0) as u8,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert() {
+        assert_eq!(ColorDepth::TrueColor.convert(1, 2, 3), Color::Rgb(1, 2, 3));
+        assert_eq!(ColorDepth::Ansi256.convert(255, 0, 0), Color::Indexed(196));
+        assert_eq!(ColorDepth::Ansi256.convert(0, 0, 0), Color::Indexed(16));
+        assert_eq!(
+            ColorDepth::Ansi256.convert(128, 128, 128),
+            Color::Indexed(244)
+        );
+        assert_eq!(ColorDepth::Ansi16.convert(250, 10, 10), Color::Ansi(9));
+        assert_eq!(ColorDepth::Ansi16.convert(10, 10, 10), Color::Ansi(0));
+    }
+
+    #[test]
+    fn guess() {
+        assert_eq!(
+            ColorDepth::guess("truecolor", "xterm"),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(ColorDepth::guess("", "xterm-256color"), ColorDepth::Ansi256);
+        assert_eq!(ColorDepth::guess("", "xterm"), ColorDepth::Ansi256);
+        assert_eq!(ColorDepth::guess("", "linux"), ColorDepth::Ansi16);
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(parse_hex("#3a3A00"), Some((0x3a, 0x3a, 0)));
+        assert_eq!(parse_hex("3a3a3a"), None);
+        assert_eq!(parse_hex("#3a3a"), None);
+        assert_eq!(parse_hex("#3a3a3g"), None);
+    }
+}
@@ -0,0 +1,233 @@
+//! Named commands, optionally bound to a space chain
+
+use std::io;
+
+/// formats a space chain for display, e.g. " fo" as "SPC f o"
+pub fn format_space_chain(space_chain: &str) -> String {
+    let v: Vec<String> = space_chain
+        .chars()
+        .map(|c| match c {
+            ' ' => String::from("SPC"),
+            '\t' => String::from("TAB"),
+            _ => String::from(c),
+        })
+        .collect();
+    v.join(" ")
+}
+
+/// parses a displayed space chain, e.g. "SPC f o" into " fo"
+pub fn parse_space_chain(s: &str) -> Option<String> {
+    let chain: Option<String> = s
+        .split_whitespace()
+        .map(|key| match key {
+            "SPC" => Some(' '),
+            "TAB" => Some('\t'),
+            _ => {
+                let mut chars = key.chars();
+                chars.next().filter(|_| chars.next().is_none())
+            }
+        })
+        .collect();
+    chain.filter(|chain| chain.starts_with(' '))
+}
+
+/// splits a command line into the command's name and its trimmed arguments
+pub fn parse_command_line(line: &str) -> (&str, &str) {
+    let line = line.trim();
+    match line.split_once(char::is_whitespace) {
+        Some((name, arguments)) => (name, arguments.trim()),
+        None => (line, ""),
+    }
+}
+
+/// how a command takes the text typed after its name, e.g. `goto_line 120`
+pub struct Arguments<T> {
+    pub run: fn(&mut T, String),
+    /// values the argument can take, to complete it
+    pub candidates: Option<fn(&T) -> Vec<String>>,
+}
+
+/// A command acting on the frontend state `T`
+pub struct Command<T> {
+    pub name: String,
+    pub desc: String,
+    /// long-form documentation shown by `help <name>`
+    pub help: Option<String>,
+    pub chain: Option<String>,
+    /// runs the command without arguments, usually prompting for them
+    pub action: fn(&mut T),
+    pub arguments: Option<Arguments<T>>,
+}
+
+impl<T> Command<T> {
+    pub fn get_action(&self) -> fn(&mut T) {
+        self.action
+    }
+
+    pub fn chain_is(&self, other: &str) -> bool {
+        self.chain
+            .as_ref()
+            .map(|chain| chain == other)
+            .unwrap_or(false)
+    }
+}
+
+pub struct Commands<T> {
+    pub commands: Vec<Command<T>>,
+}
+
+impl<T> Commands<T> {
+    pub fn get_by_chain(&self, prefix: &str) -> Vec<&Command<T>> {
+        self.commands
+            .iter()
+            .filter(|command| {
+                if let Some(chain) = &command.chain {
+                    chain.starts_with(prefix)
+                } else {
+                    false
+                }
+            })
+            .collect()
+    }
+
+    pub fn get_by_name(&self, needle: &str) -> Option<&Command<T>> {
+        self.commands.iter().find(|command| command.name == needle)
+    }
+
+    /// full command lines completing line: command names, or the arguments of the named command
+    pub fn complete(&self, state: &T, line: &str) -> Vec<String> {
+        let (name, argument) = parse_command_line(line);
+        if !line.trim_start().contains(char::is_whitespace) {
+            return self
+                .commands
+                .iter()
+                .filter(|command| command.name.starts_with(name))
+                .map(|command| command.name.clone())
+                .collect();
+        }
+        let candidates = self
+            .get_by_name(name)
+            .and_then(|command| command.arguments.as_ref())
+            .and_then(|arguments| arguments.candidates)
+            .map(|candidates| candidates(state))
+            .unwrap_or_default();
+        candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(argument))
+            .map(|candidate| format!("{} {}", name, candidate))
+            .collect()
+    }
+
+    /// fails when chain is equal to or prefixes the chain of a command other than the named
+    /// one, since one of them could never be reached
+    pub fn check_chain(&self, name: &str, chain: &str) -> io::Result<()> {
+        let clash = self.commands.iter().find(|command| {
+            command.name != name
+                && command
+                    .chain
+                    .as_deref()
+                    .filter(|other| other.starts_with(chain) || chain.starts_with(other))
+                    .is_some()
+        });
+        match clash {
+            Some(clash) => Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!(
+                    "{} clashes with {} ({})",
+                    format_space_chain(chain),
+                    clash.name,
+                    clash
+                        .chain
+                        .as_deref()
+                        .map(format_space_chain)
+                        .unwrap_or_default()
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// binds the named command to chain (or unbinds it with None), see check_chain
+    pub fn bind(&mut self, name: &str, chain: Option<String>) -> io::Result<()> {
+        if let Some(chain) = &chain {
+            self.check_chain(name, chain)?;
+        }
+        let command = self
+            .commands
+            .iter_mut()
+            .find(|command| command.name == name)
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("Unknown command {}", name))
+            })?;
+        command.chain = chain;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind() {
+        let command = |name: &str, chain: &str| Command {
+            name: name.to_string(),
+            desc: String::default(),
+            help: None,
+            chain: Some(chain.to_string()),
+            action: |_: &mut ()| {},
+            arguments: None,
+        };
+        let mut commands = Commands {
+            commands: vec![command("file_open", " fo"), command("quit", " q")],
+        };
+        assert_eq!(parse_space_chain("SPC o"), Some(String::from(" o")));
+        assert_eq!(parse_space_chain("f o"), None);
+        assert_eq!(parse_space_chain("SPC fo"), None);
+        assert!(commands.bind("quit", Some(String::from(" f"))).is_err());
+        assert!(commands.bind("quit", Some(String::from(" fox"))).is_err());
+        assert!(commands.bind("nothing", Some(String::from(" x"))).is_err());
+        commands
+            .bind("file_open", Some(String::from(" o")))
+            .unwrap();
+        assert_eq!(commands.get_by_chain(" o")[0].name, "file_open");
+        commands.bind("quit", None).unwrap();
+        assert!(commands.get_by_chain(" q").is_empty());
+    }
+
+    #[test]
+    fn complete() {
+        let mut commands = Commands {
+            commands: vec![
+                Command {
+                    name: "set_theme".to_string(),
+                    desc: String::default(),
+                    help: None,
+                    chain: None,
+                    action: |_: &mut ()| {},
+                    arguments: Some(Arguments {
+                        run: |_, _| {},
+                        candidates: Some(|_| vec![String::from("ted"), String::from("solarized")]),
+                    }),
+                },
+                Command {
+                    name: "set_lang".to_string(),
+                    desc: String::default(),
+                    help: None,
+                    chain: None,
+                    action: |_: &mut ()| {},
+                    arguments: None,
+                },
+            ],
+        };
+        assert_eq!(parse_command_line(" goto  12 "), ("goto", "12"));
+        assert_eq!(
+            commands.complete(&(), "set_"),
+            vec!["set_theme", "set_lang"]
+        );
+        assert_eq!(commands.complete(&(), "set_theme t"), vec!["set_theme ted"]);
+        assert!(commands.complete(&(), "set_lang R").is_empty());
+        commands.commands.clear();
+        assert!(commands.complete(&(), "").is_empty());
+    }
+}
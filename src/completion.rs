@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// words of the texts extending prefix, most frequent first
+pub fn words_with_prefix(prefix: &str, texts: &[&str]) -> Vec<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for text in texts {
+        for word in text.split(|c: char| !is_word_char(c)) {
+            if word.len() > prefix.len() && word.starts_with(prefix) {
+                *counts.entry(word).or_insert(0) += 1;
+            }
+        }
+    }
+    let mut words: Vec<(&str, usize)> = counts.into_iter().collect();
+    words.sort_by(|(wa, ca), (wb, cb)| cb.cmp(ca).then(wa.cmp(wb)));
+    words
+        .into_iter()
+        .map(|(word, _)| word.to_string())
+        .collect()
+}
+
+/// the longest abbreviation ending text, along with its expansion. abbreviations
+/// starting with a word character must start a word, the others must follow whitespace
+pub fn abbreviation_before<'a>(
+    text: &str,
+    abbreviations: &'a HashMap<String, String>,
+) -> Option<(&'a str, &'a str)> {
+    abbreviations
+        .iter()
+        .filter(|(from, _)| {
+            let before = match text.strip_suffix(from.as_str()) {
+                Some(before) if !from.is_empty() => before.chars().last(),
+                _ => return false,
+            };
+            match (from.starts_with(is_word_char), before) {
+                (_, None) => true,
+                (true, Some(c)) => !is_word_char(c),
+                (false, Some(c)) => c.is_whitespace(),
+            }
+        })
+        .max_by_key(|(from, _)| from.len())
+        .map(|(from, to)| (from.as_str(), to.as_str()))
+}
+
+/// longest prefix shared by all words
+pub fn common_prefix(words: &[String]) -> String {
+    let mut prefix: &str = words.first().map(String::as_str).unwrap_or_default();
+    for word in words.iter().skip(1) {
+        let n = prefix
+            .char_indices()
+            .zip(word.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(prefix.len().min(word.len()), |((i, _), _)| i);
+        prefix = &prefix[..n];
+    }
+    prefix.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    #[test]
+    fn words_with_prefix() {
+        let words = super::words_with_prefix("fo", &["foo bar foobar", "foo(fo) for_each"]);
+        assert_eq!(words, vec!["foo", "foobar", "for_each"]);
+        assert!(super::words_with_prefix("x", &["foo"]).is_empty());
+    }
+
+    #[test]
+    fn abbreviation_before() {
+        let abbreviations: HashMap<String, String> = vec![("teh", "the"), ("-->", "→"), (">", "≥")]
+            .into_iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect();
+        let before = |text| super::abbreviation_before(text, &abbreviations);
+        assert_eq!(before("is teh"), Some(("teh", "the")));
+        assert_eq!(before("teh"), Some(("teh", "the")));
+        assert_eq!(before("steh"), None);
+        assert_eq!(before("a -->"), Some(("-->", "→")));
+        assert_eq!(before("a-->"), None);
+    }
+
+    #[test]
+    fn common_prefix() {
+        let words = vec![String::from("for_each"), String::from("format")];
+        assert_eq!(super::common_prefix(&words), "for");
+        assert_eq!(super::common_prefix(&words[..1]), "for_each");
+        let words = vec![String::from("ab"), String::from("a")];
+        assert_eq!(super::common_prefix(&words), "a");
+        assert_eq!(super::common_prefix(&[]), "");
+    }
+}
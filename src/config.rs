@@ -0,0 +1,788 @@
+use crate::background::Background;
+use crate::backup::Backup;
+use crate::color::{parse_hex, ColorDepth};
+use crate::error::TedError;
+use crate::hook::Hooks;
+use crate::keymap::ModeSpec;
+use crate::path::glob_match;
+use crate::search;
+use log::LevelFilter;
+use serde_json::{Map, Value};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::{SyntaxSet, SyntaxSetBuilder};
+use syntect::LoadingError;
+
+pub struct Config {
+    /// the json file options are read from and persisted to
+    pub file: Option<PathBuf>,
+    pub syntax_set: Background<SyntaxSet>,
+    pub theme_set: Background<ThemeSet>,
+    pub whitespace: Cell<Whitespace>,
+    pub auto_save: Cell<AutoSave>,
+    /// theme given to newly highlighted buffers
+    pub default_theme: RefCell<String>,
+    /// ignore theme colors, only use text attributes
+    pub high_contrast: Cell<bool>,
+    /// the config file is reloaded when it changes
+    pub watch_config: Cell<bool>,
+    /// highlight both the cursor's line and column
+    pub crosshair: Cell<bool>,
+    /// highlight the cursor's line
+    pub cursor_line: Cell<bool>,
+    /// highlight the cursor's column
+    pub cursor_column: Cell<bool>,
+    /// draw long lines on several rows
+    pub soft_wrap: Cell<bool>,
+    /// number the lines in the gutter
+    pub line_numbers: Cell<bool>,
+    /// number lines by their distance to the cursor's one
+    pub relative_numbers: Cell<bool>,
+    /// color of the cursor's line and column, and of rulers, when the theme has none
+    pub cursor_highlight: Cell<(u8, u8, u8)>,
+    /// apply the modelines of opened files, e.g. `# vim: sw=2 et`
+    pub modelines: Cell<bool>,
+    /// reopened files start where the cursor was left
+    pub restore_cursor: Cell<bool>,
+    /// most files whose cursor position is remembered
+    pub restore_cursor_files: Cell<usize>,
+    /// undo trees are saved and loaded along with their files
+    pub persistent_undo: Cell<bool>,
+    /// most undo states saved per file
+    pub persistent_undo_states: Cell<usize>,
+    /// new lines keep the indentation of the previous one
+    pub auto_indent: Cell<bool>,
+    /// typing an opening bracket or quote inserts the closing one
+    pub auto_pairs: Cell<bool>,
+    /// disables automatic edits while text is pasted into the terminal
+    pub paste_mode: Cell<bool>,
+    /// text pasted from the kill ring is reindented to its new place
+    pub paste_reindent: Cell<bool>,
+    /// the status line shows the word count of the buffer or selection
+    pub status_count: Cell<bool>,
+    /// 1-based columns tinted as guides, e.g. 80 and 100
+    pub ruler: RefCell<Vec<usize>>,
+    /// lines of context kept above and below the cursor
+    pub scrolloff: Cell<usize>,
+    /// colors supported by the terminal
+    pub color_depth: Cell<ColorDepth>,
+    /// where the previous content of a file is copied before saving over it
+    pub backup: RefCell<Backup>,
+    /// command line writing its stdin to the path appended to it as root, for `save_as_root`
+    pub elevate: RefCell<String>,
+    /// file of tab separated words and definitions, for `define_word`
+    pub dictionary: RefCell<Option<PathBuf>>,
+    /// command line printing the definition of the word appended to it, e.g. `dict`, used when
+    /// the dictionary file has none
+    pub dictionary_command: RefCell<String>,
+    /// files a wildcard pattern may open before asking for confirmation
+    pub max_open_files: Cell<usize>,
+    /// case sensitivity and regex matching of searches
+    pub search: Cell<search::Options>,
+    /// longest lines by language name, `*` for the others, the next column tinted and the
+    /// overflowing tail of longer lines highlighted
+    pub max_line_length: RefCell<HashMap<String, usize>>,
+    /// command lines formatting stdin to stdout, by language name
+    pub formatters: RefCell<HashMap<String, String>>,
+    /// paths also written on every save, by wildcard pattern of the saved file's absolute path,
+    /// a destination ending with `/` getting the file's name
+    pub save_copies: RefCell<HashMap<String, Vec<String>>>,
+    /// command lines starting a language server on stdio, by language name
+    pub language_servers: RefCell<HashMap<String, String>>,
+    /// command lines starting an external plugin on stdio, by name
+    pub plugins: RefCell<HashMap<String, String>>,
+    /// command lines run on buffer events
+    pub hooks: RefCell<Hooks>,
+    /// space chains of commands by name as displayed, e.g. "SPC o", empty to unbind
+    pub bindings: RefCell<HashMap<String, String>>,
+    /// commands by normal mode chord as displayed, e.g. "C-s", empty to unbind
+    pub keys: RefCell<HashMap<String, String>>,
+    /// modes entered with enter_mode by name, or keys added to the builtin modes
+    pub modes: RefCell<HashMap<String, ModeSpec>>,
+    /// insert mode replacements made when a word boundary is typed
+    pub abbreviations: RefCell<HashMap<String, String>>,
+    /// snippet bodies by language name then trigger word
+    pub snippets: RefCell<HashMap<String, HashMap<String, String>>>,
+    /// most detailed records written to the log
+    pub log_level: Cell<LevelFilter>,
+    /// terminal title, where {name}, {path} and {dirty} (" [+]" once edited) are replaced
+    pub title: RefCell<String>,
+    /// what failed while loading the user's files, to be reported
+    pub load_errors: Vec<TedError>,
+}
+
+/// when buffers are saved without asking
+#[derive(Copy, Clone, Default)]
+pub struct AutoSave {
+    /// the buffer whose INSERT mode was left
+    pub insert_leave: bool,
+    /// the buffer switched away from
+    pub buffer_switch: bool,
+    /// every buffer, when the terminal loses focus
+    pub focus_lost: bool,
+}
+
+impl AutoSave {
+    /// turns a trigger on by name, returns wether it exists
+    pub fn enable(&mut self, name: &str) -> bool {
+        match name {
+            "insert_leave" => self.insert_leave = true,
+            "buffer_switch" => self.buffer_switch = true,
+            "focus_lost" => self.focus_lost = true,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// which invisible characters are rendered
+#[derive(Copy, Clone, PartialEq)]
+pub struct Whitespace {
+    /// `¶` at end of lines and `~` past the end of the buffer
+    pub newlines: bool,
+    /// `·` for spaces
+    pub spaces: bool,
+    /// `→` for tabs
+    pub tabs: bool,
+    /// highlight whitespace at end of lines
+    pub trailing: bool,
+}
+
+impl Default for Whitespace {
+    fn default() -> Self {
+        Self {
+            newlines: cfg!(debug_assertions),
+            spaces: false,
+            tabs: cfg!(debug_assertions),
+            trailing: cfg!(debug_assertions),
+        }
+    }
+}
+
+impl Whitespace {
+    /// every invisible character rendered, or none
+    pub fn all(on: bool) -> Self {
+        Self {
+            newlines: on,
+            spaces: on,
+            tabs: on,
+            trailing: on,
+        }
+    }
+
+    /// toggles the named setting ("all" flips them together), returns wether it exists
+    pub fn toggle(&mut self, name: &str) -> bool {
+        match name {
+            "newlines" => self.newlines = !self.newlines,
+            "spaces" => self.spaces = !self.spaces,
+            "tabs" => self.tabs = !self.tabs,
+            "trailing" => self.trailing = !self.trailing,
+            "all" => {
+                *self = Self::all(!(self.newlines && self.spaces && self.tabs && self.trailing));
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// replaces invisible characters with their visible counterpart
+    pub fn render(&self, text: &str) -> String {
+        text.chars()
+            .map(|c| match c {
+                '\n' if self.newlines => '¶',
+                ' ' if self.spaces => '·',
+                '\t' if self.tabs => '→',
+                c => c,
+            })
+            .collect()
+    }
+}
+
+/// returns $XDG_CONFIG_HOME/ted/name (defaults to ~/.config/ted/name)
+pub fn config_dir(name: &str) -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("ted").join(name))
+}
+
+/// returns $XDG_DATA_HOME/ted/name (defaults to ~/.local/share/ted/name)
+pub fn data_dir(name: &str) -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .map(|dir| dir.join("ted").join(name))
+}
+
+/// returns $XDG_STATE_HOME/ted/name (defaults to ~/.local/state/ted/name)
+pub fn state_dir(name: &str) -> Option<PathBuf> {
+    env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .map(|dir| dir.join("ted").join(name))
+}
+
+/// syntect's syntaxes when built with them, plain text otherwise
+fn bundled_syntaxes() -> SyntaxSet {
+    #[cfg(feature = "bundled-assets")]
+    return SyntaxSet::load_defaults_newlines();
+    #[cfg(not(feature = "bundled-assets"))]
+    return plain_text();
+}
+
+fn plain_text() -> SyntaxSet {
+    let mut builder = SyntaxSetBuilder::new();
+    builder.add_plain_text_syntax();
+    builder.build()
+}
+
+/// syntect's themes when built with them, and ted's own
+fn bundled_themes() -> ThemeSet {
+    #[cfg(feature = "bundled-assets")]
+    let theme_set = ThemeSet::load_defaults();
+    #[cfg(not(feature = "bundled-assets"))]
+    let theme_set = ThemeSet::new();
+    with_ted_theme(theme_set)
+}
+
+fn with_ted_theme(mut theme_set: ThemeSet) -> ThemeSet {
+    if let Ok(theme) = ThemeSet::load_from_reader(&mut BufReader::new(Cursor::new(
+        include_str!("../assets/themes/ted.tmTheme").as_bytes(),
+    ))) {
+        theme_set.themes.insert("ted".to_string(), theme);
+    }
+    theme_set
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::load(config_dir("config.json"))
+    }
+}
+
+impl Config {
+    /// bundled sets along with the user's config file, themes and syntaxes
+    pub fn load(file: Option<PathBuf>) -> Self {
+        let mut config = Self::builtin();
+        config.file = file;
+        let errors = config.load_files();
+        config.load_errors.extend(errors);
+        config
+    }
+
+    /// loads the config file and the user's themes, syntaxes and snippets, returns what failed
+    pub fn load_files(&mut self) -> Vec<TedError> {
+        let mut errors = vec![];
+        if let Err(err) = self.load_config_file() {
+            errors.push(TedError::Config(err.to_string()));
+        }
+        errors.extend(self.load_user_files());
+        errors
+    }
+
+    /// loads the user's themes, syntaxes and snippets, returns what failed
+    pub fn load_user_files(&mut self) -> Vec<TedError> {
+        let mut errors = vec![];
+        if let Err(err) = self.load_user_themes() {
+            errors.push(err.into());
+        }
+        if let Err(err) = self.load_user_syntaxes() {
+            errors.push(err.into());
+        }
+        if let Err(err) = self.load_user_snippets() {
+            errors.push(TedError::Config(format!("snippets: {}", err)));
+        }
+        errors
+    }
+
+    /// bundled syntaxes and themes only, loaded in the background
+    pub fn builtin() -> Self {
+        Self {
+            file: config_dir("config.json"),
+            theme_set: Background::spawn(bundled_themes),
+            syntax_set: Background::spawn(bundled_syntaxes),
+            whitespace: Cell::new(Whitespace::default()),
+            auto_save: Cell::new(AutoSave::default()),
+            default_theme: RefCell::new("ted".to_string()),
+            high_contrast: Cell::new(false),
+            watch_config: Cell::new(true),
+            crosshair: Cell::new(false),
+            cursor_line: Cell::new(true),
+            cursor_column: Cell::new(false),
+            soft_wrap: Cell::new(false),
+            line_numbers: Cell::new(false),
+            relative_numbers: Cell::new(false),
+            cursor_highlight: Cell::new((0x3a, 0x3a, 0x3a)),
+            modelines: Cell::new(false),
+            restore_cursor: Cell::new(true),
+            restore_cursor_files: Cell::new(500),
+            persistent_undo: Cell::new(true),
+            persistent_undo_states: Cell::new(500),
+            auto_indent: Cell::new(true),
+            auto_pairs: Cell::new(false),
+            paste_mode: Cell::new(false),
+            paste_reindent: Cell::new(true),
+            status_count: Cell::new(false),
+            ruler: RefCell::new(vec![]),
+            scrolloff: Cell::new(3),
+            color_depth: Cell::new(ColorDepth::detect()),
+            backup: RefCell::new(Backup::Off),
+            elevate: RefCell::new(String::from("sudo tee")),
+            dictionary: RefCell::new(config_dir("dictionary.txt")),
+            dictionary_command: RefCell::new(String::default()),
+            max_open_files: Cell::new(20),
+            search: Cell::new(search::Options::default()),
+            max_line_length: RefCell::new(HashMap::default()),
+            formatters: RefCell::new(
+                vec![
+                    ("Rust", "rustfmt --edition 2018"),
+                    ("JavaScript", "prettier --parser babel"),
+                    ("Python", "black -q -"),
+                ]
+                .into_iter()
+                .map(|(lang, command)| (lang.to_string(), command.to_string()))
+                .collect(),
+            ),
+            save_copies: RefCell::new(HashMap::new()),
+            language_servers: RefCell::new(
+                vec![
+                    ("Rust", "rust-analyzer"),
+                    ("Python", "pylsp"),
+                    ("Go", "gopls"),
+                    ("JavaScript", "typescript-language-server --stdio"),
+                ]
+                .into_iter()
+                .map(|(lang, command)| (lang.to_string(), command.to_string()))
+                .collect(),
+            ),
+            plugins: RefCell::new(HashMap::new()),
+            hooks: RefCell::new(Hooks::default()),
+            bindings: RefCell::new(HashMap::new()),
+            keys: RefCell::new(HashMap::new()),
+            modes: RefCell::new(HashMap::new()),
+            abbreviations: RefCell::new(HashMap::new()),
+            snippets: RefCell::new(HashMap::new()),
+            log_level: Cell::new(LevelFilter::Info),
+            title: RefCell::new(String::from("ted — {name}{dirty}")),
+            load_errors: vec![],
+        }
+    }
+
+    /// reads the config file (~/.config/ted/config.json), a missing file is not an error
+    fn read_config_file(&self) -> io::Result<Map<String, Value>> {
+        match self.file.as_ref().map(fs::read_to_string) {
+            Some(Ok(content)) => match serde_json::from_str(&content)? {
+                Value::Object(map) => Ok(map),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "config.json must be an object",
+                )),
+            },
+            Some(Err(err)) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(Map::default()),
+        }
+    }
+
+    /// applies the options found in the config file
+    pub fn load_config_file(&mut self) -> io::Result<()> {
+        let options = self.read_config_file()?;
+        // leaves syntect's sets out, the user's syntaxes and themes are loaded next
+        if options.get("bundled_assets").and_then(Value::as_bool) == Some(false) {
+            self.syntax_set = Background::ready(plain_text());
+            self.theme_set = Background::ready(with_ted_theme(ThemeSet::new()));
+        }
+        if let Some(theme) = options.get("theme").and_then(Value::as_str) {
+            *self.default_theme.borrow_mut() = theme.to_string();
+        }
+        if let Some(high_contrast) = options.get("high_contrast").and_then(Value::as_bool) {
+            self.high_contrast.set(high_contrast);
+        }
+        if let Some(watch) = options.get("watch_config").and_then(Value::as_bool) {
+            self.watch_config.set(watch);
+        }
+        if let Some(crosshair) = options.get("crosshair").and_then(Value::as_bool) {
+            self.crosshair.set(crosshair);
+        }
+        if let Some(cursor_line) = options.get("cursor_line").and_then(Value::as_bool) {
+            self.cursor_line.set(cursor_line);
+        }
+        if let Some(cursor_column) = options.get("cursor_column").and_then(Value::as_bool) {
+            self.cursor_column.set(cursor_column);
+        }
+        if let Some(soft_wrap) = options.get("soft_wrap").and_then(Value::as_bool) {
+            self.soft_wrap.set(soft_wrap);
+        }
+        if let Some(line_numbers) = options.get("line_numbers").and_then(Value::as_bool) {
+            self.line_numbers.set(line_numbers);
+        }
+        if let Some(relative) = options.get("relative_numbers").and_then(Value::as_bool) {
+            self.relative_numbers.set(relative);
+        }
+        match options.get("auto_save") {
+            Some(Value::Bool(on)) => self.auto_save.set(AutoSave {
+                insert_leave: *on,
+                buffer_switch: *on,
+                focus_lost: *on,
+            }),
+            Some(Value::Array(names)) => {
+                let mut auto_save = AutoSave::default();
+                for name in names {
+                    if !name
+                        .as_str()
+                        .map(|name| auto_save.enable(name))
+                        .unwrap_or(false)
+                    {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Invalid auto_save trigger {}", name),
+                        ));
+                    }
+                }
+                self.auto_save.set(auto_save);
+            }
+            Some(value) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Invalid auto_save {}, expected a boolean or triggers",
+                        value
+                    ),
+                ))
+            }
+            None => {}
+        }
+        match options.get("whitespace") {
+            Some(Value::Bool(on)) => self.whitespace.set(Whitespace::all(*on)),
+            Some(Value::Array(names)) => {
+                let mut whitespace = Whitespace::all(false);
+                for name in names {
+                    if !name
+                        .as_str()
+                        .map(|name| whitespace.toggle(name))
+                        .unwrap_or(false)
+                    {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Invalid whitespace setting {}", name),
+                        ));
+                    }
+                }
+                self.whitespace.set(whitespace);
+            }
+            Some(value) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid whitespace {}, expected a boolean or names", value),
+                ))
+            }
+            None => {}
+        }
+        if let Some(color) = options.get("cursor_highlight").and_then(Value::as_str) {
+            self.cursor_highlight.set(parse_hex(color).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid cursor_highlight {}, expected #rrggbb", color),
+                )
+            })?);
+        }
+        if let Some(modelines) = options.get("modelines").and_then(Value::as_bool) {
+            self.modelines.set(modelines);
+        }
+        if let Some(restore) = options.get("restore_cursor").and_then(Value::as_bool) {
+            self.restore_cursor.set(restore);
+        }
+        if let Some(files) = options.get("restore_cursor_files").and_then(Value::as_u64) {
+            self.restore_cursor_files.set(files as usize);
+        }
+        if let Some(persistent) = options.get("persistent_undo").and_then(Value::as_bool) {
+            self.persistent_undo.set(persistent);
+        }
+        if let Some(states) = options
+            .get("persistent_undo_states")
+            .and_then(Value::as_u64)
+        {
+            self.persistent_undo_states.set(states as usize);
+        }
+        if let Some(auto_indent) = options.get("auto_indent").and_then(Value::as_bool) {
+            self.auto_indent.set(auto_indent);
+        }
+        if let Some(auto_pairs) = options.get("auto_pairs").and_then(Value::as_bool) {
+            self.auto_pairs.set(auto_pairs);
+        }
+        if let Some(reindent) = options.get("paste_reindent").and_then(Value::as_bool) {
+            self.paste_reindent.set(reindent);
+        }
+        if let Some(status_count) = options.get("status_count").and_then(Value::as_bool) {
+            self.status_count.set(status_count);
+        }
+        match options.get("ruler") {
+            Some(Value::Array(columns)) => {
+                *self.ruler.borrow_mut() = columns
+                    .iter()
+                    .filter_map(Value::as_u64)
+                    .map(|column| column as usize)
+                    .collect()
+            }
+            Some(column) => {
+                *self.ruler.borrow_mut() = column.as_u64().map(|c| c as usize).into_iter().collect()
+            }
+            None => {}
+        }
+        if let Some(scrolloff) = options.get("scrolloff").and_then(Value::as_u64) {
+            self.scrolloff.set(scrolloff as usize);
+        }
+        if let Some(level) = options.get("log_level").and_then(Value::as_str) {
+            self.log_level.set(level.parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Unknown log_level {}, expected off, error, warn, info, debug or trace",
+                        level
+                    ),
+                )
+            })?);
+        }
+        if let Some(title) = options.get("title").and_then(Value::as_str) {
+            *self.title.borrow_mut() = title.to_string();
+        }
+        if let Some(backup) = options.get("backup") {
+            *self.backup.borrow_mut() = Backup::from_value(backup).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid backup, expected true, false or a directory",
+                )
+            })?;
+        }
+        if let Some(elevate) = options.get("elevate").and_then(Value::as_str) {
+            *self.elevate.borrow_mut() = elevate.to_string();
+        }
+        if let Some(dictionary) = options.get("dictionary").and_then(Value::as_str) {
+            *self.dictionary.borrow_mut() = Some(PathBuf::from(dictionary));
+        }
+        if let Some(command) = options.get("dictionary_command").and_then(Value::as_str) {
+            *self.dictionary_command.borrow_mut() = command.to_string();
+        }
+        if let Some(max) = options.get("max_open_files").and_then(Value::as_u64) {
+            self.max_open_files.set(max as usize);
+        }
+        let mut search = self.search.get();
+        if let Some(case) = options.get("search_case").and_then(Value::as_str) {
+            search.case = search::Case::from_name(case).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Unknown search_case {}, expected insensitive, smart or sensitive",
+                        case
+                    ),
+                )
+            })?;
+        }
+        if let Some(regex) = options.get("search_regex").and_then(Value::as_bool) {
+            search.regex = regex;
+        }
+        self.search.set(search);
+        match options.get("max_line_length") {
+            Some(Value::Object(lengths)) => {
+                let mut map = self.max_line_length.borrow_mut();
+                for (language, length) in lengths {
+                    if let Some(length) = length.as_u64() {
+                        map.insert(language.to_string(), length as usize);
+                    }
+                }
+            }
+            Some(length) => {
+                if let Some(length) = length.as_u64() {
+                    let mut map = self.max_line_length.borrow_mut();
+                    map.insert(String::from("*"), length as usize);
+                }
+            }
+            None => {}
+        }
+        if let Some(formatters) = options.get("formatters").and_then(Value::as_object) {
+            let mut map = self.formatters.borrow_mut();
+            for (lang, command) in formatters {
+                if let Some(command) = command.as_str() {
+                    map.insert(lang.to_string(), command.to_string());
+                }
+            }
+        }
+        if let Some(copies) = options.get("save_copies").and_then(Value::as_object) {
+            let mut map = self.save_copies.borrow_mut();
+            for (pattern, destinations) in copies {
+                let destinations = match destinations {
+                    Value::Array(paths) => paths.iter().filter_map(Value::as_str).collect(),
+                    path => path.as_str().into_iter().collect::<Vec<&str>>(),
+                };
+                map.insert(
+                    pattern.to_string(),
+                    destinations.into_iter().map(str::to_string).collect(),
+                );
+            }
+        }
+        if let Some(servers) = options.get("language_servers").and_then(Value::as_object) {
+            let mut map = self.language_servers.borrow_mut();
+            for (lang, command) in servers {
+                if let Some(command) = command.as_str() {
+                    map.insert(lang.to_string(), command.to_string());
+                }
+            }
+        }
+        if let Some(plugins) = options.get("plugins").and_then(Value::as_object) {
+            let mut map = self.plugins.borrow_mut();
+            for (name, command) in plugins {
+                if let Some(command) = command.as_str() {
+                    map.insert(name.to_string(), command.to_string());
+                }
+            }
+        }
+        if let Some(hooks) = options.get("hooks") {
+            // the other options still apply
+            if let Err(err) = self.hooks.borrow_mut().extend_from_json(hooks) {
+                self.load_errors
+                    .push(TedError::Config(format!("hooks: {}", err)));
+            }
+        }
+        if let Some(bindings) = options.get("bindings").and_then(Value::as_object) {
+            let mut map = self.bindings.borrow_mut();
+            for (name, chain) in bindings {
+                if let Some(chain) = chain.as_str() {
+                    map.insert(name.to_string(), chain.to_string());
+                }
+            }
+        }
+        if let Some(keys) = options.get("keys").and_then(Value::as_object) {
+            let mut map = self.keys.borrow_mut();
+            for (chord, name) in keys {
+                if let Some(name) = name.as_str() {
+                    map.insert(chord.to_string(), name.to_string());
+                }
+            }
+        }
+        if let Some(modes) = options.get("modes").and_then(Value::as_object) {
+            let mut map = self.modes.borrow_mut();
+            for (name, mode) in modes {
+                let spec = ModeSpec::from_json(mode).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Mode {} has no keys object", name),
+                    )
+                })?;
+                map.insert(name.to_string(), spec);
+            }
+        }
+        if let Some(abbreviations) = options.get("abbreviations").and_then(Value::as_object) {
+            let mut map = self.abbreviations.borrow_mut();
+            for (from, to) in abbreviations {
+                if let Some(to) = to.as_str() {
+                    map.insert(from.to_string(), to.to_string());
+                }
+            }
+        }
+        if let Some(colors) = options.get("colors").and_then(Value::as_str) {
+            self.color_depth
+                .set(ColorDepth::from_name(colors).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Unknown colors {}, expected auto, truecolor, 256 or 16",
+                            colors
+                        ),
+                    )
+                })?);
+        }
+        Ok(())
+    }
+
+    /// writes a single option to the config file, keeping the others
+    pub fn persist(&self, key: &str, value: Value) -> io::Result<()> {
+        let path = self
+            .file
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No config directory"))?;
+        let mut options = self.read_config_file()?;
+        options.insert(key.to_string(), value);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&options)?)
+    }
+
+    /// where a file is copied when saved, per save_copies
+    pub fn copies_of(&self, path: &Path) -> Vec<PathBuf> {
+        let absolute = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let name = path.file_name().unwrap_or_default();
+        let mut copies = vec![];
+        for (pattern, destinations) in self.save_copies.borrow().iter() {
+            if !glob_match(pattern, &absolute.to_string_lossy()) {
+                continue;
+            }
+            for destination in destinations {
+                copies.push(match destination.ends_with('/') {
+                    true => Path::new(destination).join(name),
+                    false => PathBuf::from(destination),
+                });
+            }
+        }
+        copies.sort();
+        copies.dedup();
+        copies
+    }
+
+    /// merges .tmTheme files from ~/.config/ted/themes
+    pub fn load_user_themes(&mut self) -> Result<(), LoadingError> {
+        if let Some(dir) = config_dir("themes").filter(|dir| dir.is_dir()) {
+            self.theme_set.add_from_folder(dir)?;
+        }
+        Ok(())
+    }
+
+    /// reads snippets from ~/.config/ted/snippets/<language>.json, objects mapping
+    /// trigger words to a body given as a string or an array of lines
+    pub fn load_user_snippets(&self) -> io::Result<()> {
+        let dir = match config_dir("snippets").filter(|dir| dir.is_dir()) {
+            Some(dir) => dir,
+            None => return Ok(()),
+        };
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let language = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(language) if path.extension().and_then(|e| e.to_str()) == Some("json") => {
+                    language.to_string()
+                }
+                _ => continue,
+            };
+            let snippets: Map<String, Value> = serde_json::from_str(&fs::read_to_string(&path)?)?;
+            let mut map = self.snippets.borrow_mut();
+            let bodies = map.entry(language).or_default();
+            for (trigger, body) in snippets {
+                let body = match body {
+                    Value::String(body) => body,
+                    Value::Array(lines) => lines
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<&str>>()
+                        .join("\n"),
+                    _ => continue,
+                };
+                bodies.insert(trigger, body);
+            }
+        }
+        Ok(())
+    }
+
+    /// merges .sublime-syntax files from ~/.config/ted/syntaxes
+    pub fn load_user_syntaxes(&mut self) -> Result<(), LoadingError> {
+        if let Some(dir) = config_dir("syntaxes").filter(|dir| dir.is_dir()) {
+            let mut builder = self.syntax_set.clone().into_builder();
+            builder.add_from_folder(dir, true)?;
+            *self.syntax_set = builder.build();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+//! Line, word, char and byte counts of text, like `wc`
+
+use ropey::RopeSlice;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Counts {
+    /// a last line without a newline counts too
+    pub lines: usize,
+    /// runs of non whitespace chars
+    pub words: usize,
+    pub chars: usize,
+    pub bytes: usize,
+}
+
+impl Counts {
+    /// walks the chunks of the rope, without collecting its text
+    pub fn of(slice: RopeSlice) -> Self {
+        let mut counts = Self {
+            chars: slice.len_chars(),
+            bytes: slice.len_bytes(),
+            ..Self::default()
+        };
+        let mut in_word = false;
+        let mut last = '\n';
+        for c in slice.chunks().flat_map(str::chars) {
+            if c == '\n' {
+                counts.lines += 1;
+            }
+            if c.is_whitespace() {
+                in_word = false;
+            } else if !in_word {
+                in_word = true;
+                counts.words += 1;
+            }
+            last = c;
+        }
+        if last != '\n' {
+            counts.lines += 1;
+        }
+        counts
+    }
+}
+
+impl fmt::Display for Counts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} lines, {} words, {} chars, {} bytes",
+            self.lines, self.words, self.chars, self.bytes
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ropey::Rope;
+
+    #[test]
+    fn count_text() {
+        let rope = Rope::from_str("fn main() {\n    println!(\"é\");\n}");
+        let counts = Counts::of(rope.slice(..));
+        assert_eq!(
+            counts,
+            Counts {
+                lines: 3,
+                words: 5,
+                chars: 32,
+                bytes: 33
+            }
+        );
+        assert_eq!(Counts::of(rope.slice(0..12)).lines, 1);
+        assert_eq!(Counts::of(rope.slice(0..0)), Counts::default());
+        assert_eq!(counts.to_string(), "3 lines, 5 words, 32 chars, 33 bytes");
+    }
+}
@@ -0,0 +1,99 @@
+//! Language of a text without a file name, e.g. pasted in a scratch buffer
+//!
+//! syntect's first line matching comes first (shebangs, `<?xml`, modelines...), then JSON when
+//! the whole text parses, then the language whose telltale line starts are the most common.
+
+use serde_json::Value;
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+/// lines guessed from
+pub const LINES: usize = 100;
+
+/// starts of (trimmed) lines telling languages apart
+const TELLS: [(&str, &str); 25] = [
+    ("fn ", "Rust"),
+    ("pub fn ", "Rust"),
+    ("use ", "Rust"),
+    ("impl ", "Rust"),
+    ("let mut ", "Rust"),
+    ("#[derive", "Rust"),
+    ("def ", "Python"),
+    ("import ", "Python"),
+    ("from ", "Python"),
+    ("elif ", "Python"),
+    ("#include", "C"),
+    ("int main(", "C"),
+    ("package ", "Go"),
+    ("func ", "Go"),
+    ("function ", "JavaScript"),
+    ("const ", "JavaScript"),
+    ("console.log(", "JavaScript"),
+    ("<!DOCTYPE html", "HTML"),
+    ("<html", "HTML"),
+    ("diff --git ", "Diff"),
+    ("@@ ", "Diff"),
+    ("```", "Markdown"),
+    ("## ", "Markdown"),
+    ("SELECT ", "SQL"),
+    ("\"", "JSON"),
+];
+
+/// the language the text most looks like, None when nothing tells
+pub fn guess<'a>(syntax_set: &'a SyntaxSet, text: &str) -> Option<&'a SyntaxReference> {
+    let first_line = text.lines().next().unwrap_or_default();
+    if let Some(syntax) = syntax_set.find_syntax_by_first_line(first_line) {
+        return Some(syntax);
+    }
+    let trimmed = text.trim_start();
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<Value>(text).is_ok()
+    {
+        return syntax_set.find_syntax_by_name("JSON");
+    }
+    // (language, lines telling it) in order of appearance
+    let mut scores: Vec<(&str, usize)> = vec![];
+    for line in text.lines().take(LINES) {
+        let line = line.trim_start();
+        let language = match TELLS.iter().find(|(start, _)| line.starts_with(start)) {
+            Some((_, language)) => language,
+            None => continue,
+        };
+        match scores.iter_mut().find(|(l, _)| l == language) {
+            Some((_, score)) => *score += 1,
+            None => scores.push((language, 1)),
+        }
+    }
+    // the first one seen wins a tie
+    let (language, _) = scores.iter().rev().max_by_key(|(_, score)| score)?;
+    syntax_set.find_syntax_by_name(language)
+}
+
+#[cfg(all(test, feature = "bundled-assets"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guess_languages() {
+        let syntax_set = SyntaxSet::load_defaults_newlines();
+        let name = |text: &str| guess(&syntax_set, text).map(|syntax| syntax.name.clone());
+        assert_eq!(
+            name("#!/bin/bash\necho hi\n").as_deref(),
+            Some("Bourne Again Shell (bash)")
+        );
+        assert_eq!(name("{\"a\": [1, 2]}\n").as_deref(), Some("JSON"));
+        assert_eq!(
+            name("use std::io;\n\nfn main() {\n    let mut x = 1;\n}\n").as_deref(),
+            Some("Rust")
+        );
+        assert_eq!(
+            name("import os\n\ndef main():\n    pass\n").as_deref(),
+            Some("Python")
+        );
+        assert_eq!(
+            name("{\n  \"a\": 1,\n  \"b\": [\n").as_deref(),
+            Some("JSON")
+        );
+        assert_eq!(name("{ not json\n"), None);
+        assert_eq!(name("just some words\n"), None);
+    }
+}
@@ -0,0 +1,58 @@
+//! Definitions of words, from a local dictionary file or a command line like `dict`
+//!
+//! The file has a word and a definition per line, separated by a tab, like `ted\ta small editor`,
+//! a word defined on several lines having several senses.
+
+use crate::format;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// definitions of the word in the file, ignoring case
+pub fn lookup(path: &Path, word: &str) -> io::Result<Vec<String>> {
+    let text = fs::read_to_string(path)?;
+    Ok(text
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter(|(entry, _)| entry.trim().eq_ignore_ascii_case(word))
+        .map(|(_, definition)| definition.trim().to_string())
+        .collect())
+}
+
+/// the output of the command line given the word as last argument
+pub fn run(command: &str, word: &str) -> io::Result<String> {
+    format::run(&format!("{} {}", command, word), "", "")
+}
+
+/// a page listing the senses of a word
+pub fn format(word: &str, definitions: &[String]) -> String {
+    let mut text = format!("# {}\n\n", word);
+    for (i, definition) in definitions.iter().enumerate() {
+        text.push_str(&format!("{}. {}\n", i + 1, definition));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn look_up_words() {
+        let path = std::env::temp_dir().join(format!("ted-dictionary-{}", std::process::id()));
+        fs::write(
+            &path,
+            "ted\ta small editor\ntedious\ttoo long\nTed\tshort for Edward\nno tab here\n",
+        )
+        .unwrap();
+        let definitions = lookup(&path, "TED").unwrap();
+        assert_eq!(definitions, vec!["a small editor", "short for Edward"]);
+        assert_eq!(
+            format("ted", &definitions),
+            "# ted\n\n1. a small editor\n2. short for Edward\n"
+        );
+        assert!(lookup(&path, "editor").unwrap().is_empty());
+        fs::remove_file(&path).unwrap();
+        assert!(lookup(&path, "ted").is_err());
+    }
+}
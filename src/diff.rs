@@ -0,0 +1,181 @@
+use std::ops::Range;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Diff<'a> {
+    Same(&'a str),
+    Added(&'a str),
+    Removed(&'a str),
+}
+
+/// line based diff from old to new (longest common subsequence)
+pub fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Diff<'a>> {
+    let (n, m) = (old.len(), new.len());
+    // lcs[i][j] = length of the lcs of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut v = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            v.push(Diff::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            v.push(Diff::Removed(old[i]));
+            i += 1;
+        } else {
+            v.push(Diff::Added(new[j]));
+            j += 1;
+        }
+    }
+    v.extend(old[i..].iter().map(|line| Diff::Removed(line)));
+    v.extend(new[j..].iter().map(|line| Diff::Added(line)));
+    v
+}
+
+/// renders a diff with one `+`, `-` or ` ` prefixed line per entry
+pub fn format_diff(old_name: &str, new_name: &str, diff: &[Diff]) -> String {
+    let mut s = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for d in diff {
+        let (prefix, line) = match d {
+            Diff::Same(line) => (' ', line),
+            Diff::Added(line) => ('+', line),
+            Diff::Removed(line) => ('-', line),
+        };
+        s.push(prefix);
+        s.push_str(line.trim_end_matches('\n'));
+        s.push('\n');
+    }
+    s
+}
+
+fn push_line(s: &mut String, line: &str) {
+    s.push_str(line);
+    if !line.ends_with('\n') {
+        s.push('\n');
+    }
+}
+
+/// merges two versions, wrapping each differing hunk in git style conflict markers,
+/// returns the merged text and the number of hunks
+pub fn merge_with_markers(mine: &str, theirs: &str, theirs_label: &str) -> (String, usize) {
+    let old: Vec<&str> = mine.split_inclusive('\n').collect();
+    let new: Vec<&str> = theirs.split_inclusive('\n').collect();
+    let mut merged = String::new();
+    let mut hunks = 0;
+    let (mut removed, mut added): (Vec<&str>, Vec<&str>) = (vec![], vec![]);
+    let mut flush = |merged: &mut String, removed: &mut Vec<&str>, added: &mut Vec<&str>| {
+        if removed.is_empty() && added.is_empty() {
+            return;
+        }
+        hunks += 1;
+        merged.push_str("<<<<<<< mine\n");
+        removed.drain(..).for_each(|line| push_line(merged, line));
+        merged.push_str("=======\n");
+        added.drain(..).for_each(|line| push_line(merged, line));
+        merged.push_str(&format!(">>>>>>> {}\n", theirs_label));
+    };
+    for d in diff_lines(&old, &new) {
+        match d {
+            Diff::Same(line) => {
+                flush(&mut merged, &mut removed, &mut added);
+                merged.push_str(line);
+            }
+            Diff::Removed(line) => removed.push(line),
+            Diff::Added(line) => added.push(line),
+        }
+    }
+    flush(&mut merged, &mut removed, &mut added);
+    (merged, hunks)
+}
+
+/// finds the conflict hunk around line_number, returns its range of lines
+/// along with the lines of the picked side
+pub fn pick_hunk(lines: &[&str], line_number: usize, mine: bool) -> Option<(Range<usize>, String)> {
+    let is = |i: usize, marker: &str| lines[i].starts_with(marker);
+    let line_number = line_number.min(lines.len().checked_sub(1)?);
+    let start = (0..=line_number)
+        .rev()
+        .find(|&i| is(i, "<<<<<<< ") || (i < line_number && is(i, ">>>>>>> ")))?;
+    if !is(start, "<<<<<<< ") {
+        return None;
+    }
+    let end = (start..lines.len()).find(|&i| is(i, ">>>>>>> "))?;
+    let separator = (start..end).find(|&i| lines[i].trim_end() == "=======")?;
+    let side = if mine {
+        start + 1..separator
+    } else {
+        separator + 1..end
+    };
+    Some((start..end + 1, lines[side].concat()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_lines() {
+        let old = vec!["a\n", "b\n", "c\n"];
+        let new = vec!["a\n", "c\n", "d\n"];
+        assert_eq!(
+            super::diff_lines(&old, &new),
+            vec![
+                Diff::Same("a\n"),
+                Diff::Removed("b\n"),
+                Diff::Same("c\n"),
+                Diff::Added("d\n"),
+            ]
+        );
+        assert_eq!(super::diff_lines(&[], &new).len(), 3);
+        assert_eq!(super::diff_lines(&old, &[]).len(), 3);
+    }
+
+    #[test]
+    fn merge_and_pick() {
+        let (merged, hunks) = merge_with_markers("a\nb\nc\n", "a\nB\nc\nd", "disk");
+        assert_eq!(hunks, 2);
+        assert_eq!(
+            merged,
+            "a\n<<<<<<< mine\nb\n=======\nB\n>>>>>>> disk\nc\n<<<<<<< mine\n=======\nd\n>>>>>>> disk\n"
+        );
+        let lines: Vec<&str> = merged.split_inclusive('\n').collect();
+        assert_eq!(
+            pick_hunk(&lines, 2, true),
+            Some((1..6, String::from("b\n")))
+        );
+        assert_eq!(
+            pick_hunk(&lines, 5, false),
+            Some((1..6, String::from("B\n")))
+        );
+        assert_eq!(pick_hunk(&lines, 0, true), None);
+        assert_eq!(pick_hunk(&lines, 6, true), None);
+        assert_eq!(
+            pick_hunk(&lines, 8, false),
+            Some((7..11, String::from("d\n")))
+        );
+        assert_eq!(pick_hunk(&lines, 8, true), Some((7..11, String::new())));
+        assert_eq!(
+            merge_with_markers("a\n", "a\n", "disk"),
+            (String::from("a\n"), 0)
+        );
+    }
+
+    #[test]
+    fn format_diff() {
+        let diff = vec![Diff::Same("a\n"), Diff::Removed("b"), Diff::Added("c\n")];
+        assert_eq!(
+            super::format_diff("old", "new", &diff),
+            "--- old\n+++ new\n a\n-b\n+c\n"
+        );
+    }
+}
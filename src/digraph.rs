@@ -0,0 +1,126 @@
+//! Characters missing from the keyboard, typed as two keys after `Ctrl-k` in INSERT mode, mostly
+//! as in vim and RFC 1345, or as a code point after `Ctrl-v`
+
+/// pairs of keys and the character they stand for, either order works
+const DIGRAPHS: [(&str, char); 72] = [
+    ("a'", 'á'),
+    ("e'", 'é'),
+    ("i'", 'í'),
+    ("o'", 'ó'),
+    ("u'", 'ú'),
+    ("E'", 'É'),
+    ("a!", 'à'),
+    ("e!", 'è'),
+    ("i!", 'ì'),
+    ("o!", 'ò'),
+    ("u!", 'ù'),
+    ("A!", 'À'),
+    ("a>", 'â'),
+    ("e>", 'ê'),
+    ("i>", 'î'),
+    ("o>", 'ô'),
+    ("u>", 'û'),
+    ("a:", 'ä'),
+    ("e:", 'ë'),
+    ("i:", 'ï'),
+    ("o:", 'ö'),
+    ("u:", 'ü'),
+    ("y:", 'ÿ'),
+    ("A:", 'Ä'),
+    ("O:", 'Ö'),
+    ("U:", 'Ü'),
+    ("a?", 'ã'),
+    ("o?", 'õ'),
+    ("n?", 'ñ'),
+    ("N?", 'Ñ'),
+    ("c,", 'ç'),
+    ("C,", 'Ç'),
+    ("ss", 'ß'),
+    ("ae", 'æ'),
+    ("AE", 'Æ'),
+    ("oe", 'œ'),
+    ("o/", 'ø'),
+    ("aa", 'å'),
+    ("AA", 'Å'),
+    ("Eu", '€'),
+    ("Pd", '£'),
+    ("Ye", '¥'),
+    ("Ct", '¢'),
+    ("Co", '©'),
+    ("Rg", '®'),
+    ("TM", '™'),
+    ("SE", '§'),
+    ("DG", '°'),
+    ("+-", '±'),
+    ("*X", '×'),
+    ("-:", '÷'),
+    ("!=", '≠'),
+    ("=<", '≤'),
+    (">=", '≥'),
+    ("?2", '≈'),
+    ("00", '∞'),
+    ("->", '→'),
+    ("<-", '←'),
+    ("-N", '–'),
+    ("-M", '—'),
+    (",.", '…'),
+    ("<<", '«'),
+    (">>", '»'),
+    ("\"6", '“'),
+    ("\"9", '”'),
+    ("a*", 'α'),
+    ("b*", 'β'),
+    ("l*", 'λ'),
+    ("m*", 'μ'),
+    ("p*", 'π'),
+    ("OK", '✓'),
+    ("XX", '✗'),
+];
+
+/// the character of a pair of keys
+pub fn lookup(first: char, second: char) -> Option<char> {
+    let find = |a: char, b: char| {
+        DIGRAPHS
+            .iter()
+            .find(|(keys, _)| keys.chars().eq([a, b]))
+            .map(|(_, c)| *c)
+    };
+    find(first, second).or_else(|| find(second, first))
+}
+
+/// the character of a hexadecimal code point, written `1F600`, `U+1F600` or `\u{1F600}`
+pub fn codepoint(text: &str) -> Option<char> {
+    let text = text.trim();
+    let digits = match text.strip_prefix("\\u{") {
+        Some(digits) => digits,
+        None => text
+            .strip_prefix("U+")
+            .or_else(|| text.strip_prefix("u+"))
+            .unwrap_or(text),
+    };
+    let digits = digits.strip_suffix('}').unwrap_or(digits);
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn special_characters() {
+        assert_eq!(lookup('e', '\''), Some('é'));
+        assert_eq!(lookup('\'', 'e'), Some('é'));
+        assert_eq!(lookup('E', 'u'), Some('€'));
+        assert_eq!(lookup('-', '>'), Some('→'));
+        assert_eq!(lookup('q', 'q'), None);
+        assert_eq!(codepoint("1F600"), Some('😀'));
+        assert_eq!(codepoint("U+e9"), Some('é'));
+        assert_eq!(codepoint("\\u{2192}"), Some('→'));
+        assert_eq!(codepoint("2192}"), Some('→'));
+        assert_eq!(codepoint("D800"), None);
+        assert_eq!(codepoint("zz"), None);
+        assert_eq!(codepoint(""), None);
+    }
+}
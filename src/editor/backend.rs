@@ -0,0 +1,104 @@
+//! The terminal ted draws on: the real one through crossterm, or a grid of cells in memory
+//! for batch scripts and for tests asserting what gets drawn
+
+use std::io;
+use tui::backend::{Backend, CrosstermBackend, TestBackend};
+use tui::buffer::{Buffer, Cell};
+use tui::layout::Rect;
+
+pub enum TermBackend {
+    Crossterm(CrosstermBackend<io::Stdout>),
+    Test(TestBackend),
+}
+
+impl TermBackend {
+    pub fn crossterm() -> Self {
+        TermBackend::Crossterm(CrosstermBackend::new(io::stdout()))
+    }
+
+    pub fn test(width: u16, height: u16) -> Self {
+        TermBackend::Test(TestBackend::new(width, height))
+    }
+
+    /// the cells drawn so far, None on a real terminal
+    pub fn drawn(&self) -> Option<&Buffer> {
+        match self {
+            TermBackend::Test(backend) => Some(backend.buffer()),
+            TermBackend::Crossterm(_) => None,
+        }
+    }
+
+    /// the rows drawn so far, None on a real terminal
+    pub fn screen(&self) -> Option<Vec<String>> {
+        let buffer = self.drawn()?;
+        let width = buffer.area.width as usize;
+        let symbols: Vec<&str> = buffer.content.iter().map(|c| c.symbol.as_str()).collect();
+        Some(
+            symbols
+                .chunks(width.max(1))
+                .map(|row| row.concat())
+                .collect(),
+        )
+    }
+}
+
+impl Backend for TermBackend {
+    fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        match self {
+            TermBackend::Crossterm(backend) => backend.draw(content),
+            TermBackend::Test(backend) => backend.draw(content),
+        }
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        match self {
+            TermBackend::Crossterm(backend) => backend.hide_cursor(),
+            TermBackend::Test(backend) => backend.hide_cursor(),
+        }
+    }
+
+    fn show_cursor(&mut self) -> io::Result<()> {
+        match self {
+            TermBackend::Crossterm(backend) => backend.show_cursor(),
+            TermBackend::Test(backend) => backend.show_cursor(),
+        }
+    }
+
+    fn get_cursor(&mut self) -> io::Result<(u16, u16)> {
+        match self {
+            TermBackend::Crossterm(backend) => backend.get_cursor(),
+            TermBackend::Test(backend) => backend.get_cursor(),
+        }
+    }
+
+    fn set_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+        match self {
+            TermBackend::Crossterm(backend) => backend.set_cursor(x, y),
+            TermBackend::Test(backend) => backend.set_cursor(x, y),
+        }
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        match self {
+            TermBackend::Crossterm(backend) => backend.clear(),
+            TermBackend::Test(backend) => backend.clear(),
+        }
+    }
+
+    fn size(&self) -> io::Result<Rect> {
+        match self {
+            TermBackend::Crossterm(backend) => backend.size(),
+            TermBackend::Test(backend) => backend.size(),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            TermBackend::Crossterm(backend) => backend.flush(),
+            TermBackend::Test(backend) => backend.flush(),
+        }
+    }
+}
@@ -0,0 +1,645 @@
+use std::ops::Range;
+use ted::buffer::InputMode;
+use ted::buffer::{HighlightedLine, Lines};
+use ted::color;
+use ted::color::ColorDepth;
+use ted::config::Whitespace;
+use ted::git::LineChange;
+use ted::lsp::Severity;
+use ted::todo;
+use ted::Buffer;
+use tui::buffer::Cell;
+use tui::layout::Rect;
+use tui::style::Color;
+use tui::style::Modifier;
+use tui::style::Style;
+use tui::text::Span;
+use tui::text::Spans;
+use tui::widgets::StatefulWidget;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Default)]
+pub struct BufferWidget<'a> {
+    /// the rows drawn last, to reuse
+    rows: Option<&'a mut Rows>,
+}
+
+impl<'a> BufferWidget<'a> {
+    /// draws again only the rows of text that changed since the last draw
+    pub fn cached(rows: &'a mut Rows) -> Self {
+        Self { rows: Some(rows) }
+    }
+}
+
+/// what the rows of text depend on besides their own line
+#[derive(PartialEq)]
+struct View {
+    area: Rect,
+    left: u16,
+    columns: usize,
+    high_contrast: bool,
+    depth: ColorDepth,
+    whitespace: Whitespace,
+    guide_color: Color,
+}
+
+/// what a row of text was drawn from: its highlighted line and the chars of it shown,
+/// whether it was the cursor's and the number of lines of its closed fold
+#[derive(PartialEq)]
+struct RowKey {
+    line: HighlightedLine,
+    chars: Range<usize>,
+    cursor_line: bool,
+    fold: Option<usize>,
+}
+
+/// the rows of text of the last draw with the cells they gave, the gutter and what is drawn
+/// over the text (selection, matches, diagnostics...) being drawn every time
+#[derive(Default)]
+pub struct Rows {
+    view: Option<View>,
+    rows: Vec<Option<(RowKey, Vec<Cell>)>>,
+    /// rows drawn again by the last draw
+    pub damaged: usize,
+}
+
+impl Rows {
+    /// forgets every row when the view changed
+    fn start(&mut self, view: View, height: usize) {
+        if self.view.as_ref() != Some(&view) {
+            self.rows.clear();
+            self.view = Some(view);
+        }
+        self.rows.resize_with(height, || None);
+        self.damaged = 0;
+    }
+}
+
+pub fn tui_color(color: color::Color) -> Color {
+    match color {
+        color::Color::Rgb(r, g, b) => Color::Rgb(r, g, b),
+        color::Color::Indexed(i) => Color::Indexed(i),
+        color::Color::Ansi(i) => [
+            Color::Black,
+            Color::Red,
+            Color::Green,
+            Color::Yellow,
+            Color::Blue,
+            Color::Magenta,
+            Color::Cyan,
+            Color::Gray,
+            Color::DarkGray,
+            Color::LightRed,
+            Color::LightGreen,
+            Color::LightYellow,
+            Color::LightBlue,
+            Color::LightMagenta,
+            Color::LightCyan,
+            Color::White,
+        ]
+        .get(i as usize)
+        .copied()
+        .unwrap_or(Color::Reset),
+    }
+}
+
+/// text attributes standing in for the colors of a syntect style
+fn high_contrast_style(
+    style: &syntect::highlighting::Style,
+    foreground: Option<syntect::highlighting::Color>,
+) -> Style {
+    use syntect::highlighting::FontStyle;
+    let mut s = Style::default().fg(Color::White).bg(Color::Black);
+    if style.font_style.contains(FontStyle::BOLD) || Some(style.foreground) != foreground {
+        s = s.add_modifier(Modifier::BOLD);
+    }
+    if style
+        .font_style
+        .intersects(FontStyle::UNDERLINE | FontStyle::ITALIC)
+    {
+        s = s.add_modifier(Modifier::UNDERLINED);
+    }
+    s
+}
+
+impl StatefulWidget for BufferWidget<'_> {
+    type State = Buffer;
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer, state: &mut Self::State) {
+        let (cursor, line_number, column_number) = state.get_cursor();
+        let status_line_number = area.height.saturating_sub(1);
+
+        // draw lines from buffer
+        let default_style = syntect::highlighting::Style {
+            foreground: syntect::highlighting::Color::WHITE,
+            background: syntect::highlighting::Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 0xff,
+            },
+            font_style: syntect::highlighting::FontStyle::default(),
+        };
+        let lines = match state.get_visible_lines() {
+            Lines::Highlighted(lines) => lines,
+            Lines::Plain(lines) => lines
+                .iter()
+                .cloned()
+                .map(|line| {
+                    let n = line.len();
+                    (line, vec![(default_style, 0..n)])
+                })
+                .collect(),
+        };
+        let line_numbers = state.visible_line_numbers();
+        let screen_rows = state.screen_rows();
+        // whether a row is the last one of its line
+        let last_row = |y: usize| screen_rows.get(y + 1).map(|(n, _)| n) != Some(&screen_rows[y].0);
+        let selection = state.get_selection_spans();
+        let high_contrast = state.get_config().high_contrast.get();
+        let depth = state.get_config().color_depth.get();
+        let whitespace = state.whitespace();
+        let columns = state.get_columns().clone();
+        // text is drawn right of the gutter
+        let left = state.gutter_width() as u16;
+        let width = area.width.saturating_sub(left);
+        let theme_foreground = state
+            .get_highlighter()
+            .as_ref()
+            .and_then(|h| h.theme.settings.foreground);
+        if high_contrast {
+            buf.set_style(area, Style::default().fg(Color::White).bg(Color::Black));
+        }
+        // tints the cursor's line and column and the rulers
+        let (r, g, b) = state
+            .get_highlighter()
+            .as_ref()
+            .and_then(|h| h.theme.settings.line_highlight)
+            .map(|c| (c.r, c.g, c.b))
+            .unwrap_or_else(|| state.get_config().cursor_highlight.get());
+        let guide_color = tui_color(depth.convert(r, g, b));
+        let crosshair = state.get_config().crosshair.get();
+        let cursor_line = crosshair || state.get_config().cursor_line.get();
+        let cursor_column = crosshair || state.get_config().cursor_column.get();
+        let max_line_length = state.max_line_length();
+        let overflow_color = tui_color(depth.convert(0x87, 0x00, 0x00));
+
+        let mut rows = self.rows;
+        if let Some(rows) = rows.as_mut() {
+            let view = View {
+                area,
+                left,
+                columns: columns.start,
+                high_contrast,
+                depth,
+                whitespace,
+                guide_color,
+            };
+            rows.start(view, status_line_number as usize);
+        }
+        let text_area = |y: u16| (left..area.right()).map(move |x| (x, y));
+        for y in 0..status_line_number {
+            let row = screen_rows.get(y as usize).and_then(|(n, chars)| {
+                let i = line_numbers.iter().position(|m| m == n)?;
+                Some((*n, chars.clone(), lines.get(i)?))
+            });
+            if let Some((row_line_number, chars, (line, ranges))) = row {
+                let is_cursor_line =
+                    cursor_line && row_line_number == line_number && selection.is_none();
+                let fold = state
+                    .closed_fold_at(row_line_number)
+                    .filter(|_| last_row(y as usize))
+                    .map(|fold| fold.len());
+                let key = RowKey {
+                    line: (line.clone(), ranges.clone()),
+                    chars: chars.clone(),
+                    cursor_line: is_cursor_line,
+                    fold,
+                };
+                let cached = rows
+                    .as_ref()
+                    .and_then(|rows| rows.rows[y as usize].as_ref())
+                    .filter(|(cached, _)| *cached == key);
+                if let Some((_, cells)) = cached {
+                    for ((x, y), cell) in text_area(y).zip(cells) {
+                        *buf.get_mut(x, y) = cell.clone();
+                    }
+                    continue;
+                }
+                if is_cursor_line {
+                    let style = if high_contrast {
+                        Style::default().add_modifier(Modifier::UNDERLINED)
+                    } else {
+                        Style::default().bg(guide_color)
+                    };
+                    buf.set_style(Rect::new(left, y, width, 1), style)
+                }
+                // skip the chars scrolled out of view on the left or drawn on rows above
+                let mut skip = chars.start;
+                let mut take = chars.len();
+                let spans = Spans::from(
+                    ranges
+                        .iter()
+                        .map(|(style, r)| {
+                            let text: String =
+                                line[r.clone()].chars().skip(skip).take(take).collect();
+                            skip = skip.saturating_sub(line[r.clone()].chars().count());
+                            take -= text.chars().count();
+                            Span::styled(
+                                whitespace.render(&text),
+                                if high_contrast {
+                                    high_contrast_style(style, theme_foreground)
+                                } else {
+                                    Style::default().fg(tui_color(depth.convert(
+                                        style.foreground.r,
+                                        style.foreground.g,
+                                        style.foreground.b,
+                                    )))
+                                },
+                            )
+                        })
+                        .collect::<Vec<Span>>(),
+                );
+                buf.set_spans(left, y, &spans, width);
+
+                // emphasize TODO, FIXME and HACK markers, where the wide chars before them
+                // put them
+                let shown_width = |range: Range<usize>| {
+                    let text: String = line
+                        .chars()
+                        .take(range.end.min(chars.end))
+                        .skip(range.start.max(chars.start))
+                        .collect();
+                    whitespace.render(&text).width()
+                };
+                for marker in todo::markers(line) {
+                    let start = shown_width(0..marker.start);
+                    let end = start + shown_width(marker);
+                    for x in start..end.min(width as usize) {
+                        let cell = buf.get_mut(left + x as u16, y);
+                        cell.modifier.insert(Modifier::BOLD | Modifier::REVERSED);
+                    }
+                }
+
+                let content = line.trim_end_matches(&['\r', '\n'][..]);
+                // summarize the lines hidden by a closed fold
+                if let Some(fold) = fold {
+                    let x = shown_width(0..content.chars().count()) as u16;
+                    if x < width {
+                        buf.set_stringn(
+                            left + x,
+                            y,
+                            format!(" ··· {} lines", fold - 1),
+                            (width - x) as usize,
+                            Style::default().fg(Color::DarkGray),
+                        );
+                    }
+                }
+
+                if whitespace.trailing {
+                    let start = shown_width(0..content.trim_end().chars().count()) as u16;
+                    let end = (shown_width(0..content.chars().count()) as u16).min(width);
+                    for x in start..end {
+                        buf.get_mut(left + x, y).set_bg(Color::Red);
+                    }
+                }
+
+                if let Some(rows) = rows.as_mut() {
+                    let cells = text_area(y).map(|(x, y)| buf.get(x, y).clone()).collect();
+                    rows.rows[y as usize] = Some((key, cells));
+                    rows.damaged += 1;
+                }
+            } else if whitespace.newlines {
+                buf.set_string(left, y, "~", Style::default());
+            }
+        }
+
+        // number the lines, after the git markers
+        let number_width = state.number_width();
+        if number_width > 0 {
+            let x = state.get_git_changes().is_some() as u16;
+            for (y, (n, _)) in screen_rows
+                .iter()
+                .enumerate()
+                .take(status_line_number as usize)
+            {
+                // a wrapped line is numbered on its first row
+                if y > 0 && screen_rows[y - 1].0 == *n {
+                    continue;
+                }
+                let style = match *n == line_number {
+                    true => Style::default().add_modifier(Modifier::BOLD),
+                    false => Style::default().fg(Color::DarkGray),
+                };
+                let label = format!("{:>1$} ", state.line_label(*n), number_width - 1);
+                buf.set_string(x, y as u16, label, style);
+            }
+        }
+
+        // mark lines changed since the last commit
+        if let Some(changes) = state.get_git_changes() {
+            for (line_number, change) in changes {
+                let row = state.screen_row(*line_number);
+                if let Some(y) = row.filter(|y| *y < status_line_number as usize) {
+                    let (symbol, color) = match change {
+                        LineChange::Added => ("+", Color::Green),
+                        LineChange::Modified => ("~", Color::Yellow),
+                        LineChange::Deleted => ("-", Color::Red),
+                    };
+                    buf.set_string(0, y as u16, symbol, Style::default().fg(color));
+                }
+            }
+        }
+
+        // the cell drawing the char at a line and column, if any
+        let cell_of = |line_number: usize, column: usize| {
+            state
+                .screen_position(line_number, column)
+                .filter(|(y, x)| *y < status_line_number as usize && *x < width as usize)
+                .map(|(y, x)| (left + x as u16, y as u16))
+        };
+
+        // underline problems reported by a language server
+        let window = state.get_window().clone();
+        for diagnostic in state.get_diagnostics() {
+            let color = match diagnostic.severity {
+                Severity::Error => Color::Red,
+                Severity::Warning => Color::Yellow,
+                _ => Color::Blue,
+            };
+            for line_number in diagnostic.start.0..=diagnostic.end.0 {
+                if state.screen_row(line_number).is_none() {
+                    continue;
+                }
+                let start = if line_number == diagnostic.start.0 {
+                    diagnostic.start.1
+                } else {
+                    0
+                };
+                let end = if line_number == diagnostic.end.0 {
+                    diagnostic.end.1
+                } else {
+                    state
+                        .get_line(line_number)
+                        .map_or(0, |line| line.chars().count())
+                };
+                for (x, y) in (start..end.max(start + 1)).filter_map(|x| cell_of(line_number, x)) {
+                    let cell = buf.get_mut(x, y);
+                    cell.modifier.insert(Modifier::UNDERLINED);
+                    cell.set_fg(color);
+                }
+            }
+        }
+
+        // highlight the matches of the last search
+        if let Some(search) = state.get_search() {
+            for m in &search.matches {
+                let (line_number, start) = state.coord_from_pos(m.start);
+                if state.screen_row(line_number).is_none() {
+                    continue;
+                }
+                for (x, y) in (start..start + m.len()).filter_map(|x| cell_of(line_number, x)) {
+                    let cell = buf.get_mut(x, y);
+                    cell.set_fg(Color::Black);
+                    cell.set_bg(Color::Yellow);
+                }
+            }
+        }
+
+        // preview a replacement being typed: each match struck out, followed by what replaces it
+        let mut previewed: Vec<(usize, usize, usize, &str)> = vec![];
+        for (m, replacement) in state.get_replacements() {
+            let (line_number, start) = state.coord_from_pos(m.start);
+            if state.screen_row(line_number).is_some() {
+                previewed.push((line_number, start, start + m.len(), replacement));
+            }
+        }
+        let removed = Style::default()
+            .fg(Color::DarkGray)
+            .add_modifier(Modifier::CROSSED_OUT);
+        let inserted = Style::default().fg(Color::Black).bg(Color::Green);
+        let mut previewed_lines: Vec<usize> = previewed.iter().map(|p| p.0).collect();
+        previewed_lines.dedup();
+        for line_number in previewed_lines {
+            let first = match state.screen_row(line_number) {
+                Some(y) if y < status_line_number as usize => y,
+                _ => continue,
+            };
+            let line = state.get_line(line_number).unwrap_or_default();
+            let text: Vec<char> = line.trim_end_matches(&['\r', '\n'][..]).chars().collect();
+            let style_at = |x: usize| match cell_of(line_number, x) {
+                Some((x, y)) => buf.get(x, y).style(),
+                None => Style::default(),
+            };
+            let mut cells: Vec<(char, Style)> = vec![];
+            let mut x = 0;
+            for (_, start, end, replacement) in previewed.iter().filter(|p| p.0 == line_number) {
+                let end = (*end).min(text.len());
+                cells.extend((x..*start).map(|x| (text[x], style_at(x))));
+                cells.extend(text[*start..end].iter().map(|c| (*c, removed)));
+                let replacement = replacement.chars().map(|c| if c == '\n' { '↵' } else { c });
+                cells.extend(replacement.map(|c| (c, inserted)));
+                x = end;
+            }
+            cells.extend((x..text.len()).map(|x| (text[x], style_at(x))));
+            // flowed over the rows of the line, cut to the view's columns when not wrapped
+            let rows = screen_rows[first..]
+                .iter()
+                .take_while(|(n, _)| *n == line_number)
+                .count();
+            let visible = cells
+                .into_iter()
+                .skip(columns.start)
+                .take(width as usize * rows)
+                .enumerate();
+            for (i, (c, style)) in visible {
+                let (x, y) = (i % width as usize, first + i / width as usize);
+                if y < status_line_number as usize {
+                    let cell = buf.get_mut(left + x as u16, y as u16);
+                    cell.set_char(c);
+                    cell.set_style(style);
+                }
+            }
+        }
+
+        // tint the ruler columns and the one after max_line_length
+        let ruler = state.get_config().ruler.borrow().clone();
+        for column in ruler.into_iter().chain(max_line_length.map(|max| max + 1)) {
+            let x = match column.checked_sub(columns.start + 1) {
+                Some(x) if x < width as usize => left + x as u16,
+                _ => continue,
+            };
+            for y in 0..status_line_number {
+                let cell = buf.get_mut(x, y);
+                if high_contrast {
+                    cell.modifier.insert(Modifier::UNDERLINED);
+                } else {
+                    cell.set_bg(guide_color);
+                }
+            }
+        }
+
+        // highlight what overflows max_line_length, over its guide
+        if let Some(max) = max_line_length {
+            for (n, (line, _)) in line_numbers.iter().zip(&lines) {
+                let length = line.trim_end_matches(&['\r', '\n'][..]).chars().count();
+                for (x, y) in (max..length).filter_map(|x| cell_of(*n, x)) {
+                    let cell = buf.get_mut(x, y);
+                    if high_contrast {
+                        cell.modifier.insert(Modifier::REVERSED);
+                    } else {
+                        cell.set_bg(overflow_color);
+                    }
+                }
+            }
+        }
+
+        // highlight the cursor's column
+        if cursor_column && selection.is_none() {
+            let cursor_x = state
+                .screen_position(line_number, column_number)
+                .map_or(column_number.saturating_sub(columns.start), |(_, x)| x)
+                as u16;
+            if cursor_x < width {
+                for y in 0..status_line_number {
+                    let cell = buf.get_mut(left + cursor_x, y);
+                    if high_contrast {
+                        cell.modifier.insert(Modifier::UNDERLINED);
+                    } else {
+                        cell.set_bg(guide_color);
+                    }
+                }
+            }
+        }
+
+        // show selected text, reversed when the theme has no selection color
+        let theme_selection = state
+            .get_highlighter()
+            .as_ref()
+            .and_then(|h| h.theme.settings.selection)
+            .filter(|_| !high_contrast);
+        let selected_style = match theme_selection {
+            Some(color) => Style::default().bg(tui_color(depth.convert(color.r, color.g, color.b))),
+            None => Style::default().add_modifier(Modifier::REVERSED),
+        };
+        for (y, columns) in selection.into_iter().flatten() {
+            let area = Rect::new(left + columns.start, y, columns.len() as u16, 1);
+            buf.set_style(area.intersection(buf.area), selected_style);
+        }
+
+        // draw status line
+        let status = match (state.mode, state.get_config().paste_mode.get()) {
+            (InputMode::Normal, _) => "NORMAL MODE",
+            (InputMode::Insert, false) => "INSERT MODE",
+            (InputMode::Insert, true) => "INSERT (PASTE) MODE",
+        };
+        let errors = state
+            .get_diagnostics()
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+        let warnings = state.get_diagnostics().len() - errors;
+        let editorconfig = state.get_editorconfig().summary();
+        let line = format!(
+            "{}{}{}{}{}{}{} - {} - ({}x{}) at {} ({}:{}) {}, lines [{} to {}), columns [{} to {}) ({} - {})",
+            state.name,
+            if state.readonly { " [RO]" } else { "" },
+            match state.get_working_dir() {
+                Some(dir) => format!(" [cd {}]", if dir.is_empty() { "." } else { dir }),
+                None => String::default(),
+            },
+            match state.hex_offset() {
+                Some(offset) => format!(" [hex 0x{:x}]", offset),
+                None if state.is_hex() => String::from(" [hex]"),
+                None => String::default(),
+            },
+            if errors + warnings > 0 {
+                format!(" [E:{} W:{}]", errors, warnings)
+            } else {
+                String::default()
+            },
+            if editorconfig.is_empty() {
+                editorconfig
+            } else {
+                format!(" [{}]", editorconfig)
+            },
+            if state.get_config().status_count.get() {
+                format!(" [{} words]", state.count().words)
+            } else {
+                String::default()
+            },
+            status,
+            area.width,
+            area.height,
+            cursor,
+            line_number,
+            column_number,
+            state.scroll_percentage(),
+            window.start,
+            window.end,
+            columns.start,
+            columns.end,
+            state
+                .get_highlighter()
+                .as_ref()
+                .map(|cached| &cached.syntax.name)
+                .unwrap_or(&"Plain Text".to_string()),
+            state
+                .get_highlighter()
+                .as_ref()
+                .and_then(|cached| cached.theme.name.as_ref())
+                .unwrap_or(&"No Theme".to_string()),
+        );
+        buf.set_string(0, status_line_number, line, Style::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::Ted;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ted::Config;
+
+    #[test]
+    fn draw_buffer() {
+        let mut ted = Ted::headless(Config::builtin()).unwrap();
+        ted.new_buffer(String::from("first line\nsecond\n"));
+        let screen = ted.screen().unwrap();
+        assert_eq!(screen.len(), 24);
+        assert!(screen[0].starts_with("first line"));
+        assert!(screen[1].starts_with("second"));
+        assert!(screen[23].starts_with("Created new buffer"));
+        for c in " srline\nX".chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                c => KeyCode::Char(c),
+            };
+            ted.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+        let screen = ted.screen().unwrap();
+        assert!(screen[0].starts_with("first lineX"));
+        assert!(screen[23].starts_with("Replace 1 matches of line with: X"));
+    }
+
+    #[test]
+    fn draw_damaged_rows() {
+        let config = Config::builtin();
+        config.cursor_line.set(true);
+        let mut ted = Ted::headless(config).unwrap();
+        ted.new_buffer(String::from("one\ntwo\nthree\n"));
+        ted.screen().unwrap();
+        assert_eq!(ted.rows.damaged, 4);
+        ted.screen().unwrap();
+        assert_eq!(ted.rows.damaged, 0);
+        // the cursor's line is tinted, the one it left is not anymore
+        ted.handle_key(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE));
+        ted.screen().unwrap();
+        assert_eq!(ted.rows.damaged, 2);
+        for c in "ia".chars() {
+            ted.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        let screen = ted.screen().unwrap();
+        assert_eq!(ted.rows.damaged, 1);
+        assert!(screen[1].starts_with("atwo"));
+        assert!(screen[2].starts_with("three"));
+    }
+}
@@ -0,0 +1,1196 @@
+use crate::Ted;
+use ted::command::{parse_space_chain, Arguments, Command, Commands};
+use ted::{Buffer, Config};
+
+/// commands available to the editor, listed in the home buffer
+pub fn builtin() -> Commands<Ted> {
+    Commands {
+        commands: vec![
+            Command {
+                name: "space".to_string(),
+                desc: "Enters command by name".to_string(),
+                help: Some(
+                    "Prompts for a command line, a command's name and its arguments, both completed with \
+                    Tab: `goto_line 42`. Commands taking arguments run at once instead of prompting for them."
+                        .to_string(),
+                ),
+                chain: Some("  ".to_string()),
+                action: (|t| {
+                    t.prompt_mode("Command".to_string(), Ted::run_command);
+                    t.prompt_candidates = Some(Ted::complete_command_line);
+                }),
+                arguments: Some(Arguments {
+                    run: Ted::run_command,
+                    candidates: Some(Ted::command_names),
+                }),
+            },
+            Command {
+                name: "quit".to_string(),
+                desc: "Exits Ted".to_string(),
+                help: None,
+                chain: Some(" q".to_string()),
+                action: (|t| t.exit = true),
+                arguments: None,
+            },
+            Command {
+                name: "new_empty_buffer".to_string(),
+                desc: "Creates a new empty buffer".to_string(),
+                help: None,
+                chain: Some(" fn".to_string()),
+                action: (|t| t.new_buffer(String::default())),
+                arguments: None,
+            },
+            Command {
+                name: "file_open".to_string(),
+                desc: "Opens given file (wildcards like src/**/*.rs open every match)".to_string(),
+                help: Some(
+                    "Prompts for a path relative to the working directory of the buffer, completed with \
+                    Tab, and opens it in a new buffer, or focuses it when already open.\n\n\
+                    Quoted wildcards open every match: `file_open \"src/**/*.rs\"` opens the Rust \
+                    files under src, `**` spanning directories. Binary files are skipped.\n\n\
+                    A path in a missing directory opens an empty buffer, saving it offers to \
+                    create the directory."
+                        .to_string(),
+                ),
+                chain: Some(" fo".to_string()),
+                action: Ted::file_open_prompt,
+                arguments: Some(Arguments {
+                    run: Ted::file_open,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "file_recent".to_string(),
+                desc: "Fuzzy finds a recently opened file".to_string(),
+                help: None,
+                chain: Some(" fr".to_string()),
+                action: Ted::file_recent,
+                arguments: None,
+            },
+            Command {
+                name: "file_save".to_string(),
+                desc: "Saves the buffer to a file".to_string(),
+                help: Some(
+                    "Writes the buffer to its file, along with its copies.\n\n\
+                    - `file_save append <path>` adds the buffer at the end of another file, \
+                    created if needed, and leaves its own file alone.\n\
+                    - `file_save tee <path> <path>…` saves the buffer to these paths too, \
+                    now and on every following save; `file_save tee` alone stops, keeping the \
+                    copies configured with `save_copies`.\n\n\
+                    Copies can also be configured per file with `save_copies`. \
+                    Each copy that fails is reported on its own, see them with `errors`."
+                        .to_string(),
+                ),
+                chain: Some(" fs".to_string()),
+                action: Ted::file_save,
+                arguments: Some(Arguments {
+                    run: Ted::file_save_with,
+                    candidates: Some(|_| vec!["append".to_string(), "tee".to_string()]),
+                }),
+            },
+            Command {
+                name: "file_rename".to_string(),
+                desc: "Moves the buffer's file on disk".to_string(),
+                help: Some(
+                    "Moves the buffer's file to another path, prefilled with the current one, and renames the \
+                    buffer after it unless it was named with `buffer_rename`. An existing file is never \
+                    replaced."
+                        .to_string(),
+                ),
+                chain: Some(" fR".to_string()),
+                action: (|t| {
+                    let path = t
+                        .buffers
+                        .focused()
+                        .get_path()
+                        .unwrap_or_default()
+                        .to_string();
+                    t.prompt_mode("Rename to".to_string(), Ted::file_rename);
+                    t.answer.set(path);
+                }),
+                arguments: Some(Arguments {
+                    run: Ted::file_rename,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "file_delete".to_string(),
+                desc: "Deletes the buffer's file from disk".to_string(),
+                help: Some(
+                    "Asks for confirmation, then deletes the buffer's file from disk. The buffer stays open as a \
+                    scratch buffer, so saving it with `file_save` as another file brings the content back."
+                        .to_string(),
+                ),
+                chain: Some(" fD".to_string()),
+                action: (|t| {
+                    let prompt = format!(
+                        "Delete {}? (y/N)",
+                        t.buffers.focused().get_path().unwrap_or("nothing")
+                    );
+                    t.prompt_mode(prompt, Ted::file_delete)
+                }),
+                arguments: None,
+            },
+            Command {
+                name: "next_buffer".to_string(),
+                desc: "Opens the next buffer".to_string(),
+                help: None,
+                chain: Some(" \t".to_string()),
+                action: Ted::next_buffer,
+                arguments: None,
+            },
+            Command {
+                name: "page_left".to_string(),
+                desc: "Moves a screenful of columns left".to_string(),
+                help: None,
+                chain: Some(" lh".to_string()),
+                action: (|t| t.buffers.focused_mut().page_left(1)),
+                arguments: None,
+            },
+            Command {
+                name: "page_right".to_string(),
+                desc: "Moves a screenful of columns right".to_string(),
+                help: None,
+                chain: Some(" ll".to_string()),
+                action: (|t| t.buffers.focused_mut().page_right(1)),
+                arguments: None,
+            },
+            Command {
+                name: "goto_line".to_string(),
+                desc: "Goes to the given 1-based line[:column]".to_string(),
+                help: Some(
+                    "Moves the cursor to a line, counted from 1, and optionally to a column on it: \
+                    `goto_line 42` or `goto_line 42:8`.\n\n\
+                    Lines past the end go to the last one."
+                        .to_string(),
+                ),
+                chain: None,
+                action: (|t| t.prompt_mode("Line".to_string(), Ted::goto_line)),
+                arguments: Some(Arguments {
+                    run: Ted::goto_line,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "goto_column".to_string(),
+                desc: "Moves to given column of the current line".to_string(),
+                help: Some(
+                    "Moves the cursor to a column of its line, counted from 1: `goto_column 80`."
+                        .to_string(),
+                ),
+                chain: Some(" lg".to_string()),
+                action: (|t| t.prompt_mode("Column".to_string(), Ted::goto_column)),
+                arguments: Some(Arguments {
+                    run: Ted::goto_column,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "goto_offset".to_string(),
+                desc: "Goes to the given hexadecimal byte offset of a binary file".to_string(),
+                help: Some(
+                    "In a binary file shown as a hex dump, moves the cursor to a byte offset given in \
+                    hexadecimal, with or without `0x`: `goto_offset 1f0`."
+                        .to_string(),
+                ),
+                chain: None,
+                action: (|t| t.prompt_mode("Offset".to_string(), Ted::goto_offset)),
+                arguments: Some(Arguments {
+                    run: Ted::goto_offset,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "snapshot_buffer".to_string(),
+                desc: "Stores a copy of the buffer's current content".to_string(),
+                help: Some(
+                    "Keeps a copy of the buffer's content for the session, numbered from 0. `list_snapshots` \
+                    shows them, `diff_snapshot` compares one with the current content and `restore_snapshot` brings \
+                    it back."
+                        .to_string(),
+                ),
+                chain: Some(" bs".to_string()),
+                action: Ted::snapshot_buffer,
+                arguments: None,
+            },
+            Command {
+                name: "list_snapshots".to_string(),
+                desc: "Lists the snapshots of the buffer".to_string(),
+                help: None,
+                chain: Some(" bl".to_string()),
+                action: Ted::list_snapshots,
+                arguments: None,
+            },
+            Command {
+                name: "diff_snapshot".to_string(),
+                desc: "Shows changes since given snapshot".to_string(),
+                help: Some(
+                    "Opens a buffer with the differences between a snapshot, by the number `list_snapshots` \
+                    shows, and the current content: `diff_snapshot 0`."
+                        .to_string(),
+                ),
+                chain: Some(" bd".to_string()),
+                action: (|t| t.prompt_mode("Snapshot".to_string(), Ted::diff_snapshot)),
+                arguments: Some(Arguments {
+                    run: Ted::diff_snapshot,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "restore_snapshot".to_string(),
+                desc: "Replaces the buffer's content with given snapshot".to_string(),
+                help: Some(
+                    "Replaces the buffer's content with a snapshot, by the number `list_snapshots` shows: \
+                    `restore_snapshot 0`. Read-only buffers are left alone."
+                        .to_string(),
+                ),
+                chain: Some(" br".to_string()),
+                action: (|t| t.prompt_mode("Snapshot".to_string(), Ted::restore_snapshot)),
+                arguments: Some(Arguments {
+                    run: Ted::restore_snapshot,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "format_buffer".to_string(),
+                desc: "Pipes the buffer (or selection) through its language's formatter"
+                    .to_string(),
+                help: Some(
+                    "Pipes the buffer, or the selection, through the formatter of its language and replaces it \
+                    with the output. `rustfmt`, `prettier` and `black` are preset, others are configured under \
+                    `formatters` in the config file: `{ \"formatters\": { \"Go\": \"gofmt\" } }`.\n\n\
+                    A formatter that fails leaves the buffer alone and its errors are shown."
+                        .to_string(),
+                ),
+                chain: Some(" bf".to_string()),
+                action: Ted::format_buffer,
+                arguments: None,
+            },
+            Command {
+                name: "audit_buffer".to_string(),
+                desc: "Reports mixed line endings, trailing whitespace, invisible characters..."
+                    .to_string(),
+                help: Some(
+                    "Opens a report of what can't be seen in the buffer: mixed line endings, trailing \
+                    whitespace, bidirectional control and other invisible characters, with the line and column of \
+                    each."
+                        .to_string(),
+                ),
+                chain: Some(" ba".to_string()),
+                action: Ted::audit_buffer,
+                arguments: None,
+            },
+            Command {
+                name: "insert_command_output".to_string(),
+                desc: "Inserts the output of a shell command line at the cursor".to_string(),
+                help: Some(
+                    "Runs a shell command line in the buffer's working directory and inserts its output at \
+                    the cursor, like vim's `:r !cmd`: `insert_command_output date`. With a count, the output is \
+                    inserted that many times."
+                        .to_string(),
+                ),
+                chain: Some(" fx".to_string()),
+                action: (|t| t.prompt_mode("Insert output of".to_string(), Ted::insert_command_output)),
+                arguments: Some(Arguments {
+                    run: Ted::insert_command_output,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "insert_file".to_string(),
+                desc: "Inserts the content of a file at the cursor, n times with a count".to_string(),
+                help: Some(
+                    "Inserts the content of a file at the cursor, like vim's `:r`, the prompt starting in the \
+                    buffer's working directory. With a count, the file is inserted that many times."
+                        .to_string(),
+                ),
+                chain: Some(" fi".to_string()),
+                action: Ted::insert_file_prompt,
+                arguments: Some(Arguments {
+                    run: Ted::insert_file,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "jump_to_occurrence".to_string(),
+                desc: "Goes to the `name:line:column` under the cursor (or press Enter)"
+                    .to_string(),
+                help: Some(
+                    "Goes to the `name:line:column` location at the start of the cursor's line, as listed by \
+                    `list_todos` or a compiler's output. Enter does the same in NORMAL mode."
+                        .to_string(),
+                ),
+                chain: Some(" bj".to_string()),
+                action: Ted::jump_to_occurrence,
+                arguments: None,
+            },
+            Command {
+                name: "pick_buffer".to_string(),
+                desc: "Fuzzy finds an open buffer".to_string(),
+                help: None,
+                chain: Some(" bb".to_string()),
+                action: Ted::pick_buffer,
+                arguments: None,
+            },
+            Command {
+                name: "pick_file".to_string(),
+                desc: "Fuzzy finds a file under the current directory".to_string(),
+                help: None,
+                chain: Some(" ff".to_string()),
+                action: Ted::pick_file,
+                arguments: None,
+            },
+            Command {
+                name: "pick_command".to_string(),
+                desc: "Fuzzy finds a command by name".to_string(),
+                help: None,
+                chain: Some(" :".to_string()),
+                action: Ted::pick_command,
+                arguments: None,
+            },
+            Command {
+                name: "pick_theme".to_string(),
+                desc: "Fuzzy finds a display theme".to_string(),
+                help: None,
+                chain: Some(" pt".to_string()),
+                action: Ted::pick_theme,
+                arguments: None,
+            },
+            Command {
+                name: "goto_definition".to_string(),
+                desc: "Jumps to the definition of the symbol under the cursor".to_string(),
+                help: None,
+                chain: Some(" gd".to_string()),
+                action: Ted::goto_definition,
+                arguments: None,
+            },
+            Command {
+                name: "hover".to_string(),
+                desc: "Shows documentation of the symbol under the cursor".to_string(),
+                help: Some(
+                    "Shows it in a panel under the cursor when it takes several lines: j/k \
+                    scroll it, Enter goes to the definition and Esc or q closes it. Asked from \
+                    INSERT mode (bound to a chord under `modes`), the panel closes at the next \
+                    key instead."
+                        .to_string(),
+                ),
+                chain: Some(" gh".to_string()),
+                action: Ted::hover,
+                arguments: None,
+            },
+            Command {
+                name: "fold_toggle".to_string(),
+                desc: "Folds the indented block around the cursor, or unfolds it".to_string(),
+                help: Some(
+                    "Folds the indented block headed by the cursor's line, or else the innermost one around \
+                    it, into a single line, or unfolds the fold under the cursor. `fold_all` and `unfold_all` do it for the whole buffer."
+                        .to_string(),
+                ),
+                chain: Some(" za".to_string()),
+                action: (|t| t.buffers.focused_mut().toggle_fold()),
+                arguments: None,
+            },
+            Command {
+                name: "fold_all".to_string(),
+                desc: "Folds every indented block".to_string(),
+                help: None,
+                chain: Some(" zm".to_string()),
+                action: (|t| t.buffers.focused_mut().close_all_folds()),
+                arguments: None,
+            },
+            Command {
+                name: "unfold_all".to_string(),
+                desc: "Unfolds every block".to_string(),
+                help: None,
+                chain: Some(" zr".to_string()),
+                action: (|t| t.buffers.focused_mut().open_all_folds()),
+                arguments: None,
+            },
+            Command {
+                name: "git_gutter".to_string(),
+                desc: "Marks lines changed since the last commit".to_string(),
+                help: Some(
+                    "Marks the lines added, changed or removed since the last commit in the gutter. The marks \
+                    are also refreshed when a file is opened or saved."
+                        .to_string(),
+                ),
+                chain: Some(" gg".to_string()),
+                action: Ted::refresh_git_gutter,
+                arguments: None,
+            },
+            Command {
+                name: "git_blame".to_string(),
+                desc: "Shows who last changed the current line".to_string(),
+                help: Some(
+                    "Shows the commit, author, date and summary that last changed the cursor's line in the echo \
+                    area, asked to git in the background."
+                        .to_string(),
+                ),
+                chain: Some(" gb".to_string()),
+                action: Ted::git_blame,
+                arguments: None,
+            },
+            Command {
+                name: "help_lang".to_string(),
+                desc: "Shows supported languages for highlighting".to_string(),
+                help: None,
+                chain: Some(" hl".to_string()),
+                action: Ted::help_lang,
+                arguments: None,
+            },
+            Command {
+                name: "help_theme".to_string(),
+                desc: "Shows supported themes for highlighting".to_string(),
+                help: None,
+                chain: Some(" ht".to_string()),
+                action: Ted::help_theme,
+                arguments: None,
+            },
+            Command {
+                name: "set_local".to_string(),
+                desc: "Sets an option of this buffer only, e.g. indent_style tab".to_string(),
+                help: Some(
+                    "Overrides a global option in the focused buffer: `set_local scrolloff 0`. \
+                    `default` goes back to the global value, and no arguments show the options \
+                    set.\n\n\
+                    The options are whitespace (on or off), auto_indent, auto_pairs, scrolloff, \
+                    indent_style (tab or space) and indent_size, the last two winning over \
+                    .editorconfig files. In a hook, `hook on_open:Makefile set_local \
+                    indent_style tab` makes Tab insert real tabs in Makefiles."
+                        .to_string(),
+                ),
+                chain: Some(" so".to_string()),
+                action: (|t| t.prompt_mode("Set local (option value)".to_string(), Ted::set_local)),
+                arguments: Some(Arguments {
+                    run: Ted::set_local,
+                    candidates: Some(Ted::local_option_names),
+                }),
+            },
+            Command {
+                name: "set_lang".to_string(),
+                desc: "Select a language for syntax highlighting".to_string(),
+                help: Some(
+                    "Highlights the focused buffer as another language, completed with Tab: \
+                    `set_lang Markdown`. `help_lang` lists the languages and `detect_language` guesses it from the \
+                    text."
+                        .to_string(),
+                ),
+                chain: Some(" sl".to_string()),
+                action: (|t| t.prompt_mode("Lang".to_string(), Ted::set_lang)),
+                arguments: Some(Arguments {
+                    run: Ted::set_lang,
+                    candidates: Some(Ted::language_names),
+                }),
+            },
+            Command {
+                name: "define_word".to_string(),
+                desc: "Shows the definitions of the word under the cursor, or of a given one".to_string(),
+                help: Some(
+                    "Looks the word up in `~/.config/ted/dictionary.txt`, or the file set as \
+                    `dictionary` in the config file, which has a word and a definition per line \
+                    separated by a tab. Each line of the word is a sense.\n\n\
+                    When the file has no definition, the `dictionary_command` command line is \
+                    run with the word appended, e.g. `dict` for a dictd client or a script \
+                    querying an online dictionary, and its output shown."
+                        .to_string(),
+                ),
+                chain: Some(" dw".to_string()),
+                action: Ted::define_word_under_cursor,
+                arguments: Some(Arguments {
+                    run: Ted::define_word,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "detect_language".to_string(),
+                desc: "Highlights the buffer as the language its text looks like".to_string(),
+                help: None,
+                chain: Some(" sL".to_string()),
+                action: Ted::detect_language,
+                arguments: None,
+            },
+            Command {
+                name: "set_theme".to_string(),
+                desc: "Select a display theme".to_string(),
+                help: Some(
+                    "Changes the syntax highlighting theme of the focused buffer only, completed with Tab. \
+                    `theme` changes it for every buffer and saves it."
+                        .to_string(),
+                ),
+                chain: Some(" st".to_string()),
+                action: (|t| t.prompt_mode("Theme".to_string(), Ted::set_theme)),
+                arguments: Some(Arguments {
+                    run: Ted::set_theme,
+                    candidates: Some(Ted::theme_names),
+                }),
+            },
+            Command {
+                name: "theme".to_string(),
+                desc: "Changes the theme of all buffers (or `<name> buffer` for this one)"
+                    .to_string(),
+                help: Some(
+                    "Changes the syntax highlighting theme, completed with Tab.\n\n\
+                    `theme base16-ocean.dark` applies to every buffer and is saved in the config \
+                    file, `theme InspiredGitHub buffer` only to the focused one."
+                        .to_string(),
+                ),
+                chain: Some(" sT".to_string()),
+                action: (|t| t.prompt_mode("Theme".to_string(), Ted::theme)),
+                arguments: Some(Arguments {
+                    run: Ted::theme,
+                    candidates: Some(Ted::theme_names),
+                }),
+            },
+            Command {
+                name: "abbreviate".to_string(),
+                desc: "Defines an insert mode abbreviation, e.g. teh the".to_string(),
+                help: Some(
+                    "Defines an abbreviation expanded in INSERT mode when a non-word character is typed after \
+                    it: `abbreviate teh the`. It is saved under `abbreviations` in the config file."
+                        .to_string(),
+                ),
+                chain: Some(" ab".to_string()),
+                action: (|t| t.prompt_mode("Abbreviation (from to)".to_string(), Ted::abbreviate)),
+                arguments: Some(Arguments {
+                    run: Ted::abbreviate,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "toggle_whitespace".to_string(),
+                desc: "Shows or hides newlines, spaces, tabs, trailing (or all)".to_string(),
+                help: Some(
+                    "Shows or hides invisible characters: `newlines`, `spaces`, `tabs`, `trailing` \
+                    whitespace, or `all` of them. The `whitespace` option sets them at startup."
+                        .to_string(),
+                ),
+                chain: Some(" tw".to_string()),
+                action: (|t| {
+                    t.prompt_mode(
+                        "Toggle whitespace (newlines, spaces, tabs, trailing, all)".to_string(),
+                        Ted::toggle_whitespace,
+                    )
+                }),
+                arguments: Some(Arguments {
+                    run: Ted::toggle_whitespace,
+                    candidates: Some(|_| {
+                        ["newlines", "spaces", "tabs", "trailing", "all"]
+                            .iter()
+                            .map(|name| name.to_string())
+                            .collect()
+                    }),
+                }),
+            },
+            Command {
+                name: "count".to_string(),
+                desc: "Counts lines, words, chars and bytes of the selection or buffer".to_string(),
+                help: Some(
+                    "Reports the lines, words, chars and bytes of the selection, or of the buffer without one. \
+                    `status_count` keeps the word count in the status line."
+                        .to_string(),
+                ),
+                chain: Some(" bc".to_string()),
+                action: Ted::count,
+                arguments: None,
+            },
+            Command {
+                name: "toggle_crosshair".to_string(),
+                desc: "Highlights the cursor's line and column".to_string(),
+                help: None,
+                chain: Some(" tx".to_string()),
+                action: Ted::toggle_crosshair,
+                arguments: None,
+            },
+            Command {
+                name: "toggle_cursor_line".to_string(),
+                desc: "Highlights the cursor's line".to_string(),
+                help: None,
+                chain: Some(" tl".to_string()),
+                action: Ted::toggle_cursor_line,
+                arguments: None,
+            },
+            Command {
+                name: "toggle_cursor_column".to_string(),
+                desc: "Highlights the cursor's column".to_string(),
+                help: None,
+                chain: Some(" tk".to_string()),
+                action: Ted::toggle_cursor_column,
+                arguments: None,
+            },
+            Command {
+                name: "toggle_soft_wrap".to_string(),
+                desc: "Draws long lines on several rows".to_string(),
+                help: Some(
+                    "Draws lines longer than the view on several rows, broken after the last space that fits. \
+                    `gj`, `gk`, `g0` and `g$` then move by rows. The `soft_wrap` option sets it at startup."
+                        .to_string(),
+                ),
+                chain: Some(" tW".to_string()),
+                action: Ted::toggle_soft_wrap,
+                arguments: None,
+            },
+            Command {
+                name: "toggle_line_numbers".to_string(),
+                desc: "Toggles the line numbers in the gutter".to_string(),
+                help: None,
+                chain: Some(" tn".to_string()),
+                action: Ted::toggle_line_numbers,
+                arguments: None,
+            },
+            Command {
+                name: "toggle_relative_numbers".to_string(),
+                desc: "Toggles numbering lines by their distance to the cursor".to_string(),
+                help: None,
+                chain: Some(" tr".to_string()),
+                action: Ted::toggle_relative_numbers,
+                arguments: None,
+            },
+            Command {
+                name: "toggle_paste_mode".to_string(),
+                desc: "Disables auto-indent and auto-pairs while pasting".to_string(),
+                help: None,
+                chain: Some(" tp".to_string()),
+                action: Ted::toggle_paste_mode,
+                arguments: None,
+            },
+            Command {
+                name: "toggle_high_contrast".to_string(),
+                desc: "Renders with text attributes only, ignoring theme colors".to_string(),
+                help: Some(
+                    "Renders the text with attributes like bold and reverse video only, ignoring the theme's \
+                    colors, and saves the choice as `high_contrast` in the config file."
+                        .to_string(),
+                ),
+                chain: Some(" tc".to_string()),
+                action: Ted::toggle_high_contrast,
+                arguments: None,
+            },
+            Command {
+                name: "pick_mine".to_string(),
+                desc: "Keeps the buffer's side of the conflict under the cursor".to_string(),
+                help: Some(
+                    "After merging a file changed on disk, keeps the buffer's side of the conflict under the \
+                    cursor and drops the markers. `pick_disk` keeps the file's side instead."
+                        .to_string(),
+                ),
+                chain: Some(" cm".to_string()),
+                action: (|t| t.pick_hunk(true)),
+                arguments: None,
+            },
+            Command {
+                name: "pick_disk".to_string(),
+                desc: "Keeps the file's side of the conflict under the cursor".to_string(),
+                help: Some(
+                    "After merging a file changed on disk, keeps the file's side of the conflict under the \
+                    cursor and drops the markers. `pick_mine` keeps the buffer's side instead."
+                        .to_string(),
+                ),
+                chain: Some(" cd".to_string()),
+                action: (|t| t.pick_hunk(false)),
+                arguments: None,
+            },
+            Command {
+                name: "bind".to_string(),
+                desc: "Binds a command to a space chain, e.g. file_open SPC o".to_string(),
+                help: Some(
+                    "Binds a command to a space chain and saves it under `bindings` in the config \
+                    file: `bind file_open SPC o`.\n\n\
+                    Without a chain, `bind file_open` unbinds the command. A chain can't be a \
+                    prefix of another one."
+                        .to_string(),
+                ),
+                chain: Some(" cb".to_string()),
+                action: (|t| t.prompt_mode("Bind (command chain)".to_string(), Ted::bind)),
+                arguments: Some(Arguments {
+                    run: Ted::bind,
+                    candidates: Some(Ted::command_names),
+                }),
+            },
+            Command {
+                name: "reload_config".to_string(),
+                desc: "Reloads the config file, user themes and syntaxes".to_string(),
+                help: Some(
+                    "Reads the config file again, along with the user's themes, syntaxes and plugins, without \
+                    restarting. When the config file doesn't parse, the error is shown and the current config kept.\n\n\
+                    The config file is also reloaded once saved, unless `watch_config` is false."
+                        .to_string(),
+                ),
+                chain: Some(" cr".to_string()),
+                action: Ted::reload_config,
+                arguments: None,
+            },
+            Command {
+                name: "help".to_string(),
+                desc: "Opens the documentation of a command, or a searchable index of them".to_string(),
+                help: Some(
+                    "Opens the documentation of a command, completed with Tab: `help file_open`. Without a \
+                    name, opens a picker of every command that previews its documentation."
+                        .to_string(),
+                ),
+                chain: Some(" hh".to_string()),
+                action: Ted::help_index,
+                arguments: Some(Arguments {
+                    run: Ted::help,
+                    candidates: Some(Ted::command_names),
+                }),
+            },
+            Command {
+                name: "hook".to_string(),
+                desc:
+                    "Runs a command on an event, e.g. on_open:Markdown theme InspiredGitHub buffer"
+                        .to_string(),
+                help: Some(
+                    "Runs a command line whenever an event happens: `hook on_save_pre:Rust \
+                    format_buffer`. The language after the colon is optional and restricts the \
+                    hook to buffers in that language.\n\n\
+                    The events are on_open, on_save_pre, on_save_post, on_mode_change and \
+                    on_buffer_switch. Hooks added this way last for the session, the ones under \
+                    `hooks` in the config file are kept."
+                        .to_string(),
+                ),
+                chain: None,
+                action: (|t| {
+                    t.prompt_mode("Hook (event[:language] command)".to_string(), Ted::add_hook)
+                }),
+                arguments: Some(Arguments {
+                    run: Ted::add_hook,
+                    candidates: Some(Ted::hook_names),
+                }),
+            },
+            Command {
+                name: "replace".to_string(),
+                desc: "Replaces the matches of a pattern, previewing the replacement".to_string(),
+                help: Some(
+                    "Prompts for a pattern, matched as searches are (Ctrl-t and Ctrl-r switch the \
+                    case sensitivity and regex matching), then for what its matches become, \
+                    shown in place of each match as it is typed. Only the matches within the \
+                    selection are replaced when there is one. In a regex replacement `$1` to \
+                    `$9` stand for what the groups matched, `$0` for the whole match and `$$` \
+                    for `$`. `replace /pattern/replacement/` replaces without prompting."
+                        .to_string(),
+                ),
+                chain: Some(" sr".to_string()),
+                action: Ted::replace_mode,
+                arguments: Some(Arguments {
+                    run: Ted::replace,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "enter_mode".to_string(),
+                desc: "Enters a mode registered by the config or a plugin".to_string(),
+                help: Some(
+                    "Modes are registered under `modes` in `config.json`, e.g. `\"modes\": \
+                    { \"buffers\": { \"keys\": { \"n\": \"next_buffer\", \"q\": \
+                    \"leave_mode\" }, \"fallthrough\": false } }`, or by plugins with \
+                    `registerMode`. While a mode is entered, the keys it binds run their \
+                    commands, and the others go on to the NORMAL or INSERT mode beneath unless \
+                    `fallthrough` is false. Esc or `leave_mode` leaves it. A mode named after a \
+                    builtin one (normal, insert, overlay, prompt, picker, space_chain) adds chords to it."
+                        .to_string(),
+                ),
+                chain: Some(" me".to_string()),
+                action: Ted::pick_mode,
+                arguments: Some(Arguments {
+                    run: Ted::enter_mode,
+                    candidates: Some(|t| t.modes.names()),
+                }),
+            },
+            Command {
+                name: "leave_mode".to_string(),
+                desc: "Leaves the mode entered last".to_string(),
+                help: Some(
+                    "Leaves the mode entered last with `enter_mode`, as Esc does when the mode doesn't bind \
+                    it."
+                        .to_string(),
+                ),
+                chain: None,
+                action: Ted::leave_mode,
+                arguments: None,
+            },
+            Command {
+                name: "clear_search".to_string(),
+                desc: "Clears the highlighting of search matches".to_string(),
+                help: None,
+                chain: Some(" sc".to_string()),
+                action: Ted::clear_search,
+                arguments: None,
+            },
+            Command {
+                name: "search_forward".to_string(),
+                desc: "Moves to the next match of the last search, in any buffer".to_string(),
+                help: None,
+                chain: Some(" sn".to_string()),
+                action: (|t| t.search_next(true)),
+                arguments: None,
+            },
+            Command {
+                name: "search_backward".to_string(),
+                desc: "Moves to the previous match of the last search, in any buffer".to_string(),
+                help: None,
+                chain: Some(" sp".to_string()),
+                action: (|t| t.search_next(false)),
+                arguments: None,
+            },
+            Command {
+                name: "errors".to_string(),
+                desc: "Opens the recent errors in a new buffer".to_string(),
+                help: Some(
+                    "Opens the last hundred errors, like invalid options, themes or plugins and failed saves, in \
+                    a read-only buffer, the newest last."
+                        .to_string(),
+                ),
+                chain: None,
+                action: Ted::show_errors,
+                arguments: None,
+            },
+            Command {
+                name: "show_log".to_string(),
+                desc: "Opens the log in a read-only buffer following its end".to_string(),
+                help: Some(
+                    "Opens `~/.local/share/ted/log` in a read-only buffer that follows it as it grows. \
+                    `log_level` in the config file sets what is logged, `info` by default."
+                        .to_string(),
+                ),
+                chain: None,
+                action: Ted::show_log,
+                arguments: None,
+            },
+            Command {
+                name: "recover".to_string(),
+                desc: "Opens the buffers recovered after a crash, to be saved".to_string(),
+                help: Some(
+                    "Opens the buffers with unsaved changes that were dumped to `~/.local/share/ted/recovery` \
+                    when ted crashed, each in a new buffer to be saved or discarded."
+                        .to_string(),
+                ),
+                chain: None,
+                action: Ted::recover,
+                arguments: None,
+            },
+            Command {
+                name: "scroll_line_down".to_string(),
+                desc: "Scrolls the view down a line".to_string(),
+                help: None,
+                chain: None,
+                action: (|t| t.with_count(|b, n| b.scroll_lines(n as isize))),
+                arguments: None,
+            },
+            Command {
+                name: "scroll_line_up".to_string(),
+                desc: "Scrolls the view up a line".to_string(),
+                help: None,
+                chain: None,
+                action: (|t| t.with_count(|b, n| b.scroll_lines(-(n as isize)))),
+                arguments: None,
+            },
+            Command {
+                name: "scroll_half_page_down".to_string(),
+                desc: "Scrolls the view and cursor down half a page".to_string(),
+                help: None,
+                chain: None,
+                action: (|t| t.with_count(|b, n| b.scroll_half_pages(n as isize))),
+                arguments: None,
+            },
+            Command {
+                name: "scroll_half_page_up".to_string(),
+                desc: "Scrolls the view and cursor up half a page".to_string(),
+                help: None,
+                chain: None,
+                action: (|t| t.with_count(|b, n| b.scroll_half_pages(-(n as isize)))),
+                arguments: None,
+            },
+            Command {
+                name: "forward_word".to_string(),
+                desc: "Moves to the start of the next word".to_string(),
+                help: None,
+                chain: None,
+                action: (|t| t.with_count(Buffer::move_word_forward)),
+                arguments: None,
+            },
+            Command {
+                name: "backward_word".to_string(),
+                desc: "Moves to the start of the previous word".to_string(),
+                help: None,
+                chain: None,
+                action: (|t| t.with_count(Buffer::move_word_backward)),
+                arguments: None,
+            },
+            Command {
+                name: "pick_kill_ring".to_string(),
+                desc: "Picks a copied or deleted text to paste".to_string(),
+                help: Some(
+                    "Picks one of the last 32 texts copied or deleted and pastes it at the cursor."
+                        .to_string(),
+                ),
+                chain: Some(" pk".to_string()),
+                action: Ted::pick_kill_ring,
+                arguments: None,
+            },
+            Command {
+                name: "paste_pop".to_string(),
+                desc: "Replaces the text just pasted with the previous copied or deleted one"
+                    .to_string(),
+                help: Some(
+                    "Right after a paste, replaces the pasted text with the previous entry of the kill ring. \
+                    Repeating it goes further back."
+                        .to_string(),
+                ),
+                chain: None,
+                action: Ted::paste_pop,
+                arguments: None,
+            },
+            Command {
+                name: "list_todos".to_string(),
+                desc: "Lists the TODO, FIXME and HACK markers of the buffer".to_string(),
+                help: Some(
+                    "Lists the `TODO`, `FIXME` and `HACK` markers of the buffer as `file:line:column:` \
+                    locations in a new buffer, Enter on one jumps to it."
+                        .to_string(),
+                ),
+                chain: Some(" ft".to_string()),
+                action: Ted::list_todos,
+                arguments: None,
+            },
+            Command {
+                name: "list_project_todos".to_string(),
+                desc: "Lists the TODO, FIXME and HACK markers of the files under the working directory".to_string(),
+                help: Some(
+                    "Lists the `TODO`, `FIXME` and `HACK` markers of every file under the buffer's working \
+                    directory, searched in the background. Enter on a location jumps to it."
+                        .to_string(),
+                ),
+                chain: Some(" fT".to_string()),
+                action: Ted::list_project_todos,
+                arguments: None,
+            },
+            Command {
+                name: "cd".to_string(),
+                desc: "Changes the working directory of the buffer, relative to the current one"
+                    .to_string(),
+                help: Some(
+                    "Changes the working directory of the focused buffer, which file_open and the \
+                    shell commands start from: `cd ..` or `cd src`.\n\n\
+                    The path is relative to the current working directory, `pwd` shows it."
+                        .to_string(),
+                ),
+                chain: Some(" bw".to_string()),
+                action: (|t| t.prompt_mode("Change directory".to_string(), Ted::cd)),
+                arguments: Some(Arguments {
+                    run: Ted::cd,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "pwd".to_string(),
+                desc: "Shows the working directory of the buffer".to_string(),
+                help: None,
+                chain: None,
+                action: Ted::pwd,
+                arguments: None,
+            },
+            Command {
+                name: "buffer_rename".to_string(),
+                desc: "Names the buffer, instead of after its file".to_string(),
+                help: Some(
+                    "Names the focused buffer, unless another buffer has that name already. The file on disk \
+                    keeps its name, `file_rename` moves it."
+                        .to_string(),
+                ),
+                chain: Some(" bn".to_string()),
+                action: (|t| {
+                    let name = t.buffers.focused().name.clone();
+                    t.prompt_mode("Buffer name".to_string(), Ted::buffer_rename);
+                    t.answer.set(name);
+                }),
+                arguments: Some(Arguments {
+                    run: Ted::buffer_rename,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "restore_backup".to_string(),
+                desc: "Replaces the buffer with its file's latest backup".to_string(),
+                help: Some(
+                    "Brings back the latest backup of the buffer's file, made before a save when `backup` is \
+                    set. The buffer is changed but not saved, save it to keep the backup."
+                        .to_string(),
+                ),
+                chain: Some(" fB".to_string()),
+                action: Ted::restore_backup,
+                arguments: None,
+            },
+            Command {
+                name: "save_as_root".to_string(),
+                desc: "Saves the buffer through the elevate command, e.g. sudo tee".to_string(),
+                help: Some(
+                    "Saves the buffer through `elevate`, `sudo tee` by default, for files the user may not \
+                    write. The terminal is handed over while it runs so its password prompt can be answered."
+                        .to_string(),
+                ),
+                chain: Some(" fS".to_string()),
+                action: Ted::save_as_root,
+                arguments: None,
+            },
+            Command {
+                name: "select_paragraph".to_string(),
+                desc: "Selects the lines of the paragraph under the cursor, or n of them".to_string(),
+                help: None,
+                chain: Some(" vp".to_string()),
+                action: (|t| t.with_count(Buffer::select_paragraph)),
+                arguments: None,
+            },
+            Command {
+                name: "tutor".to_string(),
+                desc: "Opens an interactive tutorial checking each exercise".to_string(),
+                help: None,
+                chain: Some(" hu".to_string()),
+                action: Ted::tutor,
+                arguments: None,
+            },
+            Command {
+                name: "undo_tree".to_string(),
+                desc: "Jumps to any earlier state of the buffer, even undone branches".to_string(),
+                help: Some(
+                    "Lists every state the buffer went through with the time and size of each \
+                    change, `@` marking the current one. Editing after an undo starts a branch, \
+                    listed indented under the state it forked from.\n\n\
+                    The preview shows what going to the selected state changes. In NORMAL mode, \
+                    `u` and `r` undo and redo along the branch last visited."
+                        .to_string(),
+                ),
+                chain: Some(" ut".to_string()),
+                action: Ted::undo_tree,
+                arguments: None,
+            },
+            Command {
+                name: "earlier".to_string(),
+                desc: "Goes back a number of changes or a time like 30s".to_string(),
+                help: Some(
+                    "Moves back through the states of the buffer in the order they were recorded, \
+                    whatever their branch of the undo tree: `earlier 3` by 3 changes, \
+                    `earlier 30s` (or `2m`, `1h`, `1d`) to the state as it was that long before \
+                    the current one."
+                        .to_string(),
+                ),
+                chain: None,
+                action: (|t| t.prompt_mode("Earlier".to_string(), Ted::earlier)),
+                arguments: Some(Arguments {
+                    run: Ted::earlier,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "later".to_string(),
+                desc: "Goes forward a number of changes or a time like 2m".to_string(),
+                help: Some(
+                    "Moves forward through the states of the buffer in the order they were \
+                    recorded, whatever their branch of the undo tree: `later 3` by 3 changes, \
+                    `later 30s` (or `2m`, `1h`, `1d`) to the state as it was that long after \
+                    the current one."
+                        .to_string(),
+                ),
+                chain: None,
+                action: (|t| t.prompt_mode("Later".to_string(), Ted::later)),
+                arguments: Some(Arguments {
+                    run: Ted::later,
+                    candidates: None,
+                }),
+            },
+            Command {
+                name: "expand_selection".to_string(),
+                desc: "Selects the enclosing word, string, brackets, line or block".to_string(),
+                help: Some(
+                    "Selects the unit of text around the cursor, then grows to the next one each \
+                    time: the word, a quoted string, inside then around brackets, the line's text \
+                    then the whole line, the indented block and the whole buffer.\n\n\
+                    `shrink_selection` (`M-i`) goes back a step."
+                        .to_string(),
+                ),
+                chain: Some(" ve".to_string()),
+                action: Ted::expand_selection,
+                arguments: None,
+            },
+            Command {
+                name: "shrink_selection".to_string(),
+                desc: "Goes back to the selection before the last expand_selection".to_string(),
+                help: None,
+                chain: Some(" vs".to_string()),
+                action: Ted::shrink_selection,
+                arguments: None,
+            },
+        ],
+    }
+}
+
+/// applies the user's bindings from the config file, returns the ones that failed
+///
+/// the configured commands are unbound first so they can trade chains, then bound by name
+/// so a clash always fails the same way
+pub fn bind_from_config(commands: &mut Commands<Ted>, config: &Config) -> Vec<String> {
+    let bindings = config.bindings.borrow();
+    let mut bindings: Vec<_> = bindings.iter().collect();
+    bindings.sort();
+    for (name, _) in &bindings {
+        let _ = commands.bind(name, None);
+    }
+    let mut errors = vec![];
+    for (name, chain) in bindings {
+        let result = match parse_space_chain(chain) {
+            Some(chain) => commands.bind(name, Some(chain)),
+            None if chain.trim().is_empty() => commands.bind(name, None),
+            None => {
+                errors.push(format!("{}: invalid chain {:?}", name, chain));
+                continue;
+            }
+        };
+        if let Err(err) = result {
+            errors.push(format!("{}: {}", name, err));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bind_from_config, builtin, Config};
+    use std::collections::HashSet;
+    use std::iter::FromIterator;
+
+    #[test]
+    fn no_dup_command_chain() {
+        let commands = builtin();
+        let v: Vec<String> = commands
+            .commands
+            .iter()
+            .filter_map(|c| c.chain.as_ref().map(|chain| chain.to_string()))
+            .collect();
+        let n = v.len();
+        let h: HashSet<String> = HashSet::from_iter(v);
+        assert_eq!(n, h.len());
+    }
+
+    #[test]
+    fn get_by_chain() {
+        let commands = builtin();
+        let full_list = commands.get_by_chain(" ");
+        assert!(full_list.len() > 1);
+        let exact_match = commands.get_by_chain("  ");
+        assert!(exact_match.len() == 1);
+        let empty_list = commands.get_by_chain("   ");
+        assert!(empty_list.is_empty());
+    }
+
+    #[test]
+    fn trade_chains() {
+        let mut commands = builtin();
+        let config = Config::builtin();
+        let mut bindings = config.bindings.borrow_mut();
+        bindings.insert(String::from("file_open"), String::from("SPC f s"));
+        bindings.insert(String::from("file_save"), String::from("SPC f o"));
+        drop(bindings);
+        assert!(bind_from_config(&mut commands, &config).is_empty());
+        assert_eq!(commands.get_by_chain(" fs")[0].name, "file_open");
+        assert_eq!(commands.get_by_chain(" fo")[0].name, "file_save");
+    }
+}
@@ -0,0 +1,142 @@
+use tui::layout::Rect;
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::StatefulWidget;
+
+/// most candidates shown at once in the popup
+pub const HEIGHT: usize = 8;
+
+/// Insert mode completion popup
+pub struct Completion {
+    /// the word being completed
+    pub prefix: String,
+    candidates: Vec<String>,
+    selected: usize,
+}
+
+impl Completion {
+    pub fn new(prefix: String, candidates: Vec<String>) -> Self {
+        Self {
+            prefix,
+            candidates,
+            selected: 0,
+        }
+    }
+
+    /// puts candidates (e.g. from a language server) first, dropping duplicates
+    pub fn prepend(&mut self, candidates: Vec<String>) {
+        let previous = std::mem::take(&mut self.candidates);
+        self.candidates = candidates;
+        for candidate in previous {
+            if !self.candidates.contains(&candidate) {
+                self.candidates.push(candidate);
+            }
+        }
+        self.selected = 0;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
+    /// cycles to the next candidate, wrapping around
+    pub fn select_next(&mut self) {
+        if !self.candidates.is_empty() {
+            self.selected = (self.selected + 1) % self.candidates.len();
+        }
+    }
+
+    /// cycles to the previous candidate, wrapping around
+    pub fn select_prev(&mut self) {
+        if !self.candidates.is_empty() {
+            self.selected = (self.selected + self.candidates.len() - 1) % self.candidates.len();
+        }
+    }
+
+    pub fn selected(&self) -> Option<&String> {
+        self.candidates.get(self.selected)
+    }
+
+    /// popup position under (or above) the word at the cursor, within screen
+    pub fn area(&self, cursor_x: u16, cursor_y: u16, screen: Rect) -> Rect {
+        let width = self
+            .candidates
+            .iter()
+            .map(|c| c.chars().count() + 2)
+            .max()
+            .unwrap_or(0)
+            .min(screen.width as usize) as u16;
+        let height = self.candidates.len().min(HEIGHT) as u16;
+        let x = cursor_x
+            .saturating_sub(self.prefix.chars().count() as u16)
+            .min(screen.width.saturating_sub(width));
+        let y = if cursor_y + 1 + height <= screen.height {
+            cursor_y + 1
+        } else {
+            cursor_y.saturating_sub(height)
+        };
+        Rect::new(x, y, width, height.min(screen.height))
+    }
+}
+
+pub struct CompletionWidget {}
+
+impl StatefulWidget for CompletionWidget {
+    type State = Completion;
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer, state: &mut Self::State) {
+        let style = Style::default().fg(Color::White).bg(Color::DarkGray);
+        buf.set_style(area, style);
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                buf.get_mut(x, y).set_symbol(" ");
+            }
+        }
+
+        // scroll so the selected candidate stays visible
+        let height = area.height as usize;
+        let offset = (state.selected + 1).saturating_sub(height);
+        for (y, candidate) in state
+            .candidates
+            .iter()
+            .skip(offset)
+            .take(height)
+            .enumerate()
+        {
+            let style = if y + offset == state.selected {
+                style.add_modifier(Modifier::REVERSED)
+            } else {
+                style
+            };
+            buf.set_stringn(
+                area.x,
+                area.y + y as u16,
+                format!(" {} ", candidate),
+                area.width as usize,
+                style,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Completion;
+    use tui::layout::Rect;
+
+    #[test]
+    fn prepend_and_area() {
+        let mut completion = Completion::new(
+            String::from("fo"),
+            vec![String::from("foo"), String::from("fold")],
+        );
+        completion.select_next();
+        completion.prepend(vec![String::from("format"), String::from("foo")]);
+        assert_eq!(completion.selected(), Some(&String::from("format")));
+        completion.select_prev();
+        assert_eq!(completion.selected(), Some(&String::from("fold")));
+        completion.select_next();
+        assert_eq!(completion.selected(), Some(&String::from("format")));
+        let screen = Rect::new(0, 0, 80, 24);
+        assert_eq!(completion.area(10, 5, screen), Rect::new(8, 6, 8, 3));
+        assert_eq!(completion.area(10, 22, screen), Rect::new(8, 19, 8, 3));
+    }
+}
@@ -0,0 +1,54 @@
+use super::buffer_widget::BufferWidget;
+use ted::help::{self, Kind};
+use ted::wrap;
+use ted::Buffer;
+use tui::layout::Rect;
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::StatefulWidget;
+
+/// the home buffer, drawn as any buffer then with its headings, keys and command names
+/// standing out from the descriptions
+pub struct HelpWidget {}
+
+fn style(kind: Kind, high_contrast: bool) -> Option<Style> {
+    let style = Style::default();
+    match (kind, high_contrast) {
+        (Kind::Heading, false) => Some(style.fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        (Kind::Heading, true) => Some(style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED)),
+        (Kind::Key, false) => Some(style.fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+        (Kind::Key, true) => Some(style.add_modifier(Modifier::BOLD)),
+        (Kind::Command, false) => Some(style.fg(Color::Green)),
+        (Kind::Command, true) => Some(style.add_modifier(Modifier::ITALIC)),
+        (Kind::Text, _) => None,
+    }
+}
+
+impl StatefulWidget for HelpWidget {
+    type State = Buffer;
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer, state: &mut Self::State) {
+        BufferWidget::default().render(area, buf, state);
+        let high_contrast = state.get_config().high_contrast.get();
+        let left = area.x + state.gutter_width() as u16;
+        let rows = area.height.saturating_sub(1) as usize;
+        for line_number in state.visible_line_numbers() {
+            let line = state.get_line(line_number).unwrap_or_default();
+            let chars: Vec<char> = line.chars().collect();
+            for (kind, range) in help::spans(&line) {
+                let style = match style(kind, high_contrast) {
+                    Some(style) => style,
+                    None => continue,
+                };
+                // each char where it is drawn, wide ones taking two cells
+                for column in range {
+                    let (y, x) = match state.screen_position(line_number, column) {
+                        Some((y, x)) if y < rows => (y, x),
+                        _ => continue,
+                    };
+                    let width = wrap::char_width(chars[column]) as u16;
+                    let area = Rect::new(left + x as u16, area.y + y as u16, width, 1);
+                    buf.set_style(area.intersection(buf.area), style);
+                }
+            }
+        }
+    }
+}
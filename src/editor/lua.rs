@@ -0,0 +1,226 @@
+//! Plugins written in Lua, the `.lua` files of ~/.config/ted/plugins run at startup. They reach
+//! the editor through the `ted` table, lines and columns counting from 1:
+//!
+//! ```lua
+//! ted.register("insert_date", "SPC i d", "Inserts the date", function()
+//!     ted.insert(os.date("%Y-%m-%d"))
+//! end)
+//! ```
+//!
+//! - `text()` the focused buffer's content, `line(n)` one of its lines without its newline
+//! - `cursor()` the cursor's line and column, `move_to(line, column)` moves it
+//! - `insert(text)` at the cursor, `message(text)` in the echo area
+//! - `command(line)` runs a command line, as typed in the `space` prompt
+//! - `register(name, chain, description, function)` adds a command, chain may be nil
+
+use super::{Ted, MAX_SCRIPT_DEPTH};
+use mlua::{Function, Lua, Table};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::Path;
+use std::rc::Rc;
+use ted::command::parse_space_chain;
+use ted::error::{self, TedError};
+use ted::script;
+
+/// registry table of the functions run by the commands plugins registered
+const COMMANDS: &str = "ted_commands";
+
+/// a Lua state for plugins and the names of the commands they registered
+pub struct Runtime {
+    lua: Rc<Lua>,
+    pub commands: HashSet<String>,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self {
+            lua: Rc::new(Lua::new()),
+            commands: HashSet::default(),
+        }
+    }
+}
+
+fn runtime_error(message: String) -> mlua::Error {
+    mlua::Error::RuntimeError(message)
+}
+
+impl Ted {
+    /// runs f with the `ted` table bound to this editor
+    fn with_lua(&mut self, f: impl FnOnce(&Lua) -> mlua::Result<()>) -> mlua::Result<()> {
+        if self.script_depth >= MAX_SCRIPT_DEPTH {
+            return Err(runtime_error(String::from("Scripts are nested too deeply")));
+        }
+        self.script_depth += 1;
+        let lua = self.lua.lua.clone();
+        let ted = RefCell::new(&mut *self);
+        let result = lua.scope(|scope| {
+            let api = lua.create_table()?;
+            api.set(
+                "text",
+                scope.create_function(|_, ()| Ok(ted.borrow().buffers.focused().get_text()))?,
+            )?;
+            api.set(
+                "line",
+                scope.create_function(|_, n: usize| {
+                    let line = ted.borrow().buffers.focused().get_line(n.wrapping_sub(1));
+                    Ok(line.map(|line| line.trim_end_matches(&['\r', '\n'][..]).to_string()))
+                })?,
+            )?;
+            api.set(
+                "cursor",
+                scope.create_function(|_, ()| {
+                    let (_, line, column) = ted.borrow().buffers.focused().get_cursor();
+                    Ok((line + 1, column + 1))
+                })?,
+            )?;
+            api.set(
+                "move_to",
+                scope.create_function(|_, (line, column): (usize, usize)| {
+                    let mut ted = ted.borrow_mut();
+                    let buffer = ted.buffers.focused_mut();
+                    buffer.goto(line.saturating_sub(1), column.saturating_sub(1));
+                    Ok(())
+                })?,
+            )?;
+            api.set(
+                "insert",
+                scope.create_function(|_, text: String| {
+                    ted.borrow_mut().insert_text(&text);
+                    Ok(())
+                })?,
+            )?;
+            api.set(
+                "message",
+                scope.create_function(|_, text: String| {
+                    ted.borrow_mut().message = text;
+                    Ok(())
+                })?,
+            )?;
+            api.set(
+                "command",
+                scope.create_function(|_, line: String| {
+                    ted.borrow_mut().run_command(line);
+                    Ok(())
+                })?,
+            )?;
+            api.set(
+                    "register",
+                    scope.create_function(
+                        |lua,
+                         (name, chain, desc, function): (
+                            String,
+                            Option<String>,
+                            String,
+                            Function,
+                        )| {
+                            let chain = match chain {
+                                Some(chain) => {
+                                    Some(parse_space_chain(&chain).ok_or_else(|| {
+                                        runtime_error(format!("invalid chain {}", chain))
+                                    })?)
+                                }
+                                None => None,
+                            };
+                            let mut ted = ted.borrow_mut();
+                            ted.register(&name, desc, chain)
+                                .map_err(|err| runtime_error(err.to_string()))?;
+                            let commands =
+                                match lua.named_registry_value::<Option<Table>>(COMMANDS)? {
+                                    Some(commands) => commands,
+                                    None => {
+                                        let commands = lua.create_table()?;
+                                        lua.set_named_registry_value(COMMANDS, commands.clone())?;
+                                        commands
+                                    }
+                                };
+                            commands.set(name.clone(), function)?;
+                            ted.lua.commands.insert(name);
+                            Ok(())
+                        },
+                    )?,
+                )?;
+            // a nested run binds its own table, the outer one is back once it returns
+            let outer: mlua::Value = lua.globals().get("ted")?;
+            lua.globals().set("ted", api)?;
+            let result = f(&lua);
+            lua.globals().set("ted", outer)?;
+            result
+        });
+        self.script_depth -= 1;
+        result
+    }
+
+    /// runs the function a plugin registered as command name
+    pub(super) fn run_lua_command(&mut self, name: &str) -> error::Result<()> {
+        self.with_lua(|lua| {
+            let commands: Table = lua.named_registry_value(COMMANDS)?;
+            commands.get::<_, Function>(name)?.call(())
+        })
+        .map_err(|err| TedError::Script(format!("{}: {}", name, err)))
+    }
+
+    /// runs a plugin's code, registering its commands
+    pub(super) fn load_lua(&mut self, name: &str, source: &str) -> mlua::Result<()> {
+        self.with_lua(|lua| lua.load(source).set_name(name).exec())
+    }
+
+    /// starts a new Lua state and runs the .lua files of dir, returns their errors
+    pub(super) fn load_lua_plugins(&mut self, dir: Option<&Path>) -> Vec<String> {
+        self.lua = Runtime::default();
+        let mut errors = vec![];
+        for path in dir.map(|dir| script::files(dir, "lua")).unwrap_or_default() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let result = match std::fs::read_to_string(&path) {
+                Ok(source) => self.load_lua(&name, &source),
+                Err(err) => Err(mlua::Error::external(err)),
+            };
+            if let Err(err) = result {
+                errors.push(format!("{}: {}", name, err));
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::snapshot::drive;
+
+    const SHOUT: &str = r#"
+        ted.register("shout", "SPC i !", "Inserts the line uppercased", function()
+            local line, column = ted.cursor()
+            ted.move_to(line, 1)
+            ted.insert(string.upper(ted.line(line)) .. " ")
+            ted.move_to(line, column)
+            ted.message(#ted.text() .. " chars")
+        end)
+        ted.register("broken", nil, "Fails", function() error("oops") end)
+    "#;
+
+    #[test]
+    fn plugin_commands() {
+        let (mut ted, _) = drive("one\ntwo\n", "jl");
+        ted.load_lua("shout.lua", SHOUT).unwrap();
+        assert!(ted.commands.get_by_name("shout").is_some());
+        ted.run_command(String::from("shout"));
+        assert_eq!(ted.buffers.focused().get_text(), "one\nTWO two\n");
+        assert_eq!(ted.buffers.focused().get_cursor(), (5, 1, 1));
+        assert_eq!(ted.message, "12 chars");
+        // a command run from Lua may run Lua itself
+        ted.load_lua("twice.lua", "ted.move_to(1, 1) ted.command('shout')")
+            .unwrap();
+        assert_eq!(ted.buffers.focused().get_text(), "ONE one\nTWO two\n");
+        ted.run_command(String::from("broken"));
+        assert!(ted.message.starts_with("Lua: broken: "));
+        assert!(ted.message.contains("oops"));
+        assert!(ted
+            .load_lua("bad.lua", "ted.register('shout', nil, '', print)")
+            .is_err());
+        assert!(ted.load_lua("bad.lua", "ted.nope()").is_err());
+        // a clashing chain leaves no command behind
+        let clash = "ted.register('other', 'SPC i ! x', '', print)";
+        assert!(ted.load_lua("bad.lua", clash).is_err());
+        assert!(ted.commands.get_by_name("other").is_none());
+    }
+}
@@ -0,0 +1,3242 @@
+use buffer_widget::BufferWidget;
+use completion::{Completion, CompletionWidget};
+use crossterm::cursor::{CursorShape, SetCursorShape};
+use crossterm::event::KeyCode;
+use crossterm::event::{DisableBracketedPaste, EnableBracketedPaste, Event};
+use crossterm::event::{DisableFocusChange, EnableFocusChange};
+use crossterm::event::{KeyEvent, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, SetTitle,
+};
+use help_widget::HelpWidget;
+use modes::Modes;
+use overlay::{Overlay, OverlayWidget};
+use picker::{Picker, PickerWidget};
+use serde_json::json;
+use serde_json::value::Value;
+use std::collections::HashMap;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use ted::audit::format_report;
+use ted::buffer::{Buffer, InputMode};
+use ted::command::{format_space_chain, parse_command_line, parse_space_chain, Command, Commands};
+use ted::completion::{common_prefix, words_with_prefix};
+use ted::config::{config_dir, data_dir, state_dir};
+use ted::dictionary;
+use ted::digraph;
+use ted::error::{self, TedError};
+use ted::format;
+use ted::git;
+use ted::help;
+use ted::hook;
+use ted::kill_ring::{self, KillRing};
+use ted::line_edit::LineEdit;
+use ted::lint::Warning;
+use ted::lsp;
+use ted::options;
+use ted::path;
+use ted::plugin;
+use ted::positions::Positions;
+use ted::recent::Recent;
+use ted::recovery;
+use ted::script::{self, Definition};
+use ted::todo;
+use ted::tutor::{self, Tutor};
+use ted::undo;
+use ted::{Buffers, Config};
+use tui::layout::Rect;
+use tui::widgets::Paragraph;
+use tui::Terminal;
+use which_key::{WhichKey, WhichKeyWidget};
+
+mod backend;
+mod buffer_widget;
+mod commands;
+mod completion;
+mod help_widget;
+mod lua;
+mod modes;
+mod overlay;
+mod picker;
+#[cfg(test)]
+mod snapshot;
+mod which_key;
+
+pub use backend::TermBackend;
+
+type TTerm = Terminal<TermBackend>;
+
+type TRes = Result<(), io::Error>;
+
+/// what a pending language server request was made for
+enum LspRequest {
+    Definition,
+    Hover,
+    Completion,
+}
+
+/// a character being typed by other means than its key, in INSERT mode
+enum Special {
+    /// after Ctrl-k, with the first key of the pair once typed
+    Digraph(Option<char>),
+    /// after Ctrl-v, the code point typed so far
+    Unicode(String),
+}
+
+/// a file along with its changed lines, None outside of a git repository
+type GitUpdate = (PathBuf, Option<Vec<(usize, git::LineChange)>>);
+
+/// word length from which the completion popup opens by itself
+const AUTO_COMPLETE: usize = 3;
+
+/// pause in a space chain after which its continuations pop up
+const WHICH_KEY_DELAY: Duration = Duration::from_millis(500);
+
+/// pause after which a space chain in progress is abandoned
+const CHAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// shortest time between two draws, about 60 frames per second
+const FRAME: Duration = Duration::from_millis(16);
+
+/// how long the main loop waits for input when nothing needs to be drawn
+const IDLE: Duration = Duration::from_millis(100);
+
+/// how long the text is left alone before the matches of the search are found again
+const SEARCH_DELAY: Duration = Duration::from_millis(150);
+
+/// how often the config file is checked for changes
+const CONFIG_POLL: Duration = Duration::from_secs(1);
+
+/// failures kept for the errors buffer
+const MAX_ERRORS: usize = 100;
+
+/// how deep scripted commands may call each other
+const MAX_SCRIPT_DEPTH: usize = 16;
+
+/// when the file was last modified, None when it can't be told
+fn modified(file: Option<&PathBuf>) -> Option<SystemTime> {
+    std::fs::metadata(file?)
+        .and_then(|attr| attr.modified())
+        .ok()
+}
+
+/// how many lint warnings a buffer has and the first one, None without any
+fn lint_summary(warnings: &[Warning]) -> Option<String> {
+    let first = warnings.first()?;
+    Some(format!(
+        "{} lint warning(s), {} on line {}",
+        warnings.len(),
+        first.message,
+        first.line_number + 1
+    ))
+}
+
+/// where the undo tree of a file is saved, named after a hash of its absolute path
+fn undo_file(file: &str) -> Option<PathBuf> {
+    let path = std::fs::canonicalize(file).unwrap_or_else(|_| PathBuf::from(file));
+    state_dir("undo").map(|dir| dir.join(undo::file_name(&path.to_string_lossy())))
+}
+
+pub struct Ted {
+    term: TTerm,
+    buffers: Buffers,
+    exit: bool,
+    prompt: String,
+    answer: LineEdit,
+    message: String,
+    space_chain: String,
+    /// when the last key of the space chain was pressed
+    chain_time: Option<Instant>,
+    /// continuations of the space chain, once it paused for WHICH_KEY_DELAY
+    which_key: Option<WhichKey>,
+    commands: Commands<Ted>,
+    /// what takes the keys typed, each with its keymap
+    modes: Modes,
+    /// bodies of the commands defined by plugins
+    scripts: HashMap<String, Vec<String>>,
+    script_depth: usize,
+    /// plugins written in Lua
+    lua: lua::Runtime,
+    plugins: Vec<plugin::Supervisor>,
+    /// commands registered by plugins, with the index of their plugin
+    plugin_commands: HashMap<String, usize>,
+    prompt_callback: Option<fn(&mut Ted, String)>,
+    /// full answers completing the one being typed, offered with Tab
+    prompt_candidates: Option<fn(&Ted, &str) -> Vec<String>>,
+    /// handles Ctrl keys typed in the prompt
+    prompt_control: Option<fn(&mut Ted, char)>,
+    /// shows the effect of the answer while it is typed, given None once the prompt closes
+    prompt_preview: Option<fn(&mut Ted, Option<String>)>,
+    /// the pattern whose matches are being replaced, while the replacement is typed
+    replacing: Option<String>,
+    universal_argument: Option<usize>,
+    /// first key of a two keys normal mode command, like `z` of `zz`
+    pending_key: Option<char>,
+    special: Option<Special>,
+    /// copied and deleted texts, the latest is pasted
+    kill_ring: KillRing,
+    /// where the last paste is, with the kill ring index it came from, how many times and whether
+    /// as whole lines
+    last_paste: Option<(Range<usize>, usize, usize, bool)>,
+    config: Rc<Config>,
+    picker: Option<Picker>,
+    /// floating panels over the buffer, the last one on top
+    overlays: Vec<Overlay>,
+    /// pattern and files matching it, waiting for confirmation to be opened
+    pending_files: Option<(String, Vec<String>)>,
+    /// directories files were opened from this session, most recent last
+    directories: Vec<String>,
+    prompt_history: Vec<String>,
+    history_index: Option<usize>,
+    /// running language servers by language name, None if one failed to start
+    language_servers: HashMap<String, Option<lsp::Client>>,
+    lsp_requests: HashMap<(String, u64), LspRequest>,
+    completion: Option<Completion>,
+    /// git gutters computed in the background
+    git_changes: (mpsc::Sender<GitUpdate>, mpsc::Receiver<GitUpdate>),
+    /// messages produced by background work, shown as they arrive
+    messages: (mpsc::Sender<String>, mpsc::Receiver<String>),
+    /// markers found under the working directory in the background
+    project_todos: (mpsc::Sender<String>, mpsc::Receiver<String>),
+    /// lessons of the tutor buffer, checked after each key
+    tutor: Option<Tutor>,
+    recent: Recent,
+    /// searched patterns, shared by every buffer
+    searches: Recent,
+    /// where the cursor was left in files, across sessions
+    positions: Positions,
+    /// something changed since the last draw
+    damaged: bool,
+    /// the rows of text last drawn, only those that changed are drawn again
+    rows: buffer_widget::Rows,
+    last_draw: Instant,
+    /// recent failures, oldest first
+    errors: Vec<TedError>,
+    /// when the config file was modified as last loaded, and last checked
+    config_modified: (Option<SystemTime>, Instant),
+    /// driven by a batch script, nothing is written to the terminal
+    headless: bool,
+    /// the terminal has focus, files and the config aren't watched nor the view highlighted
+    /// ahead without it
+    terminal_focused: bool,
+    /// last title set on the terminal
+    title: String,
+}
+
+impl Ted {
+    pub fn new(term: TTerm, mut config: Config) -> Ted {
+        let load_errors = std::mem::take(&mut config.load_errors);
+        let config_modified = modified(config.file.as_ref());
+        let config = Rc::new(config);
+        let commands = commands::builtin();
+        let modes = Modes::builtin();
+        let normal = modes.keymap("normal").expect("normal mode is builtin");
+        let mut ted = Ted {
+            term,
+            buffers: Buffers::home(config.clone(), &commands, normal),
+            exit: false,
+            prompt: String::default(),
+            answer: LineEdit::default(),
+            message: String::default(),
+            space_chain: String::default(),
+            chain_time: None,
+            which_key: None,
+            commands,
+            modes,
+            scripts: HashMap::default(),
+            script_depth: 0,
+            lua: lua::Runtime::default(),
+            plugins: Vec::default(),
+            plugin_commands: HashMap::default(),
+            prompt_callback: None,
+            prompt_candidates: None,
+            prompt_control: None,
+            prompt_preview: None,
+            replacing: None,
+            universal_argument: None,
+            pending_key: None,
+            special: None,
+            kill_ring: KillRing::default(),
+            last_paste: None,
+            config,
+            picker: None,
+            overlays: Vec::default(),
+            pending_files: None,
+            directories: Vec::default(),
+            prompt_history: Vec::default(),
+            history_index: None,
+            language_servers: HashMap::default(),
+            lsp_requests: HashMap::default(),
+            completion: None,
+            git_changes: mpsc::channel(),
+            messages: mpsc::channel(),
+            project_todos: mpsc::channel(),
+            tutor: None,
+            recent: Recent::default(),
+            searches: Recent::load(data_dir("searches")),
+            positions: Positions::load(state_dir("positions")),
+            damaged: true,
+            rows: buffer_widget::Rows::default(),
+            last_draw: Instant::now() - FRAME,
+            errors: Vec::default(),
+            config_modified: (config_modified, Instant::now()),
+            headless: false,
+            terminal_focused: true,
+            title: String::default(),
+        };
+        let mut errors = load_errors;
+        errors.extend(ted.load_commands().into_iter().map(TedError::Config));
+        ted.report_all(errors);
+        let recovered = data_dir("recovery")
+            .map(|dir| recovery::pending(&dir).len())
+            .unwrap_or(0);
+        if recovered > 0 {
+            ted.message = format!(
+                "{} buffers recovered from a crash, open them with recover",
+                recovered
+            );
+        }
+        ted
+    }
+
+    /// Ted drawing on an 80x24 screen in memory rather than the terminal, for batch scripts
+    /// and tests
+    pub fn headless(config: Config) -> io::Result<Ted> {
+        let term = Terminal::new(TermBackend::test(80, 24))?;
+        let mut ted = Ted::new(term, config);
+        ted.headless = true;
+        // recent files and searches stay in memory
+        ted.recent = Recent::load(None);
+        ted.searches = Recent::load(None);
+        Ok(ted)
+    }
+
+    /// runs a command line of a batch script, returns wether it exited
+    pub fn run_batch_line(&mut self, line: &str) -> bool {
+        self.run_script(&[line.to_string()]);
+        self.exit
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn errors(&self) -> &[TedError] {
+        &self.errors
+    }
+
+    pub fn focused_text(&self) -> String {
+        self.buffers.focused().get_text()
+    }
+
+    fn set_cursor_shape(&self, shape: CursorShape) {
+        if !self.headless {
+            execute!(io::stdout(), SetCursorShape(shape)).unwrap();
+        }
+    }
+
+    /// dumps the buffers with unsaved changes for `recover` and writes a crash report,
+    /// returns where
+    pub fn dump_crash(&self, message: &str, backtrace: &str) -> io::Result<(PathBuf, PathBuf)> {
+        let (dir, report) = data_dir("recovery")
+            .zip(data_dir("crash"))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory"))?;
+        recovery::dump(&dir, &self.buffers.dirty())?;
+        let text = recovery::report(message, backtrace, &self.buffers.names());
+        std::fs::write(&report, text)?;
+        Ok((dir, report))
+    }
+
+    /// opens the buffers dumped by a crash, over their file when they had one
+    fn recover(&mut self) {
+        let dir = match data_dir("recovery") {
+            Some(dir) => dir,
+            None => return,
+        };
+        let pending = recovery::pending(&dir);
+        if pending.is_empty() {
+            self.message = String::from("No buffers to recover");
+            return;
+        }
+        let n = pending.len();
+        for (name, file) in pending {
+            let text = match std::fs::read_to_string(&file) {
+                Ok(text) => text,
+                Err(err) => {
+                    self.report(err.into());
+                    continue;
+                }
+            };
+            let buffer = if Path::new(&name).is_file() {
+                Buffer::from_file(&name, self.config.clone())
+            } else {
+                Ok(Buffer::new(String::default(), name, self.config.clone()))
+            };
+            match buffer {
+                Ok(mut buffer) => {
+                    buffer.recover(&text);
+                    self.buffers.new_buffer(buffer);
+                    if let Err(err) = std::fs::remove_file(&file) {
+                        self.report(err.into());
+                    }
+                }
+                Err(err) => self.report(err.into()),
+            }
+        }
+        self.message = format!("Recovered {} buffers, save them to keep them", n);
+    }
+
+    /// shows an error in the echo area and keeps it for the errors buffer
+    fn report(&mut self, error: TedError) {
+        log::warn!("{}", error);
+        self.message = error.to_string();
+        self.errors.push(error);
+        if self.errors.len() > MAX_ERRORS {
+            self.errors.remove(0);
+        }
+    }
+
+    /// reports errors, summing them up when there are several
+    fn report_all(&mut self, errors: Vec<TedError>) {
+        let n = errors.len();
+        for error in errors {
+            self.report(error);
+        }
+        if n > 1 {
+            self.message = format!("{} errors, see them with errors", n);
+        }
+    }
+
+    /// opens the log in a read-only buffer, following its end as it grows
+    fn show_log(&mut self) {
+        let path = match data_dir("log") {
+            Some(path) => path.to_string_lossy().to_string(),
+            None => {
+                self.message = String::from("No data directory for the log");
+                return;
+            }
+        };
+        match Buffer::from_file(&path, self.config.clone()) {
+            Ok(mut buffer) => {
+                buffer.readonly = true;
+                buffer.goto(buffer.len_lines(), 0);
+                self.buffers.new_buffer(buffer);
+            }
+            Err(err) => self.report(err.into()),
+        }
+    }
+
+    /// reloads the focused buffer when it shows the log and the log grew
+    fn poll_log(&mut self) -> bool {
+        let buffer = self.buffers.focused_mut();
+        let log = data_dir("log");
+        if log.is_none() || buffer.get_path().map(PathBuf::from) != log {
+            return false;
+        }
+        if !buffer.is_modified_on_disk() || buffer.reload_backend_file().is_err() {
+            return false;
+        }
+        buffer.goto(buffer.len_lines(), 0);
+        true
+    }
+
+    /// opens the recent errors in a new buffer
+    fn show_errors(&mut self) {
+        if self.errors.is_empty() {
+            self.message = String::from("No errors");
+            return;
+        }
+        let lines: Vec<String> = self.errors.iter().map(|err| err.to_string()).collect();
+        self.new_buffer(lines.join("\n") + "\n");
+        self.buffers.focused_mut().readonly = true;
+    }
+
+    /// the screen must be drawn again, e.g. after the terminal was resized
+    pub fn damage(&mut self) {
+        self.damaged = true;
+    }
+
+    /// fits the buffers to the resized terminal, less the status and echo lines
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.buffers
+            .resize(height.saturating_sub(2) as usize, width as usize);
+        self.damage();
+    }
+
+    /// how long to wait for input before the next draw is due
+    pub fn next_frame(&self) -> Duration {
+        if self.damaged {
+            FRAME.saturating_sub(self.last_draw.elapsed())
+        } else {
+            IDLE
+        }
+    }
+
+    /// draws right away, returns the rows of the screen when headless
+    pub fn screen(&mut self) -> io::Result<Vec<String>> {
+        self.damaged = true;
+        self.last_draw = Instant::now() - FRAME;
+        self.draw()?;
+        Ok(self.term.backend().screen().unwrap_or_default())
+    }
+
+    /// Redraw the buffer when we process an event, damage since the last draw is drawn at
+    /// most once per FRAME
+    pub fn draw(&mut self) -> TRes {
+        if !self.damaged || self.last_draw.elapsed() < FRAME {
+            return Ok(());
+        }
+        self.damaged = false;
+        self.last_draw = Instant::now();
+        let title = self.buffers.focused().title(&self.config.title.borrow());
+        if title != self.title && !self.headless {
+            execute!(io::stdout(), SetTitle(&title))?;
+            self.title = title;
+        }
+        let size = self.term.size()?;
+        let preview = self.picker.as_ref().and_then(|picker| {
+            picker
+                .preview
+                .zip(picker.selected())
+                .and_then(|(preview, selected)| preview(self, selected))
+        });
+        let buffer = self.buffers.focused_mut();
+        let (_, line_number, column_number) = buffer.get_cursor();
+        let status_line_number = size.height.saturating_sub(2) as usize;
+        // the columns first, the rows of wrapped lines depending on them
+        let gutter = buffer.gutter_width();
+        buffer.resize_columns((size.width as usize).saturating_sub(gutter));
+        buffer.resize_window(status_line_number);
+        let (echo_line, cursor_x, cursor_y) = if let Some(picker) = &self.picker {
+            let line = format!("{}: {}", picker.prompt(), picker.query);
+            let n = line.len();
+            (line, n as u16, size.height.saturating_sub(1))
+        } else if self.prompt.is_empty() {
+            let message = match self.modes.current() {
+                Some(mode) if self.message.is_empty() => format!("-- {} --", mode),
+                _ => self.message.clone(),
+            };
+            let (y, x) = buffer
+                .screen_position(line_number, column_number)
+                .unwrap_or((buffer.screen_row(line_number).unwrap_or(0), 0));
+            (message, (gutter + x) as u16, y as u16)
+        } else {
+            let label = format!("{}: ", self.prompt);
+            let x = label.chars().count() + self.answer.cursor();
+            let line = label + self.answer.as_str();
+            (line, x as u16, size.height.saturating_sub(1))
+        };
+
+        let picker = &mut self.picker;
+        let rows = &mut self.rows;
+        let overlays = &mut self.overlays;
+        let completion = &mut self.completion;
+        let chaining = !self.space_chain.is_empty();
+        let which_key = self.which_key.as_mut().filter(|_| chaining);
+        self.term.draw(|f| {
+            let mut area = f.size();
+            area.height -= 1;
+            if buffer.is_help() {
+                f.render_stateful_widget(HelpWidget {}, area, buffer);
+            } else {
+                f.render_stateful_widget(BufferWidget::cached(rows), area, buffer);
+            }
+            if let Some(picker) = picker {
+                f.render_stateful_widget(PickerWidget { preview }, area, picker);
+            }
+            for overlay in overlays.iter_mut() {
+                let popup = overlay.area(cursor_x, cursor_y, area);
+                f.render_stateful_widget(OverlayWidget {}, popup, overlay);
+            }
+            if let Some(completion) = completion.as_mut().filter(|c| !c.is_empty()) {
+                let popup = completion.area(cursor_x, cursor_y, area);
+                f.render_stateful_widget(CompletionWidget {}, popup, completion);
+            }
+            if let Some(which_key) = which_key {
+                let popup = which_key.area(area);
+                f.render_stateful_widget(WhichKeyWidget {}, popup, which_key);
+            }
+            let echo = Paragraph::new(echo_line);
+            f.render_widget(echo, Rect::new(0, area.height, area.width, 1));
+            f.set_cursor(cursor_x, cursor_y);
+        })?;
+        // keep drawing while the view is highlighted over several frames
+        self.damaged |= self.terminal_focused && self.buffers.focused().is_highlight_pending();
+
+        Ok(())
+    }
+
+    fn new_buffer(&mut self, content: String) {
+        let name = format!("Buffer #{}", self.buffers.len() + 1);
+        self.message = format!("Created new buffer <{}>", name);
+        self.buffers
+            .new_buffer(Buffer::new(content, name, self.config.clone()));
+    }
+
+    /// runs `name [arguments]`, commands given no arguments prompt for them
+    fn run_command(&mut self, line: String) {
+        if let Err(err) = self.try_run_command(&line) {
+            self.report(err);
+        }
+    }
+
+    fn try_run_command(&mut self, line: &str) -> error::Result<()> {
+        let (name, arguments) = parse_command_line(line);
+        let no_arguments = || match arguments.is_empty() {
+            true => Ok(()),
+            false => Err(TedError::Command(format!("{} takes no arguments", name))),
+        };
+        if let Some(lines) = self.scripts.get(name).cloned() {
+            no_arguments()?;
+            self.run_script(&lines);
+            return Ok(());
+        }
+        if self.lua.commands.contains(name) {
+            no_arguments()?;
+            return self.run_lua_command(name);
+        }
+        if let Some(&i) = self.plugin_commands.get(name) {
+            no_arguments()?;
+            self.plugins[i].notify("commandInvoked", json!({ "name": name }));
+            return Ok(());
+        }
+        let command = self
+            .commands
+            .get_by_name(name)
+            .ok_or_else(|| TedError::Command(format!("Unrecognized command: {}", name)))?;
+        match (&command.arguments, arguments.is_empty()) {
+            (_, true) => command.get_action()(self),
+            (Some(with_arguments), false) => (with_arguments.run)(self, arguments.to_string()),
+            (None, false) => no_arguments()?,
+        }
+        Ok(())
+    }
+
+    fn complete_command_line(&self, line: &str) -> Vec<String> {
+        self.commands.complete(self, line)
+    }
+
+    fn command_names(&self) -> Vec<String> {
+        self.commands
+            .commands
+            .iter()
+            .map(|command| command.name.clone())
+            .collect()
+    }
+
+    fn language_names(&self) -> Vec<String> {
+        self.config
+            .syntax_set
+            .syntaxes()
+            .iter()
+            .map(|syntax| syntax.name.clone())
+            .collect()
+    }
+
+    fn theme_names(&self) -> Vec<String> {
+        self.config.theme_set.themes.keys().cloned().collect()
+    }
+
+    /// completes the prompt's answer up to what its candidates share, listing them
+    fn complete_answer(&mut self) {
+        let candidates = match self.prompt_candidates {
+            Some(candidates) => candidates(self, self.answer.as_str()),
+            None => return,
+        };
+        let prefix = common_prefix(&candidates);
+        if prefix.len() > self.answer.as_str().len() {
+            self.answer.set(prefix);
+        }
+        // the popup lists the last word of each candidate
+        let answer = self.answer.as_str();
+        let start = answer.rfind(' ').map_or(0, |i| i + 1);
+        let words = candidates
+            .iter()
+            .map(|candidate| candidate.get(start..).unwrap_or_default().to_string())
+            .collect();
+        self.completion = Some(Completion::new(answer[start..].to_string(), words));
+    }
+
+    /// handles background work, damaging the screen if any of it changed something
+    pub fn tick(&mut self) {
+        let picked = self
+            .picker
+            .as_mut()
+            .map(|picker| picker.poll_source())
+            .unwrap_or(false);
+        let gutters = self.poll_git_changes();
+        let mut messages = false;
+        while let Ok(message) = self.messages.1.try_recv() {
+            self.message = message;
+            messages = true;
+        }
+        let todos = self.poll_project_todos();
+        let plugins = self.poll_plugins();
+        let log = self.terminal_focused && self.poll_log();
+        let config = self.terminal_focused && self.poll_config();
+        let search = self.buffers.focused_mut().refresh_search(SEARCH_DELAY);
+        let chain = self.poll_space_chain();
+        let changed = self.poll_language_servers()
+            || picked
+            || gutters
+            || messages
+            || todos
+            || plugins
+            || log
+            || config
+            || search
+            || chain;
+        self.damaged |= changed;
+    }
+
+    /// pops the continuations of a paused space chain up, abandons it on timeout
+    fn poll_space_chain(&mut self) -> bool {
+        let elapsed = match self.chain_time {
+            Some(time) if !self.space_chain.is_empty() => time.elapsed(),
+            _ => {
+                self.chain_time = None;
+                return self.which_key.take().is_some();
+            }
+        };
+        if elapsed >= CHAIN_TIMEOUT {
+            self.message = format!("{} timed out", self.format_space_chain(false));
+            self.space_chain.clear();
+            self.normal_mode();
+            self.chain_time = None;
+            self.which_key = None;
+            true
+        } else if elapsed >= WHICH_KEY_DELAY && self.which_key.is_none() {
+            self.which_key = Some(WhichKey::new(&self.commands, &self.space_chain));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// shows the commit that last changed the current line
+    fn git_blame(&mut self) {
+        let buffer = self.buffers.focused();
+        let path = match buffer.get_path() {
+            Some(path) => PathBuf::from(path),
+            None => {
+                self.message = String::from("No backend file");
+                return;
+            }
+        };
+        let (_, line_number, _) = buffer.get_cursor();
+        let sender = self.messages.0.clone();
+        self.message = format!("Blaming line {}...", line_number + 1);
+        thread::spawn(move || {
+            let message = match git::blame(&path, line_number) {
+                Ok(blame) if blame.is_uncommitted() => String::from("Not committed yet"),
+                Ok(blame) => format!(
+                    "{} {} {} {}",
+                    &blame.hash[..blame.hash.len().min(8)],
+                    blame.author,
+                    blame.date,
+                    blame.summary
+                ),
+                Err(err) => format!("git blame: {}", err),
+            };
+            let _ = sender.send(message);
+        });
+    }
+
+    /// diffs the focused buffer against its committed file in the background
+    fn refresh_git_gutter(&mut self) {
+        let buffer = self.buffers.focused();
+        if let Some(path) = buffer.get_path().map(PathBuf::from) {
+            let text = buffer.get_text();
+            let sender = self.git_changes.0.clone();
+            thread::spawn(move || {
+                let changes = git::head_content(&path)
+                    .ok()
+                    .map(|head| git::line_changes(&head, &text));
+                let _ = sender.send((path, changes));
+            });
+        }
+    }
+
+    /// applies the gutters computed so far, returns wether any arrived
+    fn poll_git_changes(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok((path, changes)) = self.git_changes.1.try_recv() {
+            if let Some(i) = self.buffers.position_by_path(&path) {
+                if let Some(buffer) = self.buffers.get_mut(i) {
+                    buffer.set_git_changes(changes);
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    fn open_picker(&mut self, picker: Picker) {
+        self.picker = Some(picker);
+        self.set_cursor_shape(CursorShape::Line);
+    }
+
+    fn pick_buffer(&mut self) {
+        let picker = Picker::new("Buffer".to_string(), self.buffers.labels(), |t, label| {
+            if let Some(i) = t.buffers.labels().iter().position(|l| l == &label) {
+                t.buffers.focus(i);
+                t.message = format!("Switched to <{}>", t.buffers.focused().name);
+            }
+        })
+        .with_preview(|t, label| {
+            let i = t.buffers.labels().iter().position(|l| l == label)?;
+            t.buffers.get(i).and_then(|buffer| buffer.get_lines(0..100))
+        });
+        self.open_picker(picker);
+    }
+
+    fn file_recent(&mut self) {
+        let picker = Picker::new(
+            "Recent file".to_string(),
+            self.recent.entries().clone(),
+            Ted::file_open,
+        )
+        .with_preview(|_, path| {
+            std::fs::read_to_string(path)
+                .ok()
+                .map(|content| content.lines().take(100).collect::<Vec<_>>().join("\n"))
+        });
+        self.open_picker(picker);
+    }
+
+    fn pick_command(&mut self) {
+        let names = self
+            .commands
+            .commands
+            .iter()
+            .map(|command| command.name.clone())
+            .collect();
+        let picker = Picker::new("Command".to_string(), names, Ted::run_command)
+            .with_preview(|t, name| t.commands.get_by_name(name).map(|c| c.desc.clone()));
+        self.open_picker(picker);
+    }
+
+    fn define_word_under_cursor(&mut self) {
+        match self.buffers.focused().word_under_cursor() {
+            word if word.is_empty() => self.message = String::from("No word under the cursor"),
+            word => self.define_word(word),
+        }
+    }
+
+    /// opens the definitions of a word from the dictionary file, or the dictionary command
+    fn define_word(&mut self, word: String) {
+        let word = word.trim();
+        let file = self.config.dictionary.borrow().clone();
+        let definitions = match file.map(|path| dictionary::lookup(&path, word)) {
+            Some(Ok(definitions)) => definitions,
+            Some(Err(err)) if err.kind() != io::ErrorKind::NotFound => {
+                self.message = format!("Dictionary: {}", err);
+                return;
+            }
+            _ => vec![],
+        };
+        if !definitions.is_empty() {
+            self.new_buffer(dictionary::format(word, &definitions));
+            self.buffers.focused_mut().set_language("Markdown");
+        } else {
+            let command = self.config.dictionary_command.borrow().clone();
+            if command.is_empty() {
+                self.message = format!("No definition of {}", word);
+                return;
+            }
+            match dictionary::run(&command, word) {
+                Ok(output) if !output.trim().is_empty() => self.new_buffer(output),
+                Ok(_) => {
+                    self.message = format!("No definition of {}", word);
+                    return;
+                }
+                Err(err) => {
+                    self.message = format!("{}: {}", command, err);
+                    return;
+                }
+            }
+        }
+        self.buffers.focused_mut().readonly = true;
+    }
+
+    /// fuzzy finds a command to read the help of, previewing it
+    fn help_index(&mut self) {
+        let picker = Picker::new("Help".to_string(), self.command_names(), Ted::help)
+            .with_preview(|t, name| t.commands.get_by_name(name).map(help::command_help));
+        self.open_picker(picker);
+    }
+
+    /// opens the documentation of a command in a buffer
+    fn help(&mut self, name: String) {
+        let text = match self.commands.get_by_name(name.trim()) {
+            Some(command) => help::command_help(command),
+            None => {
+                self.message = format!("No command {}", name.trim());
+                return;
+            }
+        };
+        self.new_buffer(text);
+        let buffer = self.buffers.focused_mut();
+        buffer.set_language("Markdown");
+        buffer.readonly = true;
+    }
+
+    /// fuzzy finds among the commands continuing the pending space chain
+    fn pick_chain_command(&mut self) {
+        let names = self
+            .commands
+            .get_by_chain(&self.space_chain)
+            .iter()
+            .map(|command| command.name.clone())
+            .collect();
+        let title = format!("{} /", self.format_space_chain(false));
+        self.space_chain.clear();
+        let picker = Picker::new(title, names, Ted::run_command)
+            .with_preview(|t, name| t.commands.get_by_name(name).map(|c| c.desc.clone()));
+        self.open_picker(picker);
+    }
+
+    fn pick_theme(&mut self) {
+        let names = self.config.theme_set.themes.keys().cloned().collect();
+        let picker = Picker::new("Theme".to_string(), names, Ted::set_theme);
+        self.open_picker(picker);
+    }
+
+    /// fuzzy finds the files under the focused buffer's working directory
+    fn pick_file(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+        let root = match self.buffers.focused().working_dir() {
+            dir if dir.is_empty() => String::from("."),
+            dir => dir,
+        };
+        thread::spawn(move || {
+            let mut dirs = vec![PathBuf::from(root)];
+            while let Some(dir) = dirs.pop() {
+                for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+                    let path = entry.path();
+                    let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                    if path.is_dir() {
+                        if !hidden && entry.file_name() != "target" {
+                            dirs.push(path);
+                        }
+                    } else if sender
+                        .send(
+                            path.strip_prefix(".")
+                                .unwrap_or(&path)
+                                .display()
+                                .to_string(),
+                        )
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+        });
+        let picker = Picker::new("File".to_string(), vec![], Ted::file_open)
+            .with_source(receiver)
+            .with_preview(|_, path| {
+                std::fs::read_to_string(path)
+                    .ok()
+                    .map(|content| content.lines().take(100).collect::<Vec<_>>().join("\n"))
+            });
+        self.open_picker(picker);
+    }
+
+    /// prompts for a file, starting from the focused buffer's (or last used) directory
+    fn file_open_prompt(&mut self) {
+        let buffer = self.buffers.focused();
+        let directory = match buffer.working_dir() {
+            dir if dir.is_empty() && buffer.get_path().is_none() => {
+                self.directories.last().cloned().unwrap_or_default()
+            }
+            dir => dir,
+        };
+        self.prompt_mode("File open".to_string(), Ted::file_open);
+        self.answer.set(directory);
+        self.prompt_history = self.directories.clone();
+    }
+
+    /// opens the given file, or every file matching its wildcards, asking first when
+    /// there are more than max_open_files
+    pub fn file_open(&mut self, filepath: String) {
+        let files = path::glob(&filepath);
+        if files.is_empty() {
+            self.message = format!("file_open({}): no match", filepath);
+        } else if files.len() > self.config.max_open_files.get() {
+            let prompt = format!("Open {} files matching {}? (y/N)", files.len(), filepath);
+            self.pending_files = Some((filepath, files));
+            self.prompt_mode(prompt, Ted::confirm_open_files);
+        } else {
+            self.open_files(&filepath, files);
+        }
+    }
+
+    fn confirm_open_files(&mut self, answer: String) {
+        match self.pending_files.take() {
+            Some((pattern, files)) if answer.trim().eq_ignore_ascii_case("y") => {
+                self.open_files(&pattern, files)
+            }
+            _ => self.message = String::from("Cancelled"),
+        }
+    }
+
+    /// opens files matching pattern, binary ones are skipped when it has wildcards
+    fn open_files(&mut self, pattern: &str, files: Vec<String>) {
+        let wildcards = pattern.contains(&['*', '?'][..]);
+        let mut skipped = 0;
+        for file in &files {
+            if wildcards && path::is_binary(file) {
+                skipped += 1;
+                continue;
+            }
+            let n = self.buffers.len();
+            let buffer = Buffer::from_file(file, self.config.clone());
+            self.message = match buffer {
+                Ok(buffer) => {
+                    log::info!("opened {}", file);
+                    let message = match buffer.missing_dir() {
+                        Some(dir) => format!(
+                            "Created new buffer <{}>, {} will be created on save",
+                            buffer.name,
+                            dir.display()
+                        ),
+                        None => {
+                            let directory = path::directory_of(file);
+                            self.directories.retain(|d| d != &directory);
+                            self.directories.push(directory);
+                            format!("Created new buffer <{}>", buffer.name)
+                        }
+                    };
+                    self.buffers.new_buffer(buffer);
+                    let _ = self.recent.add(file);
+                    self.restore_position(file);
+                    self.load_undo_history(file);
+                    message
+                }
+                Err(err) => {
+                    let message = format!("file_open({}): {}", file, err);
+                    self.report(io::Error::new(err.kind(), message).into());
+                    self.message.clone()
+                }
+            };
+            if self.buffers.len() > n {
+                self.lsp_did_open();
+                self.notify_plugins("bufferOpened", json!({ "path": file }));
+                self.refresh_git_gutter();
+                self.run_hooks(hook::Event::Open);
+            }
+        }
+        if wildcards {
+            self.message = format!(
+                "Opened {} files matching {}",
+                files.len() - skipped,
+                pattern
+            );
+            if skipped > 0 {
+                self.message = format!("{} ({} binary skipped)", self.message, skipped);
+            }
+        }
+    }
+
+    /// moves to where the cursor was left in file, when it was opened before
+    fn restore_position(&mut self, file: &str) {
+        if self.headless || !self.config.restore_cursor.get() {
+            return;
+        }
+        if let Some((line, column)) = self.positions.get(file) {
+            self.buffers.focused_mut().goto(line, column);
+        }
+    }
+
+    /// saves what is restored when files are reopened, before exiting
+    pub fn save_session(&mut self) {
+        self.remember_positions();
+        for i in 0..self.buffers.len() {
+            self.save_undo_history(i);
+        }
+    }
+
+    /// remembers where the cursor is in each file, to be restored when reopened
+    fn remember_positions(&mut self) {
+        if self.headless || !self.config.restore_cursor.get() {
+            return;
+        }
+        let capacity = self.config.restore_cursor_files.get();
+        for i in 0..self.buffers.len() {
+            let buffer = match self.buffers.get(i) {
+                Some(buffer) => buffer,
+                None => continue,
+            };
+            if let Some(path) = buffer.get_path() {
+                let (_, line, column) = buffer.get_cursor();
+                self.positions.set(path, line, column, capacity);
+            }
+        }
+        if let Err(err) = self.positions.save() {
+            log::warn!("could not remember cursor positions: {}", err);
+        }
+    }
+
+    /// the undo tree of the focused buffer as saved for file, when it wasn't edited since
+    fn load_undo_history(&mut self, file: &str) {
+        if self.headless || !self.config.persistent_undo.get() {
+            return;
+        }
+        let history = undo_file(file)
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok());
+        if let Some(history) = history {
+            if !self.buffers.focused_mut().load_undo_history(&history) {
+                log::info!("ignored the undo history of {}, edited since", file);
+            }
+        }
+    }
+
+    /// saves the undo tree of a buffer with a file, for `load_undo_history`
+    fn save_undo_history(&mut self, index: usize) {
+        if self.headless || !self.config.persistent_undo.get() {
+            return;
+        }
+        let limit = self.config.persistent_undo_states.get();
+        let buffer = match self.buffers.get_mut(index) {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let path = match buffer.get_path().and_then(undo_file) {
+            Some(path) if !buffer.readonly => path,
+            _ => return,
+        };
+        let history = buffer.undo_history(limit).to_string();
+        let saved = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, history));
+        if let Err(err) = saved {
+            log::warn!(
+                "could not save the undo history to {}: {}",
+                path.display(),
+                err
+            );
+        }
+    }
+
+    /// opens a file and moves to the given 1-based position
+    pub fn file_open_at(
+        &mut self,
+        filepath: String,
+        line: Option<usize>,
+        column: Option<usize>,
+        readonly: bool,
+    ) {
+        let n = self.buffers.len();
+        self.file_open(filepath);
+        if self.buffers.len() > n {
+            self.buffers.focused_mut().readonly = readonly;
+            if let Some(line) = line {
+                self.buffers.focused_mut().goto(
+                    line.saturating_sub(1),
+                    column.unwrap_or(1).saturating_sub(1),
+                );
+            }
+        }
+    }
+
+    /// replaces the buffer with its file's latest backup, to be saved over the file
+    fn restore_backup(&mut self) {
+        match self.buffers.focused_mut().restore_backup() {
+            Ok(backup) => self.message = format!("Restored {}, save to keep it", backup.display()),
+            Err(err) => self.report(err.into()),
+        }
+    }
+
+    fn file_save(&mut self) {
+        let (warnings, block) = self.buffers.focused().lint();
+        if let Some(summary) = lint_summary(&warnings) {
+            if block {
+                self.prompt_mode(format!("{}, save anyway? (y/N)", summary), |t, answer| {
+                    if answer.trim().eq_ignore_ascii_case("y") {
+                        t.write_buffer(false);
+                    } else {
+                        t.message = String::from("Save cancelled");
+                    }
+                });
+            } else {
+                if self.write_buffer(false) {
+                    self.message = format!("{} ({})", self.message, summary);
+                }
+            }
+        } else {
+            self.write_buffer(false);
+        }
+    }
+
+    /// `append <path>` adds the buffer at the end of another file, `tee <paths…>` saves it to
+    /// the paths too from now on, `tee` alone forgets them but keeps the configured copies
+    fn file_save_with(&mut self, arguments: String) {
+        let mut words = arguments.split_whitespace();
+        match words.next() {
+            None => self.file_save(),
+            Some("append") => {
+                let path = arguments.trim()["append".len()..].trim();
+                if path.is_empty() {
+                    self.message = String::from("file_save append <path>");
+                    return;
+                }
+                match self.buffers.focused().append_to(Path::new(path)) {
+                    Ok(()) => self.message = format!("Appended to {}", path),
+                    Err(err) => self.message = format!("append to {}: {}", path, err),
+                }
+            }
+            Some("tee") => {
+                let copies: Vec<PathBuf> = words.map(PathBuf::from).collect();
+                let n = copies.len();
+                self.buffers.focused_mut().tee(copies);
+                if n == 0 {
+                    self.message = String::from("Only the configured copies are saved");
+                } else {
+                    self.file_save();
+                }
+            }
+            Some(other) => self.message = format!("file_save: unknown mode {}", other),
+        }
+    }
+
+    /// writes the buffer's copies, reporting each one that failed
+    fn write_copies(&mut self) {
+        let n = self.buffers.focused().copies.len();
+        let failed = match self.buffers.focused().write_copies() {
+            Ok(failed) => failed,
+            Err(err) => return self.report(err.into()),
+        };
+        if n == 0 {
+            return;
+        }
+        let errors: Vec<TedError> = failed
+            .iter()
+            .map(|(path, err)| {
+                io::Error::new(err.kind(), format!("copy to {}: {}", path.display(), err)).into()
+            })
+            .collect();
+        if errors.is_empty() {
+            self.message = format!(
+                "{}, {} cop{}",
+                self.message,
+                n,
+                if n == 1 { "y" } else { "ies" }
+            );
+        } else {
+            self.report_all(errors);
+        }
+    }
+
+    /// saves the focused buffer, force overwrites a file changed on disk meanwhile.
+    /// returns wether it was written, not when it asked something first or failed
+    fn write_buffer(&mut self, force: bool) -> bool {
+        if let Some(dir) = self.buffers.focused().missing_dir() {
+            let prompt = format!("Create directory {}? (y/N)", dir.display());
+            self.prompt_mode(prompt, Ted::create_missing_dir);
+            return false;
+        }
+        if !force && self.buffers.focused().is_modified_on_disk() {
+            self.prompt_mode(
+                String::from("File changed on disk, (o)verwrite, (r)eload or (m)erge?"),
+                Ted::resolve_external_change,
+            );
+            return false;
+        }
+        self.run_hooks(hook::Event::SavePre);
+        let buffer = self.buffers.focused_mut();
+        let saved = match force {
+            true => buffer.force_overwrite_backend_file(),
+            false => buffer.overwrite_backend_file(),
+        };
+        let saved = match saved {
+            Ok(_) => {
+                log::info!(
+                    "saved {}",
+                    self.buffers.focused().get_path().unwrap_or_default()
+                );
+                self.message = String::from("File saved");
+                self.write_copies();
+                // the focused buffer comes first
+                self.save_undo_history(0);
+                true
+            }
+            Err(err)
+                if err.kind() == io::ErrorKind::PermissionDenied
+                    && !self.buffers.focused().readonly =>
+            {
+                let prompt = format!("{}, save with {}? (y/N)", err, self.config.elevate.borrow());
+                self.prompt_mode(prompt, |t, answer| {
+                    if answer.trim().eq_ignore_ascii_case("y") {
+                        t.save_as_root();
+                    } else {
+                        t.message = String::from("Save cancelled");
+                    }
+                });
+                return false;
+            }
+            Err(err) => {
+                self.report(err.into());
+                false
+            }
+        };
+        self.after_save(saved);
+        saved
+    }
+
+    /// tells the language server and plugins a buffer was reloaded from disk
+    fn after_reload(&mut self, index: usize) {
+        let buffer = match self.buffers.get(index) {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let path = match buffer.get_path() {
+            Some(path) => PathBuf::from(path),
+            None => return,
+        };
+        let text = buffer.get_text();
+        let language = buffer
+            .get_highlighter()
+            .as_ref()
+            .map(|highlighter| highlighter.syntax.name.clone());
+        if let Some(client) = language.and_then(|language| self.lsp_client(&language)) {
+            let _ = client.did_change(&path, &text);
+        }
+        self.notify_plugins("bufferReloaded", json!({ "path": path }));
+    }
+
+    /// tells the language server and plugins about a save of the focused buffer
+    fn after_save(&mut self, saved: bool) {
+        if let Some((client, path)) = self.lsp_sync() {
+            let _ = client.did_save(&path);
+        }
+        if let Some(path) = self.buffers.focused().get_path() {
+            self.notify_plugins("bufferSaved", json!({ "path": path }));
+        }
+        self.refresh_git_gutter();
+        if saved {
+            self.run_hooks(hook::Event::SavePost);
+        }
+    }
+
+    /// creates the directories leading to the buffer's new file, then saves it
+    fn create_missing_dir(&mut self, answer: String) {
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            self.message = String::from("Save cancelled");
+            return;
+        }
+        let created = match self.buffers.focused().missing_dir() {
+            Some(dir) => std::fs::create_dir_all(dir),
+            None => Ok(()),
+        };
+        match created {
+            Ok(_) => {
+                self.write_buffer(false);
+            }
+            Err(err) => self.report(err.into()),
+        }
+    }
+
+    /// saves through the `elevate` command, which may ask for a password on the terminal
+    fn save_as_root(&mut self) {
+        let saved = self
+            .with_terminal_restored(|t| t.buffers.focused_mut().overwrite_backend_file_as_root());
+        match saved {
+            Ok(_) => {
+                self.message = String::from("File saved as root");
+                self.write_copies();
+                self.save_undo_history(0);
+                self.after_save(true);
+            }
+            Err(err) => self.report(err.into()),
+        }
+    }
+
+    /// leaves the alternate screen and raw mode while run uses the terminal, then draws
+    /// everything again
+    fn with_terminal_restored<R>(&mut self, run: impl FnOnce(&mut Ted) -> R) -> R {
+        if self.headless {
+            return run(self);
+        }
+        let _ = disable_raw_mode();
+        let _ = execute!(
+            io::stdout(),
+            DisableBracketedPaste,
+            DisableFocusChange,
+            LeaveAlternateScreen
+        );
+        let result = run(self);
+        let _ = enable_raw_mode();
+        let _ = execute!(
+            io::stdout(),
+            EnterAlternateScreen,
+            EnableBracketedPaste,
+            EnableFocusChange
+        );
+        let _ = self.term.clear();
+        self.damage();
+        result
+    }
+
+    /// handles a file modified on disk since the buffer was opened or saved
+    fn resolve_external_change(&mut self, answer: String) {
+        let answer = answer.trim();
+        if answer == "o" || answer == "overwrite" {
+            self.write_buffer(true);
+            return;
+        }
+        let buffer = self.buffers.focused_mut();
+        let resolved = match answer {
+            "r" | "reload" => buffer
+                .reload_backend_file()
+                .map(|_| String::from("Reloaded from disk")),
+            "m" | "merge" => buffer.merge_backend_file().map(|n| match n {
+                0 => String::from("No differences with the file on disk"),
+                n => format!(
+                    "{} conflict(s), keep a side with pick_mine (SPC c m) or pick_disk (SPC c d)",
+                    n
+                ),
+            }),
+            _ => Ok(String::from("Save cancelled")),
+        };
+        match resolved {
+            Ok(message) => self.message = message,
+            Err(err) => self.report(err.into()),
+        }
+        if matches!(answer, "r" | "reload") {
+            self.after_reload(0);
+        }
+        self.refresh_git_gutter();
+    }
+
+    fn pick_hunk(&mut self, mine: bool) {
+        if !self.buffers.focused_mut().pick_hunk(mine) {
+            self.message = String::from("No conflict under the cursor");
+        }
+    }
+
+    /// the focused buffer's language and file, if it has both
+    fn lsp_document(&self) -> Option<(String, PathBuf)> {
+        let buffer = self.buffers.focused();
+        let path = buffer.get_path()?;
+        let language = buffer.get_highlighter().as_ref()?.syntax.name.clone();
+        Some((language, PathBuf::from(path)))
+    }
+
+    /// the server for language, started on first use
+    fn lsp_client(&mut self, language: &str) -> Option<&mut lsp::Client> {
+        if !self.language_servers.contains_key(language) {
+            let command = self
+                .config
+                .language_servers
+                .borrow()
+                .get(language)
+                .cloned()?;
+            let root = std::env::current_dir().unwrap_or_default();
+            let client = match lsp::Client::spawn(&command, &root) {
+                Ok(client) => {
+                    log::info!("started {} for {}", command, language);
+                    Some(client)
+                }
+                Err(err) => {
+                    let message = format!("{}: {}", command, err);
+                    self.report(io::Error::new(err.kind(), message).into());
+                    None
+                }
+            };
+            self.language_servers.insert(language.to_string(), client);
+        }
+        self.language_servers.get_mut(language)?.as_mut()
+    }
+
+    /// tells the focused buffer's language server it was opened
+    fn lsp_did_open(&mut self) {
+        if let Some((language, path)) = self.lsp_document() {
+            let text = self.buffers.focused().get_text();
+            if let Some(client) = self.lsp_client(&language) {
+                let _ = client.did_open(&path, &language.to_lowercase(), &text);
+            }
+        }
+    }
+
+    /// sends the focused buffer's content to its language server
+    fn lsp_sync(&mut self) -> Option<(&mut lsp::Client, PathBuf)> {
+        let (language, path) = self.lsp_document()?;
+        let text = self.buffers.focused().get_text();
+        let client = self.lsp_client(&language)?;
+        client.did_change(&path, &text).ok()?;
+        Some((client, path))
+    }
+
+    /// asks the focused buffer's language server about the symbol under the cursor,
+    /// returns wether there was a server to ask
+    fn lsp_request(&mut self, request: LspRequest) -> bool {
+        let (_, line_number, column_number) = self.buffers.focused().get_cursor();
+        let language = self.lsp_document().map(|(language, _)| language);
+        let id = self.lsp_sync().and_then(|(client, path)| match request {
+            LspRequest::Definition => client.definition(&path, line_number, column_number).ok(),
+            LspRequest::Hover => client.hover(&path, line_number, column_number).ok(),
+            LspRequest::Completion => client.completion(&path, line_number, column_number).ok(),
+        });
+        match language.zip(id) {
+            Some((language, id)) => {
+                self.lsp_requests.insert((language, id), request);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn goto_definition(&mut self) {
+        if !self.lsp_request(LspRequest::Definition) {
+            self.message = String::from("No language server for this buffer");
+        }
+    }
+
+    fn hover(&mut self) {
+        if !self.lsp_request(LspRequest::Hover) {
+            self.message = String::from("No language server for this buffer");
+        }
+    }
+
+    /// opens the popup with words of open buffers then the language server's,
+    /// first completing the prefix these words share when asked to
+    fn complete(&mut self, insert_common_prefix: bool) {
+        let mut prefix = self.buffers.focused().word_before_cursor();
+        let texts = self.buffers.texts();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let words = words_with_prefix(&prefix, &texts);
+        let common = common_prefix(&words);
+        if insert_common_prefix && common.len() > prefix.len() {
+            self.buffers.focused_mut().complete_word(&common);
+            prefix = common;
+        }
+        self.completion = Some(Completion::new(prefix, words));
+        if self.buffers.focused().get_path().is_some() {
+            self.lsp_request(LspRequest::Completion);
+        }
+    }
+
+    /// handles keys meant for the completion popup, returns wether it consumed the key
+    fn completion_handle_key(&mut self, key: KeyEvent) -> bool {
+        let completion = match self.completion.as_mut() {
+            Some(completion) => completion,
+            None => return false,
+        };
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Down | KeyCode::Tab => completion.select_next(),
+            KeyCode::Char('n') if control => completion.select_next(),
+            KeyCode::Up | KeyCode::BackTab => completion.select_prev(),
+            KeyCode::Char('p') if control => completion.select_prev(),
+            KeyCode::Enter if !completion.is_empty() => {
+                if let Some(word) = self.completion.take().and_then(|c| c.selected().cloned()) {
+                    self.buffers.focused_mut().complete_word(&word);
+                }
+            }
+            KeyCode::Esc => self.completion = None,
+            _ => {
+                self.completion = None;
+                return false;
+            }
+        }
+        true
+    }
+
+    /// applies what language servers sent, returns wether anything changed
+    fn poll_language_servers(&mut self) -> bool {
+        let mut events = vec![];
+        for (language, client) in self.language_servers.iter_mut() {
+            if let Some(client) = client {
+                events.extend(client.poll().into_iter().map(|e| (language.clone(), e)));
+            }
+        }
+        let redraw = !events.is_empty();
+        for (language, event) in events {
+            match event {
+                lsp::Event::Diagnostics { path, diagnostics } => {
+                    if let Some(i) = self.buffers.position_by_path(&path) {
+                        if let Some(buffer) = self.buffers.get_mut(i) {
+                            buffer.set_diagnostics(diagnostics);
+                        }
+                    }
+                }
+                lsp::Event::Response { id, result } => {
+                    match self.lsp_requests.remove(&(language, id)) {
+                        Some(LspRequest::Definition) => self.show_definition(&result),
+                        Some(LspRequest::Hover) => self.show_hover(&result),
+                        Some(LspRequest::Completion) => {
+                            if let Some(completion) = self.completion.as_mut() {
+                                completion.prepend(lsp::completion_items(&result));
+                            }
+                        }
+                        None => {}
+                    }
+                }
+                lsp::Event::Exited => {
+                    self.message = format!("Language server for {} exited", language);
+                    self.language_servers.insert(language, None);
+                }
+            }
+        }
+        redraw
+    }
+
+    fn show_definition(&mut self, result: &Value) {
+        if let Some((path, line_number, column_number)) = lsp::parse_location(result) {
+            if self.buffers.position_by_path(&path).is_none() {
+                // the exact path, not a pattern to expand
+                let file = path.display().to_string();
+                self.open_files(&file, vec![file.clone()]);
+            }
+            if let Some(i) = self.buffers.position_by_path(&path) {
+                self.buffers.focus(i);
+                self.buffers.focused_mut().goto(line_number, column_number);
+            }
+        } else {
+            self.message = String::from("No definition found");
+        }
+    }
+
+    fn show_hover(&mut self, result: &Value) {
+        match lsp::hover_text(result) {
+            Some(text) if text.lines().count() > 1 => {
+                // Enter goes to the definition
+                let overlay = Overlay::new(String::from("Hover"), &text)
+                    .at_cursor()
+                    .with_keys(|t, key| {
+                        let enter = key.code == KeyCode::Enter;
+                        if enter {
+                            t.overlays.pop();
+                            t.goto_definition();
+                        }
+                        enter
+                    });
+                // typing goes on when asked from INSERT mode
+                match self.buffers.focused().mode {
+                    InputMode::Insert => self.open_overlay(overlay.unfocused()),
+                    InputMode::Normal => self.open_overlay(overlay),
+                }
+            }
+            Some(text) => self.message = text,
+            None => self.message = String::from("Nothing to show"),
+        }
+    }
+
+    /// lists the TODO, FIXME and HACK markers of the buffer, Enter jumps to them
+    fn list_todos(&mut self) {
+        let buffer = self.buffers.focused();
+        let todos = todo::find(&buffer.get_text());
+        if todos.is_empty() {
+            self.message = String::from("No TODO, FIXME or HACK");
+            return;
+        }
+        let name = buffer.get_path().unwrap_or(&buffer.name).to_string();
+        self.new_buffer(todo::format(&name, &todos));
+        self.buffers.focused_mut().readonly = true;
+        self.message = format!("{} markers in {}, Enter jumps to one", todos.len(), name);
+    }
+
+    /// lists the markers of the files under the working directory, once found in the background
+    fn list_project_todos(&mut self) {
+        let sender = self.project_todos.0.clone();
+        let root = match self.buffers.focused().working_dir() {
+            dir if dir.is_empty() => String::from("."),
+            dir => dir,
+        };
+        thread::spawn(move || {
+            let _ = sender.send(todo::scan_dir(Path::new(&root)));
+        });
+        self.message = String::from("Looking for TODO, FIXME and HACK...");
+    }
+
+    fn poll_project_todos(&mut self) -> bool {
+        let report = match self.project_todos.1.try_recv() {
+            Ok(report) => report,
+            Err(_) => return false,
+        };
+        if report.is_empty() {
+            self.message = String::from("No TODO, FIXME or HACK");
+        } else {
+            self.message = format!("{} markers, Enter jumps to one", report.lines().count());
+            self.new_buffer(report);
+            self.buffers.focused_mut().readonly = true;
+        }
+        true
+    }
+
+    /// changes the focused buffer's working directory, relative to its current one
+    fn cd(&mut self, target: String) {
+        let target = if target.trim().is_empty() {
+            "."
+        } else {
+            target.trim()
+        };
+        self.message = match self.buffers.focused_mut().change_dir(target) {
+            Ok(dir) => format!("Working directory: {}", Ted::display_dir(&dir)),
+            Err(err) => err.to_string(),
+        };
+    }
+
+    fn pwd(&mut self) {
+        let dir = self.buffers.focused().working_dir();
+        self.message = format!("Working directory: {}", Ted::display_dir(&dir));
+    }
+
+    /// the absolute path of a working directory, as given by path::directory_of
+    fn display_dir(dir: &str) -> String {
+        Path::new(".")
+            .join(dir)
+            .canonicalize()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_else(|_| dir.to_string())
+    }
+
+    /// lists the focused buffer's invisible anomalies in a report buffer
+    fn audit_buffer(&mut self) {
+        let buffer = self.buffers.focused();
+        let findings = buffer.audit();
+        let name = buffer.get_path().unwrap_or(&buffer.name).to_string();
+        self.new_buffer(format_report(&name, &findings));
+        self.buffers.focused_mut().set_language("Markdown");
+        self.message = format!("{} anomalies in {}", findings.len(), name);
+    }
+
+    /// prompts for a pattern, Up and Down recall the previous ones, Ctrl-t and Ctrl-r
+    /// switch the case sensitivity and regex matching
+    fn search_mode(&mut self) {
+        self.prompt_mode(self.search_prompt("Search"), Ted::search);
+        self.prompt_history = self.searches.entries().iter().rev().cloned().collect();
+        self.prompt_control = Some(Ted::toggle_search_option);
+    }
+
+    fn search_prompt(&self, action: &str) -> String {
+        format!(
+            "{} ({}; C-t case, C-r regex)",
+            action,
+            self.config.search.get().summary()
+        )
+    }
+
+    fn toggle_search_option(&mut self, c: char) {
+        let mut options = self.config.search.get();
+        match c {
+            't' => options.case = options.case.next(),
+            'r' => options.regex = !options.regex,
+            _ => return,
+        }
+        self.config.search.set(options);
+        let action = self
+            .prompt
+            .split(" (")
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        self.prompt = self.search_prompt(&action);
+    }
+
+    /// searches the focused buffer for pattern, or for the last one if empty
+    fn search(&mut self, pattern: String) {
+        let pattern = match self.searches.entries().first() {
+            Some(last) if pattern.is_empty() => last.clone(),
+            _ => pattern,
+        };
+        if pattern.is_empty() {
+            return;
+        }
+        let _ = self.searches.push(&pattern);
+        let found = self.buffers.focused_mut().search(&pattern);
+        self.show_match(found);
+    }
+
+    /// moves to the next (or previous) match of the last pattern searched in any buffer
+    fn search_next(&mut self, forward: bool) {
+        let last = match self.searches.entries().first() {
+            Some(last) => last.clone(),
+            None => {
+                self.message = String::from("No search to repeat");
+                return;
+            }
+        };
+        let options = self.config.search.get();
+        let buffer = self.buffers.focused_mut();
+        let searched = buffer.get_search().map(|s| (&s.pattern, s.options));
+        let found = if searched == Some((&last, options)) {
+            Ok(buffer.search_next(forward))
+        } else {
+            buffer
+                .set_search(&last)
+                .map(|_| buffer.search_next(forward))
+        };
+        self.show_match(found);
+    }
+
+    /// echoes "match i of n" after moving to a search match
+    fn show_match(&mut self, found: io::Result<Option<(usize, usize)>>) {
+        self.message = match (found, self.buffers.focused().get_search()) {
+            (Err(err), _) => format!("Invalid pattern: {}", err),
+            (Ok(Some((i, n))), _) => format!("match {} of {}", i, n),
+            (Ok(None), Some(search)) => format!("Pattern not found: {}", search.pattern),
+            (Ok(None), None) => String::default(),
+        };
+    }
+
+    /// prompts for a pattern to replace, in the selection if any, as search_mode does, then
+    /// for its replacement, previewed on the matches as it is typed
+    fn replace_mode(&mut self) {
+        self.prompt_mode(self.search_prompt("Replace"), Ted::replace_pattern);
+        self.prompt_history = self.searches.entries().iter().rev().cloned().collect();
+        self.prompt_control = Some(Ted::toggle_search_option);
+    }
+
+    /// asks what the matches of pattern, or of the last one searched if empty, become
+    fn replace_pattern(&mut self, pattern: String) {
+        let pattern = match self.searches.entries().first() {
+            Some(last) if pattern.is_empty() => last.clone(),
+            _ => pattern,
+        };
+        if pattern.is_empty() {
+            return;
+        }
+        let _ = self.searches.push(&pattern);
+        match self.buffers.focused().count_replacements(&pattern) {
+            Err(err) => self.message = format!("Invalid pattern: {}", err),
+            Ok(0) => self.message = format!("Pattern not found: {}", pattern),
+            Ok(n) => {
+                let _ = self.buffers.focused_mut().preview_replace(&pattern, "");
+                let groups = match self.config.search.get().regex {
+                    true => " ($1 to $9 for groups)",
+                    false => "",
+                };
+                let prompt = format!("Replace {} matches of {} with{}", n, pattern, groups);
+                self.prompt_mode(prompt, Ted::replace_with);
+                self.prompt_preview = Some(Ted::preview_replacement);
+                self.replacing = Some(pattern);
+            }
+        }
+    }
+
+    fn preview_replacement(&mut self, template: Option<String>) {
+        let buffer = self.buffers.focused_mut();
+        match (template, &self.replacing) {
+            (Some(template), Some(pattern)) => {
+                let _ = buffer.preview_replace(pattern, &template);
+            }
+            _ => {
+                buffer.clear_replace_preview();
+                self.replacing = None;
+            }
+        }
+    }
+
+    fn replace_with(&mut self, template: String) {
+        let pattern = match self.replacing.take() {
+            Some(pattern) => pattern,
+            None => return,
+        };
+        let buffer = self.buffers.focused_mut();
+        self.message = match buffer.replace_matches(&pattern, &template) {
+            Ok(0) => format!("Pattern not found: {}", pattern),
+            Ok(n) => format!("Replaced {} matches of {}", n, pattern),
+            Err(err) => format!("Invalid pattern: {}", err),
+        };
+    }
+
+    /// replaces without prompting, given `/pattern/replacement/` where any character can
+    /// stand for `/`
+    fn replace(&mut self, arguments: String) {
+        let mut chars = arguments.chars();
+        let separator = chars.next().unwrap_or('/');
+        let parts: Vec<&str> = chars.as_str().split(separator).collect();
+        match parts.as_slice() {
+            [pattern, template] | [pattern, template, ""] if !pattern.is_empty() => {
+                self.replacing = Some(pattern.to_string());
+                self.replace_with(template.to_string());
+            }
+            _ => self.message = String::from("Expected /pattern/replacement/"),
+        }
+    }
+
+    fn clear_search(&mut self) {
+        self.buffers.focused_mut().clear_search();
+        self.message = String::from("Search cleared");
+    }
+
+    /// follows a `name:line:column: ...` entry on the current line to its buffer
+    fn jump_to_occurrence(&mut self) {
+        let line = self
+            .buffers
+            .focused()
+            .get_current_line()
+            .unwrap_or_default();
+        let location = line.split(": ").next().unwrap_or_default().to_string();
+        let mut parts = location.rsplitn(3, ':');
+        let target = match (parts.next(), parts.next(), parts.next()) {
+            (Some(column), Some(line_number), Some(name)) => column
+                .parse::<usize>()
+                .ok()
+                .zip(line_number.parse::<usize>().ok())
+                .map(|(column, line_number)| (name, line_number, column)),
+            _ => None,
+        };
+        let (name, line_number, column) = match target {
+            Some(target) => target,
+            None => {
+                self.message = String::from("No location on this line");
+                return;
+            }
+        };
+        let index = self
+            .buffers
+            .position_by_path(Path::new(name))
+            .or_else(|| self.buffers.position_by_name(name));
+        match index {
+            Some(index) => {
+                self.buffers.focus(index);
+                self.buffers
+                    .focused_mut()
+                    .goto(line_number.saturating_sub(1), column.saturating_sub(1));
+            }
+            None if Path::new(name).is_file() => {
+                let name = name.to_string();
+                self.file_open_at(name, Some(line_number), Some(column), false)
+            }
+            None => self.message = format!("No buffer {}", name),
+        }
+    }
+
+    fn file_rename(&mut self, path: String) {
+        let buffer = self.buffers.focused_mut();
+        self.message = match buffer.rename_backend_file(path.trim()) {
+            Ok(_) => {
+                self.buffers.disambiguate();
+                format!("Renamed to {}", path.trim())
+            }
+            Err(err) => format!("file_rename({}): {}", path.trim(), err),
+        };
+    }
+
+    /// names the focused buffer, unless another one already has that name
+    fn buffer_rename(&mut self, name: String) {
+        let name = name.trim().to_string();
+        self.message = match self.buffers.position_by_name(&name) {
+            _ if name.is_empty() => String::from("Rename cancelled"),
+            Some(0) => format!("Already named <{}>", name),
+            Some(_) => format!("A buffer is already named <{}>", name),
+            None => {
+                self.buffers.focused_mut().rename(name.clone());
+                format!("Renamed buffer to <{}>", name)
+            }
+        };
+    }
+
+    fn file_delete(&mut self, answer: String) {
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            self.message = String::from("Delete cancelled");
+            return;
+        }
+        let buffer = self.buffers.focused_mut();
+        self.message = match buffer.delete_backend_file() {
+            Ok(_) => format!("Deleted, <{}> is now a scratch buffer", buffer.name),
+            Err(err) => format!("file_delete: {}", err),
+        };
+        self.buffers.focused_mut().set_git_changes(None);
+    }
+
+    fn next_buffer(&mut self) {
+        if self.buffers.len() > 1 {
+            self.buffers.cycle_next();
+            self.message = format!("Switched to <{}>", self.buffers.focused().name);
+        }
+    }
+
+    fn snapshot_buffer(&mut self) {
+        let buffer = self.buffers.focused_mut();
+        let index = buffer.snapshot();
+        let label = buffer.get_snapshots()[index].label();
+        self.message = format!("Snapshot #{} taken at {}", index, label);
+    }
+
+    fn list_snapshots(&mut self) {
+        let buffer = self.buffers.focused();
+        let mut content = format!("# Snapshots of <{}>\n\n", buffer.name);
+        for (i, snapshot) in buffer.get_snapshots().iter().enumerate() {
+            content.push_str(&format!(
+                "- #{} at {} ({} lines)\n",
+                i,
+                snapshot.label(),
+                snapshot.content.len_lines()
+            ));
+        }
+        self.new_buffer(content);
+        self.buffers.focused_mut().set_language("Markdown");
+    }
+
+    fn diff_snapshot(&mut self, index: String) {
+        let diff = index
+            .trim()
+            .parse()
+            .ok()
+            .and_then(|i| self.buffers.focused().diff_snapshot(i));
+        if let Some(diff) = diff {
+            self.new_buffer(diff);
+            self.buffers.focused_mut().set_language("Diff");
+        } else {
+            self.message = format!("No snapshot {}", index);
+        }
+    }
+
+    fn expand_selection(&mut self) {
+        if !self.buffers.focused_mut().expand_selection() {
+            self.message = String::from("Everything is selected");
+        }
+    }
+
+    fn shrink_selection(&mut self) {
+        if !self.buffers.focused_mut().shrink_selection() {
+            self.message = String::from("No expanded selection to shrink");
+        }
+    }
+
+    fn undo(&mut self, n: usize) {
+        self.message = match self.buffers.focused_mut().undo(n) {
+            0 => String::from("Already at the oldest change"),
+            1 => String::from("Undone"),
+            undone => format!("Undone {} changes", undone),
+        };
+    }
+
+    fn redo(&mut self, n: usize) {
+        self.message = match self.buffers.focused_mut().redo(n) {
+            0 => String::from("Already at the newest change"),
+            1 => String::from("Redone"),
+            redone => format!("Redone {} changes", redone),
+        };
+    }
+
+    /// goes back a number of changes or a time like `30s`, `2m` or `1h`, across branches
+    fn earlier(&mut self, step: String) {
+        match undo::Step::parse(&step) {
+            Some(step) => {
+                let reached = self.buffers.focused_mut().earlier(step);
+                self.report_undo_step(reached, "oldest");
+            }
+            None => self.message = format!("Expected a count or a time like 30s, not {}", step),
+        }
+    }
+
+    /// goes forward a number of changes or a time, across branches
+    fn later(&mut self, step: String) {
+        match undo::Step::parse(&step) {
+            Some(step) => {
+                let reached = self.buffers.focused_mut().later(step);
+                self.report_undo_step(reached, "newest");
+            }
+            None => self.message = format!("Expected a count or a time like 30s, not {}", step),
+        }
+    }
+
+    fn report_undo_step(&mut self, reached: Option<usize>, end: &str) {
+        self.message = match reached {
+            Some(i) => format!("Went to change #{}", i),
+            None => format!("Already at the {} change", end),
+        };
+    }
+
+    /// picks a state of the undo tree to go to, previewing the change
+    fn undo_tree(&mut self) {
+        let lines = self.buffers.focused_mut().undo_tree();
+        let current = lines.iter().position(|line| line.starts_with('@'));
+        let picker = Picker::new(
+            "Undo tree".to_string(),
+            lines,
+            |t, line| match undo::parse_index(&line) {
+                Some(i) if t.buffers.focused_mut().undo_jump(i) => {
+                    t.message = format!("Went to change #{}", i)
+                }
+                _ => t.message = format!("No change {}", line.trim()),
+            },
+        )
+        .with_preview(|t, line| {
+            let i = undo::parse_index(line)?;
+            t.buffers.focused().diff_undo_state(i)
+        })
+        .ordered()
+        .with_selected(current.unwrap_or(0));
+        self.open_picker(picker);
+    }
+
+    fn restore_snapshot(&mut self, index: String) {
+        let restored = index
+            .trim()
+            .parse()
+            .map(|i| self.buffers.focused_mut().restore_snapshot(i))
+            .unwrap_or(false);
+        self.message = if restored {
+            format!("Restored snapshot #{}", index.trim())
+        } else {
+            format!("No snapshot {}", index)
+        };
+    }
+
+    /// goes to a 1-based `line` or `line:column`
+    fn goto_line(&mut self, position: String) {
+        let mut parts = position.trim().splitn(2, ':').map(str::parse::<usize>);
+        match (parts.next(), parts.next()) {
+            (Some(Ok(line)), None) => self.buffers.focused_mut().goto(line.saturating_sub(1), 0),
+            (Some(Ok(line)), Some(Ok(column))) => self
+                .buffers
+                .focused_mut()
+                .goto(line.saturating_sub(1), column.saturating_sub(1)),
+            _ => self.message = format!("Invalid position {}, expected line[:column]", position),
+        }
+    }
+
+    /// moves to the given 1-based column of the current line
+    fn goto_column(&mut self, column: String) {
+        match column.trim().parse::<usize>() {
+            Ok(column) => self
+                .buffers
+                .focused_mut()
+                .goto_column(column.saturating_sub(1)),
+            Err(_) => self.message = format!("Invalid column {}", column),
+        }
+    }
+
+    /// goes to a byte offset like `1f0` or `0x1f0` in a hex view
+    fn goto_offset(&mut self, offset: String) {
+        let buffer = self.buffers.focused_mut();
+        let digits = offset.trim().trim_start_matches("0x");
+        match usize::from_str_radix(digits, 16) {
+            Ok(offset) if buffer.is_hex() => buffer.goto_offset(offset),
+            Ok(_) => self.message = String::from("Not a binary file"),
+            Err(_) => self.message = format!("Invalid offset {}", offset),
+        }
+    }
+
+    fn insert_mode(&mut self) {
+        let buffer = self.buffers.focused_mut();
+        buffer.insert_mode();
+        if buffer.mode == InputMode::Insert {
+            self.set_cursor_shape(CursorShape::Line);
+        } else {
+            self.message = format!("<{}> is read-only", buffer.name);
+        }
+    }
+
+    fn normal_mode(&mut self) {
+        self.buffers.focused_mut().normal_mode();
+        self.set_cursor_shape(CursorShape::Block);
+    }
+
+    fn prompt_mode(&mut self, prompt: String, f: fn(&mut Ted, String)) {
+        self.prompt = prompt;
+        self.prompt_callback = Some(f);
+        self.prompt_candidates = None;
+        self.prompt_control = None;
+        self.prompt_preview = None;
+        self.prompt_history.clear();
+        self.history_index = None;
+        self.set_cursor_shape(CursorShape::Line);
+    }
+
+    fn space_mode(&mut self) {
+        self.space_chain = " ".to_string();
+        self.chain_time = Some(Instant::now());
+        self.message = "SPC-".to_string();
+    }
+
+    fn format_space_chain(&self, completed: bool) -> String {
+        let mut s = format_space_chain(&self.space_chain);
+        s.push_str(if completed { "" } else { "-" });
+        s
+    }
+
+    fn print_space_chain(&mut self, completed: bool) {
+        self.message = self.format_space_chain(completed);
+    }
+
+    /// handles the events read before the next draw, returns whether to exit
+    pub fn handle_events(&mut self, events: Vec<Event>) -> bool {
+        events.into_iter().any(|event| match event {
+            Event::Key(key) => self.handle_key(key),
+            Event::Paste(text) => {
+                self.paste_text(&text);
+                false
+            }
+            Event::FocusGained => {
+                self.focus_gained();
+                false
+            }
+            Event::FocusLost => {
+                self.focus_lost();
+                false
+            }
+            _ => false,
+        })
+    }
+
+    /// reloads the files changed on disk meanwhile, unless their buffer has unsaved changes
+    fn focus_gained(&mut self) {
+        self.terminal_focused = true;
+        self.damaged = true;
+        let mut reloaded = 0;
+        let mut conflicts = vec![];
+        for i in 0..self.buffers.len() {
+            let buffer = match self.buffers.get_mut(i) {
+                Some(buffer) if buffer.is_modified_on_disk() => buffer,
+                _ => continue,
+            };
+            if buffer.is_dirty() {
+                conflicts.push(buffer.name.clone());
+                continue;
+            }
+            match buffer.reload_backend_file() {
+                Ok(()) => {
+                    reloaded += 1;
+                    self.after_reload(i);
+                }
+                Err(err) => {
+                    let message = format!("reload {}: {}", buffer.name, err);
+                    self.report(io::Error::new(err.kind(), message).into());
+                }
+            }
+        }
+        if reloaded > 0 {
+            self.refresh_git_gutter();
+        }
+        if !conflicts.is_empty() {
+            self.message = format!(
+                "Changed on disk with unsaved changes: <{}>, saving asks what to do",
+                conflicts.join(">, <")
+            );
+        } else if reloaded > 0 {
+            self.message = format!("Reloaded {} file(s) changed on disk", reloaded);
+        }
+    }
+
+    /// saves every buffer with unsaved changes when auto_save has `focus_lost`
+    fn focus_lost(&mut self) {
+        self.terminal_focused = false;
+        if !self.config.auto_save.get().focus_lost {
+            return;
+        }
+        let ids: Vec<usize> = (0..self.buffers.len())
+            .filter_map(|i| self.buffers.get(i).map(Buffer::id))
+            .collect();
+        for id in ids {
+            if let Some(i) = self.buffers.position_by_id(id) {
+                self.auto_save(i);
+            }
+        }
+    }
+
+    /// inserts text pasted in the terminal at the cursor as a single edit, without
+    /// auto-indent, auto-pairs or completion. the prompt and the picker take its first line
+    /// as if typed
+    pub fn paste_text(&mut self, text: &str) {
+        self.damaged = true;
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        if self.prompt.is_empty() && self.picker.is_none() {
+            self.completion = None;
+            self.insert_text(&text);
+            if self.buffers.focused().mode == InputMode::Normal {
+                self.buffers.focused_mut().checkpoint();
+            }
+            self.check_tutor();
+        } else {
+            for c in text.lines().next().unwrap_or_default().chars() {
+                self.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+            }
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        self.damaged = true;
+        let id = self.buffers.focused().id();
+        let mode = self.buffers.focused().mode;
+        let exit = self.dispatch_key(key);
+        // an insertion is undone at once, other changes key by key
+        if self.buffers.focused().mode == InputMode::Normal {
+            self.buffers.focused_mut().checkpoint();
+        }
+        self.check_tutor();
+        let auto_save = self.config.auto_save.get();
+        if self.buffers.focused().id() != id {
+            if auto_save.buffer_switch {
+                if let Some(i) = self.buffers.position_by_id(id) {
+                    self.auto_save(i);
+                }
+            }
+            self.run_hooks(hook::Event::BufferSwitch);
+        } else if self.buffers.focused().mode != mode {
+            if auto_save.insert_leave && mode == InputMode::Insert {
+                // the focused buffer comes first
+                self.auto_save(0);
+            }
+            self.run_hooks(hook::Event::ModeChange);
+        }
+        exit
+    }
+
+    /// saves a buffer with unsaved changes as file_save does, unless that takes a question like
+    /// confirming lint warnings, creating its directory or overwriting changes made on disk
+    fn auto_save(&mut self, index: usize) {
+        let buffer = match self.buffers.get(index) {
+            Some(buffer) if !self.headless => buffer,
+            _ => return,
+        };
+        let (warnings, block) = buffer.lint();
+        if !buffer.is_dirty()
+            || buffer.readonly
+            || buffer.get_path().is_none()
+            || buffer.missing_dir().is_some()
+            || buffer.is_modified_on_disk()
+            || (block && !warnings.is_empty())
+        {
+            return;
+        }
+        // hooks, copies and notifications go by the focused buffer
+        let focused = self.buffers.focused().id();
+        self.buffers.focus(index);
+        self.run_hooks(hook::Event::SavePre);
+        let saved = match self.buffers.focused_mut().overwrite_backend_file() {
+            Ok(()) => {
+                let buffer = self.buffers.focused();
+                log::info!("auto-saved {}", buffer.get_path().unwrap_or_default());
+                self.message = match lint_summary(&warnings) {
+                    Some(summary) => format!("Saved <{}> ({})", buffer.name, summary),
+                    None => format!("Saved <{}>", buffer.name),
+                };
+                self.write_copies();
+                self.save_undo_history(0);
+                true
+            }
+            Err(err) => {
+                let message = format!("auto_save({}): {}", self.buffers.focused().name, err);
+                self.report(io::Error::new(err.kind(), message).into());
+                false
+            }
+        };
+        self.after_save(saved);
+        if let Some(i) = self.buffers.position_by_id(focused) {
+            self.buffers.focus(i);
+        }
+    }
+
+    /// reads the keys of a digraph or code point, inserting its character once complete
+    fn special_handle_key(&mut self, code: KeyCode) {
+        let inserted = match (self.special.take(), code) {
+            (_, KeyCode::Esc) => {
+                self.message.clear();
+                return;
+            }
+            (Some(Special::Digraph(None)), KeyCode::Char(first)) => {
+                self.special = Some(Special::Digraph(Some(first)));
+                self.message = format!("Digraph: {}", first);
+                return;
+            }
+            (Some(Special::Digraph(Some(first))), KeyCode::Char(second)) => {
+                digraph::lookup(first, second).ok_or_else(|| format!("{}{}", first, second))
+            }
+            (Some(Special::Unicode(typed)), KeyCode::Enter) => {
+                digraph::codepoint(&typed).ok_or(typed)
+            }
+            (Some(Special::Unicode(mut typed)), code) => {
+                match code {
+                    KeyCode::Char(c) => typed.push(c),
+                    KeyCode::Backspace => {
+                        typed.pop();
+                    }
+                    _ => {}
+                }
+                self.message = format!("Unicode: \\u{{{}", typed);
+                self.special = Some(Special::Unicode(typed));
+                return;
+            }
+            (special, _) => {
+                self.special = special;
+                return;
+            }
+        };
+        self.message = match inserted {
+            Ok(c) => {
+                self.buffers.focused_mut().insert_char(c);
+                format!("Inserted {} (U+{:04X})", c, c as u32)
+            }
+            Err(typed) => format!("No character for {}", typed),
+        };
+    }
+
+    /// arrows, Home, End, page keys and Delete, as in conventional editors, in either mode
+    fn handle_editing_key(&mut self, code: KeyCode) {
+        let buffer = self.buffers.focused_mut();
+        match code {
+            KeyCode::Left => buffer.move_cursor_left(1),
+            KeyCode::Right => buffer.move_cursor_right(1),
+            KeyCode::Up => buffer.move_cursor_up(1),
+            KeyCode::Down => buffer.move_cursor_down(1),
+            KeyCode::Home => buffer.move_cursor_bol(),
+            KeyCode::End => buffer.move_cursor_eol(),
+            KeyCode::PageUp => buffer.scroll_half_pages(-2),
+            KeyCode::PageDown => buffer.scroll_half_pages(2),
+            KeyCode::Delete => buffer.delete_char(),
+            _ => {}
+        }
+    }
+
+    fn help_lang(&mut self) {
+        let obj: Vec<Value> = self
+            .config
+            .syntax_set
+            .syntaxes()
+            .iter()
+            .map(|syntax| {
+                json!({
+                    "name": syntax.name,
+                    "ext": syntax.file_extensions,
+                    "first_line": syntax.first_line_match,
+                })
+            })
+            .collect();
+        if let Ok(json) = serde_json::to_string_pretty(&obj) {
+            self.new_buffer(json);
+            self.buffers
+                .focused_mut()
+                .set_language(&String::from("JSON"));
+        }
+    }
+
+    /// opens a copy of the tutorial, whose lessons are checked as it is edited
+    fn tutor(&mut self) {
+        let path = std::env::temp_dir().join(format!("ted-tutor-{}.md", std::process::id()));
+        if let Err(err) = std::fs::write(&path, tutor::TEXT) {
+            self.report(err.into());
+            return;
+        }
+        let path = path.display().to_string();
+        self.file_open_at(path.clone(), Some(1), None, false);
+        self.buffers.focused_mut().rename(String::from("tutor"));
+        self.tutor = Some(Tutor::new(path));
+        self.message = format!("Lesson 1: {}", tutor::LESSONS[0].title);
+    }
+
+    /// tells which lessons of the tutor were just done, and the next one
+    fn check_tutor(&mut self) {
+        let tutor = match self.tutor.as_mut() {
+            Some(tutor) if self.buffers.focused().get_path() == Some(tutor.path.as_str()) => tutor,
+            _ => return,
+        };
+        let done = tutor.check(self.buffers.focused());
+        let last = match done.last() {
+            Some(lesson) => lesson.title,
+            None => return,
+        };
+        self.message = match tutor::LESSONS.get(tutor.lesson) {
+            Some(next) => format!(
+                "Well done, {}! Lesson {}: {}",
+                last,
+                tutor.lesson + 1,
+                next.title
+            ),
+            None => String::from("Tutorial complete, well done!"),
+        };
+        if tutor.is_done() {
+            self.tutor = None;
+        }
+    }
+
+    fn detect_language(&mut self) {
+        self.message = match self.buffers.focused_mut().detect_language() {
+            Some(name) => format!("Highlighting as {}", name),
+            None => String::from("Could not tell the language"),
+        };
+    }
+
+    fn set_lang(&mut self, name: String) {
+        if !self.buffers.focused_mut().set_language(&name) {
+            self.message = format!("Could not load lang {}", name);
+        }
+    }
+
+    /// `<name>` changes the theme of every buffer and persists it as the default,
+    /// `<name> buffer` only changes the focused buffer
+    fn theme(&mut self, answer: String) {
+        let mut words = answer.split_whitespace();
+        let name = words.next().unwrap_or_default().to_string();
+        if !self.config.theme_set.themes.contains_key(&name) {
+            self.message = format!("Could not load theme {}", name);
+        } else if words.next() == Some("buffer") {
+            self.set_theme(name);
+        } else {
+            *self.config.default_theme.borrow_mut() = name.clone();
+            self.buffers.set_theme(&name);
+            self.message = match self.persist("theme", json!(name)) {
+                Ok(_) => format!("Default theme set to {}", name),
+                Err(err) => format!("Theme set to {} but not saved: {}", name, err),
+            };
+        }
+    }
+
+    /// sets an option of the focused buffer from a "name value" answer, shows them without one
+    fn set_local(&mut self, answer: String) {
+        let buffer = self.buffers.focused_mut();
+        let (name, value) = parse_command_line(&answer);
+        if name.is_empty() {
+            self.message = match buffer.options.summary() {
+                summary if summary.is_empty() => String::from("No local options"),
+                summary => format!("Local options: {}", summary),
+            };
+            return;
+        }
+        self.message = match buffer.options.set(name, value) {
+            Ok(()) => format!("{} set to {} in {}", name, value, buffer.name),
+            Err(err) => err.to_string(),
+        };
+    }
+
+    fn local_option_names(&self) -> Vec<String> {
+        options::NAMES.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn toggle_whitespace(&mut self, name: String) {
+        let mut whitespace = self.config.whitespace.get();
+        let name = name.trim();
+        self.message = if whitespace.toggle(name) {
+            self.config.whitespace.set(whitespace);
+            format!(
+                "Whitespace: newlines {}, spaces {}, tabs {}, trailing {}",
+                whitespace.newlines, whitespace.spaces, whitespace.tabs, whitespace.trailing
+            )
+        } else {
+            format!("Unknown whitespace setting {}", name)
+        };
+    }
+
+    fn insert_file_prompt(&mut self) {
+        let directory = self.buffers.focused().working_dir();
+        self.prompt_mode("Insert file".to_string(), Ted::insert_file);
+        self.answer.set(directory);
+        self.prompt_history = self.directories.clone();
+    }
+
+    /// inserts the content of a file at the cursor, n times with a count
+    fn insert_file(&mut self, path: String) {
+        match std::fs::read_to_string(path.trim()) {
+            Ok(text) => self.insert_counted(&text, path.trim()),
+            Err(err) => self.message = format!("insert_file({}): {}", path.trim(), err),
+        }
+    }
+
+    /// inserts the output of a shell command line at the cursor, n times with a count
+    fn insert_command_output(&mut self, line: String) {
+        let dir = self.buffers.focused().working_dir();
+        match format::shell(&line, "", &dir) {
+            Ok(text) => self.insert_counted(&text, &line),
+            Err(err) => self.message = format!("{}: {}", line.trim(), err),
+        }
+    }
+
+    fn insert_counted(&mut self, text: &str, source: &str) {
+        let n = self.universal_argument.take().unwrap_or(1);
+        let buffer = self.buffers.focused_mut();
+        if buffer.readonly {
+            self.message = format!("<{}> is read-only", buffer.name);
+            return;
+        }
+        let range = buffer.paste_chars(n, text);
+        let lines = buffer
+            .get_range(range)
+            .map(|text| text.lines().count())
+            .unwrap_or_default();
+        self.message = format!("Inserted {} lines from {}", lines, source);
+    }
+
+    /// runs the buffer (or selection) through the formatter configured for its language
+    fn format_buffer(&mut self) {
+        let language = self
+            .buffers
+            .focused()
+            .get_highlighter()
+            .as_ref()
+            .map(|cached| cached.syntax.name.clone());
+        let command = language
+            .as_ref()
+            .and_then(|language| self.config.formatters.borrow().get(language).cloned());
+        let command = match command {
+            Some(command) => command,
+            None => {
+                self.message = format!(
+                    "No formatter for {}",
+                    language.unwrap_or_else(|| "Plain Text".to_string())
+                );
+                return;
+            }
+        };
+        match self.buffers.focused_mut().format_with(&command) {
+            Ok(()) => self.message = format!("Formatted with {}", command),
+            Err(err) if err.to_string().lines().count() > 1 => {
+                self.new_buffer(err.to_string());
+                self.message = format!("{} failed", command);
+            }
+            Err(err) => self.message = format!("{}: {}", command, err),
+        }
+    }
+
+    /// defines an insert mode abbreviation from a "from to" answer and saves it
+    fn abbreviate(&mut self, answer: String) {
+        let (from, to) = match answer.trim().split_once(char::is_whitespace) {
+            Some((from, to)) if !to.trim().is_empty() => (from.to_string(), to.trim().to_string()),
+            _ => {
+                self.message = String::from("Expected an abbreviation and its expansion");
+                return;
+            }
+        };
+        let mut abbreviations = self.config.abbreviations.borrow_mut();
+        abbreviations.insert(from.clone(), to.clone());
+        let saved = json!(*abbreviations);
+        drop(abbreviations);
+        self.message = match self.persist("abbreviations", saved) {
+            Ok(_) => format!("{} now expands to {}", from, to),
+            Err(err) => format!("{} now expands to {} (not saved: {})", from, to, err),
+        };
+    }
+
+    fn count(&mut self) {
+        let buffer = self.buffers.focused();
+        let counts = buffer.count();
+        self.message = match buffer.get_selection_range() {
+            Some(_) => format!("Selection: {}", counts),
+            None => format!("Buffer: {}", counts),
+        };
+    }
+
+    fn toggle_crosshair(&mut self) {
+        let crosshair = !self.config.crosshair.get();
+        self.config.crosshair.set(crosshair);
+        self.message = format!("Crosshair: {}", crosshair);
+    }
+
+    fn toggle_cursor_line(&mut self) {
+        let cursor_line = !self.config.cursor_line.get();
+        self.config.cursor_line.set(cursor_line);
+        self.message = format!("Cursor line: {}", cursor_line);
+    }
+
+    fn toggle_cursor_column(&mut self) {
+        let cursor_column = !self.config.cursor_column.get();
+        self.config.cursor_column.set(cursor_column);
+        self.message = format!("Cursor column: {}", cursor_column);
+    }
+
+    fn toggle_soft_wrap(&mut self) {
+        let soft_wrap = !self.config.soft_wrap.get();
+        self.config.soft_wrap.set(soft_wrap);
+        // scroll the view back for the cursor to be in it with the new layout
+        let buffer = self.buffers.focused_mut();
+        buffer.move_cursor(buffer.get_cursor().0);
+        self.message = format!("Soft wrap: {}", soft_wrap);
+    }
+
+    fn toggle_line_numbers(&mut self) {
+        let line_numbers = !self.config.line_numbers.get();
+        self.config.line_numbers.set(line_numbers);
+        self.message = format!("Line numbers: {}", line_numbers);
+    }
+
+    fn toggle_relative_numbers(&mut self) {
+        let relative_numbers = !self.config.relative_numbers.get();
+        self.config.relative_numbers.set(relative_numbers);
+        self.message = format!("Relative numbers: {}", relative_numbers);
+    }
+
+    fn toggle_paste_mode(&mut self) {
+        let paste_mode = !self.config.paste_mode.get();
+        self.config.paste_mode.set(paste_mode);
+        self.message = format!("Paste mode: {}", paste_mode);
+    }
+
+    fn toggle_high_contrast(&mut self) {
+        let high_contrast = !self.config.high_contrast.get();
+        self.config.high_contrast.set(high_contrast);
+        self.message = match self.persist("high_contrast", json!(high_contrast)) {
+            Ok(_) => format!("High contrast: {}", high_contrast),
+            Err(err) => format!("High contrast: {} (not saved: {})", high_contrast, err),
+        };
+    }
+
+    fn help_theme(&mut self) {
+        let obj: Vec<Value> = self
+            .config
+            .theme_set
+            .themes
+            .iter()
+            .map(|(name, theme)| {
+                json!({
+                    "name": name,
+                    "theme": {
+                        "prettyName": theme.name
+                    }
+                })
+            })
+            .collect();
+        if let Ok(json) = serde_json::to_string_pretty(&obj) {
+            self.new_buffer(json);
+            self.buffers
+                .focused_mut()
+                .set_language(&String::from("JSON"));
+        }
+    }
+
+    /// reloads the config once it changed, at most every CONFIG_POLL, returns whether it did
+    fn poll_config(&mut self) -> bool {
+        if self.headless || !self.config.watch_config.get() {
+            return false;
+        }
+        if self.config_modified.1.elapsed() < CONFIG_POLL {
+            return false;
+        }
+        self.config_modified.1 = Instant::now();
+        let modified = modified(self.config.file.as_ref());
+        if modified.is_none() || modified == self.config_modified.0 {
+            return false;
+        }
+        self.reload_config();
+        true
+    }
+
+    /// writes an option to the config file, which isn't reloaded for it unless it was changed
+    /// meanwhile
+    fn persist(&mut self, key: &str, value: Value) -> io::Result<()> {
+        let seen = modified(self.config.file.as_ref()) == self.config_modified.0;
+        self.config.persist(key, value)?;
+        if seen {
+            self.config_modified.0 = modified(self.config.file.as_ref());
+        }
+        Ok(())
+    }
+
+    /// rebuilds the config from its files, keeping the current one when the config file is invalid
+    fn reload_config(&mut self) {
+        let mut config = Config::builtin();
+        config.file = self.config.file.clone();
+        self.config_modified = (modified(config.file.as_ref()), Instant::now());
+        if let Err(err) = config.load_config_file() {
+            let err = format!("{}, keeping the current config", err);
+            self.report(TedError::Config(err));
+            return;
+        }
+        let mut errors = std::mem::take(&mut config.load_errors);
+        errors.extend(config.load_user_files());
+        let summary = format!(
+            "Reloaded config ({} themes, {} syntaxes)",
+            config.theme_set.themes.len(),
+            config.syntax_set.syntaxes().len()
+        );
+        self.config = Rc::new(config);
+        self.buffers.set_config(self.config.clone());
+        errors.extend(self.load_commands().into_iter().map(TedError::Config));
+        if errors.is_empty() {
+            self.message = summary;
+        } else {
+            self.report_all(errors);
+        }
+    }
+
+    /// registers the builtin commands, those of the plugins in ~/.config/ted/plugins and
+    /// the user's bindings, then runs the plugins' top level lines and (re)starts the
+    /// external plugins, which register their commands later on. returns the errors
+    ///
+    /// Lua plugins run before the bindings so the user's chains win over theirs
+    fn load_commands(&mut self) -> Vec<String> {
+        self.commands = commands::builtin();
+        self.scripts.clear();
+        self.plugin_commands.clear();
+        let mut errors = vec![];
+        let mut lines = vec![];
+        let dir = config_dir("plugins");
+        let plugins = dir
+            .as_ref()
+            .map(|dir| script::load_dir(dir))
+            .unwrap_or_default();
+        for (path, plugin) in plugins {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            match plugin {
+                Ok(plugin) => {
+                    for definition in plugin.definitions {
+                        if let Err(err) = self.define(definition) {
+                            errors.push(format!("{}: {}", name, err));
+                        }
+                    }
+                    lines.extend(plugin.lines);
+                }
+                Err(err) => errors.push(format!("{}: {}", name, err)),
+            }
+        }
+        errors.extend(self.load_lua_plugins(dir.as_deref()));
+        errors.extend(commands::bind_from_config(&mut self.commands, &self.config));
+        errors.extend(self.modes.bind_from_config(&self.config));
+        self.run_script(&lines);
+        self.refresh_help();
+        self.plugins.clear();
+        let external = self.config.plugins.borrow().clone();
+        for (name, command) in external {
+            match plugin::Supervisor::start(&name, &command) {
+                Ok(plugin) => self.plugins.push(plugin),
+                Err(err) => errors.push(format!("{}: {}", name, err)),
+            }
+        }
+        errors
+    }
+
+    /// registers a command without action, run_command handles it
+    fn register(&mut self, name: &str, desc: String, chain: Option<String>) -> io::Result<()> {
+        if self.commands.get_by_name(name).is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} is already defined", name),
+            ));
+        }
+        // checked first to leave no command behind when its chain clashes
+        if let Some(chain) = &chain {
+            self.commands.check_chain(name, chain)?;
+        }
+        self.commands.commands.push(Command {
+            name: name.to_string(),
+            desc,
+            help: None,
+            chain,
+            action: |_| {},
+            arguments: None,
+        });
+        Ok(())
+    }
+
+    /// registers a scripted command, run from its body by run_command
+    fn define(&mut self, definition: Definition) -> io::Result<()> {
+        self.scripts
+            .insert(definition.name.clone(), definition.body);
+        self.register(&definition.name, definition.desc, definition.chain)
+    }
+
+    /// runs the hooks of event for the focused buffer
+    fn run_hooks(&mut self, event: hook::Event) {
+        let buffer = self.buffers.focused();
+        let language = buffer
+            .get_highlighter()
+            .as_ref()
+            .map(|highlighter| highlighter.syntax.name.clone());
+        let lines = self.config.hooks.borrow().lines(event, language.as_deref());
+        self.run_script(&lines);
+    }
+
+    /// attaches a command line to an event from an "event[:language] line" answer,
+    /// for this session only
+    fn add_hook(&mut self, answer: String) {
+        let (target, line) = parse_command_line(&answer);
+        let (name, language) = match target.split_once(':') {
+            Some((name, language)) => (name, Some(language.to_string())),
+            None => (target, None),
+        };
+        let event = match hook::Event::from_name(name) {
+            Some(event) if !line.is_empty() => event,
+            Some(_) => {
+                self.message = String::from("Expected a command line to run");
+                return;
+            }
+            None => {
+                self.message = format!("Unknown hook {}", name);
+                return;
+            }
+        };
+        self.message = format!("{} now runs {}", name, line);
+        let action = hook::Action {
+            language,
+            line: line.to_string(),
+        };
+        self.config.hooks.borrow_mut().add(event, action);
+    }
+
+    fn hook_names(&self) -> Vec<String> {
+        hook::Event::ALL
+            .iter()
+            .map(|event| event.name().to_string())
+            .collect()
+    }
+
+    /// sends a notification to every external plugin
+    fn notify_plugins(&mut self, method: &str, params: Value) {
+        for plugin in self.plugins.iter_mut() {
+            plugin.notify(method, params.clone());
+        }
+    }
+
+    /// applies what external plugins sent, returns wether anything changed
+    fn poll_plugins(&mut self) -> bool {
+        let mut events = vec![];
+        for (i, plugin) in self.plugins.iter_mut().enumerate() {
+            events.extend(plugin.poll().into_iter().map(|e| (i, e)));
+        }
+        let redraw = !events.is_empty();
+        for (i, event) in events {
+            match event {
+                plugin::Event::RegisterCommand { name, desc, chain } => {
+                    // a restarted plugin registers its commands again
+                    if self.plugin_commands.get(&name) == Some(&i) {
+                        continue;
+                    }
+                    match self.register(&name, desc, chain) {
+                        Ok(_) => {
+                            self.plugin_commands.insert(name.clone(), i);
+                            let binding = self.config.bindings.borrow().get(&name).cloned();
+                            if let Some(chain) = binding {
+                                let _ = self.commands.bind(&name, parse_space_chain(&chain));
+                            }
+                            self.refresh_help();
+                        }
+                        Err(err) => {
+                            self.message = format!("Plugin {}: {}", self.plugins[i].name, err)
+                        }
+                    }
+                }
+                plugin::Event::RegisterMode { name, spec } => {
+                    let errors = self.modes.register(&name, &spec);
+                    if !errors.is_empty() {
+                        self.message =
+                            format!("Plugin {}: {}", self.plugins[i].name, errors.join(", "));
+                    }
+                }
+                plugin::Event::ShowMessage(text) => self.message = text,
+                plugin::Event::InsertText(text) => self.insert_text(&text),
+                plugin::Event::RunCommand(line) => self.run_command(line),
+                plugin::Event::GetText(id) => {
+                    let buffer = self.buffers.focused();
+                    let (_, line, column) = buffer.get_cursor();
+                    let result = json!({
+                        "path": buffer.get_path(),
+                        "text": buffer.get_text(),
+                        "line": line,
+                        "column": column,
+                    });
+                    self.plugins[i].respond(id, result);
+                }
+                plugin::Event::Exited => {
+                    log::error!("plugin {} exited too many times", self.plugins[i].name);
+                    self.message = format!("Plugin {} exited", self.plugins[i].name)
+                }
+            }
+        }
+        redraw
+    }
+
+    /// inserts text at the cursor, moving past it
+    fn insert_text(&mut self, text: &str) {
+        let buffer = self.buffers.focused_mut();
+        let (cursor, _, _) = buffer.get_cursor();
+        buffer.paste_chars(1, text);
+        buffer.move_cursor(cursor + text.chars().count());
+    }
+
+    /// runs command lines, along with `insert <text>`, `message <text>` and
+    /// `move <left|right|up|down> [n]` which only scripts use
+    fn run_script(&mut self, lines: &[String]) {
+        if self.script_depth >= MAX_SCRIPT_DEPTH {
+            self.message = String::from("Scripts are nested too deeply");
+            return;
+        }
+        self.script_depth += 1;
+        for line in lines {
+            let (name, arguments) = parse_command_line(line);
+            match name {
+                "insert" => self.insert_text(&script::unescape(arguments)),
+                "message" => self.message = script::unescape(arguments),
+                "move" => self.move_cursor(arguments),
+                _ => self.run_command(line.clone()),
+            }
+        }
+        self.script_depth -= 1;
+    }
+
+    /// moves the cursor from a "direction [n]" answer
+    fn move_cursor(&mut self, answer: &str) {
+        let mut parts = answer.split_whitespace();
+        let direction = parts.next().unwrap_or_default();
+        let n = match parts.next().map(str::parse) {
+            None => 1,
+            Some(Ok(n)) => n,
+            Some(Err(_)) => {
+                self.message = format!("Invalid count in move {}", answer);
+                return;
+            }
+        };
+        let buffer = self.buffers.focused_mut();
+        match direction {
+            "left" => buffer.move_cursor_left(n),
+            "right" => buffer.move_cursor_right(n),
+            "up" => buffer.move_cursor_up(n),
+            "down" => buffer.move_cursor_down(n),
+            _ => self.message = format!("Invalid direction in move {}", answer),
+        }
+    }
+
+    /// writes the help buffers again with the commands and bindings as they are now
+    fn refresh_help(&mut self) {
+        let normal = self.modes.keymap("normal").expect("normal mode is builtin");
+        let help = help::document(&self.commands, normal);
+        self.buffers.refresh_help(&help);
+    }
+
+    /// binds a command to a space chain from a "command chain" answer and saves it
+    fn bind(&mut self, answer: String) {
+        let (name, display) = match answer.trim().split_once(char::is_whitespace) {
+            Some((name, display)) => (name.to_string(), display.trim().to_string()),
+            None => (answer.trim().to_string(), String::default()),
+        };
+        let chain = match parse_space_chain(&display) {
+            Some(chain) => Some(chain),
+            None if display.is_empty() => None,
+            None => {
+                self.message = format!("Invalid chain {:?}, expected e.g. SPC f o", display);
+                return;
+            }
+        };
+        if let Err(err) = self.commands.bind(&name, chain.clone()) {
+            self.message = err.to_string();
+            return;
+        }
+        self.refresh_help();
+        let mut bindings = self.config.bindings.borrow_mut();
+        bindings.insert(name.clone(), display.clone());
+        let saved = json!(*bindings);
+        drop(bindings);
+        let bound = match chain {
+            Some(chain) => format!("{} bound to {}", name, format_space_chain(&chain)),
+            None => format!("{} unbound", name),
+        };
+        self.message = match self.persist("bindings", saved) {
+            Ok(_) => bound,
+            Err(err) => format!("{} (not saved: {})", bound, err),
+        };
+    }
+
+    fn set_theme(&mut self, name: String) {
+        if !self.buffers.focused_mut().set_theme(&name) {
+            self.message = format!("Could not load theme {}", name);
+        }
+    }
+
+    /// copies up to n characters from the current line (at the current cursor position) into the clipboard
+    fn copy_chars(&mut self, n: usize) {
+        let buffer = self.buffers.focused_mut();
+        if let Some(selection) = buffer.get_selection() {
+            self.kill_ring.push(selection);
+            buffer.remove_selection();
+        } else if let Some(chars) = buffer
+            .get_current_line()
+            .and_then(|line| line.get(0..n.min(line.len())).map(String::from))
+        {
+            self.kill_ring.push(chars);
+        }
+    }
+
+    /// copies up to n lines from the current line into the clipboard
+    fn copy_lines(&mut self, n: usize) {
+        let buffer = self.buffers.focused_mut();
+        let (_, line_number, _) = buffer.get_cursor();
+        if let Some(selection) = buffer.get_selection() {
+            self.kill_ring.push(selection);
+            buffer.remove_selection();
+        } else if let Some(lines) =
+            buffer.get_lines(line_number..buffer.lines_below(line_number, n))
+        {
+            self.kill_ring.push(lines);
+        }
+    }
+
+    /// pastes the latest kill ring entry n times, under the cursor or the current line
+    fn paste(&mut self, n: usize, lines: bool) {
+        let text = self.kill_ring.latest().to_string();
+        let range = self.buffers.focused_mut().paste_kill(n, &text, lines);
+        self.last_paste = Some((range, 0, n, lines));
+    }
+
+    /// replaces the text just pasted with the next older kill ring entry
+    fn paste_pop(&mut self) {
+        let (range, index, n, lines) = match self.last_paste.take() {
+            Some(paste) => paste,
+            None => {
+                self.message = String::from("Nothing was pasted");
+                return;
+            }
+        };
+        let kill_ring = &self.kill_ring;
+        let entry = |index| kill_ring.get(index).unwrap_or_default();
+        let buffer = self.buffers.focused_mut();
+        let pasted = buffer
+            .reindented(range.clone(), entry(index), lines)
+            .repeat(n);
+        if buffer.get_range(range.clone()).as_ref() != Some(&pasted) {
+            self.message = String::from("The pasted text was changed");
+            return;
+        }
+        let index = kill_ring.next_index(index);
+        let text = buffer
+            .reindented(range.clone(), entry(index), lines)
+            .repeat(n);
+        let range = buffer.replace_range(range, &text);
+        self.last_paste = Some((range, index, n, lines));
+        self.message = format!("Kill ring entry {}", index);
+    }
+
+    /// opens a picker over the kill ring, pasting the chosen entry under the cursor
+    fn pick_kill_ring(&mut self) {
+        if self.kill_ring.entries().is_empty() {
+            self.message = String::from("The kill ring is empty");
+            return;
+        }
+        let labels = self
+            .kill_ring
+            .entries()
+            .iter()
+            .enumerate()
+            .map(|(i, text)| kill_ring::label(i, text))
+            .collect();
+        let picker = Picker::new("Kill ring".to_string(), labels, |t, label| {
+            let index = kill_ring::parse_label(&label).unwrap_or_default();
+            if let Some(text) = t.kill_ring.get(index).map(String::from) {
+                let range = t.buffers.focused_mut().paste_kill(1, &text, false);
+                t.last_paste = Some((range, index, 1, false));
+            }
+        })
+        .with_preview(|t, label| {
+            kill_ring::parse_label(label)
+                .and_then(|index| t.kill_ring.get(index))
+                .map(String::from)
+        });
+        self.open_picker(picker);
+    }
+
+    /// runs a buffer motion or scroll with the universal argument as count, 1 by default
+    fn with_count(&mut self, f: fn(&mut Buffer, usize)) {
+        let n = self.universal_argument.take().unwrap_or(1);
+        f(self.buffers.focused_mut(), n);
+    }
+
+    fn normal_mode_handle_key(&mut self, c: char) {
+        let uarg = self.universal_argument;
+        self.universal_argument = None;
+        let n = uarg.unwrap_or(1);
+        match self.pending_key.take() {
+            Some('z') => self.normal_mode_handle_z(c),
+            // screen rows, the same as lines unless soft_wrap is on
+            Some('g') => match c {
+                'j' => self.buffers.focused_mut().move_cursor_row_down(n),
+                'k' => self.buffers.focused_mut().move_cursor_row_up(n),
+                '0' => self.buffers.focused_mut().move_cursor_view_start(),
+                '$' => self.buffers.focused_mut().move_cursor_view_end(),
+                _ => self.message = format!("g{} is undefined", c),
+            },
+            _ => self.normal_mode_key(c, uarg, n),
+        }
+    }
+
+    fn normal_mode_handle_z(&mut self, c: char) {
+        let buffer = self.buffers.focused_mut();
+        match c {
+            'z' => buffer.scroll_cursor_center(),
+            't' => buffer.scroll_cursor_top(),
+            'b' => buffer.scroll_cursor_bottom(),
+            'a' => buffer.toggle_fold(),
+            'o' => {
+                buffer.open_fold();
+            }
+            'c' => buffer.close_fold(),
+            'R' => buffer.open_all_folds(),
+            'M' => buffer.close_all_folds(),
+            _ => self.message = format!("z{} is undefined", c),
+        }
+    }
+
+    fn normal_mode_key(&mut self, c: char, uarg: Option<usize>, n: usize) {
+        match c {
+            'z' | 'g' => {
+                self.pending_key = Some(c);
+                // the count applies to the key completing it, like 3gj
+                self.universal_argument = uarg;
+            }
+            '/' => self.search_mode(),
+            'n' => self.search_next(true),
+            'N' => self.search_next(false),
+            ' ' => self.space_mode(),
+            'i' => self.insert_mode(),
+            'I' => {
+                self.insert_mode();
+                self.buffers.focused_mut().move_cursor_bol();
+            }
+            'a' => {
+                self.insert_mode();
+                self.buffers.focused_mut().move_cursor_right(1);
+            }
+            'A' => {
+                self.insert_mode();
+                self.buffers.focused_mut().move_cursor_eol();
+            }
+            'o' => {
+                self.insert_mode();
+                self.buffers.focused_mut().append_newline();
+            }
+            'O' => {
+                self.insert_mode();
+                self.buffers.focused_mut().prepend_newline();
+            }
+            'h' => self.buffers.focused_mut().move_cursor_left(n),
+            'H' => self.buffers.focused_mut().move_cursor_bol(),
+            'k' => self.buffers.focused_mut().move_cursor_up(n),
+            'K' => self.buffers.focused_mut().page_up(n),
+            'j' => self.buffers.focused_mut().move_cursor_down(n),
+            'J' => self.buffers.focused_mut().page_down(n),
+            'l' => self.buffers.focused_mut().move_cursor_right(n),
+            'L' => self.buffers.focused_mut().move_cursor_eol(),
+            '(' => self.buffers.focused_mut().move_sentence_backward(n),
+            ')' => self.buffers.focused_mut().move_sentence_forward(n),
+            '{' => self.buffers.focused_mut().move_paragraph_backward(n),
+            '}' => self.buffers.focused_mut().move_paragraph_forward(n),
+            '<' => self.buffers.focused_mut().page_left(n),
+            '>' => self.buffers.focused_mut().page_right(n),
+            'd' => {
+                let text = self.buffers.focused_mut().delete_chars(n);
+                self.kill_ring.push(text);
+            }
+            'D' => {
+                let text = self.buffers.focused_mut().delete_lines(n);
+                self.kill_ring.push(text);
+            }
+            'c' => self.copy_chars(n),
+            'C' => self.copy_lines(n),
+            'p' => self.paste(n, false),
+            'P' => self.paste(n, true),
+            'v' => self.buffers.focused_mut().select_chars(),
+            'V' => self.buffers.focused_mut().select_lines(),
+            'u' => self.undo(n),
+            'r' => self.redo(n),
+            'f' => todo!(), // find
+            c if c.is_ascii_digit() => {
+                let current = uarg.unwrap_or(0);
+                if let Some(u) = c.to_digit(10) {
+                    let x = current * 10 + u as usize;
+                    self.universal_argument = Some(x);
+                    self.message = format!("C-u: {}", x);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,546 @@
+//! Modes taking the keys typed, tried from the top until one handles the key: the focused
+//! overlay, the picker, the space chain and the prompt, then the modes entered with
+//! `enter_mode` (latest first), then the focused buffer's completion, special characters,
+//! INSERT and NORMAL modes. A key bound in the keymap of a mode runs its command, others go to
+//! the mode's handler, which may let them fall through to the modes below.
+
+use super::picker::Picker;
+use super::which_key::WhichKey;
+use super::{Special, Ted, AUTO_COMPLETE};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+use std::time::Instant;
+use ted::buffer::InputMode;
+use ted::error::TedError;
+use ted::keymap::{Chord, Keymap, ModeSpec};
+use ted::Config;
+
+/// handles a key the keymap doesn't bind, false to let the modes below have it
+pub type Handler = fn(&mut Ted, KeyEvent) -> bool;
+
+pub struct Mode {
+    pub name: String,
+    /// whether it takes keys, None for registered modes, which do once entered
+    active: Option<fn(&Ted) -> bool>,
+    pub keymap: Keymap,
+    handle: Handler,
+}
+
+impl Mode {
+    fn builtin(name: &str, active: fn(&Ted) -> bool, handle: Handler) -> Self {
+        Self {
+            name: name.to_string(),
+            active: Some(active),
+            keymap: Keymap::default(),
+            handle,
+        }
+    }
+
+    /// a mode entered with enter_mode and left with Esc, with the keys that failed to bind
+    fn registered(name: &str, spec: &ModeSpec) -> (Self, Vec<String>) {
+        let mut keymap = Keymap::plain();
+        let errors = keymap.bind_all(&spec.keys);
+        let handle: Handler = match spec.fallthrough {
+            true => |t, key| key.code == KeyCode::Esc && t.leave_entered_mode(),
+            false => |t, key| {
+                if key.code == KeyCode::Esc {
+                    t.leave_entered_mode();
+                } else if let Some(chord) = chord(key) {
+                    t.message = format!("{} is undefined", chord);
+                }
+                true
+            },
+        };
+        let mode = Self {
+            name: name.to_string(),
+            active: None,
+            keymap,
+            handle,
+        };
+        (mode, errors)
+    }
+}
+
+/// the chord of a key typed, None for keys other than characters
+fn chord(key: KeyEvent) -> Option<Chord> {
+    match key.code {
+        KeyCode::Char(c) => Some(Chord {
+            control: key.modifiers.contains(KeyModifiers::CONTROL),
+            alt: key.modifiers.contains(KeyModifiers::ALT),
+            key: c,
+        }),
+        _ => None,
+    }
+}
+
+pub struct Modes {
+    /// the focused overlay, picker, space chain and prompt, over the modes entered
+    overlays: Vec<Mode>,
+    /// registered by the config and plugins, by name
+    registered: HashMap<String, Mode>,
+    /// names of the registered modes entered, the latest last
+    entered: Vec<String>,
+    /// the focused buffer's modes, under the modes entered
+    buffer: Vec<Mode>,
+}
+
+impl Modes {
+    // Option::is_some_and needs Rust 1.70
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn builtin() -> Self {
+        let insert = |t: &Ted| t.buffers.focused().mode == InputMode::Insert;
+        let mut modes = Self {
+            overlays: vec![
+                Mode::builtin(
+                    "overlay",
+                    |t| t.overlays.last().map_or(false, |overlay| overlay.focused),
+                    Ted::overlay_handle_key,
+                ),
+                Mode::builtin("picker", |t| t.picker.is_some(), Ted::picker_handle_key),
+                Mode::builtin(
+                    "space_chain",
+                    |t| !t.space_chain.is_empty(),
+                    Ted::chain_handle_key,
+                ),
+                Mode::builtin("prompt", |t| !t.prompt.is_empty(), Ted::prompt_handle_key),
+            ],
+            registered: HashMap::new(),
+            entered: vec![],
+            buffer: vec![
+                Mode::builtin("completion", insert, Ted::completion_handle_key),
+                Mode::builtin(
+                    "special",
+                    |t| t.special.is_some() && t.buffers.focused().mode == InputMode::Insert,
+                    |t, key| {
+                        t.special_handle_key(key.code);
+                        true
+                    },
+                ),
+                Mode::builtin("insert", insert, Ted::insert_handle_key),
+                Mode::builtin(
+                    "normal",
+                    |t| t.buffers.focused().mode == InputMode::Normal,
+                    Ted::normal_handle_key,
+                ),
+            ],
+        };
+        modes.builtin_mut("normal").unwrap().keymap = Keymap::builtin();
+        modes
+    }
+
+    fn builtin_mut(&mut self, name: &str) -> Option<&mut Mode> {
+        self.overlays
+            .iter_mut()
+            .chain(self.buffer.iter_mut())
+            .find(|mode| mode.name == name)
+    }
+
+    /// the keymap of a builtin or registered mode
+    pub fn keymap(&self, name: &str) -> Option<&Keymap> {
+        self.overlays
+            .iter()
+            .chain(self.buffer.iter())
+            .chain(self.registered.values())
+            .find(|mode| mode.name == name)
+            .map(|mode| &mode.keymap)
+    }
+
+    /// rebuilds the keymaps from `keys` and `modes` in the config, registering the modes that
+    /// aren't builtin ones, returns the keys that failed to bind
+    pub fn bind_from_config(&mut self, config: &Config) -> Vec<String> {
+        for mode in self.overlays.iter_mut().chain(self.buffer.iter_mut()) {
+            mode.keymap = Keymap::default();
+        }
+        let normal = self.builtin_mut("normal").unwrap();
+        normal.keymap = Keymap::builtin();
+        let mut errors = normal.keymap.bind_all(&config.keys.borrow());
+        self.registered.clear();
+        for (name, spec) in config.modes.borrow().iter() {
+            errors.extend(self.register(name, spec));
+        }
+        let registered = &self.registered;
+        self.entered.retain(|name| registered.contains_key(name));
+        errors
+    }
+
+    /// registers a mode, or adds its keys to the builtin mode of that name, returns the keys
+    /// that failed to bind
+    pub fn register(&mut self, name: &str, spec: &ModeSpec) -> Vec<String> {
+        let errors = match self.builtin_mut(name) {
+            Some(mode) => mode.keymap.bind_all(&spec.keys),
+            None => {
+                let (mode, errors) = Mode::registered(name, spec);
+                self.registered.insert(name.to_string(), mode);
+                errors
+            }
+        };
+        errors
+            .into_iter()
+            .map(|err| format!("mode {}: {}", name, err))
+            .collect()
+    }
+
+    /// names of the registered modes
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.registered.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// puts a registered mode on top of the ones entered, false if there is none by that name
+    pub fn enter(&mut self, name: &str) -> bool {
+        if !self.registered.contains_key(name) {
+            return false;
+        }
+        self.entered.retain(|entered| entered != name);
+        self.entered.push(name.to_string());
+        true
+    }
+
+    /// leaves the mode entered last
+    pub fn leave(&mut self) -> Option<String> {
+        self.entered.pop()
+    }
+
+    /// the mode entered last
+    pub fn current(&self) -> Option<&str> {
+        self.entered.last().map(String::as_str)
+    }
+
+    /// the modes taking keys, from the top
+    fn stack(&self, ted: &Ted) -> Vec<&Mode> {
+        let is_active = |mode: &&Mode| mode.active.map(|active| active(ted)).unwrap_or(false);
+        let entered = self
+            .entered
+            .iter()
+            .rev()
+            .filter_map(|name| self.registered.get(name));
+        self.overlays
+            .iter()
+            .filter(is_active)
+            .chain(entered)
+            .chain(self.buffer.iter().filter(is_active))
+            .collect()
+    }
+}
+
+impl Ted {
+    /// hands the key down the modes until one handles it
+    pub fn dispatch_key(&mut self, key: KeyEvent) -> bool {
+        self.overlays.retain(|overlay| overlay.focused);
+        let chord = chord(key);
+        let steps: Vec<(Option<String>, Handler)> = self
+            .modes
+            .stack(self)
+            .into_iter()
+            .map(|mode| {
+                let bound = chord.and_then(|chord| mode.keymap.get(&chord));
+                (bound.map(String::from), mode.handle)
+            })
+            .collect();
+        for (bound, handle) in steps {
+            if let Some(name) = bound {
+                self.run_command(name);
+                // a count the command didn't take is dropped, not left for the next key
+                self.universal_argument = None;
+                break;
+            }
+            if handle(self, key) {
+                break;
+            }
+        }
+        self.exit
+    }
+
+    /// picks a registered mode to enter
+    pub fn pick_mode(&mut self) {
+        let picker = Picker::new("Mode".to_string(), self.modes.names(), Ted::enter_mode);
+        self.open_picker(picker);
+    }
+
+    pub fn enter_mode(&mut self, name: String) {
+        match self.modes.enter(name.trim()) {
+            true => self.message.clear(),
+            false => self.report(TedError::Command(format!("No mode named {}", name.trim()))),
+        }
+    }
+
+    pub fn leave_mode(&mut self) {
+        if !self.leave_entered_mode() {
+            self.message = String::from("No mode entered");
+        }
+    }
+
+    /// leaves the mode entered last, true if there was one
+    fn leave_entered_mode(&mut self) -> bool {
+        match self.modes.leave() {
+            Some(name) => {
+                self.message = format!("Left {} mode", name);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn picker_handle_key(&mut self, key: KeyEvent) -> bool {
+        let picker = match self.picker.as_mut() {
+            Some(picker) => picker,
+            None => return false,
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.picker = None;
+                self.normal_mode();
+            }
+            KeyCode::Enter => {
+                if let Some(picker) = self.picker.take() {
+                    self.normal_mode();
+                    if let Some(selected) = picker.selected() {
+                        (picker.on_select)(self, selected.clone());
+                    }
+                }
+            }
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                picker.select_next()
+            }
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                picker.select_prev()
+            }
+            KeyCode::Down | KeyCode::Tab => picker.select_next(),
+            KeyCode::Up | KeyCode::BackTab => picker.select_prev(),
+            KeyCode::Backspace => picker.pop_char(),
+            KeyCode::Char(c) => picker.push_char(c),
+            _ => {}
+        }
+        true
+    }
+
+    fn chain_handle_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc => {
+                self.normal_mode();
+                self.space_chain.clear();
+            }
+            KeyCode::Char('/') => {
+                self.pick_chain_command();
+                return true;
+            }
+            KeyCode::Char(c) => self.space_chain.push(c),
+            KeyCode::Tab => self.space_chain.push('\t'),
+            _ => {}
+        }
+        self.chain_time = Some(Instant::now());
+        if self.which_key.is_some() {
+            self.which_key = Some(WhichKey::new(&self.commands, &self.space_chain));
+        }
+        let commands = self.commands.get_by_chain(&self.space_chain);
+        match commands.len() {
+            0 => {
+                self.normal_mode();
+                self.message = format!("{:?} is undefined", self.format_space_chain(true));
+                self.space_chain.clear();
+            }
+            1 if commands[0].chain_is(&self.space_chain) => {
+                let name = commands[0].name.clone();
+                self.print_space_chain(true);
+                self.run_command(name);
+                self.universal_argument = None;
+                self.normal_mode();
+                self.space_chain.clear();
+            }
+            _ => self.print_space_chain(false),
+        }
+        true
+    }
+
+    fn prompt_handle_key(&mut self, key: KeyEvent) -> bool {
+        self.completion = None;
+        let answer = self.answer.as_str().to_string();
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Tab => self.complete_answer(),
+            KeyCode::Enter => {
+                self.normal_mode();
+                self.prompt.clear();
+                if let Some(f) = self.prompt_callback {
+                    self.prompt_callback = None;
+                    f(self, self.answer.as_str().to_string());
+                }
+                self.answer.clear();
+            }
+            KeyCode::Esc => {
+                self.normal_mode();
+                self.prompt_callback = None;
+                self.prompt.clear();
+                self.answer.clear();
+            }
+            KeyCode::Backspace => self.answer.backspace(),
+            KeyCode::Delete => self.answer.delete(),
+            KeyCode::Left => self.answer.left(),
+            KeyCode::Right => self.answer.right(),
+            KeyCode::Home => self.answer.home(),
+            KeyCode::End => self.answer.end(),
+            KeyCode::Char('w') if control => self.answer.delete_word(),
+            KeyCode::Char('u') if control => self.answer.delete_to_start(),
+            KeyCode::Up if !self.prompt_history.is_empty() => {
+                let i = self
+                    .history_index
+                    .map(|i| i.saturating_sub(1))
+                    .unwrap_or(self.prompt_history.len() - 1);
+                self.history_index = Some(i);
+                self.answer.set(self.prompt_history[i].clone());
+            }
+            KeyCode::Down => {
+                if let Some(i) = self.history_index {
+                    if i + 1 < self.prompt_history.len() {
+                        self.history_index = Some(i + 1);
+                        self.answer.set(self.prompt_history[i + 1].clone());
+                    }
+                }
+            }
+            KeyCode::Char(c) if control => {
+                if let Some(f) = self.prompt_control {
+                    f(self, c);
+                }
+            }
+            KeyCode::Char(c) => self.answer.insert(c),
+            _ => {}
+        };
+        if let Some(preview) = self.prompt_preview {
+            if self.prompt.is_empty() {
+                self.prompt_preview = None;
+                preview(self, None);
+            } else if self.answer.as_str() != answer {
+                preview(self, Some(self.answer.as_str().to_string()));
+            }
+        }
+        true
+    }
+
+    fn insert_handle_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.complete(true)
+            }
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.complete(true);
+                if let Some(completion) = self.completion.as_mut() {
+                    completion.select_prev();
+                }
+            }
+            KeyCode::Backspace => self.buffers.focused_mut().back_delete_char(),
+            KeyCode::Enter => self.buffers.focused_mut().insert_newline(),
+            KeyCode::Tab => {
+                let buffer = self.buffers.focused_mut();
+                if !buffer.next_placeholder() && !buffer.expand_snippet() {
+                    buffer.insert_indent();
+                }
+            }
+            KeyCode::BackTab => self.buffers.focused_mut().prev_placeholder(),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.normal_mode()
+            }
+            KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.special = Some(Special::Digraph(None));
+                self.message = String::from("Digraph: ");
+            }
+            KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.special = Some(Special::Unicode(String::default()));
+                self.message = String::from("Unicode: \\u{");
+            }
+            KeyCode::Esc => self.normal_mode(),
+            KeyCode::Char(c) => {
+                self.buffers.focused_mut().type_char(c);
+                let typed = self.buffers.focused().word_before_cursor();
+                if typed.chars().count() >= AUTO_COMPLETE && !self.config.paste_mode.get() {
+                    self.complete(false);
+                }
+            }
+            code => self.handle_editing_key(code),
+        };
+        true
+    }
+
+    fn normal_handle_key(&mut self, key: KeyEvent) -> bool {
+        // chords bound in the keymap never get here
+        if let Some(chord) = chord(key).filter(|chord| chord.control || chord.alt) {
+            self.message = format!("{} is undefined", chord);
+            return true;
+        }
+        match key.code {
+            KeyCode::Char(c) => self.normal_mode_handle_key(c),
+            KeyCode::Enter => self.jump_to_occurrence(),
+            KeyCode::Esc => {
+                self.universal_argument = None;
+                self.message = "ESC".to_string();
+                self.buffers.focused_mut().remove_selection();
+            }
+            code => self.handle_editing_key(code),
+        };
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::snapshot::drive;
+    use super::*;
+    use crate::batch::parse_keys;
+
+    fn type_keys(ted: &mut Ted, keys: &str) {
+        for key in parse_keys(keys).unwrap() {
+            ted.handle_key(key);
+        }
+    }
+
+    fn column(ted: &Ted) -> usize {
+        ted.buffers.focused().get_cursor().2
+    }
+
+    #[test]
+    fn entered_modes() {
+        let (mut ted, _) = drive("one two three four\n", "");
+        let spec = |fallthrough| ModeSpec {
+            keys: [("n", "forward_word"), ("C-w", "leave_mode")]
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            fallthrough,
+        };
+        assert!(ted.modes.register("words", &spec(true)).is_empty());
+        assert!(!ted.modes.enter("missing"));
+        assert!(ted.modes.enter("words"));
+        type_keys(&mut ted, "n");
+        assert_eq!(column(&ted), 4);
+        // unbound keys fall through to NORMAL mode
+        type_keys(&mut ted, "l<Esc>n");
+        assert_eq!(ted.modes.current(), None);
+        assert_eq!(column(&ted), 5);
+        ted.modes.register("words", &spec(false));
+        ted.modes.enter("words");
+        type_keys(&mut ted, "ln");
+        assert_eq!(column(&ted), 8);
+        type_keys(&mut ted, "<C-w>l");
+        assert_eq!((ted.modes.current(), column(&ted)), (None, 9));
+        // single keys can't be bound in the builtin modes, chords can
+        assert_eq!(ted.modes.register("insert", &spec(true)).len(), 1);
+        let keymap = ted.modes.keymap("insert").unwrap();
+        assert_eq!(keymap.get(&Chord::control('w')), Some("leave_mode"));
+    }
+
+    #[test]
+    fn chords_drop_count() {
+        let (mut ted, _) = drive("one two three four\n", "");
+        let alt = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT);
+        type_keys(&mut ted, "3");
+        ted.handle_key(alt('y'));
+        type_keys(&mut ted, "l");
+        assert_eq!(column(&ted), 1);
+        type_keys(&mut ted, "2");
+        ted.handle_key(alt('f'));
+        type_keys(&mut ted, "l");
+        assert_eq!(column(&ted), 9);
+        type_keys(&mut ted, "3 tnl");
+        assert_eq!(column(&ted), 10);
+    }
+}
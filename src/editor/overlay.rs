@@ -0,0 +1,292 @@
+//! Floating panels drawn with a border over the buffer area, like hover documentation. The one
+//! opened last is on top and takes the keys while it has focus: `j`/`k` and the arrows scroll
+//! it, `C-d`/`C-u` and the page keys by half panels, Esc or `q` closes it. Panels without focus
+//! close at the next key, which goes on as usual.
+//!
+//! What a panel shows is any `Panel`, plain text with `Text`.
+
+use super::Ted;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tui::layout::Rect;
+use tui::style::{Color, Style};
+use tui::widgets::{Block, Borders, Clear, StatefulWidget, Widget};
+
+/// the content of an overlay, drawn within its borders
+pub trait Panel {
+    /// the columns and lines it takes, unclipped
+    fn size(&self) -> (usize, usize);
+    /// draws its lines from first on within area, over the overlay's style
+    fn render(&self, area: Rect, buf: &mut tui::buffer::Buffer, first: usize, style: Style);
+}
+
+/// lines of plain text
+pub struct Text {
+    lines: Vec<String>,
+}
+
+impl Text {
+    pub fn new(text: &str) -> Self {
+        Self {
+            lines: text.lines().map(String::from).collect(),
+        }
+    }
+}
+
+impl Panel for Text {
+    fn size(&self) -> (usize, usize) {
+        let width = self.lines.iter().map(|line| line.chars().count()).max();
+        (width.unwrap_or(0), self.lines.len())
+    }
+
+    fn render(&self, area: Rect, buf: &mut tui::buffer::Buffer, first: usize, style: Style) {
+        let shown = self.lines.iter().skip(first).take(area.height as usize);
+        for (y, line) in shown.enumerate() {
+            buf.set_stringn(area.x, area.y + y as u16, line, area.width as usize, style);
+        }
+    }
+}
+
+/// where a panel goes on screen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Placement {
+    /// under the cursor, or above it without room below
+    Cursor,
+    Center,
+}
+
+pub struct Overlay {
+    pub title: String,
+    panel: Box<dyn Panel>,
+    /// first line shown
+    scroll: usize,
+    /// lines shown when last drawn
+    rows: usize,
+    placement: Placement,
+    /// takes the keys until closed
+    pub focused: bool,
+    /// handles keys before the panel does, false to let it scroll or close as usual
+    pub on_key: Option<fn(&mut Ted, KeyEvent) -> bool>,
+}
+
+impl Overlay {
+    /// a focused panel of text at the center of the screen
+    pub fn new(title: String, text: &str) -> Self {
+        Self::with_panel(title, Box::new(Text::new(text)))
+    }
+
+    /// a focused panel at the center of the screen
+    pub fn with_panel(title: String, panel: Box<dyn Panel>) -> Self {
+        Self {
+            title,
+            panel,
+            scroll: 0,
+            rows: 0,
+            placement: Placement::Center,
+            focused: true,
+            on_key: None,
+        }
+    }
+
+    pub fn at_cursor(mut self) -> Self {
+        self.placement = Placement::Cursor;
+        self
+    }
+
+    /// leaves the keys to the modes below, closing at the next one
+    pub fn unfocused(mut self) -> Self {
+        self.focused = false;
+        self
+    }
+
+    pub fn with_keys(mut self, on_key: fn(&mut Ted, KeyEvent) -> bool) -> Self {
+        self.on_key = Some(on_key);
+        self
+    }
+
+    /// scrolls by delta lines, keeping the last line at the bottom at most
+    pub fn scroll_by(&mut self, delta: isize) {
+        let last = self.panel.size().1.saturating_sub(self.rows.max(1));
+        self.scroll = (self.scroll as isize + delta).clamp(0, last as isize) as usize;
+    }
+
+    /// the panel's area within screen, borders included
+    pub fn area(&self, cursor_x: u16, cursor_y: u16, screen: Rect) -> Rect {
+        let (columns, lines) = self.panel.size();
+        let content = columns.max(self.title.chars().count() + 2);
+        let width = (content as u16 + 2).min(screen.width);
+        let lines = lines.max(1) as u16 + 2;
+        match self.placement {
+            Placement::Center => {
+                let height = lines.min(screen.height.saturating_sub(2));
+                Rect::new(
+                    screen.x + (screen.width - width) / 2,
+                    screen.y + (screen.height - height) / 2,
+                    width,
+                    height,
+                )
+            }
+            Placement::Cursor => {
+                let below = screen.bottom().saturating_sub(cursor_y + 1);
+                let above = cursor_y.saturating_sub(screen.y);
+                let height = lines.min(below.max(above));
+                let x = cursor_x.min(screen.right().saturating_sub(width));
+                let y = match height <= below {
+                    true => cursor_y + 1,
+                    false => cursor_y - height,
+                };
+                Rect::new(x, y, width, height)
+            }
+        }
+    }
+}
+
+pub struct OverlayWidget {}
+
+impl StatefulWidget for OverlayWidget {
+    type State = Overlay;
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer, state: &mut Self::State) {
+        Clear.render(area, buf);
+        let style = Style::default().fg(Color::White).bg(Color::DarkGray);
+        buf.set_style(area, style);
+        state.rows = area.height.saturating_sub(2) as usize;
+        state.scroll_by(0);
+        let lines = state.panel.size().1;
+        let title = match lines > state.rows {
+            true => format!(
+                "{} {}-{}/{}",
+                state.title,
+                state.scroll + 1,
+                (state.scroll + state.rows).min(lines),
+                lines
+            ),
+            false => state.title.clone(),
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(style)
+            .title(title);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        state.panel.render(inner, buf, state.scroll, style);
+    }
+}
+
+impl Ted {
+    /// shows a panel on top of the others
+    pub fn open_overlay(&mut self, overlay: Overlay) {
+        self.overlays.push(overlay);
+    }
+
+    /// scrolls or closes the focused panel
+    pub fn overlay_handle_key(&mut self, key: KeyEvent) -> bool {
+        if let Some(on_key) = self.overlays.last().and_then(|overlay| overlay.on_key) {
+            if on_key(self, key) {
+                return true;
+            }
+        }
+        let overlay = match self.overlays.last_mut() {
+            Some(overlay) => overlay,
+            None => return false,
+        };
+        let half = (overlay.rows / 2).max(1) as isize;
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.overlays.pop();
+            }
+            KeyCode::Char('d') if control => overlay.scroll_by(half),
+            KeyCode::Char('u') if control => overlay.scroll_by(-half),
+            KeyCode::PageDown => overlay.scroll_by(half),
+            KeyCode::PageUp => overlay.scroll_by(-half),
+            KeyCode::Char('j') | KeyCode::Down => overlay.scroll_by(1),
+            KeyCode::Char('k') | KeyCode::Up => overlay.scroll_by(-1),
+            _ => {}
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::snapshot::drive;
+    use super::*;
+    use crate::batch::parse_keys;
+
+    #[test]
+    fn placement_and_scroll() {
+        let mut overlay = Overlay::new(String::from("doc"), "fn main()\n\nruns");
+        let screen = Rect::new(0, 0, 80, 23);
+        assert_eq!(overlay.area(4, 2, screen), Rect::new(34, 9, 11, 5));
+        overlay = overlay.at_cursor();
+        assert_eq!(overlay.area(4, 2, screen), Rect::new(4, 3, 11, 5));
+        assert_eq!(overlay.area(75, 20, screen), Rect::new(69, 15, 11, 5));
+        overlay.rows = 2;
+        overlay.scroll_by(5);
+        assert_eq!(overlay.scroll, 1);
+        overlay.scroll_by(-5);
+        assert_eq!(overlay.scroll, 0);
+    }
+
+    #[test]
+    fn drawn_over_buffer() {
+        let (mut ted, _) = drive("one\ntwo\nthree\n", "j");
+        let text = "first\nsecond\nthird\nfourth";
+        ted.open_overlay(Overlay::new(String::from("doc"), text).at_cursor());
+        let frame = ted.frame().unwrap();
+        assert_eq!(frame.rows[2].trim_end(), "┌doc───┐");
+        assert_eq!(frame.rows[3].trim_end(), "│first │");
+        assert_eq!(frame.style(2, 3).bg, Some(Color::DarkGray));
+        // the focused panel takes the keys, scrolling then closing
+        for key in parse_keys("jjjjj").unwrap() {
+            ted.handle_key(key);
+        }
+        assert_eq!(ted.buffers.focused().get_cursor().1, 1);
+        assert_eq!(ted.overlays[0].scroll, 0);
+        ted.overlays[0].rows = 2;
+        for key in parse_keys("jjjq").unwrap() {
+            ted.handle_key(key);
+        }
+        assert!(ted.overlays.is_empty());
+        // one without focus closes at the next key
+        ted.open_overlay(Overlay::new(String::from("doc"), text).unfocused());
+        ted.handle_key(parse_keys("j").unwrap()[0]);
+        assert!(ted.overlays.is_empty());
+        assert_eq!(ted.buffers.focused().get_cursor().1, 2);
+    }
+
+    /// numbered lines in green
+    struct Numbers(usize);
+
+    impl Panel for Numbers {
+        fn size(&self) -> (usize, usize) {
+            (12, self.0)
+        }
+
+        fn render(&self, area: Rect, buf: &mut tui::buffer::Buffer, first: usize, style: Style) {
+            let lines = (area.height as usize).min(self.0 - first);
+            for y in 0..lines {
+                let line = format!("#{}", first + y);
+                buf.set_string(area.x, area.y + y as u16, line, style.fg(Color::Green));
+            }
+        }
+    }
+
+    #[test]
+    fn any_panel() {
+        let (mut ted, _) = drive("one\n", "");
+        let numbers = Overlay::with_panel(String::from("n"), Box::new(Numbers(100)));
+        ted.open_overlay(numbers);
+        let frame = ted.frame().unwrap();
+        let top = frame
+            .rows
+            .iter()
+            .position(|row| row.contains("┌n 1-"))
+            .unwrap();
+        assert!(frame.rows[top + 1].contains("│#0  "));
+        let x = frame.rows[top + 1].chars().position(|c| c == '#').unwrap();
+        assert_eq!(frame.style(x, top + 1).fg, Some(Color::Green));
+        ted.handle_key(parse_keys("j").unwrap()[0]);
+        let frame = ted.frame().unwrap();
+        assert!(frame.rows[top + 1].contains("│#1  "));
+    }
+}
@@ -0,0 +1,277 @@
+use crate::Ted;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use tui::layout::Rect;
+use tui::style::{Modifier, Style};
+use tui::widgets::{Block, Borders, Paragraph, StatefulWidget, Widget};
+
+/// scores how well query fuzzy-matches candidate, None if it doesn't
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let mut score = 0;
+    let mut chars = candidate.chars().enumerate().peekable();
+    let mut previous: Option<(usize, char)> = None;
+    for q in query.chars().flat_map(char::to_lowercase) {
+        loop {
+            let (i, c) = chars.next()?;
+            if c.to_lowercase().any(|c| c == q) {
+                score += 1;
+                match previous {
+                    // consecutive matches
+                    Some((j, _)) if j + 1 == i => score += 5,
+                    _ => {}
+                }
+                // match at the start of a word
+                let boundary = i == 0
+                    || candidate
+                        .chars()
+                        .nth(i - 1)
+                        .map(|p| !p.is_alphanumeric())
+                        .unwrap_or(false);
+                if boundary {
+                    score += 3;
+                }
+                previous = Some((i, c));
+                break;
+            }
+        }
+    }
+    Some(score)
+}
+
+/// Reusable fuzzy finder over a list of candidates
+pub struct Picker {
+    pub title: String,
+    pub query: String,
+    candidates: Vec<String>,
+    /// indices of matching candidates, best first
+    matches: Vec<usize>,
+    selected: usize,
+    /// matches keep the candidates' order instead of shorter ones coming first
+    ordered: bool,
+    /// candidates produced in the background
+    source: Option<Receiver<String>>,
+    pub on_select: fn(&mut Ted, String),
+    pub preview: Option<fn(&Ted, &str) -> Option<String>>,
+}
+
+impl Picker {
+    pub fn new(title: String, candidates: Vec<String>, on_select: fn(&mut Ted, String)) -> Self {
+        let mut picker = Self {
+            title,
+            query: String::default(),
+            candidates,
+            matches: vec![],
+            selected: 0,
+            ordered: false,
+            source: None,
+            on_select,
+            preview: None,
+        };
+        picker.refilter();
+        picker
+    }
+
+    pub fn with_preview(mut self, preview: fn(&Ted, &str) -> Option<String>) -> Self {
+        self.preview = Some(preview);
+        self
+    }
+
+    /// keeps the candidates in order, for lists where it means something
+    pub fn ordered(mut self) -> Self {
+        self.ordered = true;
+        self.refilter();
+        self
+    }
+
+    /// starts on the given candidate
+    pub fn with_selected(mut self, candidate: usize) -> Self {
+        if let Some(i) = self.matches.iter().position(|&m| m == candidate) {
+            self.selected = i;
+        }
+        self
+    }
+
+    pub fn with_source(mut self, source: Receiver<String>) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// drains candidates sent by the background source, returns wether any arrived
+    pub fn poll_source(&mut self) -> bool {
+        let mut changed = false;
+        if let Some(source) = &self.source {
+            loop {
+                match source.try_recv() {
+                    Ok(candidate) => {
+                        self.candidates.push(candidate);
+                        changed = true;
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.source = None;
+                        break;
+                    }
+                }
+            }
+        }
+        if changed {
+            self.refilter();
+        }
+        changed
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.source.is_some()
+    }
+
+    fn refilter(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self
+            .candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| fuzzy_score(&self.query, c).map(|score| (score, i)))
+            .collect();
+        let candidates = &self.candidates;
+        let ordered = self.ordered;
+        scored.sort_by(|(sa, ia), (sb, ib)| {
+            let by_length = match ordered {
+                true => std::cmp::Ordering::Equal,
+                false => candidates[*ia].len().cmp(&candidates[*ib].len()),
+            };
+            sb.cmp(sa).then(by_length).then(ia.cmp(ib))
+        });
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = self.selected.min(self.matches.len().saturating_sub(1));
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        self.query.push(c);
+        self.selected = 0;
+        self.refilter();
+    }
+
+    pub fn pop_char(&mut self) {
+        self.query.pop();
+        self.refilter();
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn selected(&self) -> Option<&String> {
+        self.matches
+            .get(self.selected)
+            .map(|&i| &self.candidates[i])
+    }
+
+    /// "title (matches/candidates)"
+    pub fn prompt(&self) -> String {
+        format!(
+            "{} ({}/{}{})",
+            self.title,
+            self.matches.len(),
+            self.candidates.len(),
+            if self.is_loading() { "+" } else { "" }
+        )
+    }
+}
+
+pub struct PickerWidget {
+    pub preview: Option<String>,
+}
+
+impl StatefulWidget for PickerWidget {
+    type State = Picker;
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer, state: &mut Self::State) {
+        buf.set_style(area, Style::default());
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                buf.get_mut(x, y).reset();
+            }
+        }
+        let list_width = if self.preview.is_some() {
+            area.width / 2
+        } else {
+            area.width
+        };
+
+        // scroll so the selected candidate stays visible
+        let height = area.height as usize;
+        let offset = (state.selected + 1).saturating_sub(height);
+        for (y, &i) in state.matches.iter().skip(offset).take(height).enumerate() {
+            let style = if y + offset == state.selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            buf.set_stringn(
+                area.x,
+                area.y + y as u16,
+                &state.candidates[i],
+                list_width as usize,
+                style,
+            );
+        }
+
+        if let Some(preview) = self.preview {
+            let preview_area = Rect::new(
+                area.x + list_width,
+                area.y,
+                area.width - list_width,
+                area.height,
+            );
+            Paragraph::new(preview)
+                .block(Block::default().borders(Borders::LEFT))
+                .render(preview_area, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_score() {
+        assert_eq!(super::fuzzy_score("xyz", "file_open"), None);
+        assert!(super::fuzzy_score("", "file_open").is_some());
+        let consecutive = super::fuzzy_score("fil", "file_open").unwrap();
+        let scattered = super::fuzzy_score("fil", "fancy_ill").unwrap();
+        assert!(consecutive > scattered);
+        let boundary = super::fuzzy_score("fo", "file_open").unwrap();
+        let inner = super::fuzzy_score("fo", "fxxo").unwrap();
+        assert!(boundary > inner);
+    }
+
+    #[test]
+    fn picker_filters_and_sorts() {
+        let mut picker = Picker::new(
+            String::from("test"),
+            vec![String::from("file_save"), String::from("file_open")],
+            |_, _| {},
+        );
+        assert_eq!(picker.selected(), Some(&String::from("file_save")));
+        picker.push_char('o');
+        assert_eq!(picker.selected(), Some(&String::from("file_open")));
+        picker.push_char('z');
+        assert_eq!(picker.selected(), None);
+    }
+
+    #[test]
+    fn ordered_picker() {
+        let candidates = vec![String::from("long one"), String::from("short")];
+        let picker = Picker::new(String::from("test"), candidates.clone(), |_, _| {});
+        assert_eq!(picker.selected(), Some(&candidates[1]));
+        let picker = Picker::new(String::from("test"), candidates.clone(), |_, _| {})
+            .ordered()
+            .with_selected(1);
+        assert_eq!(picker.selected(), Some(&candidates[1]));
+        assert_eq!(picker.with_selected(0).selected(), Some(&candidates[0]));
+    }
+}
@@ -0,0 +1,290 @@
+//! Frames drawn by a headless ted as plain text, to assert on what the renderer draws after
+//! some keys: the symbols of each row, a letter per cell standing for its style, and where the
+//! cursor is
+
+use super::Ted;
+use crate::batch::parse_keys;
+use std::io;
+use std::ops::Range;
+use ted::color::ColorDepth;
+use ted::Config;
+use tui::backend::Backend;
+use tui::buffer::{Buffer, Cell};
+use tui::style::Style;
+
+pub struct Frame {
+    pub rows: Vec<String>,
+    /// a letter per cell, `.` for a blank style, others in the order they first appear
+    pub marks: Vec<String>,
+    /// the styles of `a`, `b`, `c`...
+    pub styles: Vec<Style>,
+    /// column and row of the terminal's cursor
+    pub cursor: (u16, u16),
+}
+
+impl Frame {
+    pub fn capture(buffer: &Buffer, cursor: (u16, u16)) -> Self {
+        let width = (buffer.area.width as usize).max(1);
+        let blank = Cell::default().style();
+        let mut styles: Vec<Style> = vec![];
+        let mut mark = |style: Style| {
+            if style == blank {
+                return '.';
+            }
+            let i = match styles.iter().position(|s| *s == style) {
+                Some(i) => i,
+                None => {
+                    styles.push(style);
+                    styles.len() - 1
+                }
+            };
+            char::from_u32('a' as u32 + i as u32).unwrap_or('?')
+        };
+        let mut rows = vec![];
+        let mut marks = vec![];
+        for row in buffer.content.chunks(width) {
+            rows.push(row.iter().map(|cell| cell.symbol.as_str()).collect());
+            marks.push(row.iter().map(|cell| mark(cell.style())).collect());
+        }
+        Self {
+            rows,
+            marks,
+            styles,
+            cursor,
+        }
+    }
+
+    /// the style of a cell
+    pub fn style(&self, x: usize, y: usize) -> Style {
+        let mark = self.marks[y].chars().nth(x).unwrap_or('.');
+        match mark {
+            '.' => Cell::default().style(),
+            mark => self.styles[mark as usize - 'a' as usize],
+        }
+    }
+
+    /// the rows with their marks underneath, trailing blanks left out
+    pub fn render(&self, rows: Range<usize>) -> String {
+        let mut text = String::new();
+        for y in rows {
+            text.push_str(self.rows[y].trim_end());
+            text.push('\n');
+            text.push_str(self.marks[y].trim_end_matches('.'));
+            text.push('\n');
+        }
+        text
+    }
+}
+
+impl Ted {
+    /// draws right away and captures the frame, when headless
+    pub fn frame(&mut self) -> io::Result<Frame> {
+        self.screen()?;
+        let backend = self.term.backend_mut();
+        let cursor = backend.get_cursor()?;
+        let buffer = backend
+            .drawn()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "Not drawing in memory"))?;
+        Ok(Frame::capture(buffer, cursor))
+    }
+}
+
+/// a headless ted editing text in a new buffer, after typing keys in vim's notation, with
+/// true colors and without the cursor's line highlighted for steady snapshots
+pub fn drive(text: &str, keys: &str) -> (Ted, Frame) {
+    let config = Config::builtin();
+    config.color_depth.set(ColorDepth::TrueColor);
+    config.cursor_line.set(false);
+    let mut ted = Ted::headless(config).unwrap();
+    ted.new_buffer(text.to_string());
+    for key in parse_keys(keys).unwrap() {
+        ted.handle_key(key);
+    }
+    let frame = ted.frame().unwrap();
+    (ted, frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::drive;
+    use crate::batch::parse_keys;
+    use crate::editor::Ted;
+    use crossterm::event::Event;
+    use std::time::{Duration, SystemTime};
+    use ted::Config;
+    use tui::style::{Color, Modifier};
+
+    #[test]
+    fn cursor_placement() {
+        let (mut ted, frame) = drive("one two\nthree\n", "llllj");
+        assert_eq!(frame.cursor, (4, 1));
+        ted.config().line_numbers.set(true);
+        assert_eq!(ted.frame().unwrap().cursor, (8, 1));
+    }
+
+    #[test]
+    fn selection() {
+        let (_, frame) = drive("abc def\n", "lvll");
+        assert_eq!(frame.render(0..1), "abc def¶\nabbbaaaa\n");
+        assert_eq!(frame.style(1, 0).add_modifier, Modifier::REVERSED);
+    }
+
+    #[test]
+    fn todo_after_wide_chars() {
+        let (_, frame) = drive("漢字 TODO\n", "");
+        let bold = Modifier::BOLD | Modifier::REVERSED;
+        assert_eq!(frame.style(4, 0).add_modifier, Modifier::empty());
+        assert_eq!(frame.style(5, 0).add_modifier, bold);
+        assert_eq!(frame.style(8, 0).add_modifier, bold);
+        assert_eq!(frame.style(9, 0).add_modifier, Modifier::empty());
+    }
+
+    #[test]
+    fn soft_wrap() {
+        let (mut ted, _) = drive(&format!("{}\nx\n", "ab ".repeat(30)), "");
+        ted.config().soft_wrap.set(true);
+        ted.config().line_numbers.set(true);
+        let frame = ted.frame().unwrap();
+        assert_eq!(
+            frame.rows[0].trim_end(),
+            format!("  1 {}", "ab ".repeat(25)).trim_end()
+        );
+        assert_eq!(
+            frame.rows[1].trim_end(),
+            format!("    {}", "ab ".repeat(5)).trim_end()
+        );
+        assert!(frame.rows[2].starts_with("  2 x"));
+        for key in parse_keys("gj").unwrap() {
+            ted.handle_key(key);
+        }
+        assert_eq!(ted.frame().unwrap().cursor, (4, 1));
+    }
+
+    #[test]
+    fn help_follows_commands() {
+        let mut config = Config::builtin();
+        config.file = None;
+        let mut ted = Ted::headless(config).unwrap();
+        ted.bind(String::from("file_save SPC f w"));
+        assert!(ted
+            .buffers
+            .focused()
+            .get_text()
+            .contains("- `SPC f w` (file_save): "));
+        ted.register("漢字", String::from("Wide"), None).unwrap();
+        ted.refresh_help();
+        let text = ted.buffers.focused().get_text();
+        let line = text.lines().position(|l| l.contains("(漢字)")).unwrap();
+        ted.buffers.focused_mut().goto(line, 0);
+        let frame = ted.frame().unwrap();
+        let y = frame.rows.iter().position(|r| r.contains("(漢")).unwrap();
+        // 漢 and 字 take two cells each
+        assert_eq!(frame.style(13, y).fg, Some(Color::Green));
+        assert_eq!(frame.style(15, y).fg, Some(Color::Green));
+        assert_ne!(frame.style(17, y).fg, Some(Color::Green));
+    }
+
+    #[test]
+    fn prompt_editing() {
+        let (mut ted, frame) = drive("x\n", "<Space><Space>fil open<Home><Del>f<End><C-w><Left>e");
+        assert_eq!(frame.rows[23].trim_end(), "Command: file");
+        assert_eq!(frame.cursor, (13, 23));
+        for key in crate::batch::parse_keys("<Left><Left><C-u>").unwrap() {
+            ted.handle_key(key);
+        }
+        let frame = ted.frame().unwrap();
+        assert_eq!(
+            (frame.rows[23].trim_end(), frame.cursor),
+            ("Command: le", (9, 23))
+        );
+    }
+
+    #[test]
+    fn bracketed_paste() {
+        let (mut ted, _) = drive("x\n", "");
+        ted.paste_text("if a {\r\tb\r}");
+        assert_eq!(ted.buffers.focused().get_text(), "if a {\n\tb\n}x\n");
+        ted.handle_key(parse_keys("u").unwrap()[0]);
+        assert_eq!(ted.buffers.focused().get_text(), "x\n");
+        ted.handle_key(parse_keys("/").unwrap()[0]);
+        ted.paste_text("ab\ncd");
+        assert_eq!(ted.answer.as_str(), "ab");
+    }
+
+    #[test]
+    fn focus_changes() {
+        let path = std::env::temp_dir().join(format!("ted-focus-{}.txt", std::process::id()));
+        std::fs::write(&path, "one\n").unwrap();
+        let (mut ted, _) = drive("", "");
+        ted.file_open_at(path.display().to_string(), None, None, false);
+        let change_on_disk = |text: &str, secs| {
+            std::fs::write(&path, text).unwrap();
+            let later = SystemTime::now() + Duration::from_secs(secs);
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(later).unwrap();
+        };
+        ted.handle_events(vec![Event::FocusLost]);
+        assert!(!ted.terminal_focused);
+        change_on_disk("two\n", 5);
+        ted.handle_events(vec![Event::FocusGained]);
+        assert!(ted.terminal_focused);
+        assert_eq!(ted.buffers.focused().get_text(), "two\n");
+        // unsaved changes are kept, saving asks what to do
+        for key in parse_keys("iz<Esc>").unwrap() {
+            ted.handle_key(key);
+        }
+        change_on_disk("three\n", 10);
+        ted.handle_events(vec![Event::FocusGained]);
+        assert_eq!(ted.buffers.focused().get_text(), "ztwo\n");
+        assert!(ted
+            .message
+            .starts_with("Changed on disk with unsaved changes"));
+        // overwriting saves like any other save, copies included
+        let copy = path.with_extension("copy");
+        ted.run_command(format!("file_save tee {}", copy.display()));
+        ted.resolve_external_change(String::from("o"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "ztwo\n");
+        assert_eq!(std::fs::read_to_string(&copy).unwrap(), "ztwo\n");
+        assert!(!ted.buffers.focused().is_dirty());
+        std::fs::remove_file(&copy).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn definition_in_another_file() {
+        let path = std::env::temp_dir().join(format!("ted-definition-{}.rs", std::process::id()));
+        std::fs::write(&path, "fn a() {}\nfn b() {}\n").unwrap();
+        let (mut ted, _) = drive("b();\n", "");
+        let location = serde_json::json!({
+            "uri": format!("file://{}", path.display()),
+            "range": { "start": { "line": 1, "character": 3 } },
+        });
+        ted.show_definition(&location);
+        let buffer = ted.buffers.focused();
+        assert_eq!(buffer.get_path(), Some(path.display().to_string().as_str()));
+        assert_eq!(buffer.get_cursor(), (13, 1, 3));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn persist_without_reload() {
+        let path = std::env::temp_dir().join(format!("ted-persist-{}.json", std::process::id()));
+        std::fs::write(&path, "{}").unwrap();
+        let mut config = Config::builtin();
+        config.file = Some(path.clone());
+        let mut ted = Ted::headless(config).unwrap();
+        ted.run_command(String::from("theme ted"));
+        assert_eq!(ted.message, "Default theme set to ted");
+        let written = std::fs::metadata(&path).unwrap().modified().ok();
+        assert_eq!(ted.config_modified.0, written);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn search_matches() {
+        let (_, frame) = drive("ab cab\n", "l/ab<CR>");
+        assert_eq!(frame.render(0..1), "ab cab¶\naabbaab\n");
+        assert_eq!(frame.style(0, 0).bg, Some(Color::Yellow));
+        assert_eq!(frame.cursor, (4, 0));
+    }
+}
@@ -0,0 +1,140 @@
+use ted::command::{format_space_chain, Commands};
+use tui::layout::Rect;
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::StatefulWidget;
+
+/// continuations of a space chain in progress, as (key, name, description)
+pub struct WhichKey {
+    entries: Vec<(String, String, String)>,
+}
+
+impl WhichKey {
+    /// commands one key away from chain, longer chains grouped by their next key
+    pub fn new<T>(commands: &Commands<T>, chain: &str) -> Self {
+        let mut entries = vec![];
+        let mut groups: Vec<(char, usize)> = vec![];
+        for command in commands.get_by_chain(chain) {
+            let rest: Vec<char> = command
+                .chain
+                .as_ref()
+                .map(|c| c.chars().skip(chain.chars().count()).collect())
+                .unwrap_or_default();
+            match rest.as_slice() {
+                [] => {}
+                [key] => entries.push((
+                    format_space_chain(&key.to_string()),
+                    command.name.clone(),
+                    command.desc.clone(),
+                )),
+                [key, ..] => match groups.iter_mut().find(|(c, _)| c == key) {
+                    Some((_, n)) => *n += 1,
+                    None => groups.push((*key, 1)),
+                },
+            }
+        }
+        for (key, n) in groups {
+            entries.push((
+                format_space_chain(&key.to_string()),
+                String::from("+prefix"),
+                format!("{} commands", n),
+            ));
+        }
+        entries.sort();
+        Self { entries }
+    }
+
+    /// full width panel at the bottom of screen, in as many columns as needed
+    pub fn area(&self, screen: Rect) -> Rect {
+        let height = (self.entries.len() as u16).min(screen.height / 2).max(1);
+        let rows = self.rows(height);
+        Rect::new(
+            screen.x,
+            screen.bottom().saturating_sub(rows),
+            screen.width,
+            rows,
+        )
+    }
+
+    /// rows used when at most height are available
+    fn rows(&self, height: u16) -> u16 {
+        let n = (self.entries.len() as u16).saturating_sub(1);
+        let columns = 1 + n / height.max(1);
+        1 + n / columns
+    }
+}
+
+pub struct WhichKeyWidget {}
+
+impl StatefulWidget for WhichKeyWidget {
+    type State = WhichKey;
+    fn render(self, area: Rect, buf: &mut tui::buffer::Buffer, state: &mut Self::State) {
+        let style = Style::default().fg(Color::White).bg(Color::DarkGray);
+        buf.set_style(area, style);
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                buf.get_mut(x, y).set_symbol(" ");
+            }
+        }
+
+        let rows = area.height.max(1) as usize;
+        let columns = 1 + state.entries.len().saturating_sub(1) / rows;
+        let width = area.width / columns as u16;
+        for (i, (key, name, desc)) in state.entries.iter().enumerate() {
+            let x = area.x + (i / rows) as u16 * width;
+            let y = area.y + (i % rows) as u16;
+            let (x, _) = buf.set_stringn(
+                x,
+                y,
+                format!(" {} ", key),
+                width as usize,
+                style.add_modifier(Modifier::BOLD),
+            );
+            let used = x - area.x - (i / rows) as u16 * width;
+            buf.set_stringn(
+                x,
+                y,
+                format!("{}: {}", name, desc),
+                width.saturating_sub(used + 1) as usize,
+                style,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WhichKey;
+    use ted::command::{Command, Commands};
+    use tui::layout::Rect;
+
+    #[test]
+    fn continuations() {
+        let command = |name: &str, chain: &str| Command {
+            name: name.to_string(),
+            desc: String::default(),
+            help: None,
+            chain: Some(chain.to_string()),
+            action: |_: &mut ()| {},
+            arguments: None,
+        };
+        let commands = Commands {
+            commands: vec![
+                command("file_open", " fo"),
+                command("file_save", " fs"),
+                command("quit", " q"),
+            ],
+        };
+        let which_key = WhichKey::new(&commands, " ");
+        let keys: Vec<(&str, &str)> = which_key
+            .entries
+            .iter()
+            .map(|(key, name, _)| (key.as_str(), name.as_str()))
+            .collect();
+        assert_eq!(keys, vec![("f", "+prefix"), ("q", "quit")]);
+        assert_eq!(
+            which_key.area(Rect::new(0, 0, 80, 24)),
+            Rect::new(0, 22, 80, 2)
+        );
+        assert_eq!(WhichKey::new(&commands, " f").entries.len(), 2);
+    }
+}
@@ -0,0 +1,239 @@
+use crate::path::{glob_match, normalize};
+use std::path::Path;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum IndentStyle {
+    Tab,
+    Space,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EndOfLine {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl EndOfLine {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EndOfLine::Lf => "\n",
+            EndOfLine::CrLf => "\r\n",
+            EndOfLine::Cr => "\r",
+        }
+    }
+}
+
+/// properties of the .editorconfig files applying to a path, None when unset
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Settings {
+    pub indent_style: Option<IndentStyle>,
+    pub indent_size: Option<usize>,
+    pub end_of_line: Option<EndOfLine>,
+    pub trim_trailing_whitespace: Option<bool>,
+}
+
+impl Settings {
+    fn set(&mut self, key: &str, value: &str) {
+        match (key, value) {
+            ("indent_style", "tab") => self.indent_style = Some(IndentStyle::Tab),
+            ("indent_style", "space") => self.indent_style = Some(IndentStyle::Space),
+            ("indent_style", _) => self.indent_style = None,
+            ("indent_size", size) => self.indent_size = size.parse().ok(),
+            ("end_of_line", "lf") => self.end_of_line = Some(EndOfLine::Lf),
+            ("end_of_line", "crlf") => self.end_of_line = Some(EndOfLine::CrLf),
+            ("end_of_line", "cr") => self.end_of_line = Some(EndOfLine::Cr),
+            ("end_of_line", _) => self.end_of_line = None,
+            ("trim_trailing_whitespace", trim) => self.trim_trailing_whitespace = trim.parse().ok(),
+            _ => {}
+        }
+    }
+
+    /// text inserted by the Tab key at the given column
+    pub fn indent(&self, column: usize) -> String {
+        match self.indent_style {
+            Some(IndentStyle::Space) => {
+                let size = self.indent_size.unwrap_or(4).max(1);
+                " ".repeat(size - column % size)
+            }
+            _ => String::from("\t"),
+        }
+    }
+
+    /// the text with its line endings converted to end_of_line
+    pub fn convert_line_endings(&self, text: &str) -> Option<String> {
+        let ending = self.end_of_line?;
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        Some(match ending {
+            EndOfLine::Lf => text,
+            _ => text.replace('\n', ending.as_str()),
+        })
+    }
+
+    /// short description of the settings in use, empty if there are none
+    pub fn summary(&self) -> String {
+        let mut parts = vec![];
+        match (self.indent_style, self.indent_size) {
+            (Some(IndentStyle::Tab), _) => parts.push(String::from("tabs")),
+            (Some(IndentStyle::Space), size) => parts.push(format!("spaces:{}", size.unwrap_or(4))),
+            (None, Some(size)) => parts.push(format!("indent:{}", size)),
+            (None, None) => {}
+        }
+        if let Some(ending) = self.end_of_line {
+            parts.push(format!("{:?}", ending).to_lowercase());
+        }
+        if self.trim_trailing_whitespace == Some(true) {
+            parts.push(String::from("trim"));
+        }
+        parts.join(" ")
+    }
+}
+
+struct Section {
+    pattern: String,
+    properties: Vec<(String, String)>,
+}
+
+/// sections of an .editorconfig file, and whether it is the root one
+fn parse(text: &str) -> (bool, Vec<Section>) {
+    let mut root = false;
+    let mut sections: Vec<Section> = vec![];
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(&['#', ';'][..]) {
+            continue;
+        }
+        if let Some(pattern) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push(Section {
+                pattern: pattern.to_string(),
+                properties: vec![],
+            });
+        } else if let Some((key, value)) = line.split_once('=') {
+            let key = key.trim().to_lowercase();
+            let value = value.trim().to_lowercase();
+            match sections.last_mut() {
+                Some(section) => section.properties.push((key, value)),
+                None if key == "root" => root = value == "true",
+                None => {}
+            }
+        }
+    }
+    (root, sections)
+}
+
+/// expands `{a,b}` alternatives of a pattern
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let open = match pattern.find('{') {
+        Some(open) => open,
+        None => return vec![pattern.to_string()],
+    };
+    let close = match pattern[open..].find('}') {
+        Some(close) => open + close,
+        None => return vec![pattern.to_string()],
+    };
+    pattern[open + 1..close]
+        .split(',')
+        .flat_map(|alternative| {
+            expand_braces(&format!(
+                "{}{}{}",
+                &pattern[..open],
+                alternative,
+                &pattern[close + 1..]
+            ))
+        })
+        .collect()
+}
+
+/// whether a section applies to a path relative to its .editorconfig, patterns
+/// without a `/` matching the file name in any directory
+fn matches(pattern: &str, relative: &str) -> bool {
+    let name = relative.rsplit('/').next().unwrap_or(relative);
+    expand_braces(pattern).iter().any(|pattern| {
+        if pattern.contains('/') {
+            glob_match(pattern.trim_start_matches('/'), relative)
+        } else {
+            glob_match(pattern, name)
+        }
+    })
+}
+
+/// settings for path from the .editorconfig files of its directory and parents,
+/// up to the root one, nearer files taking precedence
+pub fn resolve(path: &Path) -> Settings {
+    let path = match std::env::current_dir() {
+        Ok(dir) => normalize(&dir.join(path)),
+        Err(_) => normalize(path),
+    };
+    let mut files = vec![];
+    for dir in path.ancestors().skip(1) {
+        if let Ok(text) = std::fs::read_to_string(dir.join(".editorconfig")) {
+            let (root, sections) = parse(&text);
+            files.push((dir, sections));
+            if root {
+                break;
+            }
+        }
+    }
+    let mut settings = Settings::default();
+    for (dir, sections) in files.iter().rev() {
+        let relative = match path.strip_prefix(dir) {
+            Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+            Err(_) => continue,
+        };
+        for section in sections.iter().filter(|s| matches(&s.pattern, &relative)) {
+            for (key, value) in &section.properties {
+                settings.set(key, value);
+            }
+        }
+    }
+    settings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections() {
+        assert!(matches("*", "src/main.rs"));
+        assert!(matches("*.{rs,toml}", "Cargo.toml"));
+        assert!(!matches("*.{rs,toml}", "README.md"));
+        assert!(matches("/src/*.rs", "src/main.rs"));
+        assert!(!matches("src/*.rs", "main.rs"));
+        let (root, sections) = parse("root = true\n# comment\n[*.rs]\nindent_style = Space\n");
+        assert!(root);
+        assert_eq!(sections[0].pattern, "*.rs");
+        assert_eq!(
+            sections[0].properties,
+            vec![(String::from("indent_style"), String::from("space"))]
+        );
+    }
+
+    #[test]
+    fn resolve_settings() {
+        let dir = std::env::temp_dir().join(format!("ted-editorconfig-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(
+            dir.join(".editorconfig"),
+            "root = true\n[*]\nend_of_line = crlf\nindent_style = tab\n[*.rs]\nindent_style = space\nindent_size = 2\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("src/.editorconfig"),
+            "[*.rs]\ntrim_trailing_whitespace = true\n",
+        )
+        .unwrap();
+        let settings = resolve(&dir.join("src/main.rs"));
+        assert_eq!(settings.indent_style, Some(IndentStyle::Space));
+        assert_eq!(
+            (settings.indent(0).as_str(), settings.indent(3).as_str()),
+            ("  ", " ")
+        );
+        assert_eq!(settings.summary(), "spaces:2 crlf trim");
+        assert_eq!(
+            settings.convert_line_endings("a\nb\r\n").as_deref(),
+            Some("a\r\nb\r\n")
+        );
+        assert_eq!(resolve(&dir.join("notes.txt")).summary(), "tabs crlf");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,62 @@
+//! Failures reported to the user, in the echo area and the `errors` buffer
+
+use std::fmt;
+use std::io;
+use syntect::LoadingError;
+
+#[derive(Debug)]
+pub enum TedError {
+    /// reading or writing files, running processes
+    Io(io::Error),
+    /// an invalid option, binding or plugin in the user's configuration
+    Config(String),
+    /// themes and syntaxes that failed to load
+    Highlight(String),
+    /// a command that doesn't exist or was given invalid arguments
+    Command(String),
+    /// a Lua plugin that raised an error
+    Script(String),
+}
+
+pub type Result<T> = std::result::Result<T, TedError>;
+
+impl fmt::Display for TedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TedError::Io(err) => write!(f, "{}", err),
+            TedError::Config(message) => write!(f, "Config: {}", message),
+            TedError::Highlight(message) => write!(f, "Highlighting: {}", message),
+            TedError::Command(message) => write!(f, "{}", message),
+            TedError::Script(message) => write!(f, "Lua: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for TedError {}
+
+impl From<io::Error> for TedError {
+    fn from(err: io::Error) -> Self {
+        TedError::Io(err)
+    }
+}
+
+impl From<LoadingError> for TedError {
+    fn from(err: LoadingError) -> Self {
+        TedError::Highlight(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let err: TedError = io::Error::new(io::ErrorKind::NotFound, "a.txt not found").into();
+        assert_eq!(err.to_string(), "a.txt not found");
+        let err = TedError::Config(String::from("Unknown hook on_exit"));
+        assert_eq!(err.to_string(), "Config: Unknown hook on_exit");
+        let err: TedError = LoadingError::BadPath.into();
+        assert!(err.to_string().starts_with("Highlighting: "));
+    }
+}
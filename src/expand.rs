@@ -0,0 +1,191 @@
+//! Units of text around a selection, from the smallest: the word, a quoted string on the line,
+//! inside then around brackets, the line's text then the whole lines, the indented block, and
+//! everything. `expand_selection` grows to the next one, `shrink_selection` goes back.
+
+use crate::completion::is_word_char;
+use crate::fold::indent_fold;
+use ropey::Rope;
+use std::ops::Range;
+
+const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+const QUOTES: [char; 3] = ['"', '\'', '`'];
+
+/// the smallest unit strictly containing range, None when it is the whole text
+pub fn enclosing(content: &Rope, range: Range<usize>) -> Option<Range<usize>> {
+    let size = |unit: &Range<usize>| unit.end - unit.start;
+    let mut units = vec![];
+    units.extend(word(content, &range));
+    units.extend(quoted(content, &range));
+    units.extend(bracketed(content, &range));
+    units.extend(lines(content, &range));
+    units.extend(block(content, &range));
+    units.push(0..content.len_chars());
+    units
+        .into_iter()
+        .filter(|unit| unit.start <= range.start && range.end <= unit.end)
+        .filter(|unit| size(unit) > size(&range))
+        .min_by_key(size)
+}
+
+fn word(content: &Rope, range: &Range<usize>) -> Option<Range<usize>> {
+    let is_word = |i: usize| content.get_char(i).map(is_word_char).unwrap_or(false);
+    if !is_word(range.start) {
+        return None;
+    }
+    let mut start = range.start;
+    while start > 0 && is_word(start - 1) {
+        start -= 1;
+    }
+    let mut end = range.end.max(range.start + 1);
+    while is_word(end) {
+        end += 1;
+    }
+    Some(start..end)
+}
+
+/// inside and around each pair of quotes on the line, a quote after `\` not counting
+fn quoted(content: &Rope, range: &Range<usize>) -> Vec<Range<usize>> {
+    let line_number = content.char_to_line(range.start);
+    let bol = content.line_to_char(line_number);
+    let mut units = vec![];
+    for quote in QUOTES {
+        let mut escaped = false;
+        let mut open = None;
+        for (i, c) in content.line(line_number).chars().enumerate() {
+            if c == quote && !escaped {
+                match open.take() {
+                    Some(start) => {
+                        units.extend([bol + start + 1..bol + i, bol + start..bol + i + 1])
+                    }
+                    None => open = Some(i),
+                }
+            }
+            escaped = c == '\\' && !escaped;
+        }
+    }
+    units
+}
+
+/// inside and around the innermost brackets strictly containing range
+fn bracketed(content: &Rope, range: &Range<usize>) -> Vec<Range<usize>> {
+    let mut from = range.start;
+    while let Some(open) = unmatched_open(content, from) {
+        if let Some(close) = matching_close(content, open) {
+            if close + 1 >= range.end && close + 1 - open > range.end - range.start {
+                return vec![open + 1..close, open..close + 1];
+            }
+        }
+        from = open;
+    }
+    vec![]
+}
+
+/// the last opening bracket before position that isn't closed before it
+fn unmatched_open(content: &Rope, position: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = position;
+    for c in content.chars_at(position).reversed() {
+        i -= 1;
+        if PAIRS.iter().any(|(_, close)| *close == c) {
+            depth += 1;
+        } else if PAIRS.iter().any(|(open, _)| *open == c) {
+            if depth == 0 {
+                return Some(i);
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// the bracket closing the one at open, of the same kind
+fn matching_close(content: &Rope, open: usize) -> Option<usize> {
+    let (_, close) = PAIRS
+        .iter()
+        .find(|(c, _)| Some(*c) == content.get_char(open))?;
+    let mut depth = 0;
+    for (i, c) in content.chars_at(open + 1).enumerate() {
+        if PAIRS.iter().any(|(open, _)| *open == c) {
+            depth += 1;
+        } else if PAIRS.iter().any(|(_, close)| *close == c) {
+            if depth == 0 {
+                return Some(open + 1 + i).filter(|_| c == *close);
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// the text of a single line without its indentation and newline, then the whole lines
+fn lines(content: &Rope, range: &Range<usize>) -> Vec<Range<usize>> {
+    let first = content.char_to_line(range.start);
+    let last = content.char_to_line(range.end.saturating_sub(1).max(range.start));
+    let mut units = Vec::with_capacity(2);
+    units.push(content.line_to_char(first)..content.line_to_char(last + 1));
+    if first == last {
+        let line = content.line(first);
+        let bol = content.line_to_char(first);
+        let indent = line.chars().take_while(|c| *c == ' ' || *c == '\t').count();
+        let text = line
+            .chars()
+            .take_while(|c| *c != '\n' && *c != '\r')
+            .count();
+        if indent < text {
+            units.push(bol + indent..bol + text);
+        }
+    }
+    units
+}
+
+/// the innermost indented block, header included, holding the lines of range and more
+fn block(content: &Rope, range: &Range<usize>) -> Option<Range<usize>> {
+    let first = content.char_to_line(range.start);
+    let last = content.char_to_line(range.end.saturating_sub(1).max(range.start));
+    (0..=first)
+        .rev()
+        .filter_map(|n| indent_fold(content, n))
+        .filter(|fold| fold.start <= first && last < fold.end)
+        .map(|fold| content.line_to_char(fold.start)..content.line_to_char(fold.end))
+        .find(|unit| unit.end - unit.start > range.end - range.start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the text of each unit from the position of `at`, growing until everything
+    fn units(text: &str, at: &str) -> Vec<String> {
+        let content = Rope::from(text);
+        let start = content.byte_to_char(text.find(at).unwrap());
+        let mut range = start..start;
+        let mut units = vec![];
+        while let Some(unit) = enclosing(&content, range) {
+            units.push(content.slice(unit.clone()).to_string());
+            range = unit;
+        }
+        units
+    }
+
+    #[test]
+    fn grow_units() {
+        let text = "fn main() {\n    let x = f(\"some text\", [a, b]);\n}\n";
+        assert_eq!(
+            units(text, "text"),
+            vec![
+                "text",
+                "some text",
+                "\"some text\"",
+                "\"some text\", [a, b]",
+                "(\"some text\", [a, b])",
+                "let x = f(\"some text\", [a, b]);",
+                "    let x = f(\"some text\", [a, b]);\n",
+                "\n    let x = f(\"some text\", [a, b]);\n",
+                "{\n    let x = f(\"some text\", [a, b]);\n}",
+                "fn main() {\n    let x = f(\"some text\", [a, b]);\n}\n",
+            ]
+        );
+        assert_eq!(units("a (b\\\"c) d", "c")[..3], ["c", "b\\\"c", "(b\\\"c)"]);
+        assert_eq!(units("x", "x"), vec!["x"]);
+    }
+}
@@ -0,0 +1,82 @@
+//! Renders highlighted lines for use outside of the editor
+
+use crate::buffer::{HighlightedLine, Lines};
+use crate::color::{Color, ColorDepth};
+use syntect::highlighting::Theme;
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn highlighted(lines: Lines) -> Vec<HighlightedLine> {
+    match lines {
+        Lines::Highlighted(lines) => lines,
+        Lines::Plain(lines) => lines.into_iter().map(|line| (line, vec![])).collect(),
+    }
+}
+
+/// a standalone `<pre>` block with inline styles
+pub fn to_html(lines: Lines, theme: Option<&Theme>) -> String {
+    let background = theme
+        .and_then(|theme| theme.settings.background)
+        .map(|c| {
+            format!(
+                " style=\"background-color:#{:02x}{:02x}{:02x}\"",
+                c.r, c.g, c.b
+            )
+        })
+        .unwrap_or_default();
+    let mut html = format!("<pre{}>", background);
+    for (line, ranges) in highlighted(lines) {
+        if ranges.is_empty() {
+            html.push_str(&escape_html(&line));
+        }
+        for (style, range) in ranges {
+            let c = style.foreground;
+            html.push_str(&format!(
+                "<span style=\"color:#{:02x}{:02x}{:02x}\">{}</span>",
+                c.r,
+                c.g,
+                c.b,
+                escape_html(&line[range])
+            ));
+        }
+    }
+    html.push_str("</pre>\n");
+    html
+}
+
+/// text with terminal escape codes for the given color depth
+pub fn to_ansi(lines: Lines, depth: ColorDepth) -> String {
+    let mut s = String::default();
+    for (line, ranges) in highlighted(lines) {
+        if ranges.is_empty() {
+            s.push_str(&line);
+        }
+        for (style, range) in ranges {
+            let c = style.foreground;
+            let escape = match depth.convert(c.r, c.g, c.b) {
+                Color::Rgb(r, g, b) => format!("38;2;{};{};{}", r, g, b),
+                Color::Indexed(i) => format!("38;5;{}", i),
+                Color::Ansi(i) if i < 8 => format!("{}", 30 + i),
+                Color::Ansi(i) => format!("{}", 90 + i - 8),
+            };
+            s.push_str(&format!("\x1b[{}m{}", escape, &line[range]));
+        }
+    }
+    s.push_str("\x1b[0m");
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_html() {
+        let lines = Lines::Plain(vec![String::from("a < b\n")]);
+        assert_eq!(super::to_html(lines, None), "<pre>a &lt; b\n</pre>\n");
+    }
+}
@@ -0,0 +1,77 @@
+use ropey::Rope;
+use std::ops::Range;
+
+/// width of a line's indentation, tabs counting as 4, None for blank lines
+fn indent(line: &str) -> Option<usize> {
+    if line.trim().is_empty() {
+        return None;
+    }
+    Some(
+        line.chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum(),
+    )
+}
+
+fn line_indent(content: &Rope, line_number: usize) -> Option<usize> {
+    indent(&String::from(content.line(line_number)))
+}
+
+/// lines of the block starting at line_number: the header followed by the
+/// lines indented deeper than it, trailing blank lines excluded
+pub fn indent_fold(content: &Rope, line_number: usize) -> Option<Range<usize>> {
+    if line_number >= content.len_lines() {
+        return None;
+    }
+    let header = line_indent(content, line_number)?;
+    let mut last = line_number;
+    for n in line_number + 1..content.len_lines() {
+        match line_indent(content, n) {
+            None => {}
+            Some(i) if i > header => last = n,
+            _ => break,
+        }
+    }
+    if last > line_number {
+        Some(line_number..last + 1)
+    } else {
+        None
+    }
+}
+
+/// the fold headed by line_number, or else the innermost one containing it
+pub fn fold_around(content: &Rope, line_number: usize) -> Option<Range<usize>> {
+    if let Some(fold) = indent_fold(content, line_number) {
+        return Some(fold);
+    }
+    (0..line_number)
+        .rev()
+        .filter_map(|n| indent_fold(content, n))
+        .find(|fold| fold.contains(&line_number))
+}
+
+/// every fold of the content, outermost first
+pub fn all_folds(content: &Rope) -> Vec<Range<usize>> {
+    (0..content.len_lines())
+        .filter_map(|n| indent_fold(content, n))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds() {
+        let content = Rope::from("fn a() {\n    if b {\n        c\n\n    }\n}\nd\n");
+        assert_eq!(indent_fold(&content, 0), Some(0..5));
+        assert_eq!(indent_fold(&content, 1), Some(1..3));
+        assert_eq!(indent_fold(&content, 2), None);
+        assert_eq!(indent_fold(&content, 6), None);
+        assert_eq!(fold_around(&content, 2), Some(1..3));
+        assert_eq!(fold_around(&content, 4), Some(0..5));
+        assert_eq!(fold_around(&content, 6), None);
+        assert_eq!(all_folds(&content), vec![0..5, 1..3]);
+    }
+}
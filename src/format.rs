@@ -0,0 +1,68 @@
+use std::io;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// pipes input through a command line (e.g. `black -q -`) run from dir (the current
+/// directory if empty), returns its output
+///
+/// a non-zero exit status is an error carrying the formatter's stderr
+pub fn run(command: &str, input: &str, dir: &str) -> io::Result<String> {
+    let mut words = command.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Empty formatter command"))?;
+    let mut child = Command::new(program);
+    child.args(words);
+    pipe(child, program, input, dir)
+}
+
+/// runs a line with `sh -c`, so it may use pipes and redirections, like `run`
+pub fn shell(line: &str, input: &str, dir: &str) -> io::Result<String> {
+    let mut child = Command::new("sh");
+    child.arg("-c").arg(line);
+    pipe(child, "sh", input, dir)
+}
+
+fn pipe(mut child: Command, program: &str, input: &str, dir: &str) -> io::Result<String> {
+    if !dir.is_empty() {
+        child.current_dir(dir);
+    }
+    let mut child = child
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // write from another thread so a formatter filling its stdout can't deadlock us
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output()?;
+    let _ = writer.join();
+    if output.status.success() {
+        String::from_utf8(output.stdout)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            if stderr.is_empty() {
+                format!("{} exited with {}", program, output.status)
+            } else {
+                stderr
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn run() {
+        assert_eq!(super::run("tr a-z A-Z", "abc\n", "").unwrap(), "ABC\n");
+        assert!(super::run("pwd", "", "src").unwrap().ends_with("/src\n"));
+        assert!(super::run("false", "", "").is_err());
+        assert!(super::run("", "", "").is_err());
+        assert_eq!(super::shell("echo a | tr a b", "", "").unwrap(), "b\n");
+        assert!(super::shell("exit 3", "", "").is_err());
+    }
+}
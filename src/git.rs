@@ -0,0 +1,194 @@
+use crate::diff::{diff_lines, Diff};
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// how a line differs from the committed version of its file
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LineChange {
+    Added,
+    Modified,
+    /// lines were removed right above this one
+    Deleted,
+}
+
+/// runs git in the directory of path, returns its stdout
+fn git(path: &Path, args: &[&str]) -> io::Result<String> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// content of the file as of the HEAD commit
+pub fn head_content(path: &Path) -> io::Result<String> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Not a file"))?;
+    git(
+        path,
+        &["show", &format!("HEAD:./{}", name.to_string_lossy())],
+    )
+}
+
+/// who last changed a line
+#[derive(Debug, Eq, PartialEq)]
+pub struct Blame {
+    pub hash: String,
+    pub author: String,
+    /// YYYY-MM-DD (UTC)
+    pub date: String,
+    pub summary: String,
+}
+
+impl Blame {
+    /// whether the line only exists in the working tree
+    pub fn is_uncommitted(&self) -> bool {
+        self.hash.chars().all(|c| c == '0')
+    }
+}
+
+/// YYYY-MM-DD of a unix timestamp
+fn format_date(secs: i64) -> String {
+    // days to civil date, from Howard Hinnant's date algorithms
+    let z = secs.div_euclid(86400) + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// reads the output of `git blame --porcelain` for a single line
+pub fn parse_blame(porcelain: &str) -> Option<Blame> {
+    let mut lines = porcelain.lines();
+    let hash = lines.next()?.split_whitespace().next()?.to_string();
+    let mut blame = Blame {
+        hash,
+        author: String::default(),
+        date: String::default(),
+        summary: String::default(),
+    };
+    for line in lines {
+        if let Some(author) = line.strip_prefix("author ") {
+            blame.author = author.to_string();
+        } else if let Some(time) = line.strip_prefix("author-time ") {
+            blame.date = time.parse().map(format_date).unwrap_or_default();
+        } else if let Some(summary) = line.strip_prefix("summary ") {
+            blame.summary = summary.to_string();
+        }
+    }
+    Some(blame)
+}
+
+/// blames the given 0-based line of the file as saved on disk
+pub fn blame(path: &Path, line_number: usize) -> io::Result<Blame> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Not a file"))?;
+    let range = format!("{},{}", line_number + 1, line_number + 1);
+    let output = git(
+        path,
+        &[
+            "blame",
+            "--porcelain",
+            "-L",
+            &range,
+            "--",
+            &name.to_string_lossy(),
+        ],
+    )?;
+    parse_blame(&output)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Unexpected git blame output"))
+}
+
+/// (line_number, change) of the lines of new differing from old
+pub fn line_changes(old: &str, new: &str) -> Vec<(usize, LineChange)> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let mut changes = vec![];
+    let mut line_number = 0;
+    let mut removed = 0;
+    for diff in diff_lines(&old, &new) {
+        match diff {
+            Diff::Same(_) => {
+                if removed > 0 {
+                    changes.push((line_number, LineChange::Deleted));
+                }
+                removed = 0;
+                line_number += 1;
+            }
+            Diff::Removed(_) => removed += 1,
+            Diff::Added(_) => {
+                // an added line replacing a removed one was modified
+                let change = if removed > 0 {
+                    removed -= 1;
+                    LineChange::Modified
+                } else {
+                    LineChange::Added
+                };
+                changes.push((line_number, change));
+                line_number += 1;
+            }
+        }
+    }
+    if removed > 0 {
+        changes.push((line_number, LineChange::Deleted));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes() {
+        let old = "a\nb\nc\nd\n";
+        let new = "a\nB\nc\nnew\n";
+        assert_eq!(
+            line_changes(old, new),
+            vec![(1, LineChange::Modified), (3, LineChange::Modified)]
+        );
+        assert_eq!(
+            line_changes("a\nb\nc\n", "a\nc\nd\n"),
+            vec![(1, LineChange::Deleted), (2, LineChange::Added)]
+        );
+        assert_eq!(
+            line_changes("a\nb\n", "a\n"),
+            vec![(1, LineChange::Deleted)]
+        );
+        assert!(line_changes("a\n", "a\n").is_empty());
+    }
+
+    #[test]
+    fn blame() {
+        let porcelain = "0123abcd 3 3 1\nauthor Ted\nauthor-mail <ted@example.com>\n\
+                         author-time 1622505600\nauthor-tz +0000\nsummary Fix it\n\tcode\n";
+        assert_eq!(
+            parse_blame(porcelain),
+            Some(Blame {
+                hash: String::from("0123abcd"),
+                author: String::from("Ted"),
+                date: String::from("2021-06-01"),
+                summary: String::from("Fix it"),
+            })
+        );
+        assert!(parse_blame("0000000 1 1 1\n").unwrap().is_uncommitted());
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951782400), "2000-02-29");
+    }
+}
@@ -0,0 +1,77 @@
+//! Grapheme cluster boundaries over a rope, so an emoji with modifiers or a letter with
+//! combining accents is moved over and deleted as a single character
+
+use ropey::Rope;
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
+
+/// char index of the grapheme boundary after pos, or the end of the rope
+pub fn next_boundary(rope: &Rope, pos: usize) -> usize {
+    let len = rope.len_bytes();
+    let byte = rope.char_to_byte(pos.min(rope.len_chars()));
+    let (mut chunk, mut chunk_start, _, _) = rope.chunk_at_byte(byte);
+    let mut cursor = GraphemeCursor::new(byte, len, true);
+    loop {
+        match cursor.next_boundary(chunk, chunk_start) {
+            Ok(Some(boundary)) => return rope.byte_to_char(boundary),
+            Ok(None) => return rope.len_chars(),
+            Err(GraphemeIncomplete::NextChunk) => {
+                chunk_start += chunk.len();
+                chunk = rope.chunk_at_byte(chunk_start).0;
+            }
+            Err(GraphemeIncomplete::PreContext(n)) => {
+                let context = rope.chunk_at_byte(n - 1).0;
+                cursor.provide_context(context, n - context.len());
+            }
+            Err(_) => return pos + 1,
+        }
+    }
+}
+
+/// char index of the grapheme boundary before pos, or 0
+pub fn prev_boundary(rope: &Rope, pos: usize) -> usize {
+    let len = rope.len_bytes();
+    let byte = rope.char_to_byte(pos.min(rope.len_chars()));
+    let (mut chunk, mut chunk_start, _, _) = rope.chunk_at_byte(byte);
+    let mut cursor = GraphemeCursor::new(byte, len, true);
+    loop {
+        match cursor.prev_boundary(chunk, chunk_start) {
+            Ok(Some(boundary)) => return rope.byte_to_char(boundary),
+            Ok(None) => return 0,
+            Err(GraphemeIncomplete::PrevChunk) => {
+                let (previous, start, _, _) = rope.chunk_at_byte(chunk_start - 1);
+                chunk = previous;
+                chunk_start = start;
+            }
+            Err(GraphemeIncomplete::PreContext(n)) => {
+                let context = rope.chunk_at_byte(n - 1).0;
+                cursor.provide_context(context, n - context.len());
+            }
+            Err(_) => return pos.saturating_sub(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boundaries() {
+        // thumbs up with a skin tone, e and a combining acute accent, a ZWJ family, a flag
+        let text = "a👍🏽e\u{301}👨\u{200d}👩\u{200d}👧🇫🇷\r\nb";
+        let rope = Rope::from_str(text);
+        let mut forward = vec![0];
+        while *forward.last().unwrap() < rope.len_chars() {
+            forward.push(next_boundary(&rope, *forward.last().unwrap()));
+        }
+        assert_eq!(forward, vec![0, 1, 3, 5, 10, 12, 14, 15]);
+        let mut backward = vec![rope.len_chars()];
+        while *backward.last().unwrap() > 0 {
+            backward.push(prev_boundary(&rope, *backward.last().unwrap()));
+        }
+        backward.reverse();
+        assert_eq!(backward, forward);
+        assert_eq!(next_boundary(&rope, 15), 15);
+        assert_eq!(prev_boundary(&rope, 0), 0);
+    }
+}
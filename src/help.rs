@@ -0,0 +1,158 @@
+//! The home buffer: the guide of `assets/HELP.md` followed by the commands and chords as bound
+//! now, with each line split into headings, keys, command names and descriptions to style them,
+//! and the page of each command
+
+use crate::command::{format_space_chain, Command, Commands};
+use crate::keymap::Keymap;
+use std::ops::Range;
+
+const HELP: &str = include_str!("../assets/HELP.md");
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Heading,
+    /// between backquotes, like `SPC f o` or `C-s`
+    Key,
+    /// between parentheses right after a key
+    Command,
+    Text,
+}
+
+/// the help text, listing the commands by space chain and the chords of the keymap
+pub fn document<T>(commands: &Commands<T>, keymap: &Keymap) -> String {
+    let mut text = String::from(HELP);
+    for command in &commands.commands {
+        let chain = command
+            .chain
+            .as_deref()
+            .map(format_space_chain)
+            .unwrap_or_else(|| "unbound".to_string());
+        text.push_str(&format!(
+            "- `{}` ({}): {}\n",
+            chain, command.name, command.desc
+        ));
+    }
+    text.push_str("\n## Chords\n\nIn NORMAL mode, set with `keys` in `config.json`.\n\n");
+    for (chord, name) in keymap.entries() {
+        let desc = commands
+            .get_by_name(name)
+            .map(|command| command.desc.as_str())
+            .unwrap_or("undefined command");
+        text.push_str(&format!("- `{}` ({}): {}\n", chord, name, desc));
+    }
+    text
+}
+
+/// the page of `help <name>`: how to run the command, then its long-form documentation
+pub fn command_help<T>(command: &Command<T>) -> String {
+    let chain = command
+        .chain
+        .as_deref()
+        .map(|chain| format!("`{}`", format_space_chain(chain)))
+        .unwrap_or_else(|| "unbound".to_string());
+    let usage = match command.arguments {
+        Some(_) => format!(
+            "`{} <arguments>`, or without them to be prompted",
+            command.name
+        ),
+        None => format!("`{}`, takes no arguments", command.name),
+    };
+    let mut text = format!(
+        "# {}\n\n{}\n\n- Chain: {}\n- Usage: {}\n",
+        command.name, command.desc, chain, usage
+    );
+    if let Some(help) = &command.help {
+        text.push_str(&format!("\n{}\n", help));
+    }
+    text
+}
+
+/// char ranges of a line by kind, in order and covering it
+pub fn spans(line: &str) -> Vec<(Kind, Range<usize>)> {
+    let chars: Vec<char> = line.trim_end_matches(&['\r', '\n'][..]).chars().collect();
+    if chars.first() == Some(&'#') {
+        return vec![(Kind::Heading, 0..chars.len())];
+    }
+    let mut spans: Vec<(Kind, Range<usize>)> = vec![];
+    let mut push = |kind: Kind, range: Range<usize>| match spans.last_mut() {
+        Some((last, r)) if *last == kind && r.end == range.start => r.end = range.end,
+        _ if range.is_empty() => {}
+        _ => spans.push((kind, range)),
+    };
+    let mut i = 0;
+    while i < chars.len() {
+        let closing = (i + 1..chars.len()).find(|&j| chars[j] == '`');
+        match closing {
+            Some(end) if chars[i] == '`' => {
+                push(Kind::Text, i..i + 1);
+                push(Kind::Key, i + 1..end);
+                push(Kind::Text, end..end + 1);
+                i = end + 1;
+                // a command name in parentheses right after the key
+                if chars.get(i..i + 2) == Some(&[' ', '(']) {
+                    if let Some(close) = (i + 2..chars.len()).find(|&j| chars[j] == ')') {
+                        push(Kind::Text, i..i + 2);
+                        push(Kind::Command, i + 2..close);
+                        push(Kind::Text, close..close + 1);
+                        i = close + 1;
+                    }
+                }
+            }
+            _ => {
+                push(Kind::Text, i..i + 1);
+                i += 1;
+            }
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_lines() {
+        assert_eq!(spans("## NORMAL mode\n"), vec![(Kind::Heading, 0..14)]);
+        assert_eq!(
+            spans("- `SPC f o` (file_open): Opens"),
+            vec![
+                (Kind::Text, 0..3),
+                (Kind::Key, 3..10),
+                (Kind::Text, 10..13),
+                (Kind::Command, 13..22),
+                (Kind::Text, 22..30),
+            ]
+        );
+        assert_eq!(
+            spans("press `/` or ` alone"),
+            vec![(Kind::Text, 0..7), (Kind::Key, 7..8), (Kind::Text, 8..20)]
+        );
+        let commands: Commands<()> = Commands { commands: vec![] };
+        let text = document(&commands, &Keymap::builtin());
+        assert!(text.starts_with("# Welcome to Ted"));
+        assert!(text.contains("- `C-s` (file_save): undefined command\n"));
+    }
+
+    #[test]
+    fn command_page() {
+        let mut command: Command<()> = Command {
+            name: "pwd".to_string(),
+            desc: "Shows the working directory".to_string(),
+            help: None,
+            chain: None,
+            action: |_| {},
+            arguments: None,
+        };
+        assert_eq!(
+            command_help(&command),
+            "# pwd\n\nShows the working directory\n\n- Chain: unbound\n\
+             - Usage: `pwd`, takes no arguments\n"
+        );
+        command.chain = Some(" wd".to_string());
+        command.help = Some("Relative to the buffer.".to_string());
+        let text = command_help(&command);
+        assert!(text.contains("- Chain: `SPC w d`\n"));
+        assert!(text.ends_with("\n\nRelative to the buffer.\n"));
+    }
+}
@@ -0,0 +1,126 @@
+//! Hex dumps of binary files, edited as text and parsed back to bytes on save
+//!
+//! ```text
+//! 00000000  7f 45 4c 46 02 01 01 00  00 00 00 00 00 00 00 00  |.ELF............|
+//! ```
+
+use std::io;
+
+pub const BYTES_PER_LINE: usize = 16;
+
+/// column of the first hex byte, after the offset
+const HEX_COLUMN: usize = 10;
+
+/// content with NUL bytes near its start, or that is not UTF-8
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(8000)].contains(&0) || std::str::from_utf8(bytes).is_err()
+}
+
+/// column of the i-th byte of a line
+fn byte_column(i: usize) -> usize {
+    HEX_COLUMN + 3 * i + (i >= BYTES_PER_LINE / 2) as usize
+}
+
+/// lines of offset, hex bytes and printable ASCII
+pub fn dump(bytes: &[u8]) -> String {
+    let mut text = String::default();
+    for (n, chunk) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+        let mut line = format!("{:08x}  ", n * BYTES_PER_LINE);
+        for (i, byte) in chunk.iter().enumerate() {
+            line.push_str(&format!("{:02x} ", byte));
+            if i + 1 == BYTES_PER_LINE / 2 {
+                line.push(' ');
+            }
+        }
+        let ascii: String = chunk
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        text.push_str(&format!(
+            "{:width$}|{}|\n",
+            line,
+            ascii,
+            width = byte_column(BYTES_PER_LINE) + 1
+        ));
+    }
+    text
+}
+
+/// bytes of a dump: the hex pairs between the offset and ASCII columns of each line,
+/// so bytes can be changed, added or removed
+pub fn parse(text: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    for (n, line) in text.lines().enumerate() {
+        let hex: String = line
+            .chars()
+            .skip(HEX_COLUMN)
+            .take_while(|c| *c != '|')
+            .collect();
+        for pair in hex.split_whitespace() {
+            let byte = u8::from_str_radix(pair, 16)
+                .ok()
+                .filter(|_| pair.len() == 2);
+            bytes.push(byte.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid byte {} on line {}", pair, n + 1),
+                )
+            })?);
+        }
+    }
+    Ok(bytes)
+}
+
+/// offset of the byte displayed at a position of the dump
+pub fn offset_at(line_number: usize, column_number: usize) -> Option<usize> {
+    let i = (0..BYTES_PER_LINE).find(|i| {
+        let column = byte_column(*i);
+        (column..column + 2).contains(&column_number)
+    })?;
+    Some(line_number * BYTES_PER_LINE + i)
+}
+
+/// (line, column) of the byte at offset in the dump
+pub fn position_of(offset: usize) -> (usize, usize) {
+    (
+        offset / BYTES_PER_LINE,
+        byte_column(offset % BYTES_PER_LINE),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dump_and_parse() {
+        let bytes: Vec<u8> = (0..20).map(|i| i * 13).collect();
+        let text = dump(&bytes);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines[0],
+            "00000000  00 0d 1a 27 34 41 4e 5b  68 75 82 8f 9c a9 b6 c3  |...'4AN[hu......|"
+        );
+        assert_eq!(
+            lines[1],
+            "00000010  d0 dd ea f7                                       |....|"
+        );
+        assert_eq!(parse(&text).unwrap(), bytes);
+        assert_eq!(
+            parse("00000000  41 4g  |A|").unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(offset_at(1, 10), Some(16));
+        assert_eq!(offset_at(0, 35), Some(8));
+        assert_eq!(offset_at(0, 34), None);
+        assert_eq!(position_of(8), (0, 35));
+        assert!(is_binary(&[0x89, b'P', b'N', b'G']));
+        assert!(!is_binary("é\n".as_bytes()));
+    }
+}
@@ -0,0 +1,152 @@
+//! Command lines run when something happens to a buffer, e.g. formatting Rust files
+//! before they are saved:
+//!
+//! ```json
+//! { "hooks": { "on_save_pre": [{ "language": "Rust", "run": "format_buffer" }] } }
+//! ```
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io;
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Event {
+    /// a file was opened in a new buffer
+    Open,
+    /// the focused buffer is about to be written
+    SavePre,
+    /// the focused buffer was written
+    SavePost,
+    /// the focused buffer switched between normal and insert mode
+    ModeChange,
+    /// another buffer got the focus
+    BufferSwitch,
+}
+
+impl Event {
+    pub const ALL: [Event; 5] = [
+        Event::Open,
+        Event::SavePre,
+        Event::SavePost,
+        Event::ModeChange,
+        Event::BufferSwitch,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Open => "on_open",
+            Event::SavePre => "on_save_pre",
+            Event::SavePost => "on_save_post",
+            Event::ModeChange => "on_mode_change",
+            Event::BufferSwitch => "on_buffer_switch",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|event| event.name() == name)
+    }
+}
+
+/// a command line, only run for buffers of the given language if any
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Action {
+    pub language: Option<String>,
+    pub line: String,
+}
+
+impl Action {
+    /// `"line"` or `{ "language": "Markdown", "run": "line" }`
+    fn from_json(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(line) => Some(Self {
+                language: None,
+                line: line.to_string(),
+            }),
+            _ => Some(Self {
+                language: value["language"].as_str().map(String::from),
+                line: value["run"].as_str()?.to_string(),
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Hooks {
+    actions: HashMap<Event, Vec<Action>>,
+}
+
+impl Hooks {
+    pub fn add(&mut self, event: Event, action: Action) {
+        self.actions.entry(event).or_default().push(action);
+    }
+
+    /// adds the actions of a "hooks" object, mapping event names to arrays of actions
+    pub fn extend_from_json(&mut self, hooks: &Value) -> io::Result<()> {
+        let hooks = hooks
+            .as_object()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "hooks must be an object"))?;
+        for (name, actions) in hooks {
+            let event = Event::from_name(name).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("Unknown hook {}", name))
+            })?;
+            let actions = match actions {
+                Value::Array(actions) => actions.iter().collect(),
+                action => vec![action],
+            };
+            for action in actions {
+                let action = Action::from_json(action).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid action for {}", name),
+                    )
+                })?;
+                self.add(event, action);
+            }
+        }
+        Ok(())
+    }
+
+    /// command lines to run on event in a buffer of the given language
+    pub fn lines(&self, event: Event, language: Option<&str>) -> Vec<String> {
+        self.actions
+            .get(&event)
+            .into_iter()
+            .flatten()
+            .filter(|action| action.language.is_none() || action.language.as_deref() == language)
+            .map(|action| action.line.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn hook_actions() {
+        let mut hooks = Hooks::default();
+        hooks
+            .extend_from_json(&json!({
+                "on_save_pre": [{ "language": "Rust", "run": "format_buffer" }, "lint"],
+                "on_open": { "language": "Markdown", "run": "theme InspiredGitHub buffer" },
+            }))
+            .unwrap();
+        assert_eq!(
+            hooks.lines(Event::SavePre, Some("Rust")),
+            vec!["format_buffer", "lint"]
+        );
+        assert_eq!(hooks.lines(Event::SavePre, None), vec!["lint"]);
+        assert_eq!(hooks.lines(Event::Open, Some("Rust")), Vec::<String>::new());
+        assert_eq!(
+            hooks.lines(Event::Open, Some("Markdown")),
+            vec!["theme InspiredGitHub buffer"]
+        );
+        assert!(hooks.extend_from_json(&json!({ "on_exit": [] })).is_err());
+        assert!(hooks.extend_from_json(&json!({ "on_open": [1] })).is_err());
+        assert_eq!(
+            Event::from_name("on_buffer_switch"),
+            Some(Event::BufferSwitch)
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Keys pressed with Control or Alt in normal mode, like `C-s` or `M-f`, bound to commands, and
+//! the keymaps of the modes registered by the config or plugins, which can bind single keys too
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// a key with modifiers, displayed as e.g. `C-M-x`
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Chord {
+    pub control: bool,
+    pub alt: bool,
+    pub key: char,
+}
+
+impl Chord {
+    pub fn control(key: char) -> Self {
+        Self {
+            control: true,
+            alt: false,
+            key,
+        }
+    }
+
+    pub fn alt(key: char) -> Self {
+        Self {
+            control: false,
+            alt: true,
+            key,
+        }
+    }
+
+    /// a key without modifiers
+    pub fn key(key: char) -> Self {
+        Self {
+            control: false,
+            alt: false,
+            key,
+        }
+    }
+
+    /// reads `C-` and `M-` prefixes before a single key, at least one of them
+    pub fn parse(s: &str) -> Option<Self> {
+        let (mut control, mut alt) = (false, false);
+        let mut rest = s.trim();
+        loop {
+            if let Some(tail) = rest.strip_prefix("C-").filter(|tail| !tail.is_empty()) {
+                control = true;
+                rest = tail;
+            } else if let Some(tail) = rest.strip_prefix("M-").filter(|tail| !tail.is_empty()) {
+                alt = true;
+                rest = tail;
+            } else {
+                break;
+            }
+        }
+        let mut chars = rest.chars();
+        let key = chars.next().filter(|_| chars.next().is_none())?;
+        Some(Self { control, alt, key }).filter(|_| control || alt)
+    }
+
+    /// like parse, a single key without modifiers being accepted too
+    pub fn parse_key(s: &str) -> Option<Self> {
+        let mut chars = s.trim().chars();
+        match (chars.next(), chars.next()) {
+            (Some(key), None) => Some(Self::key(key)),
+            _ => Self::parse(s),
+        }
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.control {
+            write!(f, "C-")?;
+        }
+        if self.alt {
+            write!(f, "M-")?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+/// names of the commands run by chords
+#[derive(Default)]
+pub struct Keymap {
+    commands: HashMap<Chord, String>,
+    /// single keys can be bound as well
+    plain: bool,
+}
+
+impl Keymap {
+    /// an empty keymap binding single keys too
+    pub fn plain() -> Self {
+        Self {
+            commands: HashMap::new(),
+            plain: true,
+        }
+    }
+
+    pub fn builtin() -> Self {
+        let commands = [
+            (Chord::control('e'), "scroll_line_down"),
+            (Chord::control('y'), "scroll_line_up"),
+            (Chord::control('d'), "scroll_half_page_down"),
+            (Chord::control('u'), "scroll_half_page_up"),
+            (Chord::control('s'), "file_save"),
+            (Chord::alt('f'), "forward_word"),
+            (Chord::alt('b'), "backward_word"),
+            (Chord::alt('y'), "paste_pop"),
+            (Chord::alt('o'), "expand_selection"),
+            (Chord::alt('i'), "shrink_selection"),
+        ]
+        .iter()
+        .map(|(chord, name)| (*chord, name.to_string()))
+        .collect();
+        Self {
+            commands,
+            plain: false,
+        }
+    }
+
+    /// binds a chord to a command, or unbinds it
+    pub fn bind(&mut self, chord: Chord, name: Option<String>) {
+        match name {
+            Some(name) => self.commands.insert(chord, name),
+            None => self.commands.remove(&chord),
+        };
+    }
+
+    /// applies the user's chords by display, e.g. "C-s": "file_save" (an empty name unbinds
+    /// it), returns the ones that failed
+    pub fn bind_all(&mut self, keys: &HashMap<String, String>) -> Vec<String> {
+        let mut errors = vec![];
+        for (display, name) in keys {
+            let chord = match self.plain {
+                true => Chord::parse_key(display),
+                false => Chord::parse(display),
+            };
+            match chord {
+                Some(chord) if name.trim().is_empty() => self.bind(chord, None),
+                Some(chord) => self.bind(chord, Some(name.trim().to_string())),
+                None => errors.push(format!("{}: invalid key, expected e.g. C-s", display)),
+            }
+        }
+        errors
+    }
+
+    pub fn get(&self, chord: &Chord) -> Option<&str> {
+        self.commands.get(chord).map(String::as_str)
+    }
+
+    /// (chord, command name) bindings, by chord as displayed
+    pub fn entries(&self) -> Vec<(Chord, &str)> {
+        let mut entries: Vec<(Chord, &str)> = self
+            .commands
+            .iter()
+            .map(|(chord, name)| (*chord, name.as_str()))
+            .collect();
+        entries.sort_by_key(|(chord, _)| chord.to_string());
+        entries
+    }
+}
+
+/// a mode registered by the config or a plugin, taking keys once entered with `enter_mode`, or
+/// keys added to a builtin mode of the same name
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModeSpec {
+    /// commands by key as displayed, e.g. "n" or "C-s"
+    pub keys: HashMap<String, String>,
+    /// keys left unbound go on to the modes below instead of being ignored
+    pub fallthrough: bool,
+}
+
+impl ModeSpec {
+    /// reads `{ "keys": { "n": "next_buffer" }, "fallthrough": false }`, falling through
+    /// by default
+    pub fn from_json(value: &Value) -> Option<Self> {
+        let keys = value.get("keys").and_then(Value::as_object)?;
+        Some(Self {
+            keys: keys
+                .iter()
+                .filter_map(|(key, name)| Some((key.to_string(), name.as_str()?.to_string())))
+                .collect(),
+            fallthrough: value
+                .get("fallthrough")
+                .and_then(Value::as_bool)
+                .unwrap_or(true),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_bind() {
+        assert_eq!(Chord::parse("C-s"), Some(Chord::control('s')));
+        assert_eq!(Chord::parse("M-f"), Some(Chord::alt('f')));
+        let chord = Chord::parse("C-M--").unwrap();
+        assert!(chord.control && chord.alt && chord.key == '-');
+        assert_eq!(chord.to_string(), "C-M--");
+        assert_eq!(Chord::parse("s"), None);
+        assert_eq!(Chord::parse("C-"), None);
+        assert_eq!(Chord::parse("C-ss"), None);
+        let mut keymap = Keymap::builtin();
+        assert_eq!(keymap.get(&Chord::control('s')), Some("file_save"));
+        let keys = [("C-s", ""), ("M-x", "pick_command"), ("x", "quit")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        assert_eq!(keymap.bind_all(&keys).len(), 1);
+        assert_eq!(keymap.get(&Chord::control('s')), None);
+        assert_eq!(keymap.get(&Chord::alt('x')), Some("pick_command"));
+        assert_eq!(
+            keymap.entries()[0],
+            (Chord::control('d'), "scroll_half_page_down")
+        );
+        let mut plain = Keymap::plain();
+        assert!(plain.bind_all(&keys).is_empty());
+        assert_eq!(plain.get(&Chord::key('x')), Some("quit"));
+        assert_eq!(Chord::key('x').to_string(), "x");
+        let spec = ModeSpec::from_json(&serde_json::json!({ "keys": { "n": "next" } })).unwrap();
+        assert!(spec.fallthrough && spec.keys["n"] == "next");
+        assert_eq!(ModeSpec::from_json(&serde_json::json!({})), None);
+    }
+}
@@ -0,0 +1,84 @@
+//! Texts copied and deleted, the latest one pasted by `p` and older ones picked or cycled
+//! through with `paste_pop`
+
+/// most texts remembered
+pub const CAPACITY: usize = 32;
+
+#[derive(Default)]
+pub struct KillRing {
+    /// most recent first
+    entries: Vec<String>,
+}
+
+impl KillRing {
+    /// moves text to the front of the ring, empty texts are ignored
+    pub fn push(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        self.entries.retain(|entry| entry != &text);
+        self.entries.insert(0, text);
+        self.entries.truncate(CAPACITY);
+    }
+
+    /// what `p` pastes, empty before anything was copied
+    pub fn latest(&self) -> &str {
+        self.entries.first().map(String::as_str).unwrap_or_default()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// most recent first
+    pub fn entries(&self) -> &Vec<String> {
+        &self.entries
+    }
+
+    /// index of the entry older than the one at index, back to the latest after the oldest
+    pub fn next_index(&self, index: usize) -> usize {
+        (index + 1) % self.entries.len().max(1)
+    }
+}
+
+/// "index: first line", for pickers
+pub fn label(index: usize, text: &str) -> String {
+    let first = text.lines().next().unwrap_or_default();
+    let more = if text.trim_end().contains('\n') {
+        " …"
+    } else {
+        ""
+    };
+    format!("{}: {}{}", index, first, more)
+}
+
+/// index of the entry a label was made from
+pub fn parse_label(label: &str) -> Option<usize> {
+    label.split_once(": ")?.0.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_and_cycle() {
+        let mut ring = KillRing::default();
+        assert_eq!(ring.latest(), "");
+        assert_eq!(ring.next_index(0), 0);
+        for text in ["a", "b", "", "a"].iter() {
+            ring.push(text.to_string());
+        }
+        assert_eq!(ring.entries(), &vec![String::from("a"), String::from("b")]);
+        assert_eq!(ring.next_index(0), 1);
+        assert_eq!(ring.next_index(1), 0);
+        for i in 0..CAPACITY {
+            ring.push(i.to_string());
+        }
+        assert_eq!(ring.entries().len(), CAPACITY);
+        assert_eq!(ring.get(0), Some("31"));
+        assert_eq!(label(2, "fn main() {\n}\n"), "2: fn main() { …");
+        assert_eq!(label(0, "line\n"), "0: line");
+        assert_eq!(parse_label("2: fn main() { …"), Some(2));
+    }
+}
@@ -0,0 +1,65 @@
+//! Ted's editing core, independent of any terminal or UI library.
+//!
+//! - [`Buffer`] holds text in a rope with a cursor, selection, snapshots and an optional
+//!   syntax highlighter
+//! - [`Buffers`] is the ring of open buffers
+//! - [`Commands`] is a registry of named commands acting on a frontend state
+//! - [`Config`] holds the syntax and theme sets along with the user's options
+//!
+//! The `ted` binary is a crossterm/tui frontend built on top of it.
+
+pub mod audit;
+pub mod background;
+pub mod backup;
+pub mod buffer;
+pub mod buffers;
+pub mod cached_highlighter;
+pub mod color;
+pub mod command;
+pub mod completion;
+pub mod config;
+pub mod count;
+pub mod detect;
+pub mod dictionary;
+pub mod diff;
+pub mod digraph;
+pub mod editorconfig;
+pub mod error;
+pub mod expand;
+pub mod export;
+pub mod fold;
+pub mod format;
+pub mod git;
+pub mod grapheme;
+pub mod help;
+pub mod hex;
+pub mod hook;
+pub mod keymap;
+pub mod kill_ring;
+pub mod line_edit;
+pub mod lint;
+pub mod logger;
+pub mod lsp;
+pub mod modeline;
+pub mod options;
+pub mod path;
+pub mod plugin;
+pub mod positions;
+pub mod profile;
+pub mod prose;
+pub mod recent;
+pub mod recovery;
+pub mod regex;
+pub mod save;
+pub mod script;
+pub mod search;
+pub mod snippet;
+pub mod todo;
+pub mod tutor;
+pub mod undo;
+pub mod wrap;
+
+pub use buffer::Buffer;
+pub use buffers::Buffers;
+pub use command::{Command, Commands};
+pub use config::Config;
@@ -0,0 +1,144 @@
+//! A single line of text being typed with a cursor in it, like the prompt's answer, edited with
+//! the keys of a shell: insertion at the cursor, moves by char or to either end, and deletion of
+//! a char, the word or everything before the cursor
+
+/// text with a cursor, counted in chars
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LineEdit {
+    text: String,
+    cursor: usize,
+}
+
+impl LineEdit {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// replaces the text, the cursor going at its end
+    pub fn set(&mut self, text: String) {
+        self.cursor = text.chars().count();
+        self.text = text;
+    }
+
+    pub fn clear(&mut self) {
+        self.set(String::default());
+    }
+
+    /// byte index of the char at i
+    fn byte(&self, i: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(i)
+            .map_or(self.text.len(), |(byte, _)| byte)
+    }
+
+    pub fn insert(&mut self, c: char) {
+        let byte = self.byte(self.cursor);
+        self.text.insert(byte, c);
+        self.cursor += 1;
+    }
+
+    /// deletes the char before the cursor
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            let byte = self.byte(self.cursor);
+            self.text.remove(byte);
+        }
+    }
+
+    /// deletes the char under the cursor
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.chars().count() {
+            let byte = self.byte(self.cursor);
+            self.text.remove(byte);
+        }
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.text.chars().count());
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.text.chars().count();
+    }
+
+    /// deletes the whitespace then the word before the cursor, words ending at whitespace or
+    /// `/` so paths lose a component at a time
+    pub fn delete_word(&mut self) {
+        let before: Vec<char> = self.text.chars().take(self.cursor).collect();
+        let mut start = before.len();
+        while start > 0 && before[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        if start > 0 && before[start - 1] == '/' {
+            start -= 1;
+        }
+        while start > 0 && !before[start - 1].is_whitespace() && before[start - 1] != '/' {
+            start -= 1;
+        }
+        let (from, to) = (self.byte(start), self.byte(self.cursor));
+        self.text.replace_range(from..to, "");
+        self.cursor = start;
+    }
+
+    /// deletes everything before the cursor, the whole text when at its end
+    pub fn delete_to_start(&mut self) {
+        let to = self.byte(self.cursor);
+        self.text.replace_range(..to, "");
+        self.cursor = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_at_cursor() {
+        let mut line = LineEdit::default();
+        line.set(String::from("héllo"));
+        assert_eq!(line.cursor(), 5);
+        line.home();
+        line.right();
+        line.right();
+        line.insert('x');
+        assert_eq!((line.as_str(), line.cursor()), ("héxllo", 3));
+        line.backspace();
+        line.left();
+        line.delete();
+        assert_eq!((line.as_str(), line.cursor()), ("hllo", 1));
+        line.end();
+        line.right();
+        line.delete();
+        assert_eq!(line.cursor(), 4);
+        line.set(String::from("file_open src/editor/ "));
+        line.delete_word();
+        assert_eq!(line.as_str(), "file_open src/");
+        line.delete_word();
+        assert_eq!(line.as_str(), "file_open ");
+        line.set(String::from("ab cd"));
+        line.left();
+        line.delete_to_start();
+        assert_eq!((line.as_str(), line.cursor()), ("d", 0));
+        line.end();
+        line.delete_to_start();
+        assert!(line.is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+//! A `log` sink appending to ~/.local/share/ted/log, which `show_log` follows in a buffer
+
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// size from which the log is started over when the editor starts
+const MAX_SIZE: u64 = 1 << 20;
+
+struct Logger {
+    file: Mutex<File>,
+    level: LevelFilter,
+}
+
+/// `HH:MM:SS.mmm` UTC time of day
+fn timestamp(time: SystemTime) -> String {
+    let elapsed = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = elapsed.as_secs() % 86400;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        elapsed.subsec_millis()
+    )
+}
+
+/// e.g. `12:04:31.250 WARN ted::lsp: rust-analyzer exited`
+fn format_record(time: SystemTime, record: &Record) -> String {
+    format!(
+        "{} {} {}: {}\n",
+        timestamp(time),
+        record.level(),
+        record.target(),
+        record.args()
+    )
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.write_all(format_record(SystemTime::now(), record).as_bytes());
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// logs records up to level to the file at path, started over once it grew too big
+pub fn init(path: &Path, level: LevelFilter) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let too_big = matches!(fs::metadata(path), Ok(meta) if meta.len() > MAX_SIZE);
+    let file = OpenOptions::new()
+        .create(true)
+        .append(!too_big)
+        .write(true)
+        .truncate(too_big)
+        .open(path)?;
+    let logger = Logger {
+        file: Mutex::new(file),
+        level,
+    };
+    log::set_logger(Box::leak(Box::new(logger)))
+        .map_err(|err| io::Error::new(io::ErrorKind::AlreadyExists, err.to_string()))?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+    use std::time::Duration;
+
+    #[test]
+    fn format() {
+        let time = UNIX_EPOCH + Duration::from_millis((86400 * 3 + 3600 * 12 + 62) * 1000 + 5);
+        let record = Record::builder()
+            .args(format_args!("rust-analyzer exited"))
+            .level(Level::Warn)
+            .target("ted::lsp")
+            .build();
+        assert_eq!(
+            format_record(time, &record),
+            "12:01:02.005 WARN ted::lsp: rust-analyzer exited\n"
+        );
+    }
+}
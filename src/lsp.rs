@@ -0,0 +1,503 @@
+//! Minimal Language Server Protocol client speaking JSON-RPC over a server's stdio
+//!
+//! Documents are synced whole (no incremental edits) and positions are counted in
+//! characters, which matches servers for any text outside of the UTF-16 surrogate range.
+//! Messages are written to the server from a background thread so a slow server never blocks
+//! the editor.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+/// a problem the server reported, positions are 0-based (line, column)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn from_json(value: &Value) -> Option<Self> {
+        let position = |key: &str| {
+            let position = &value["range"][key];
+            Some((
+                position["line"].as_u64()? as usize,
+                position["character"].as_u64()? as usize,
+            ))
+        };
+        Some(Self {
+            start: position("start")?,
+            end: position("end")?,
+            severity: match value["severity"].as_u64() {
+                Some(2) => Severity::Warning,
+                Some(3) => Severity::Information,
+                Some(4) => Severity::Hint,
+                _ => Severity::Error,
+            },
+            message: value["message"].as_str()?.to_string(),
+        })
+    }
+}
+
+/// what a language server sent, as far as the editor cares
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    Diagnostics {
+        path: PathBuf,
+        diagnostics: Vec<Diagnostic>,
+    },
+    Response {
+        id: u64,
+        result: Value,
+    },
+    Exited,
+}
+
+/// writes a message with its Content-Length header
+pub fn encode(message: &Value) -> Vec<u8> {
+    let body = message.to_string();
+    let mut bytes = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+    bytes.extend(body.into_bytes());
+    bytes
+}
+
+/// reads the next message, failing with UnexpectedEof once the stream ends
+pub fn decode(reader: &mut impl BufRead) -> io::Result<Value> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Language server closed its output",
+            ));
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let length = length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// writes the bytes sent on the returned channel from a background thread, until the channel
+/// closes or a write fails
+pub fn spawn_writer(mut stdin: ChildStdin) -> Sender<Vec<u8>> {
+    let (writer, bytes) = mpsc::channel::<Vec<u8>>();
+    thread::spawn(move || {
+        for bytes in bytes {
+            if stdin.write_all(&bytes).and_then(|_| stdin.flush()).is_err() {
+                break;
+            }
+        }
+    });
+    writer
+}
+
+/// a file:// uri, bytes other than unreserved ones and `/` being percent-encoded
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// the path of a file:// uri, percent-encoded bytes decoded
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let hex = encoded
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok());
+        match hex.filter(|_| encoded[i] == b'%') {
+            Some(hex) => {
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            None => {
+                bytes.push(encoded[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// first (path, line, column) of a definition result: Location, Location[] or LocationLink[]
+pub fn parse_location(result: &Value) -> Option<(PathBuf, usize, usize)> {
+    let location = match result {
+        Value::Array(locations) => locations.first()?,
+        location => location,
+    };
+    let uri = location["uri"]
+        .as_str()
+        .or_else(|| location["targetUri"].as_str())?;
+    let range = if location["range"].is_object() {
+        &location["range"]
+    } else {
+        &location["targetSelectionRange"]
+    };
+    Some((
+        uri_to_path(uri)?,
+        range["start"]["line"].as_u64()? as usize,
+        range["start"]["character"].as_u64()? as usize,
+    ))
+}
+
+/// plain text of a hover result: MarkupContent, MarkedString or MarkedString[]
+pub fn hover_text(result: &Value) -> Option<String> {
+    fn marked(value: &Value) -> Option<String> {
+        value
+            .as_str()
+            .or_else(|| value["value"].as_str())
+            .map(String::from)
+    }
+    let text = match &result["contents"] {
+        Value::Array(contents) => contents
+            .iter()
+            .filter_map(marked)
+            .collect::<Vec<String>>()
+            .join("\n\n"),
+        contents => marked(contents)?,
+    };
+    Some(text.trim().to_string()).filter(|text| !text.is_empty())
+}
+
+/// texts to insert from a completion result: CompletionItem[] or CompletionList
+pub fn completion_items(result: &Value) -> Vec<String> {
+    result
+        .as_array()
+        .or_else(|| result["items"].as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    item["insertText"]
+                        .as_str()
+                        .or_else(|| item["label"].as_str())
+                })
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// a running language server, messages are read and written by background threads
+pub struct Client {
+    child: Child,
+    /// encoded messages for the writer thread
+    writer: Sender<Vec<u8>>,
+    messages: Receiver<Value>,
+    next_id: u64,
+    /// messages held until the server answers `initialize`
+    queue: Option<Vec<Value>>,
+    versions: HashMap<String, i64>,
+}
+
+impl Client {
+    /// spawns the server command line and starts initializing it for root
+    pub fn spawn(command: &str, root: &Path) -> io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Empty language server command")
+        })?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let writer = spawn_writer(child.stdin.take().expect("stdin is piped"));
+        let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(message) = decode(&mut stdout) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut client = Self {
+            child,
+            writer,
+            messages,
+            next_id: 1,
+            queue: None,
+            versions: HashMap::default(),
+        };
+        client.write(&json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "initialize",
+            "params": {
+                "processId": std::process::id(),
+                "rootUri": path_to_uri(root),
+                "capabilities": {
+                    "textDocument": {
+                        "publishDiagnostics": {},
+                        "hover": { "contentFormat": ["plaintext", "markdown"] },
+                        "definition": {},
+                        "completion": { "completionItem": { "snippetSupport": false } },
+                        "synchronization": { "didSave": true },
+                    }
+                },
+            },
+        }))?;
+        client.queue = Some(vec![]);
+        Ok(client)
+    }
+
+    /// hands message to the writer thread, failing once the server stopped reading
+    fn write(&mut self, message: &Value) -> io::Result<()> {
+        self.writer.send(encode(message)).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "Language server closed its input",
+            )
+        })
+    }
+
+    fn send(&mut self, message: Value) -> io::Result<()> {
+        match self.queue.as_mut() {
+            Some(queue) => {
+                queue.push(message);
+                Ok(())
+            }
+            None => self.write(&message),
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> io::Result<()> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> io::Result<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+        Ok(id)
+    }
+
+    pub fn did_open(&mut self, path: &Path, language_id: &str, text: &str) -> io::Result<()> {
+        let uri = path_to_uri(path);
+        self.versions.insert(uri.clone(), 0);
+        self.notify(
+            "textDocument/didOpen",
+            json!({ "textDocument": {
+                "uri": uri, "languageId": language_id, "version": 0, "text": text
+            }}),
+        )
+    }
+
+    /// sends the whole new text of a document
+    pub fn did_change(&mut self, path: &Path, text: &str) -> io::Result<()> {
+        let uri = path_to_uri(path);
+        let version = self.versions.entry(uri.clone()).or_insert(0);
+        *version += 1;
+        let version = *version;
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": uri, "version": version },
+                "contentChanges": [{ "text": text }],
+            }),
+        )
+    }
+
+    pub fn did_save(&mut self, path: &Path) -> io::Result<()> {
+        self.notify(
+            "textDocument/didSave",
+            json!({ "textDocument": { "uri": path_to_uri(path) } }),
+        )
+    }
+
+    fn position_params(path: &Path, line: usize, column: usize) -> Value {
+        json!({
+            "textDocument": { "uri": path_to_uri(path) },
+            "position": { "line": line, "character": column },
+        })
+    }
+
+    /// returns the id of the request, answered by a later Event::Response
+    pub fn definition(&mut self, path: &Path, line: usize, column: usize) -> io::Result<u64> {
+        self.request(
+            "textDocument/definition",
+            Self::position_params(path, line, column),
+        )
+    }
+
+    /// returns the id of the request, answered by a later Event::Response
+    pub fn hover(&mut self, path: &Path, line: usize, column: usize) -> io::Result<u64> {
+        self.request(
+            "textDocument/hover",
+            Self::position_params(path, line, column),
+        )
+    }
+
+    /// returns the id of the request, answered by a later Event::Response
+    pub fn completion(&mut self, path: &Path, line: usize, column: usize) -> io::Result<u64> {
+        self.request(
+            "textDocument/completion",
+            Self::position_params(path, line, column),
+        )
+    }
+
+    /// handles the messages received so far
+    pub fn poll(&mut self) -> Vec<Event> {
+        let mut events = vec![];
+        loop {
+            let message = match self.messages.try_recv() {
+                Ok(message) => message,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    events.push(Event::Exited);
+                    break;
+                }
+            };
+            match (message["id"].as_u64(), message["method"].as_str()) {
+                // the server asks something of us, decline politely
+                (Some(_), Some(_)) => {
+                    let reply = json!({ "jsonrpc": "2.0", "id": message["id"], "result": null });
+                    let _ = self.send(reply);
+                }
+                (Some(0), None) => {
+                    let _ = self.write(&json!({
+                        "jsonrpc": "2.0", "method": "initialized", "params": {}
+                    }));
+                    for message in self.queue.take().unwrap_or_default() {
+                        let _ = self.write(&message);
+                    }
+                }
+                (Some(id), None) => events.push(Event::Response {
+                    id,
+                    result: message["result"].clone(),
+                }),
+                (None, Some("textDocument/publishDiagnostics")) => {
+                    let params = &message["params"];
+                    if let Some(path) = params["uri"].as_str().and_then(uri_to_path) {
+                        events.push(Event::Diagnostics {
+                            path,
+                            diagnostics: params["diagnostics"]
+                                .as_array()
+                                .map(|diagnostics| {
+                                    diagnostics
+                                        .iter()
+                                        .filter_map(Diagnostic::from_json)
+                                        .collect()
+                                })
+                                .unwrap_or_default(),
+                        })
+                    }
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        let message = json!({ "jsonrpc": "2.0", "id": 1, "result": "é" });
+        let bytes = encode(&message);
+        assert!(bytes.starts_with(b"Content-Length: 38\r\n\r\n"));
+        let mut reader = io::Cursor::new([bytes.clone(), bytes].concat());
+        assert_eq!(decode(&mut reader).unwrap(), message);
+        assert_eq!(decode(&mut reader).unwrap(), message);
+        assert!(decode(&mut reader).is_err());
+    }
+
+    #[test]
+    fn uris() {
+        let path = Path::new("/tmp/no such dir/é%.rs");
+        let uri = path_to_uri(path);
+        assert_eq!(uri, "file:///tmp/no%20such%20dir/%C3%A9%25.rs");
+        assert_eq!(uri_to_path(&uri).as_deref(), Some(path));
+        assert_eq!(uri_to_path("file:///a%2"), Some(PathBuf::from("/a%2")));
+        assert_eq!(uri_to_path("http://a"), None);
+    }
+
+    #[test]
+    fn parse_results() {
+        let location = json!({
+            "uri": "file:///src/main.rs",
+            "range": { "start": { "line": 3, "character": 4 }, "end": { "line": 3, "character": 8 } },
+        });
+        let expected = Some((PathBuf::from("/src/main.rs"), 3, 4));
+        assert_eq!(parse_location(&location), expected);
+        assert_eq!(parse_location(&json!([location])), expected);
+        let link = json!([{
+            "targetUri": "file:///src/main.rs",
+            "targetSelectionRange": { "start": { "line": 3, "character": 4 } },
+        }]);
+        assert_eq!(parse_location(&link), expected);
+        assert_eq!(parse_location(&json!([])), None);
+
+        let markup = json!({ "contents": { "kind": "plaintext", "value": "fn main()" } });
+        assert_eq!(hover_text(&markup), Some(String::from("fn main()")));
+        let marked = json!({ "contents": ["a", { "language": "rust", "value": "b" }] });
+        assert_eq!(hover_text(&marked), Some(String::from("a\n\nb")));
+        assert_eq!(hover_text(&Value::Null), None);
+
+        let items = json!({ "items": [{ "label": "len", "insertText": "len()" }, { "label": "is_empty" }] });
+        assert_eq!(completion_items(&items), vec!["len()", "is_empty"]);
+        assert!(completion_items(&Value::Null).is_empty());
+
+        let diagnostic = json!({
+            "range": { "start": { "line": 1, "character": 2 }, "end": { "line": 1, "character": 5 } },
+            "severity": 2,
+            "message": "unused",
+        });
+        assert_eq!(
+            Diagnostic::from_json(&diagnostic),
+            Some(Diagnostic {
+                start: (1, 2),
+                end: (1, 5),
+                severity: Severity::Warning,
+                message: String::from("unused"),
+            })
+        );
+    }
+}
@@ -1,57 +1,224 @@
-mod ted;
+mod batch;
+mod cli;
+mod editor;
+mod subcommands;
 
-use self::ted::Ted;
-use crossterm::event::{read, Event};
+use self::cli::{Args, USAGE};
+use self::editor::{Ted, TermBackend};
+use crossterm::event::{poll, read, DisableBracketedPaste, EnableBracketedPaste, Event};
+use crossterm::event::{DisableFocusChange, EnableFocusChange};
 use crossterm::execute;
+use crossterm::style::Print;
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
-use std::{env, io, panic};
-use tui::backend::CrosstermBackend;
+use log::LevelFilter;
+use std::backtrace::Backtrace;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::{env, io, panic, process};
+use ted::config::data_dir;
+use ted::logger;
+use ted::profile::Profile;
+use ted::Config;
 use tui::Terminal;
 
-fn run() -> Result<(), io::Error> {
-    let stdout = io::stdout();
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+/// message and backtrace of the last panic, for the crash report
+static PANIC: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+/// xterm sequences saving the terminal's title on its stack, and restoring it
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+
+fn usage_error(err: &str) -> ! {
+    eprintln!("ted: {}\n\n{}", err, USAGE);
+    process::exit(2);
+}
+
+/// config from the --config file and --theme override
+fn load_config(args: &Args) -> Result<Config, String> {
+    let config = match &args.config {
+        Some(path) if !path.is_file() => {
+            return Err(format!("config file {} not found", path.display()))
+        }
+        Some(path) => {
+            let mut config = Config::builtin();
+            config.file = Some(path.clone());
+            config
+                .load_config_file()
+                .map_err(|err| format!("{}: {}", path.display(), err))?;
+            let errors = config.load_user_files();
+            config.load_errors.extend(errors);
+            config
+        }
+        None => Config::default(),
+    };
+    if let Some(theme) = &args.theme {
+        if !config.theme_set.themes.contains_key(theme) {
+            let mut themes: Vec<&String> = config.theme_set.themes.keys().collect();
+            themes.sort();
+            return Err(format!(
+                "unknown theme {}, available themes: {}",
+                theme,
+                themes
+                    .iter()
+                    .map(|t| t.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ));
+        }
+        *config.default_theme.borrow_mut() = theme.clone();
+    }
+    Ok(config)
+}
+
+fn run(args: Args, mut config: Config, mut profile: Option<Profile>) -> Result<(), io::Error> {
+    let level = config.log_level.get();
+    if let Some(path) = data_dir("log").filter(|_| level != LevelFilter::Off) {
+        if let Err(err) = logger::init(&path, level) {
+            config.load_errors.push(err.into());
+        }
+    }
+    let mut terminal = Terminal::new(TermBackend::crossterm())?;
 
     enable_raw_mode().expect("Failed to enable raw mode");
-    execute!(io::stdout(), EnterAlternateScreen)?;
+    execute!(
+        io::stdout(),
+        EnterAlternateScreen,
+        EnableBracketedPaste,
+        EnableFocusChange,
+        Print(PUSH_TITLE)
+    )?;
     terminal.clear()?;
+    let mut lap = |phase| {
+        if let Some(profile) = profile.as_mut() {
+            profile.lap(phase);
+        }
+    };
+    lap("terminal");
 
-    let mut ted = Ted::new(terminal);
+    let mut ted = Ted::new(terminal, config);
+    lap("editor");
 
-    for argument in env::args().skip(1) {
-        println!("{}", argument);
-        ted.file_open(argument);
+    for file in args.files {
+        ted.file_open_at(file.path, file.line, file.column, args.readonly);
     }
+    lap("open files");
     ted.draw()?;
+    lap("first draw");
+    let report = profile.map(|profile| profile.report(ted.config()));
+
+    // after a panic the hook restored the terminal, save what can be before exiting
+    if let Ok(result) = panic::catch_unwind(panic::AssertUnwindSafe(|| event_loop(&mut ted))) {
+        ted.save_session();
+        result?;
+    } else {
+        let (message, backtrace) = PANIC.lock().ok().and_then(|mut p| p.take()).unzip();
+        match ted.dump_crash(
+            message.as_deref().unwrap_or("unknown"),
+            backtrace.as_deref().unwrap_or_default(),
+        ) {
+            Ok((dir, report)) => eprintln!(
+                "ted crashed, unsaved buffers were dumped to {} and a report to {}",
+                dir.display(),
+                report.display()
+            ),
+            Err(err) => eprintln!("ted crashed, unsaved buffers were lost: {}", err),
+        }
+        process::exit(101);
+    }
+
+    disable_raw_mode().expect("Failed to disable raw mode");
+    execute!(
+        io::stdout(),
+        DisableBracketedPaste,
+        DisableFocusChange,
+        LeaveAlternateScreen,
+        Print(POP_TITLE)
+    )?;
+    if let Some(report) = report {
+        eprint!("{}", report);
+    }
+    Ok(())
+}
 
-    // TODO: loop with event polling
+fn event_loop(ted: &mut Ted) -> io::Result<()> {
     loop {
-        if let Event::Key(k) = read()? {
-            if ted.handle_key(k) {
+        ted.tick();
+        if poll(ted.next_frame())? {
+            // drain the queued events so a burst of keys is drawn once
+            let mut events = vec![];
+            loop {
+                match read()? {
+                    Event::Resize(width, height) => ted.resize(width, height),
+                    event => events.push(event),
+                }
+                if !poll(Duration::ZERO)? {
+                    break;
+                }
+            }
+            if ted.handle_events(events) {
                 break;
             }
         }
         ted.draw()?;
     }
-
-    disable_raw_mode().expect("Failed to disable raw mode");
-    execute!(io::stdout(), LeaveAlternateScreen)
+    Ok(())
 }
 
 fn main() -> Result<(), io::Error> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(result) = subcommands::run(&args) {
+        return result;
+    }
+    let args = Args::parse(&args).unwrap_or_else(|err| usage_error(&err));
+    if args.version {
+        println!("ted {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    let mut profile = args.profile_startup.then(Profile::default);
+    let config = load_config(&args).unwrap_or_else(|err| usage_error(&err));
+    if let Some(profile) = profile.as_mut() {
+        profile.lap("config");
+    }
+    if let Some(script) = args.batch.clone() {
+        if let Err(err) = batch::run(&script, args, config) {
+            eprintln!("ted: {}", err);
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
     let default_panic = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
+        if let Ok(mut panic) = PANIC.lock() {
+            *panic = Some((
+                panic_info.to_string(),
+                Backtrace::force_capture().to_string(),
+            ));
+        }
         disable_raw_mode().unwrap();
-        execute!(io::stdout(), LeaveAlternateScreen).unwrap();
+        execute!(
+            io::stdout(),
+            DisableBracketedPaste,
+            DisableFocusChange,
+            LeaveAlternateScreen,
+            Print(POP_TITLE)
+        )
+        .unwrap();
         default_panic(panic_info);
     }));
 
-    run().map_err(|err| {
+    run(args, config, profile).map_err(|err| {
         disable_raw_mode().unwrap();
-        execute!(io::stdout(), LeaveAlternateScreen).unwrap();
+        execute!(
+            io::stdout(),
+            DisableBracketedPaste,
+            DisableFocusChange,
+            LeaveAlternateScreen,
+            Print(POP_TITLE)
+        )
+        .unwrap();
         println!("main returned an error: {:?}", err);
         err
     })
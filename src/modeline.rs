@@ -0,0 +1,124 @@
+//! Settings written in a file itself, in vim's modelines or ted's own
+//!
+//! Both forms are looked for in the first and last lines, after whitespace or at the start:
+//! `# vim: set ts=2 sw=2 et:`, `// vim: ft=rust` or `# ted: lang=YAML theme=ted indent_size=2`.
+//! Only the language, theme and buffer-local options can be set, never a command.
+
+use crate::options;
+
+/// lines looked into at the start and end of a file
+pub const LINES: usize = 5;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Setting {
+    Language(String),
+    Theme(String),
+    /// a buffer-local option by name and value
+    Option(&'static str, String),
+}
+
+/// the settings of the modelines among lines, in order
+pub fn parse(lines: &[String]) -> Vec<Setting> {
+    lines.iter().flat_map(|line| parse_line(line)).collect()
+}
+
+fn parse_line(line: &str) -> Vec<Setting> {
+    for marker in ["ted:", "vim:", "vi:", "ex:"] {
+        let start = line.match_indices(marker).map(|(i, _)| i).find(|&i| {
+            line[..i]
+                .chars()
+                .next_back()
+                .map(char::is_whitespace)
+                .unwrap_or(true)
+        });
+        let rest = match start {
+            Some(i) => line[i + marker.len()..].trim(),
+            None => continue,
+        };
+        if marker == "ted:" {
+            return rest.split_whitespace().filter_map(ted_setting).collect();
+        }
+        // `set` options end at the next colon, others are separated by colons too
+        let words: Vec<&str> = match rest
+            .strip_prefix("set ")
+            .or_else(|| rest.strip_prefix("se "))
+        {
+            Some(options) => options
+                .split(':')
+                .next()
+                .unwrap_or_default()
+                .split_whitespace()
+                .collect(),
+            None => rest
+                .split(|c: char| c == ':' || c.is_whitespace())
+                .collect(),
+        };
+        return words.into_iter().filter_map(vim_setting).collect();
+    }
+    vec![]
+}
+
+fn ted_setting(word: &str) -> Option<Setting> {
+    let (key, value) = word.split_once('=')?;
+    match key {
+        "lang" => Some(Setting::Language(value.to_string())),
+        "theme" => Some(Setting::Theme(value.to_string())),
+        _ => options::NAMES
+            .iter()
+            .find(|name| **name == key)
+            .map(|name| Setting::Option(name, value.to_string())),
+    }
+}
+
+fn vim_setting(word: &str) -> Option<Setting> {
+    let (key, value) = word.split_once('=').unwrap_or((word, ""));
+    let option = |name, value: &str| Some(Setting::Option(name, value.to_string()));
+    match key {
+        "ft" | "filetype" | "syn" | "syntax" if !value.is_empty() => {
+            Some(Setting::Language(value.to_string()))
+        }
+        "sw" | "shiftwidth" | "sts" | "softtabstop" => option("indent_size", value),
+        "et" | "expandtab" => option("indent_style", "space"),
+        "noet" | "noexpandtab" => option("indent_style", "tab"),
+        "ai" | "autoindent" => option("auto_indent", "on"),
+        "noai" | "noautoindent" => option("auto_indent", "off"),
+        "list" => option("whitespace", "on"),
+        "nolist" => option("whitespace", "off"),
+        "so" | "scrolloff" => option("scrolloff", value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_modelines() {
+        let settings = |line: &str| parse(&[line.to_string()]);
+        assert_eq!(
+            settings("# vim: set ts=2 sw=2 et: trailing"),
+            vec![
+                Setting::Option("indent_size", "2".to_string()),
+                Setting::Option("indent_style", "space".to_string()),
+            ]
+        );
+        assert_eq!(
+            settings("// vim: ft=rust:noet"),
+            vec![
+                Setting::Language("rust".to_string()),
+                Setting::Option("indent_style", "tab".to_string()),
+            ]
+        );
+        assert_eq!(
+            settings("# ted: lang=YAML theme=ted scrolloff=0 rm=-rf"),
+            vec![
+                Setting::Language("YAML".to_string()),
+                Setting::Theme("ted".to_string()),
+                Setting::Option("scrolloff", "0".to_string()),
+            ]
+        );
+        assert_eq!(settings("see the index: ft=rust"), vec![]);
+        assert_eq!(settings("no modeline here"), vec![]);
+    }
+}
@@ -0,0 +1,161 @@
+//! Options of a single buffer, layered over the global config and the .editorconfig files
+//!
+//! Unset options fall back to the global value, so `set_local` in a hook tailors some buffers
+//! only, e.g. real tabs in Makefiles:
+//! { "hooks": { "on_open": [{ "language": "Makefile", "run": "set_local indent_style tab" }] } }
+
+use crate::config::{Config, Whitespace};
+use crate::editorconfig::{IndentStyle, Settings};
+use std::io;
+
+/// names of the options, in the order they are summarized
+pub const NAMES: [&str; 6] = [
+    "whitespace",
+    "auto_indent",
+    "auto_pairs",
+    "scrolloff",
+    "indent_style",
+    "indent_size",
+];
+
+/// overrides of one buffer, None when the global value applies
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BufferOptions {
+    /// shows every invisible character, or none of them
+    pub whitespace: Option<bool>,
+    pub auto_indent: Option<bool>,
+    pub auto_pairs: Option<bool>,
+    pub scrolloff: Option<usize>,
+    pub indent_style: Option<IndentStyle>,
+    pub indent_size: Option<usize>,
+}
+
+impl BufferOptions {
+    /// sets the named option, "default" going back to the global value
+    pub fn set(&mut self, name: &str, value: &str) -> io::Result<()> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid value {:?} for {}", value, name),
+            )
+        };
+        let reset = value == "default";
+        let flag = || match value {
+            "on" | "true" => Ok(true),
+            "off" | "false" => Ok(false),
+            _ => Err(invalid()),
+        };
+        match name {
+            "whitespace" => self.whitespace = if reset { None } else { Some(flag()?) },
+            "auto_indent" => self.auto_indent = if reset { None } else { Some(flag()?) },
+            "auto_pairs" => self.auto_pairs = if reset { None } else { Some(flag()?) },
+            "scrolloff" if reset => self.scrolloff = None,
+            "scrolloff" => self.scrolloff = Some(value.parse().map_err(|_| invalid())?),
+            "indent_style" => {
+                self.indent_style = match value {
+                    "default" => None,
+                    "tab" => Some(IndentStyle::Tab),
+                    "space" => Some(IndentStyle::Space),
+                    _ => return Err(invalid()),
+                }
+            }
+            "indent_size" if reset => self.indent_size = None,
+            "indent_size" => match value.parse() {
+                Ok(size) if size > 0 => self.indent_size = Some(size),
+                _ => return Err(invalid()),
+            },
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown option {}", name),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    pub fn whitespace(&self, config: &Config) -> Whitespace {
+        match self.whitespace {
+            Some(all) => Whitespace::all(all),
+            None => config.whitespace.get(),
+        }
+    }
+
+    pub fn auto_indent(&self, config: &Config) -> bool {
+        self.auto_indent.unwrap_or_else(|| config.auto_indent.get())
+    }
+
+    pub fn auto_pairs(&self, config: &Config) -> bool {
+        self.auto_pairs.unwrap_or_else(|| config.auto_pairs.get())
+    }
+
+    pub fn scrolloff(&self, config: &Config) -> usize {
+        self.scrolloff.unwrap_or_else(|| config.scrolloff.get())
+    }
+
+    /// the .editorconfig settings with the indentation overridden
+    pub fn indentation(&self, settings: &Settings) -> Settings {
+        Settings {
+            indent_style: self.indent_style.or(settings.indent_style),
+            indent_size: self.indent_size.or(settings.indent_size),
+            ..settings.clone()
+        }
+    }
+
+    /// the options set, like "indent_style=tab scrolloff=0", empty if there are none
+    pub fn summary(&self) -> String {
+        NAMES
+            .iter()
+            .filter_map(|name| {
+                let value = match *name {
+                    "whitespace" => self.whitespace.map(|on| on.to_string()),
+                    "auto_indent" => self.auto_indent.map(|on| on.to_string()),
+                    "auto_pairs" => self.auto_pairs.map(|on| on.to_string()),
+                    "scrolloff" => self.scrolloff.map(|n| n.to_string()),
+                    "indent_style" => self.indent_style.map(|style| match style {
+                        IndentStyle::Tab => "tab".to_string(),
+                        IndentStyle::Space => "space".to_string(),
+                    }),
+                    _ => self.indent_size.map(|size| size.to_string()),
+                };
+                value.map(|value| format!("{}={}", name, value))
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layer_over_global() {
+        let config = Config::builtin();
+        config.scrolloff.set(3);
+        let mut options = BufferOptions::default();
+        assert_eq!(options.scrolloff(&config), 3);
+        options.set("scrolloff", "0").unwrap();
+        options.set("indent_style", "tab").unwrap();
+        options.set("auto_pairs", "off").unwrap();
+        assert_eq!(options.scrolloff(&config), 0);
+        assert!(!options.auto_pairs(&config));
+        assert_eq!(
+            options.summary(),
+            "auto_pairs=false scrolloff=0 indent_style=tab"
+        );
+        let settings = Settings {
+            indent_style: Some(IndentStyle::Space),
+            indent_size: Some(2),
+            ..Settings::default()
+        };
+        assert_eq!(options.indentation(&settings).indent(0), "\t");
+        options.set("indent_style", "default").unwrap();
+        assert_eq!(options.indentation(&settings).indent(1), " ");
+        assert!(options.set("indent_size", "0").is_err());
+        assert!(options.set("tab_width", "4").is_err());
+        options.set("auto_pairs", "default").unwrap();
+        options.set("scrolloff", "default").unwrap();
+        assert_eq!(options, BufferOptions::default());
+    }
+}
@@ -0,0 +1,274 @@
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+/// resolves `.` and `..` components without touching the filesystem
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// returns the directory of path, with a trailing separator, ready to be completed
+pub fn directory_of(path: &str) -> String {
+    match normalize(Path::new(path)).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => format!("{}/", dir.display()),
+        _ => String::default(),
+    }
+}
+
+/// the file stem of each path, after as many of its parent directories as it takes to tell
+/// it apart from the others, e.g. `editor/mod` and `lsp/mod`
+pub fn unique_names(paths: &[&str]) -> Vec<String> {
+    let segments: Vec<Vec<String>> = paths
+        .iter()
+        .map(|path| {
+            let path = normalize(Path::new(path));
+            let mut segments: Vec<String> = path
+                .parent()
+                .into_iter()
+                .flat_map(Path::components)
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .filter(|c| c != "/")
+                .collect();
+            let stem = path.file_stem().unwrap_or_default();
+            segments.push(stem.to_string_lossy().to_string());
+            segments
+        })
+        .collect();
+    let suffix =
+        |segments: &[String], n: usize| segments[segments.len().saturating_sub(n)..].join("/");
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, own)| {
+            let n = (1..own.len())
+                .find(|&n| {
+                    let name = suffix(own, n);
+                    segments
+                        .iter()
+                        .enumerate()
+                        .all(|(j, other)| i == j || suffix(other, n) != name)
+                })
+                .unwrap_or(own.len());
+            suffix(own, n)
+        })
+        .collect()
+}
+
+/// the directory target leads to from dir (both as given by directory_of), None if it
+/// doesn't exist
+pub fn change_dir(dir: &str, target: &str) -> Option<String> {
+    let joined = normalize(&Path::new(dir).join(target.trim()));
+    if !Path::new(".").join(&joined).is_dir() {
+        return None;
+    }
+    let dir = joined.display().to_string();
+    if dir.is_empty() || dir.ends_with('/') {
+        Some(dir)
+    } else {
+        Some(dir + "/")
+    }
+}
+
+/// matches name against a pattern where `*` is any run of characters and `?` any one
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (p, n): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    // backtrack to the last star when a match fails
+    let (mut i, mut j) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while j < n.len() {
+        match p.get(i) {
+            Some('*') => {
+                star = Some((i, j));
+                i += 1;
+            }
+            Some(&c) if c == '?' || c == n[j] => {
+                i += 1;
+                j += 1;
+            }
+            _ => match star {
+                Some((si, sj)) => {
+                    i = si + 1;
+                    j = sj + 1;
+                    star = Some((si, sj + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    p[i..].iter().all(|&c| c == '*')
+}
+
+fn has_wildcards(component: &str) -> bool {
+    component.contains(&['*', '?'][..])
+}
+
+/// entries of dir, hidden ones only if the pattern asks for them
+fn entries(dir: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            (pattern.starts_with('.') || !name.starts_with('.')) && glob_match(pattern, &name)
+        })
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+    entries
+}
+
+/// dir and every directory below it, hidden ones excepted
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![dir.to_path_buf()];
+    for entry in entries(dir, "*").into_iter().filter(|path| path.is_dir()) {
+        dirs.extend(subdirectories(&entry));
+    }
+    dirs
+}
+
+/// expands the wildcards of pattern into the sorted matching files, `*` and `?` matching
+/// within a component and `**` any number of directories. a pattern without wildcards
+/// is returned as is, whether the file exists or not
+pub fn glob(pattern: &str) -> Vec<String> {
+    let path = normalize(Path::new(pattern));
+    if !has_wildcards(&path.to_string_lossy()) {
+        return vec![path.display().to_string()];
+    }
+    let mut candidates = vec![PathBuf::new()];
+    let components: Vec<Component> = path.components().collect();
+    for (i, component) in components.iter().enumerate() {
+        let last = i + 1 == components.len();
+        let name = component.as_os_str().to_string_lossy();
+        candidates = candidates
+            .into_iter()
+            .flat_map(|candidate| {
+                let dir = if candidate.as_os_str().is_empty() {
+                    PathBuf::from(".")
+                } else {
+                    candidate.clone()
+                };
+                match name.as_ref() {
+                    "**" => subdirectories(&dir)
+                        .into_iter()
+                        .map(|sub| {
+                            normalize(&candidate.join(sub.strip_prefix(&dir).unwrap_or(&sub)))
+                        })
+                        .collect(),
+                    name if has_wildcards(name) => entries(&dir, name)
+                        .into_iter()
+                        .filter(|entry| last || entry.is_dir())
+                        .map(|entry| normalize(&entry))
+                        .collect(),
+                    _ => vec![candidate.join(component)],
+                }
+            })
+            .collect();
+    }
+    let mut files: Vec<String> = candidates
+        .into_iter()
+        .filter(|path| path.is_file())
+        .map(|path| path.display().to_string())
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// whether the start of a file has NUL bytes, as text files don't
+pub fn is_binary(path: &str) -> bool {
+    let mut start = vec![0; 8000];
+    match std::fs::File::open(path).and_then(|mut file| file.read(&mut start)) {
+        Ok(n) => start[..n].contains(&0),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_parent_dirs() {
+        assert_eq!(normalize(Path::new("a/b/../c")), PathBuf::from("a/c"));
+        assert_eq!(normalize(Path::new("./a/./b")), PathBuf::from("a/b"));
+        assert_eq!(normalize(Path::new("../a/..")), PathBuf::from(".."));
+        assert_eq!(normalize(Path::new("/../a")), PathBuf::from("/a"));
+        assert_eq!(directory_of("src/../src/main.rs"), "src/");
+        assert_eq!(directory_of("main.rs"), "");
+        assert_eq!(change_dir("", "src"), Some(String::from("src/")));
+        assert_eq!(change_dir("src/", ".."), Some(String::default()));
+        assert_eq!(change_dir("src/", "/"), Some(String::from("/")));
+        assert_eq!(change_dir("", "no such dir"), None);
+    }
+
+    #[test]
+    fn unique_file_names() {
+        assert_eq!(
+            unique_names(&[
+                "src/main.rs",
+                "src/editor/mod.rs",
+                "src/lsp/mod.rs",
+                "/mod.rs"
+            ]),
+            vec!["main", "editor/mod", "lsp/mod", "mod"]
+        );
+        assert_eq!(
+            unique_names(&["a/x/mod.rs", "b/x/mod.rs", "README.md"]),
+            vec!["a/x/mod", "b/x/mod", "README"]
+        );
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(glob_match("m?in.*", "main.rs"));
+        assert!(glob_match("*a*b", "xaxxb"));
+        assert!(!glob_match("*.rs", "main.rs.bak"));
+        assert!(!glob_match("?", ""));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn glob_directories() {
+        let dir = std::env::temp_dir().join(format!("ted-glob-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src/editor")).unwrap();
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        for file in &["src/main.rs", "src/editor/mod.rs", ".git/x.rs", "README.md"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        std::fs::write(dir.join("src/icon.rs"), b"\x89PNG\0").unwrap();
+        let root = dir.display().to_string();
+        let found = |pattern: &str| -> Vec<String> {
+            glob(&format!("{}/{}", root, pattern))
+                .iter()
+                .map(|path| path[root.len() + 1..].to_string())
+                .collect()
+        };
+        assert_eq!(
+            found("**/*.rs"),
+            vec!["src/editor/mod.rs", "src/icon.rs", "src/main.rs"]
+        );
+        assert_eq!(found("s*/m*.rs"), vec!["src/main.rs"]);
+        assert_eq!(found("*/*/*.rs"), vec!["src/editor/mod.rs"]);
+        assert_eq!(found("src/**/mod.rs"), vec!["src/editor/mod.rs"]);
+        assert_eq!(found("none"), vec!["none"]);
+        assert!(is_binary(&format!("{}/src/icon.rs", root)));
+        assert!(!is_binary(&format!("{}/src/main.rs", root)));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
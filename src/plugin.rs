@@ -0,0 +1,261 @@
+//! External plugins: processes exchanging JSON-RPC messages with the editor on their stdio,
+//! framed with a Content-Length header like language servers
+//!
+//! The editor notifies plugins of `bufferOpened`, `bufferSaved` and `bufferReloaded`
+//! (`{ "path" }`), and of `commandInvoked` (`{ "name" }`) when one of their commands runs.
+//! Plugins send:
+//!
+//! - `registerCommand` `{ "name", "desc", "chain" }`, chain as displayed e.g. `"SPC x p"`
+//! - `registerMode` `{ "name", "keys", "fallthrough" }`, a mode entered with `enter_mode`
+//! - `showMessage` `{ "text" }`
+//! - `insertText` `{ "text" }` at the cursor
+//! - `runCommand` `{ "line" }` e.g. `"goto_line 12"`
+//! - `getText` requests, answered with `{ "path", "text", "line", "column" }`
+
+use crate::command::parse_space_chain;
+use crate::keymap::ModeSpec;
+use crate::lsp::{decode, encode, spawn_writer};
+use serde_json::{json, Value};
+use std::io;
+use std::io::BufReader;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+/// times a plugin exiting is restarted before giving up on it
+pub const MAX_RESTARTS: usize = 3;
+
+/// what a plugin asked of the editor
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    RegisterCommand {
+        name: String,
+        desc: String,
+        chain: Option<String>,
+    },
+    RegisterMode {
+        name: String,
+        spec: ModeSpec,
+    },
+    ShowMessage(String),
+    InsertText(String),
+    RunCommand(String),
+    /// a request for the focused buffer, to answer with the given id
+    GetText(Value),
+    /// the plugin exited too many times
+    Exited,
+}
+
+impl Event {
+    fn from_message(message: &Value) -> Option<Self> {
+        let params = &message["params"];
+        let text = |key: &str| params[key].as_str().map(String::from);
+        Some(match message["method"].as_str()? {
+            "registerCommand" => Event::RegisterCommand {
+                name: text("name")?,
+                desc: text("desc").unwrap_or_default(),
+                chain: params["chain"].as_str().and_then(parse_space_chain),
+            },
+            "registerMode" => Event::RegisterMode {
+                name: text("name")?,
+                spec: ModeSpec::from_json(params)?,
+            },
+            "showMessage" => Event::ShowMessage(text("text")?),
+            "insertText" => Event::InsertText(text("text")?),
+            "runCommand" => Event::RunCommand(text("line")?),
+            "getText" if !message["id"].is_null() => Event::GetText(message["id"].clone()),
+            _ => return None,
+        })
+    }
+}
+
+/// a running plugin process, messages are read and written by background threads
+struct Process {
+    child: Child,
+    /// encoded messages for the writer thread
+    writer: Sender<Vec<u8>>,
+    messages: Receiver<Value>,
+}
+
+impl Process {
+    fn spawn(command: &str) -> io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Empty plugin command"))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let writer = spawn_writer(child.stdin.take().expect("stdin is piped"));
+        let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(message) = decode(&mut stdout) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            child,
+            writer,
+            messages,
+        })
+    }
+
+    fn write(&mut self, message: &Value) -> io::Result<()> {
+        self.writer
+            .send(encode(message))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Plugin closed its input"))
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// keeps a plugin running, restarting it when it exits up to MAX_RESTARTS times
+pub struct Supervisor {
+    pub name: String,
+    command: String,
+    process: Option<Process>,
+    restarts: usize,
+}
+
+impl Supervisor {
+    pub fn start(name: &str, command: &str) -> io::Result<Self> {
+        Ok(Self {
+            name: name.to_string(),
+            command: command.to_string(),
+            process: Some(Process::spawn(command)?),
+            restarts: 0,
+        })
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.process.is_some()
+    }
+
+    fn write(&mut self, message: Value) {
+        if let Some(process) = self.process.as_mut() {
+            // a broken pipe shows up as the plugin exiting on the next poll
+            let _ = process.write(&message);
+        }
+    }
+
+    pub fn notify(&mut self, method: &str, params: Value) {
+        self.write(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    pub fn respond(&mut self, id: Value, result: Value) {
+        self.write(json!({ "jsonrpc": "2.0", "id": id, "result": result }));
+    }
+
+    /// handles the messages received so far, restarting the plugin if it exited
+    pub fn poll(&mut self) -> Vec<Event> {
+        let mut events = vec![];
+        let mut exited = false;
+        while let Some(process) = self.process.as_mut() {
+            let message = match process.messages.try_recv() {
+                Ok(message) => message,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    exited = true;
+                    break;
+                }
+            };
+            match Event::from_message(&message) {
+                Some(event) => events.push(event),
+                None if !message["id"].is_null() && message["method"].is_string() => {
+                    let reply = json!({
+                        "jsonrpc": "2.0",
+                        "id": message["id"],
+                        "error": { "code": -32601, "message": "Method not found" },
+                    });
+                    let _ = process.write(&reply);
+                }
+                None => {}
+            }
+        }
+        if exited {
+            self.process = None;
+            if self.restarts < MAX_RESTARTS {
+                log::warn!("plugin {} exited, restarting it", self.name);
+                self.restarts += 1;
+                self.process = Process::spawn(&self.command).ok();
+            }
+            if self.process.is_none() {
+                events.push(Event::Exited);
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plugin_events() {
+        let register = json!({
+            "jsonrpc": "2.0",
+            "method": "registerCommand",
+            "params": { "name": "word_count", "desc": "Counts words", "chain": "SPC x w" },
+        });
+        assert_eq!(
+            Event::from_message(&register),
+            Some(Event::RegisterCommand {
+                name: String::from("word_count"),
+                desc: String::from("Counts words"),
+                chain: Some(String::from(" xw")),
+            })
+        );
+        let get_text = json!({ "jsonrpc": "2.0", "id": 4, "method": "getText" });
+        assert_eq!(
+            Event::from_message(&get_text),
+            Some(Event::GetText(json!(4)))
+        );
+        let mode = json!({
+            "jsonrpc": "2.0",
+            "method": "registerMode",
+            "params": { "name": "nav", "keys": { "n": "next_buffer" }, "fallthrough": false },
+        });
+        match Event::from_message(&mode) {
+            Some(Event::RegisterMode { name, spec }) => {
+                assert_eq!(name, "nav");
+                assert!(!spec.fallthrough && spec.keys["n"] == "next_buffer");
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+        let notification = json!({ "jsonrpc": "2.0", "method": "getText" });
+        assert_eq!(Event::from_message(&notification), None);
+        let message = json!({ "method": "showMessage", "params": { "text": "hi" } });
+        assert_eq!(
+            Event::from_message(&message),
+            Some(Event::ShowMessage(String::from("hi")))
+        );
+    }
+
+    #[test]
+    fn restart_exited_plugin() {
+        let mut supervisor = Supervisor::start("echo", "true").unwrap();
+        let mut events = vec![];
+        for _ in 0..200 {
+            events.extend(supervisor.poll());
+            if !supervisor.is_running() {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(events, vec![Event::Exited]);
+        assert_eq!(supervisor.restarts, MAX_RESTARTS);
+        assert!(Supervisor::start("empty", "").is_err());
+    }
+}
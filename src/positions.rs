@@ -0,0 +1,98 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// last cursor position in files, restored when they are reopened, e.g. in
+/// ~/.local/state/ted/positions, one `line column path` per line, most recent first
+pub struct Positions {
+    file: Option<PathBuf>,
+    entries: Vec<(String, usize, usize)>,
+}
+
+impl Positions {
+    /// reads the positions, a missing or malformed file remembers nothing
+    pub fn load(file: Option<PathBuf>) -> Self {
+        let entries = file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|content| content.lines().filter_map(parse).collect())
+            .unwrap_or_default();
+        Self { file, entries }
+    }
+
+    /// the 0-based line and column last left in file
+    pub fn get(&self, file: &str) -> Option<(usize, usize)> {
+        let path = absolute(file);
+        self.entries
+            .iter()
+            .find(|(p, _, _)| *p == path)
+            .map(|&(_, line, column)| (line, column))
+    }
+
+    /// moves file to the front with its new position, keeping the `capacity` most recent
+    pub fn set(&mut self, file: &str, line: usize, column: usize, capacity: usize) {
+        let path = absolute(file);
+        self.entries.retain(|(p, _, _)| *p != path);
+        self.entries.insert(0, (path, line, column));
+        self.entries.truncate(capacity);
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let file = self
+            .file
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No state directory"))?;
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content: String = self
+            .entries
+            .iter()
+            .map(|(path, line, column)| format!("{} {} {}\n", line, column, path))
+            .collect();
+        fs::write(file, content)
+    }
+}
+
+fn parse(line: &str) -> Option<(String, usize, usize)> {
+    let mut fields = line.splitn(3, ' ');
+    let line_number = fields.next()?.parse().ok()?;
+    let column = fields.next()?.parse().ok()?;
+    let path = fields.next().filter(|path| !path.is_empty())?;
+    Some((path.to_string(), line_number, column))
+}
+
+fn absolute(file: &str) -> String {
+    Path::new(file)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(file))
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Positions;
+
+    #[test]
+    fn remember_positions() {
+        let file = std::env::temp_dir().join(format!("ted-positions-{}", std::process::id()));
+        let mut positions = Positions::load(Some(file.clone()));
+        assert_eq!(positions.get("/a"), None);
+        positions.set("/a", 4, 2, 2);
+        positions.set("/b c", 1, 0, 2);
+        positions.set("/a", 5, 0, 2);
+        positions.set("/d", 0, 0, 2);
+        positions.save().unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "0 0 /d\n5 0 /a\n");
+        let positions = Positions::load(Some(file.clone()));
+        assert_eq!(positions.get("/a"), Some((5, 0)));
+        assert_eq!(positions.get("/b c"), None);
+        std::fs::write(&file, "x y /a\n3 1 /b c\n").unwrap();
+        assert_eq!(
+            Positions::load(Some(file.clone())).get("/b c"),
+            Some((3, 1))
+        );
+        std::fs::remove_file(file).unwrap();
+    }
+}
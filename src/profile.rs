@@ -0,0 +1,77 @@
+//! Timings of the startup phases, reported by `--profile-startup`
+
+use crate::config::Config;
+use std::time::{Duration, Instant};
+
+pub struct Profile {
+    last: Instant,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            last: Instant::now(),
+            phases: vec![],
+        }
+    }
+}
+
+impl Profile {
+    /// records the time since the previous phase ended
+    pub fn lap(&mut self, phase: &'static str) {
+        let now = Instant::now();
+        self.phases.push((phase, now - self.last));
+        self.last = now;
+    }
+
+    /// the phases and their total, then how the syntaxes and themes loaded in the background
+    pub fn report(&self, config: &Config) -> String {
+        let mut report = String::from("startup profile:\n");
+        let mut total = Duration::ZERO;
+        for (phase, duration) in &self.phases {
+            report.push_str(&format!("  {:<12}{:>10}\n", phase, millis(*duration)));
+            total += *duration;
+        }
+        report.push_str(&format!("  {:<12}{:>10}\n", "total", millis(total)));
+        let timings = [
+            ("syntaxes", config.syntax_set.timing()),
+            ("themes", config.theme_set.timing()),
+        ];
+        for (name, timing) in timings {
+            let line = match timing {
+                Some((built, waited)) => format!(
+                    "built in {} in the background, waited {}",
+                    millis(built),
+                    millis(waited)
+                ),
+                None => String::from("not needed yet"),
+            };
+            report.push_str(&format!("  {:<12}{}\n", name, line));
+        }
+        report
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(all(test, feature = "bundled-assets"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_phases() {
+        let config = Config::builtin();
+        let mut profile = Profile::default();
+        profile.lap("config");
+        profile.lap("first draw");
+        let report = profile.report(&config);
+        assert!(report.starts_with("startup profile:\n  config  "));
+        assert!(report.contains("\n  total  "));
+        assert!(report.ends_with("  themes      not needed yet\n"));
+        config.syntax_set.find_syntax_by_name("Rust").unwrap();
+        assert!(profile.report(&config).contains("  syntaxes    built in "));
+    }
+}
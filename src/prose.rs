@@ -0,0 +1,161 @@
+//! Sentences and paragraphs, moved over by `(`, `)`, `{` and `}`
+//!
+//! A paragraph is a run of non-blank lines, between blank ones. A sentence starts at the
+//! beginning of a paragraph or after a `.`, `!` or `?`, maybe closed by quotes or brackets, then
+//! whitespace.
+
+use ropey::Rope;
+use std::ops::Range;
+
+/// closes a sentence after its final punctuation, like `."` or `.)`
+const CLOSERS: [char; 4] = [')', ']', '"', '\''];
+
+fn is_blank(text: &Rope, line_number: usize) -> bool {
+    text.line(line_number).chars().all(char::is_whitespace)
+}
+
+/// lines of the text, without the empty one after a final newline
+fn len_lines(text: &Rope) -> usize {
+    match text.len_lines() {
+        n if n > 1 && text.line(n - 1).len_chars() == 0 => n - 1,
+        n => n,
+    }
+}
+
+/// wether a sentence starts at the char index
+fn starts_sentence(text: &Rope, i: usize) -> bool {
+    if i >= text.len_chars() || text.char(i).is_whitespace() {
+        return false;
+    }
+    let (mut j, mut newlines) = (i, 0);
+    while j > 0 && text.char(j - 1).is_whitespace() {
+        newlines += (text.char(j - 1) == '\n') as usize;
+        j -= 1;
+    }
+    if j == 0 || newlines > 1 {
+        return true;
+    }
+    if j == i {
+        return false;
+    }
+    while j > 0 && CLOSERS.contains(&text.char(j - 1)) {
+        j -= 1;
+    }
+    j > 0 && matches!(text.char(j - 1), '.' | '!' | '?')
+}
+
+/// char index of the start of the n-th next sentence, or the end of the text
+pub fn next_sentence(text: &Rope, from: usize, n: usize) -> usize {
+    let mut i = from;
+    for _ in 0..n {
+        i = (i + 1..text.len_chars())
+            .find(|&i| starts_sentence(text, i))
+            .unwrap_or_else(|| text.len_chars());
+    }
+    i
+}
+
+/// char index of the start of the sentence, or of the previous one when already there
+pub fn previous_sentence(text: &Rope, from: usize, n: usize) -> usize {
+    let mut i = from;
+    for _ in 0..n {
+        i = (0..i)
+            .rev()
+            .find(|&i| starts_sentence(text, i))
+            .unwrap_or(0);
+    }
+    i
+}
+
+/// the blank line after the n-th next paragraph, or the last line
+pub fn next_paragraph(text: &Rope, line_number: usize, n: usize) -> usize {
+    let last = len_lines(text).saturating_sub(1);
+    let mut i = line_number;
+    for _ in 0..n {
+        i += 1;
+        while i < last && is_blank(text, i) {
+            i += 1;
+        }
+        while i < last && !is_blank(text, i) {
+            i += 1;
+        }
+    }
+    i.min(last)
+}
+
+/// the blank line before the n-th previous paragraph, or the first line
+pub fn previous_paragraph(text: &Rope, line_number: usize, n: usize) -> usize {
+    let mut i = line_number;
+    for _ in 0..n {
+        i = i.saturating_sub(1);
+        while i > 0 && is_blank(text, i) {
+            i -= 1;
+        }
+        while i > 0 && !is_blank(text, i) {
+            i -= 1;
+        }
+    }
+    i
+}
+
+/// lines of the paragraph around the line and the n - 1 next ones, blank lines between them
+/// included, or of the blank lines around it
+pub fn paragraph(text: &Rope, line_number: usize, n: usize) -> Range<usize> {
+    let len = len_lines(text);
+    let line_number = line_number.min(len.saturating_sub(1));
+    let blank = is_blank(text, line_number);
+    let mut start = line_number;
+    while start > 0 && is_blank(text, start - 1) == blank {
+        start -= 1;
+    }
+    let mut end = line_number + 1;
+    let mut count = 1;
+    loop {
+        while end < len && is_blank(text, end) == blank {
+            end += 1;
+        }
+        if count == n || blank || end == len {
+            break;
+        }
+        while end < len && is_blank(text, end) {
+            end += 1;
+        }
+        count += 1;
+    }
+    start..end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sentences() {
+        let text = Rope::from("One. Two! (Three?) Four\nstill four.\n\nFive e.g.five\n");
+        let starts: Vec<usize> = (0..text.len_chars())
+            .filter(|&i| starts_sentence(&text, i))
+            .collect();
+        assert_eq!(starts, vec![0, 5, 10, 19, 37]);
+        assert_eq!(next_sentence(&text, 0, 1), 5);
+        assert_eq!(next_sentence(&text, 0, 3), 19);
+        assert_eq!(next_sentence(&text, 37, 1), text.len_chars());
+        assert_eq!(previous_sentence(&text, 21, 1), 19);
+        assert_eq!(previous_sentence(&text, 19, 2), 5);
+        assert_eq!(previous_sentence(&text, 3, 1), 0);
+    }
+
+    #[test]
+    fn paragraphs() {
+        let text = Rope::from("a\nb\n\n\nc\n\nd\ne\n");
+        assert_eq!(next_paragraph(&text, 0, 1), 2);
+        assert_eq!(next_paragraph(&text, 2, 1), 5);
+        assert_eq!(next_paragraph(&text, 0, 9), 7);
+        assert_eq!(previous_paragraph(&text, 7, 1), 5);
+        assert_eq!(previous_paragraph(&text, 7, 2), 3);
+        assert_eq!(previous_paragraph(&text, 1, 1), 0);
+        assert_eq!(paragraph(&text, 1, 1), 0..2);
+        assert_eq!(paragraph(&text, 0, 2), 0..5);
+        assert_eq!(paragraph(&text, 3, 1), 2..4);
+        assert_eq!(paragraph(&text, 7, 3), 6..8);
+    }
+}
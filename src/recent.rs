@@ -0,0 +1,86 @@
+use crate::config::data_dir;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// most entries remembered
+pub const CAPACITY: usize = 100;
+
+/// most recently used entries, e.g. opened files in ~/.local/share/ted/recent,
+/// one per line
+pub struct Recent {
+    file: Option<PathBuf>,
+    entries: Vec<String>,
+}
+
+impl Default for Recent {
+    fn default() -> Self {
+        Self::load(data_dir("recent"))
+    }
+}
+
+impl Recent {
+    /// reads the list, a missing file is an empty list
+    pub fn load(file: Option<PathBuf>) -> Self {
+        let entries = file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|content| content.lines().map(String::from).collect())
+            .unwrap_or_default();
+        Self { file, entries }
+    }
+
+    /// most recent first
+    pub fn entries(&self) -> &Vec<String> {
+        &self.entries
+    }
+
+    /// moves the absolute path of file to the front of the list and saves it
+    pub fn add(&mut self, file: &str) -> io::Result<()> {
+        let path = Path::new(file)
+            .canonicalize()
+            .unwrap_or_else(|_| PathBuf::from(file))
+            .display()
+            .to_string();
+        self.push(&path)
+    }
+
+    /// moves entry to the front of the list and saves it
+    pub fn push(&mut self, entry: &str) -> io::Result<()> {
+        self.entries.retain(|e| e != entry);
+        self.entries.insert(0, entry.to_string());
+        self.entries.truncate(CAPACITY);
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let file = self
+            .file
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory"))?;
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut content = self.entries.join("\n");
+        content.push('\n');
+        fs::write(file, content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Recent;
+
+    #[test]
+    fn most_recent_first() {
+        let file = std::env::temp_dir().join(format!("ted-recent-{}", std::process::id()));
+        let mut recent = Recent::load(Some(file.clone()));
+        assert!(recent.entries().is_empty());
+        recent.add("/a").unwrap();
+        recent.add("/b").unwrap();
+        recent.add("/a").unwrap();
+        assert_eq!(recent.entries(), &vec!["/a", "/b"]);
+        assert_eq!(Recent::load(Some(file.clone())).entries(), recent.entries());
+        std::fs::remove_file(file).unwrap();
+    }
+}
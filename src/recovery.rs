@@ -0,0 +1,95 @@
+//! Buffers with unsaved changes dumped when ted panics, offered back on the next start
+//!
+//! Each buffer is written to a file of the recovery directory named after its path, or its
+//! name when it has no backend file, with `%` and `/` escaped as `%25` and `%2F`.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// recovery file name of a buffer
+pub fn file_name(name: &str) -> String {
+    name.replace('%', "%25").replace('/', "%2F")
+}
+
+/// path or name of the buffer a recovery file was dumped from
+pub fn original_name(file_name: &str) -> String {
+    file_name.replace("%2F", "/").replace("%25", "%")
+}
+
+/// what went wrong, where, and which buffers were open
+pub fn report(message: &str, backtrace: &str, buffers: &[String]) -> String {
+    let mut report = format!(
+        "ted {} panicked: {}\n\nOpen buffers:\n",
+        env!("CARGO_PKG_VERSION"),
+        message
+    );
+    for name in buffers {
+        report.push_str(&format!("  {}\n", name));
+    }
+    report.push_str(&format!("\nBacktrace:\n{}\n", backtrace));
+    report
+}
+
+/// writes each (name, text) buffer to dir, returns the files written
+pub fn dump(dir: &Path, buffers: &[(String, String)]) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    let mut files = vec![];
+    for (name, text) in buffers {
+        let file = dir.join(file_name(name));
+        fs::write(&file, text)?;
+        files.push(file);
+    }
+    Ok(files)
+}
+
+/// (original name, recovery file) of the buffers left in dir by a crash
+pub fn pending(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut buffers: Vec<(String, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| {
+            let name = original_name(&entry.file_name().to_string_lossy());
+            (name, entry.path())
+        })
+        .collect();
+    buffers.sort();
+    buffers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dump_and_recover() {
+        let dir = std::env::temp_dir().join(format!("ted-recovery-{}", std::process::id()));
+        let buffers = vec![
+            (
+                String::from("/home/me/main.rs"),
+                String::from("fn main() {}\n"),
+            ),
+            (String::from("Buffer #2"), String::from("notes\n")),
+        ];
+        let files = dump(&dir, &buffers).unwrap();
+        assert_eq!(files[0], dir.join("%2Fhome%2Fme%2Fmain.rs"));
+        for name in ["/a%b/c", "/a%2Fb", "%/25"] {
+            assert_eq!(original_name(&file_name(name)), name);
+        }
+        let pending = pending(&dir);
+        assert_eq!(
+            pending
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["/home/me/main.rs", "Buffer #2"]
+        );
+        assert_eq!(fs::read_to_string(&pending[1].1).unwrap(), "notes\n");
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(super::pending(&dir).is_empty());
+        let report = report("boom", "0: main", &[String::from("Buffer #2")]);
+        assert!(report.contains("panicked: boom\n\nOpen buffers:\n  Buffer #2\n"));
+    }
+}
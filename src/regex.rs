@@ -0,0 +1,532 @@
+//! Small regular expressions for searching buffers: literals, `.`, classes (`[a-z]`, `[^"]`,
+//! `\d`, `\w`, `\s`), anchors (`^`, `$` at line boundaries), groups with alternatives
+//! (`(a|b)`), capturing what they matched, and the greedy `*`, `+` and `?` quantifiers
+//!
+//! Patterns compile to a program run by a Pike VM: every thread of the program steps through
+//! the text at once, one char at a time, so matching takes time linear in the text and never
+//! recurses, whatever the pattern. Earlier threads take precedence, which gives the leftmost
+//! match a backtracking engine would find.
+
+use std::io;
+use std::ops::Range;
+
+/// what each group matched last as byte ranges, the whole match first
+pub type Groups = Vec<Option<Range<usize>>>;
+
+enum Node {
+    Char(char),
+    Any,
+    /// inclusive char ranges, and whether the class is negated
+    Class(Vec<(char, char)>, bool),
+    LineStart,
+    LineEnd,
+    /// alternative sequences, and the 1-based number of the group
+    Group(usize, Vec<Vec<Node>>),
+    /// node repeated at least min and at most max times
+    Repeat(Box<Node>, usize, Option<usize>),
+}
+
+/// an instruction of a compiled pattern
+enum Inst {
+    Char(char),
+    Any,
+    Class(Vec<(char, char)>, bool),
+    LineStart,
+    LineEnd,
+    /// goes on at both, the first one taking precedence
+    Split(usize, usize),
+    Jump(usize),
+    /// records the position in a slot, the start and end of group i being 2i and 2i + 1
+    Save(usize),
+    Match,
+}
+
+pub struct Regex {
+    program: Vec<Inst>,
+    ignore_case: bool,
+    groups: usize,
+}
+
+/// a thread of the program: the instruction it is at and the positions it saved
+struct Thread {
+    pc: usize,
+    slots: Vec<Option<usize>>,
+}
+
+/// the threads stepping at a position, each instruction taken by the first one to reach it
+struct Threads {
+    threads: Vec<Thread>,
+    visited: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Self {
+            threads: vec![],
+            visited: vec![false; len],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.threads.clear();
+        self.visited.iter_mut().for_each(|visited| *visited = false);
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.to_string())
+}
+
+/// ranges of the class an escaped char stands for, if any
+fn escape_class(c: char) -> Option<Vec<(char, char)>> {
+    match c {
+        'd' => Some(vec![('0', '9')]),
+        'w' => Some(vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')]),
+        's' => Some(vec![(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')]),
+        _ => None,
+    }
+}
+
+fn escape_char(c: char) -> char {
+    match c {
+        'n' => '\n',
+        't' => '\t',
+        c => c,
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /// groups opened so far
+    groups: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn alternatives(&mut self) -> io::Result<Vec<Vec<Node>>> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> io::Result<Vec<Node>> {
+        let mut nodes = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                _ => {
+                    nodes.push(atom);
+                    continue;
+                }
+            };
+            self.pos += 1;
+            match atom {
+                Node::LineStart | Node::LineEnd => return Err(invalid("Nothing to repeat")),
+                atom => nodes.push(Node::Repeat(Box::new(atom), min, max)),
+            }
+        }
+        Ok(nodes)
+    }
+
+    fn atom(&mut self) -> io::Result<Node> {
+        Ok(match self.next() {
+            Some('(') => {
+                self.groups += 1;
+                let index = self.groups;
+                let alternatives = self.alternatives()?;
+                if self.next() != Some(')') {
+                    return Err(invalid("Unclosed group"));
+                }
+                Node::Group(index, alternatives)
+            }
+            Some('[') => self.class()?,
+            Some('.') => Node::Any,
+            Some('^') => Node::LineStart,
+            Some('$') => Node::LineEnd,
+            Some('*') | Some('+') | Some('?') => return Err(invalid("Nothing to repeat")),
+            Some('\\') => {
+                let c = self.next().ok_or_else(|| invalid("Trailing backslash"))?;
+                match escape_class(c) {
+                    Some(ranges) => Node::Class(ranges, false),
+                    None => Node::Char(escape_char(c)),
+                }
+            }
+            Some(c) => Node::Char(c),
+            None => return Err(invalid("Unexpected end of pattern")),
+        })
+    }
+
+    fn class(&mut self) -> io::Result<Node> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut ranges = vec![];
+        let mut first = true;
+        loop {
+            let c = match self.next() {
+                None => return Err(invalid("Unclosed class")),
+                Some(']') if !first => break,
+                Some('\\') => {
+                    let c = self.next().ok_or_else(|| invalid("Unclosed class"))?;
+                    if let Some(class) = escape_class(c) {
+                        ranges.extend(class);
+                        first = false;
+                        continue;
+                    }
+                    escape_char(c)
+                }
+                Some(c) => c,
+            };
+            first = false;
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                let end = match self.next() {
+                    Some('\\') => self.next().map(escape_char),
+                    end => end,
+                }
+                .ok_or_else(|| invalid("Unclosed class"))?;
+                ranges.push((c, end));
+            } else {
+                ranges.push((c, c));
+            }
+        }
+        Ok(Node::Class(ranges, negated))
+    }
+}
+
+/// appends the alternatives, the first ones taking precedence
+fn compile_alternatives(program: &mut Vec<Inst>, alternatives: &[Vec<Node>]) {
+    let mut jumps = vec![];
+    for (i, alternative) in alternatives.iter().enumerate() {
+        let last = i + 1 == alternatives.len();
+        let split = program.len();
+        if !last {
+            program.push(Inst::Split(split + 1, 0));
+        }
+        for node in alternative {
+            compile(program, node);
+        }
+        if !last {
+            jumps.push(program.len());
+            program.push(Inst::Jump(0));
+            program[split] = Inst::Split(split + 1, program.len());
+        }
+    }
+    for jump in jumps {
+        program[jump] = Inst::Jump(program.len());
+    }
+}
+
+fn compile(program: &mut Vec<Inst>, node: &Node) {
+    match node {
+        Node::Char(c) => program.push(Inst::Char(*c)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class(ranges, negated) => program.push(Inst::Class(ranges.clone(), *negated)),
+        Node::LineStart => program.push(Inst::LineStart),
+        Node::LineEnd => program.push(Inst::LineEnd),
+        Node::Group(index, alternatives) => {
+            program.push(Inst::Save(2 * index));
+            compile_alternatives(program, alternatives);
+            program.push(Inst::Save(2 * index + 1));
+        }
+        Node::Repeat(node, min, max) => {
+            for _ in 0..*min {
+                compile(program, node);
+            }
+            match max {
+                None => {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(program, node);
+                    program.push(Inst::Jump(split));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+                Some(max) => {
+                    let mut splits = vec![];
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(0, 0));
+                        compile(program, node);
+                    }
+                    for split in splits {
+                        program[split] = Inst::Split(split + 1, program.len());
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+impl Regex {
+    pub fn new(pattern: &str, ignore_case: bool) -> io::Result<Self> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            groups: 0,
+        };
+        let alternatives = parser.alternatives()?;
+        if parser.pos < parser.chars.len() {
+            return Err(invalid("Unmatched )"));
+        }
+        Ok(Self::compiled(&alternatives, ignore_case, parser.groups))
+    }
+
+    /// matches text as is
+    pub fn literal(text: &str, ignore_case: bool) -> Self {
+        let sequence = text.chars().map(Node::Char).collect();
+        Self::compiled(&[sequence], ignore_case, 0)
+    }
+
+    fn compiled(alternatives: &[Vec<Node>], ignore_case: bool, groups: usize) -> Self {
+        let mut program = vec![Inst::Save(0)];
+        compile_alternatives(&mut program, alternatives);
+        program.push(Inst::Save(1));
+        program.push(Inst::Match);
+        Self {
+            program,
+            ignore_case,
+            groups,
+        }
+    }
+
+    fn eq(&self, a: char, b: char) -> bool {
+        a == b || (self.ignore_case && fold(a) == fold(b))
+    }
+
+    fn in_class(&self, c: char, ranges: &[(char, char)]) -> bool {
+        let within = |c: char| ranges.iter().any(|(start, end)| *start <= c && c <= *end);
+        within(c)
+            || (self.ignore_case && (c.to_lowercase().any(within) || c.to_uppercase().any(within)))
+    }
+
+    /// adds the thread at pc to threads, following jumps, splits, saves and anchors at byte
+    /// pos, between the chars before and after it
+    fn add_thread(
+        &self,
+        threads: &mut Threads,
+        thread: Thread,
+        pos: usize,
+        (before, after): (Option<char>, Option<char>),
+    ) {
+        let mut stack = vec![thread];
+        while let Some(mut thread) = stack.pop() {
+            let pc = thread.pc;
+            if threads.visited[pc] {
+                continue;
+            }
+            threads.visited[pc] = true;
+            thread.pc += 1;
+            match &self.program[pc] {
+                Inst::Jump(to) => {
+                    thread.pc = *to;
+                    stack.push(thread);
+                }
+                Inst::Split(first, second) => {
+                    let slots = thread.slots.clone();
+                    stack.push(Thread { pc: *second, slots });
+                    thread.pc = *first;
+                    stack.push(thread);
+                }
+                Inst::Save(slot) => {
+                    thread.slots[*slot] = Some(pos);
+                    stack.push(thread);
+                }
+                Inst::LineStart if matches!(before, None | Some('\n')) => stack.push(thread),
+                Inst::LineEnd if matches!(after, None | Some('\n')) => stack.push(thread),
+                Inst::LineStart | Inst::LineEnd => {}
+                _ => {
+                    thread.pc = pc;
+                    threads.threads.push(thread);
+                }
+            }
+        }
+    }
+
+    /// the groups of the leftmost match starting at or after byte start
+    fn captures_from(&self, text: &str, start: usize) -> Option<Groups> {
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        let mut matched = None;
+        let mut pos = start;
+        let mut before = text[..start].chars().next_back();
+        loop {
+            let c = text[pos..].chars().next();
+            if matched.is_none() {
+                let slots = vec![None; 2 * self.groups + 2];
+                let thread = Thread { pc: 0, slots };
+                self.add_thread(&mut current, thread, pos, (before, c));
+            }
+            if current.threads.is_empty() && (matched.is_some() || c.is_none()) {
+                break;
+            }
+            next.clear();
+            let after = c.and_then(|c| text[pos + c.len_utf8()..].chars().next());
+            for thread in current.threads.drain(..) {
+                let step = match (&self.program[thread.pc], c) {
+                    (Inst::Match, _) => {
+                        // the threads after this one are worse matches
+                        matched = Some(thread.slots);
+                        break;
+                    }
+                    (_, None) => false,
+                    (Inst::Char(expected), Some(c)) => self.eq(c, *expected),
+                    (Inst::Any, Some(c)) => c != '\n',
+                    (Inst::Class(ranges, negated), Some(c)) => self.in_class(c, ranges) != *negated,
+                    _ => false,
+                };
+                if let (true, Some(c)) = (step, c) {
+                    let thread = Thread {
+                        pc: thread.pc + 1,
+                        slots: thread.slots,
+                    };
+                    self.add_thread(&mut next, thread, pos + c.len_utf8(), (Some(c), after));
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+            match c {
+                Some(c) => pos += c.len_utf8(),
+                None => break,
+            }
+            before = c;
+        }
+        let slots = matched?;
+        Some(
+            slots
+                .chunks(2)
+                .map(|slot| Some(slot[0]?..slot[1]?))
+                .collect(),
+        )
+    }
+
+    /// the groups of the non overlapping, non empty matches in text
+    pub fn captures_all(&self, text: &str) -> Vec<Groups> {
+        let mut matches = vec![];
+        let mut pos = 0;
+        while pos <= text.len() {
+            let groups = match self.captures_from(text, pos) {
+                Some(groups) => groups,
+                None => break,
+            };
+            let m = groups[0].clone().unwrap_or_default();
+            if m.is_empty() {
+                pos = m.end + text[m.end..].chars().next().map_or(1, char::len_utf8);
+            } else {
+                pos = m.end;
+                matches.push(groups);
+            }
+        }
+        matches
+    }
+
+    /// byte ranges of the non overlapping, non empty matches in text
+    pub fn find_all(&self, text: &str) -> Vec<Range<usize>> {
+        self.captures_all(text)
+            .into_iter()
+            .filter_map(|groups| groups[0].clone())
+            .collect()
+    }
+}
+
+/// the char ranges of byte ranges of text, sorted and not overlapping
+pub fn char_ranges(
+    text: &str,
+    ranges: impl IntoIterator<Item = Range<usize>>,
+) -> Vec<Range<usize>> {
+    let (mut byte, mut char) = (0, 0);
+    ranges
+        .into_iter()
+        .map(|range| {
+            char += text[byte..range.start].chars().count();
+            let start = char;
+            char += text[range.clone()].chars().count();
+            byte = range.end;
+            start..char
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pattern: &str, text: &str) -> Vec<Range<usize>> {
+        Regex::new(pattern, false).unwrap().find_all(text)
+    }
+
+    #[test]
+    fn matches() {
+        assert_eq!(find("a.c", "abc a\nc"), vec![0..3]);
+        assert_eq!(find("[0-9]+", "a12 b3"), vec![1..3, 5..6]);
+        assert_eq!(find("\\d+px", "12px 3em"), vec![0..4]);
+        assert_eq!(find("[^ ]+", "ab cd"), vec![0..2, 3..5]);
+        assert_eq!(find("^fn|;$", "fn a();\n fn b()"), vec![0..2, 6..7]);
+        assert_eq!(find("colou?r", "color colour"), vec![0..5, 6..12]);
+        assert_eq!(find("(ab)+c", "ababc abc ac"), vec![0..5, 6..9]);
+        assert_eq!(find("a*", "baab"), vec![1..3]);
+        assert_eq!(find("\\.\\[", "a.[b"), vec![1..3]);
+        assert_eq!(find("é+", "aéé"), vec![1..5]);
+        assert_eq!(char_ranges("aéé", find("é+", "aéé")), vec![1..3]);
+        let regex = Regex::new("fo[a-o]", true).unwrap();
+        assert_eq!(regex.find_all("Foo FOO"), vec![0..3, 4..7]);
+        assert_eq!(
+            Regex::literal("a.", true).find_all("A.a.ab"),
+            vec![0..2, 2..4]
+        );
+        for invalid in &["(a", "a)", "[a", "*a", "a\\", "^*"] {
+            assert!(Regex::new(invalid, false).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn linear_time() {
+        // a backtracking engine overflows its stack on the first, takes ages on the second
+        let text = format!("\"{}", "a".repeat(100_000));
+        assert!(find("\"[^\"]*\"", &text).is_empty());
+        assert!(find("(a*)*b", &"a".repeat(10_000)).is_empty());
+        assert_eq!(
+            find("(a|aa)*c", &format!("{}c", "a".repeat(50))),
+            vec![0..51]
+        );
+    }
+
+    #[test]
+    fn groups() {
+        let text = "key = value; k2 = v";
+        let regex = Regex::new("(\\w+) = (\\w+)(;)?", false).unwrap();
+        let all = regex.captures_all(text);
+        assert_eq!(
+            all[0],
+            vec![Some(0..12), Some(0..3), Some(6..11), Some(11..12)]
+        );
+        assert_eq!(all[1], vec![Some(13..19), Some(13..15), Some(18..19), None]);
+        let regex = Regex::new("((a)|b)+ (ab|x)", false).unwrap();
+        assert_eq!(
+            regex.captures_all("abab ab")[0],
+            vec![Some(0..7), Some(3..4), Some(2..3), Some(5..7)]
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Saves that never leave a file half written
+//!
+//! The content goes to a temporary file next to the target, synced to disk with the target's
+//! permissions and owner, then renamed over it. Symbolic links are followed, and files with
+//! several hard links, owned by someone else or whose directory doesn't allow the rename are
+//! written in place.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// writes content to path atomically when possible
+pub fn write(path: &Path, content: &[u8]) -> io::Result<()> {
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let metadata = fs::metadata(&target).ok();
+    if metadata.as_ref().map(links).unwrap_or(1) > 1 {
+        return write_in_place(&target, content);
+    }
+    let temporary = match write_temporary(&target, content, metadata.as_ref()) {
+        Ok(temporary) => temporary,
+        Err(err) => {
+            log::warn!("writing {} in place: {}", target.display(), err);
+            return write_in_place(&target, content);
+        }
+    };
+    if fs::rename(&temporary, &target).is_err() {
+        let _ = fs::remove_file(&temporary);
+        return write_in_place(&target, content);
+    }
+    sync_dir(&target);
+    Ok(())
+}
+
+/// adds content at the end of path, creating it if needed
+pub fn append(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+/// pipes content to an elevation command line given path, like `sudo tee`, which may ask for
+/// a password on the terminal
+pub fn write_elevated(command: &str, path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut words = command.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No elevation command"))?;
+    let mut child = Command::new(program)
+        .args(words)
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(content)?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} failed: {}", command, status),
+        ));
+    }
+    Ok(())
+}
+
+/// a synced copy of content next to target, with its permissions
+fn write_temporary(
+    target: &Path,
+    content: &[u8],
+    metadata: Option<&fs::Metadata>,
+) -> io::Result<PathBuf> {
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    let temporary = target.with_file_name(format!(".{}.ted-{}~", name, std::process::id()));
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temporary)?;
+        file.write_all(content)?;
+        if let Some(metadata) = metadata {
+            file.set_permissions(metadata.permissions())?;
+            set_owner(&file, metadata)?;
+        }
+        file.sync_all()
+    })();
+    match result {
+        Ok(()) => Ok(temporary),
+        Err(err) => {
+            let _ = fs::remove_file(&temporary);
+            Err(err)
+        }
+    }
+}
+
+fn write_in_place(target: &Path, content: &[u8]) -> io::Result<()> {
+    let mut file = File::create(target)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+#[cfg(unix)]
+fn links(metadata: &fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::nlink(metadata)
+}
+
+#[cfg(not(unix))]
+fn links(_: &fs::Metadata) -> u64 {
+    1
+}
+
+/// keeps the owner of the replaced file, failing when not allowed to so the file is written in
+/// place instead
+#[cfg(unix)]
+fn set_owner(file: &File, metadata: &fs::Metadata) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    std::os::unix::fs::fchown(file, Some(metadata.uid()), Some(metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn set_owner(_: &File, _: &fs::Metadata) -> io::Result<()> {
+    Ok(())
+}
+
+/// makes the rename itself durable
+fn sync_dir(target: &Path) {
+    if let Some(dir) = target.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_atomically() {
+        let dir = std::env::temp_dir().join(format!("ted-save-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("main.rs");
+        write(&file, b"new file\n").unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new file\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&file, fs::Permissions::from_mode(0o750)).unwrap();
+            let link = dir.join("link.rs");
+            std::os::unix::fs::symlink(&file, &link).unwrap();
+            write(&link, b"through the link\n").unwrap();
+            assert!(fs::symlink_metadata(&link)
+                .unwrap()
+                .file_type()
+                .is_symlink());
+            let mode = fs::metadata(&file).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o750);
+            let hard = dir.join("hard.rs");
+            fs::hard_link(&file, &hard).unwrap();
+            write(&hard, b"in place\n").unwrap();
+            assert_eq!(fs::read_to_string(&file).unwrap(), "in place\n");
+        }
+        assert_eq!(
+            fs::read_dir(&dir).unwrap().count(),
+            if cfg!(unix) { 3 } else { 1 }
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn append_at_end() {
+        let file = std::env::temp_dir().join(format!("ted-append-{}", std::process::id()));
+        append(&file, b"one\n").unwrap();
+        append(&file, b"two\n").unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "one\ntwo\n");
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn pipe_to_command() {
+        let file = std::env::temp_dir().join(format!("ted-elevated-{}", std::process::id()));
+        write_elevated("tee -a", &file, b"through tee\n").unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "through tee\n");
+        fs::remove_file(&file).unwrap();
+        assert!(write_elevated("false", &file, b"").is_err());
+        assert!(write_elevated(" ", &file, b"").is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! Plugin scripts: lines of commands, optionally grouped into new commands
+//!
+//! ```text
+//! # run at startup
+//! set_theme ted
+//!
+//! def insert_date SPC i d: Inserts a date header
+//!     goto_line 1
+//!     insert # Notes\n\n
+//!     message Header inserted
+//! end
+//! ```
+
+use crate::command::parse_space_chain;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// a command defined by a script, running the lines of its body
+#[derive(Debug, Eq, PartialEq)]
+pub struct Definition {
+    pub name: String,
+    pub desc: String,
+    pub chain: Option<String>,
+    pub body: Vec<String>,
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Script {
+    pub definitions: Vec<Definition>,
+    /// lines outside of definitions, run when the script is loaded
+    pub lines: Vec<String>,
+}
+
+fn invalid(line_number: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line_number + 1, message),
+    )
+}
+
+/// reads `def name [chain]: description` header
+fn parse_header(header: &str, line_number: usize) -> io::Result<Definition> {
+    let (signature, desc) = header.split_once(':').unwrap_or((header, ""));
+    let (name, chain) = signature
+        .trim()
+        .split_once(char::is_whitespace)
+        .unwrap_or((signature.trim(), ""));
+    if name.is_empty() {
+        return Err(invalid(line_number, "def without a name"));
+    }
+    let chain = match chain.trim() {
+        "" => None,
+        chain => Some(
+            parse_space_chain(chain)
+                .ok_or_else(|| invalid(line_number, &format!("invalid chain {}", chain)))?,
+        ),
+    };
+    Ok(Definition {
+        name: name.to_string(),
+        desc: desc.trim().to_string(),
+        chain,
+        body: vec![],
+    })
+}
+
+pub fn parse(text: &str) -> io::Result<Script> {
+    let mut script = Script::default();
+    let mut definition: Option<Definition> = None;
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match (definition.as_mut(), line.strip_prefix("def ")) {
+            (Some(_), Some(_)) => return Err(invalid(line_number, "nested def")),
+            (None, Some(header)) => definition = Some(parse_header(header, line_number)?),
+            (Some(_), None) if line == "end" => {
+                script.definitions.extend(definition.take());
+            }
+            (Some(definition), None) => definition.body.push(line.to_string()),
+            (None, None) if line == "end" => return Err(invalid(line_number, "end without def")),
+            (None, None) => script.lines.push(line.to_string()),
+        }
+    }
+    match definition {
+        Some(definition) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("def {} is missing its end", definition.name),
+        )),
+        None => Ok(script),
+    }
+}
+
+/// replaces `\n`, `\t` and `\\` escapes
+pub fn unescape(text: &str) -> String {
+    let mut unescaped = String::default();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => unescaped.push('\n'),
+            ('\\', Some('t')) => unescaped.push('\t'),
+            ('\\', Some('\\')) => unescaped.push('\\'),
+            _ => {
+                unescaped.push(c);
+                continue;
+            }
+        }
+        chars.next();
+    }
+    unescaped
+}
+
+/// the files of a directory with the given extension, sorted by name
+pub fn files(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some(extension))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// parses the .ted files of a directory, sorted by name
+pub fn load_dir(dir: &Path) -> Vec<(PathBuf, io::Result<Script>)> {
+    files(dir, "ted")
+        .into_iter()
+        .map(|path| {
+            let script = std::fs::read_to_string(&path).and_then(|text| parse(&text));
+            (path, script)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_script() {
+        let script =
+            parse("# hi\nset_theme ted\ndef header SPC i h: Adds a header\n  insert # \\n\nend\n")
+                .unwrap();
+        assert_eq!(script.lines, vec!["set_theme ted"]);
+        assert_eq!(
+            script.definitions,
+            vec![Definition {
+                name: String::from("header"),
+                desc: String::from("Adds a header"),
+                chain: Some(String::from(" ih")),
+                body: vec![String::from("insert # \\n")],
+            }]
+        );
+        assert!(parse("def a\nx\n").is_err());
+        assert!(parse("def a SPC xy\nend\n").is_err());
+        assert!(parse("end\n").is_err());
+        assert_eq!(unescape("a\\nb\\\\n\\x"), "a\nb\\n\\x");
+    }
+}
@@ -0,0 +1,235 @@
+use crate::regex::{char_ranges, Groups, Regex};
+use std::io;
+use std::ops::Range;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Case {
+    Insensitive,
+    /// sensitive only when the pattern has uppercase letters
+    Smart,
+    Sensitive,
+}
+
+impl Case {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Case::Insensitive => "insensitive",
+            Case::Smart => "smart",
+            Case::Sensitive => "sensitive",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Case::Insensitive, Case::Smart, Case::Sensitive]
+            .iter()
+            .copied()
+            .find(|case| case.name() == name)
+    }
+
+    /// the following mode, cycling
+    pub fn next(&self) -> Self {
+        match self {
+            Case::Insensitive => Case::Smart,
+            Case::Smart => Case::Sensitive,
+            Case::Sensitive => Case::Insensitive,
+        }
+    }
+}
+
+/// how patterns are matched
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Options {
+    pub case: Case,
+    /// patterns are regular expressions rather than literal text
+    pub regex: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            case: Case::Insensitive,
+            regex: false,
+        }
+    }
+}
+
+impl Options {
+    pub fn ignores_case(&self, pattern: &str) -> bool {
+        match self.case {
+            Case::Insensitive => true,
+            Case::Smart => !pattern.chars().any(char::is_uppercase),
+            Case::Sensitive => false,
+        }
+    }
+
+    /// e.g. "smart case, regex"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} case, {}",
+            self.case.name(),
+            if self.regex { "regex" } else { "literal" }
+        )
+    }
+}
+
+/// the regular expression matching pattern with the options, fails on invalid ones
+fn compile(pattern: &str, options: Options) -> io::Result<Regex> {
+    let ignore_case = options.ignores_case(pattern);
+    match options.regex {
+        true => Regex::new(pattern, ignore_case),
+        false => Ok(Regex::literal(pattern, ignore_case)),
+    }
+}
+
+/// char ranges of the non overlapping occurrences of pattern in text
+pub fn find_all(text: &str, pattern: &str, options: Options) -> io::Result<Vec<Range<usize>>> {
+    if pattern.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(char_ranges(text, compile(pattern, options)?.find_all(text)))
+}
+
+/// the matches of pattern in text with what they are replaced by: the template as is for
+/// literal patterns, for regular expressions with `$1` to `$9` standing for what the groups
+/// matched, `$0` for the whole match and `$$` for `$`
+pub fn replacements(
+    text: &str,
+    pattern: &str,
+    options: Options,
+    template: &str,
+) -> io::Result<Vec<(Range<usize>, String)>> {
+    if !options.regex {
+        let matches = find_all(text, pattern, options)?;
+        return Ok(matches
+            .into_iter()
+            .map(|m| (m, template.to_string()))
+            .collect());
+    }
+    let all = compile(pattern, options)?.captures_all(text);
+    let matches = char_ranges(text, all.iter().filter_map(|groups| groups[0].clone()));
+    Ok(matches
+        .into_iter()
+        .zip(all.iter().map(|groups| expand(template, text, groups)))
+        .collect())
+}
+
+/// the template with the groups' text in place of `$0` to `$9`
+fn expand(template: &str, text: &str, groups: &Groups) -> String {
+    let mut expanded = String::new();
+    let mut template = template.chars().peekable();
+    while let Some(c) = template.next() {
+        let group = template.peek().and_then(|next| next.to_digit(10));
+        match (c, group) {
+            ('$', Some(i)) => {
+                template.next();
+                if let Some(Some(range)) = groups.get(i as usize) {
+                    expanded.push_str(&text[range.clone()]);
+                }
+            }
+            ('$', None) if template.peek() == Some(&'$') => {
+                template.next();
+                expanded.push('$');
+            }
+            (c, _) => expanded.push(c),
+        }
+    }
+    expanded
+}
+
+/// the last pattern searched in a buffer, with its matches as char ranges
+pub struct Search {
+    pub pattern: String,
+    pub options: Options,
+    pub matches: Vec<Range<usize>>,
+}
+
+impl Search {
+    /// fails on invalid regular expressions
+    pub fn new(pattern: &str, options: Options, text: &str) -> io::Result<Self> {
+        Ok(Self {
+            pattern: pattern.to_string(),
+            options,
+            matches: find_all(text, pattern, options)?,
+        })
+    }
+
+    /// finds the matches again after the text changed
+    pub fn update(&mut self, text: &str) {
+        self.matches = find_all(text, &self.pattern, self.options).unwrap_or_default();
+    }
+
+    /// index of the closest match after pos (or at it when inclusive) in the given
+    /// direction, wrapping around the text
+    pub fn next_from(&self, pos: usize, forward: bool, inclusive: bool) -> Option<usize> {
+        if self.matches.is_empty() {
+            return None;
+        }
+        let found = if forward {
+            self.matches
+                .iter()
+                .position(|m| m.start > pos || (inclusive && m.start == pos))
+                .unwrap_or(0)
+        } else {
+            self.matches
+                .iter()
+                .rposition(|m| m.start < pos || (inclusive && m.start == pos))
+                .unwrap_or(self.matches.len() - 1)
+        };
+        Some(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_matches() {
+        let options = Options::default();
+        let find = |text, pattern| find_all(text, pattern, options).unwrap();
+        assert_eq!(find("é a é aa", "a"), vec![2..3, 6..7, 7..8]);
+        assert_eq!(find("aaaa", "aa"), vec![0..2, 2..4]);
+        assert!(find("abc", "").is_empty());
+        assert_eq!(find("Ab aB", "ab"), vec![0..2, 3..5]);
+        let smart = Options {
+            case: Case::Smart,
+            regex: false,
+        };
+        assert_eq!(find_all("Ab ab", "ab", smart).unwrap(), vec![0..2, 3..5]);
+        assert_eq!(find_all("Ab ab", "Ab", smart).unwrap(), vec![0..2]);
+        let regex = Options {
+            case: Case::Sensitive,
+            regex: true,
+        };
+        assert_eq!(find_all("a1 A22", "a\\d+", regex).unwrap(), vec![0..2]);
+        assert!(find_all("a", "(", regex).is_err());
+        let search = Search::new("a", options, "a b a b a").unwrap();
+        assert_eq!(search.next_from(0, true, true), Some(0));
+        assert_eq!(search.next_from(0, true, false), Some(1));
+        assert_eq!(search.next_from(8, true, false), Some(0));
+        assert_eq!(search.next_from(0, false, false), Some(2));
+        assert_eq!(search.next_from(5, false, false), Some(1));
+    }
+
+    #[test]
+    fn replace_matches() {
+        let literal = Options::default();
+        assert_eq!(
+            replacements("a $1 A", "a", literal, "$1").unwrap(),
+            vec![(0..1, String::from("$1")), (5..6, String::from("$1"))]
+        );
+        let regex = Options {
+            case: Case::Sensitive,
+            regex: true,
+        };
+        let text = "f(a, b) f(c)";
+        assert_eq!(
+            replacements(text, "f\\((\\w+)(, (\\w+))?\\)", regex, "g($3 $$$1) [$0]").unwrap(),
+            vec![
+                (0..7, String::from("g(b $a) [f(a, b)]")),
+                (8..12, String::from("g( $c) [f(c)]"))
+            ]
+        );
+        assert!(replacements(text, "(", regex, "").is_err());
+    }
+}
@@ -0,0 +1,247 @@
+use std::ops::Range;
+
+/// a snippet body with its placeholders resolved
+#[derive(Debug, Eq, PartialEq)]
+pub struct Expansion {
+    pub text: String,
+    /// char ranges of the text for each tab stop, in visiting order ($0 last)
+    pub stops: Vec<Vec<Range<usize>>>,
+}
+
+enum Token {
+    Text(char),
+    /// stop number and default text
+    Stop(usize, String),
+}
+
+fn tokenize(body: &str) -> Vec<Token> {
+    let chars: Vec<char> = body.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if chars.get(i + 1) == Some(&'$') => {
+                tokens.push(Token::Text('$'));
+                i += 2;
+            }
+            '$' => {
+                let braced = chars.get(i + 1) == Some(&'{');
+                let digits_start = i + 1 + braced as usize;
+                let digits: String = chars[digits_start..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect();
+                let number = match digits.parse() {
+                    Ok(number) => number,
+                    Err(_) => {
+                        tokens.push(Token::Text('$'));
+                        i += 1;
+                        continue;
+                    }
+                };
+                i = digits_start + digits.len();
+                let mut default = String::default();
+                if braced {
+                    if chars.get(i) == Some(&':') {
+                        i += 1;
+                        while i < chars.len() && chars[i] != '}' {
+                            default.push(chars[i]);
+                            i += 1;
+                        }
+                    }
+                    i += 1;
+                }
+                tokens.push(Token::Stop(number, default));
+            }
+            c => {
+                tokens.push(Token::Text(c));
+                i += 1;
+            }
+        }
+    }
+    tokens
+}
+
+/// resolves `$1`, `${1:default}` and `$0` in body, indenting continuation lines with indent.
+/// a stop appearing several times is mirrored, `\$` is a literal dollar
+pub fn expand(body: &str, indent: &str) -> Expansion {
+    let tokens = tokenize(body);
+    // mirrors of a stop show its first default
+    let mut defaults: Vec<(usize, &str)> = vec![];
+    for token in &tokens {
+        if let Token::Stop(number, default) = token {
+            if !default.is_empty() && !defaults.iter().any(|(n, _)| n == number) {
+                defaults.push((*number, default));
+            }
+        }
+    }
+
+    let mut text = String::default();
+    let mut len = 0;
+    let mut numbered: Vec<(usize, Range<usize>)> = vec![];
+    for token in &tokens {
+        match token {
+            Token::Text('\n') => {
+                text.push('\n');
+                text.push_str(indent);
+                len += 1 + indent.chars().count();
+            }
+            Token::Text(c) => {
+                text.push(*c);
+                len += 1;
+            }
+            Token::Stop(number, _) => {
+                let default = defaults
+                    .iter()
+                    .find(|(n, _)| n == number)
+                    .map_or("", |(_, default)| default);
+                let n = default.chars().count();
+                numbered.push((*number, len..len + n));
+                text.push_str(default);
+                len += n;
+            }
+        }
+    }
+
+    let mut numbers: Vec<usize> = numbered.iter().map(|(n, _)| *n).collect();
+    numbers.sort_by_key(|n| (*n == 0, *n));
+    numbers.dedup();
+    let mut stops: Vec<Vec<Range<usize>>> = numbers
+        .iter()
+        .map(|number| {
+            numbered
+                .iter()
+                .filter(|(n, _)| n == number)
+                .map(|(_, range)| range.clone())
+                .collect()
+        })
+        .collect();
+    if !numbers.contains(&0) {
+        // the snippet ends after its text without an explicit $0
+        let end = len..len;
+        stops.push(vec![end]);
+    }
+    Expansion { text, stops }
+}
+
+/// tab stops of an expanded snippet, as char ranges of the buffer
+pub struct Placeholders {
+    stops: Vec<Vec<Range<usize>>>,
+    current: usize,
+    /// whether the text was edited since the current stop was reached
+    pub typed: bool,
+}
+
+impl Placeholders {
+    /// stops of an expansion inserted at the given char position
+    pub fn new(stops: Vec<Vec<Range<usize>>>, offset: usize) -> Self {
+        let stops = stops
+            .into_iter()
+            .map(|ranges| {
+                ranges
+                    .into_iter()
+                    .map(|r| r.start + offset..r.end + offset)
+                    .collect()
+            })
+            .collect();
+        Self {
+            stops,
+            current: 0,
+            typed: false,
+        }
+    }
+
+    /// range of the current stop that is edited, the others mirror it
+    pub fn current(&self) -> Option<&Range<usize>> {
+        self.stops
+            .get(self.current)
+            .and_then(|ranges| ranges.first())
+    }
+
+    /// the other ranges of the current stop
+    pub fn mirrors(&self) -> Vec<Range<usize>> {
+        self.stops
+            .get(self.current)
+            .map(|ranges| ranges.iter().skip(1).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// whether the current stop is the last one
+    pub fn is_last(&self) -> bool {
+        self.current + 1 >= self.stops.len()
+    }
+
+    pub fn next(&mut self) {
+        self.current = (self.current + 1).min(self.stops.len());
+        self.typed = false;
+    }
+
+    pub fn prev(&mut self) {
+        self.current = self.current.saturating_sub(1);
+        self.typed = false;
+    }
+
+    /// shifts the stops after n chars were inserted at pos, the current stop grows
+    pub fn inserted(&mut self, pos: usize, n: usize) {
+        self.typed = true;
+        for (stop, ranges) in self.stops.iter_mut().enumerate() {
+            for range in ranges.iter_mut() {
+                if stop == self.current && range.start <= pos && pos <= range.end {
+                    range.end += n;
+                } else if range.start >= pos {
+                    *range = range.start + n..range.end + n;
+                } else if range.end > pos {
+                    range.end += n;
+                }
+            }
+        }
+    }
+
+    /// shifts the stops after the chars of removed were deleted
+    pub fn removed(&mut self, removed: Range<usize>) {
+        self.typed = true;
+        let shrink = |x: usize| {
+            if x <= removed.start {
+                x
+            } else if x >= removed.end {
+                x - removed.len()
+            } else {
+                removed.start
+            }
+        };
+        for range in self.stops.iter_mut().flatten() {
+            *range = shrink(range.start)..shrink(range.end);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_placeholders() {
+        let expansion = expand("fn ${1:name}() {\n\t$0\n}", "  ");
+        assert_eq!(expansion.text, "fn name() {\n  \t\n  }");
+        assert_eq!(expansion.stops, vec![vec![3..7], vec![15..15]]);
+        let expansion = expand("${1:a} = $1; \\$2 $2", "");
+        assert_eq!(expansion.text, "a = a; $2 ");
+        assert_eq!(
+            expansion.stops,
+            vec![vec![0..1, 4..5], vec![10..10], vec![10..10]]
+        );
+    }
+
+    #[test]
+    fn shift_placeholders() {
+        let mut placeholders = Placeholders::new(vec![vec![0..1, 4..5], vec![8..8]], 10);
+        placeholders.inserted(11, 2);
+        assert_eq!(placeholders.current(), Some(&(10..13)));
+        assert_eq!(placeholders.mirrors(), vec![16..17]);
+        placeholders.removed(10..13);
+        assert_eq!(placeholders.current(), Some(&(10..10)));
+        placeholders.next();
+        assert!(placeholders.is_last());
+        assert_eq!(placeholders.current(), Some(&(17..17)));
+    }
+}
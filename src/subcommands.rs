@@ -0,0 +1,77 @@
+use crate::cli::USAGE;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use ted::export::{to_ansi, to_html};
+use ted::{Buffer, Config};
+
+fn highlight(args: &[String]) -> io::Result<()> {
+    let mut path = None;
+    let mut theme = None;
+    let mut html = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--html" => html = true,
+            "--theme" => theme = args.next(),
+            _ => path = Some(arg),
+        }
+    }
+    let path = path.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, USAGE))?;
+    let config = Rc::new(Config::default());
+    if !Path::new(path).is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{}: no such file", path),
+        ));
+    }
+    let mut buffer = Buffer::from_file(path, config.clone())?;
+    if let Some(theme) = theme {
+        if !buffer.set_theme(theme) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Could not load theme {}", theme),
+            ));
+        }
+    }
+    let lines = buffer.get_highlighted_lines(0..buffer.len_lines());
+    if html {
+        let theme = buffer
+            .get_highlighter()
+            .as_ref()
+            .map(|cached| &cached.theme);
+        print!("{}", to_html(lines, theme));
+    } else {
+        print!("{}", to_ansi(lines, config.color_depth.get()));
+    }
+    Ok(())
+}
+
+fn replace(args: &[String]) -> io::Result<()> {
+    if args.len() < 3 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, USAGE));
+    }
+    let config = Rc::new(Config::default());
+    for path in &args[2..] {
+        let mut buffer = Buffer::from_file(path, config.clone())?;
+        let n = buffer.replace_all(&args[0], &args[1]);
+        if n > 0 {
+            buffer.overwrite_backend_file()?;
+        }
+        eprintln!("{}: {} replacement(s)", path, n);
+    }
+    Ok(())
+}
+
+/// runs a non-interactive subcommand, None if args don't start with one
+pub fn run(args: &[String]) -> Option<io::Result<()>> {
+    match args.first().map(String::as_str) {
+        Some("highlight") => Some(highlight(&args[1..])),
+        Some("replace") => Some(replace(&args[1..])),
+        Some("--help") | Some("-h") => {
+            println!("{}", USAGE);
+            Some(Ok(()))
+        }
+        _ => None,
+    }
+}
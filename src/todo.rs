@@ -0,0 +1,151 @@
+//! TODO, FIXME and HACK markers left in the code, emphasized and listed as `name:line:column:`
+//! locations to jump to
+
+use crate::completion::is_word_char;
+use crate::path;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+pub const MARKERS: [&str; 3] = ["TODO", "FIXME", "HACK"];
+
+pub struct Todo {
+    pub line_number: usize,
+    /// char index of the marker in its line
+    pub column: usize,
+    /// the marker and the rest of its line
+    pub text: String,
+}
+
+/// char ranges of the markers of a line, as whole words
+pub fn markers(line: &str) -> Vec<Range<usize>> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut ranges = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let starts_word = i == 0 || !is_word_char(chars[i - 1]);
+        let marker = MARKERS.iter().find(|marker| {
+            let end = i + marker.len();
+            starts_word
+                && matches!(chars.get(i..end), Some(word) if word.iter().copied().eq(marker.chars()))
+                && !matches!(chars.get(end), Some(c) if is_word_char(*c))
+        });
+        match marker {
+            Some(marker) => {
+                ranges.push(i..i + marker.len());
+                i += marker.len();
+            }
+            None => i += 1,
+        }
+    }
+    ranges
+}
+
+/// the markers of a text, in order
+pub fn find(text: &str) -> Vec<Todo> {
+    let mut todos = vec![];
+    for (line_number, line) in text.lines().enumerate() {
+        for marker in markers(line) {
+            todos.push(Todo {
+                line_number,
+                column: marker.start,
+                text: line
+                    .chars()
+                    .skip(marker.start)
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string(),
+            });
+        }
+    }
+    todos
+}
+
+/// one `name:line:column: text` line per marker
+pub fn format(name: &str, todos: &[Todo]) -> String {
+    todos
+        .iter()
+        .map(|todo| {
+            format!(
+                "{}:{}:{}: {}\n",
+                name,
+                todo.line_number + 1,
+                todo.column + 1,
+                todo.text
+            )
+        })
+        .collect()
+}
+
+/// markers of the text files under dir, skipping hidden directories, `target` and links to
+/// directories
+pub fn scan_dir(dir: &Path) -> String {
+    let mut report = String::default();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries: Vec<_> = fs::read_dir(dir).into_iter().flatten().flatten().collect();
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(_) => continue,
+            };
+            if file_type.is_symlink() && path.is_dir() {
+                continue;
+            }
+            if file_type.is_dir() {
+                if !hidden && entry.file_name() != "target" {
+                    dirs.push(path);
+                }
+                continue;
+            }
+            let name = path
+                .strip_prefix(".")
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            if path::is_binary(&name) {
+                continue;
+            }
+            if let Ok(text) = fs::read_to_string(&path) {
+                report.push_str(&format(&name, &find(&text)));
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_markers() {
+        assert_eq!(
+            markers("// TODO: a FIXME(me) HACK"),
+            vec![3..7, 11..16, 21..25]
+        );
+        assert!(markers("TODOS MY_TODO todo").is_empty());
+        let text = "fn main() {\n    // TODO: héllo  \n}\n# FIXME\n";
+        let todos = find(text);
+        assert_eq!(
+            format("main.rs", &todos),
+            "main.rs:2:8: TODO: héllo\nmain.rs:4:3: FIXME\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scan_skips_linked_dirs() {
+        let dir = std::env::temp_dir().join(format!("ted-todo-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "// TODO: loop\n").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("src/up")).unwrap();
+        let report = scan_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(report.lines().count(), 1);
+        assert!(report.ends_with("main.rs:1:4: TODO: loop\n"));
+    }
+}
@@ -0,0 +1,126 @@
+//! `tutor`: a copy of `assets/TUTOR.md` to practice on, whose lessons are checked in order as
+//! the buffer changes
+
+use crate::Buffer;
+
+pub const TEXT: &str = include_str!("../assets/TUTOR.md");
+
+pub struct Lesson {
+    pub title: &'static str,
+    /// whether the exercise was done
+    pub check: fn(&Buffer) -> bool,
+}
+
+/// whether the text has the lines one after the other, ignoring their indentation
+fn has_lines(buffer: &Buffer, expected: &[&str]) -> bool {
+    let text = buffer.get_text();
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+    lines
+        .windows(expected.len())
+        .any(|window| window == expected)
+}
+
+pub const LESSONS: [Lesson; 7] = [
+    Lesson {
+        title: "moving the cursor",
+        check: |buffer| {
+            let (_, _, column_number) = buffer.get_cursor();
+            let line = buffer.get_current_line().unwrap_or_default();
+            line.chars().nth(column_number) == Some('@')
+        },
+    },
+    Lesson {
+        title: "deleting characters",
+        check: |buffer| has_lines(buffer, &["The cow jumped over the moon."]),
+    },
+    Lesson {
+        title: "deleting lines",
+        check: |buffer| has_lines(buffer, &["Roses are red,", "Violets are blue,"]),
+    },
+    Lesson {
+        title: "inserting text",
+        check: |buffer| has_lines(buffer, &["There is some text missing from this line."]),
+    },
+    Lesson {
+        title: "selecting text",
+        check: |buffer| has_lines(buffer, &["The quick brown fox."]),
+    },
+    Lesson {
+        title: "copying and pasting",
+        check: |buffer| has_lines(buffer, &["Copy me once.", "Copy me once."]),
+    },
+    Lesson {
+        title: "saving",
+        check: |buffer| !buffer.is_dirty(),
+    },
+];
+
+/// progress through the lessons of a tutor buffer
+pub struct Tutor {
+    /// the copy being edited
+    pub path: String,
+    /// index of the lesson to do
+    pub lesson: usize,
+}
+
+impl Tutor {
+    pub fn new(path: String) -> Self {
+        Self { path, lesson: 0 }
+    }
+
+    /// checks the current lesson against the buffer, returns the lessons just done
+    pub fn check(&mut self, buffer: &Buffer) -> Vec<&'static Lesson> {
+        let mut done = vec![];
+        while let Some(lesson) = LESSONS
+            .get(self.lesson)
+            .filter(|lesson| (lesson.check)(buffer))
+        {
+            done.push(lesson);
+            self.lesson += 1;
+        }
+        done
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.lesson >= LESSONS.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+    use std::rc::Rc;
+
+    #[test]
+    fn lessons_in_order() {
+        let config = Rc::new(Config::builtin());
+        let mut buffer = Buffer::new(TEXT.to_string(), String::from("tutor"), config);
+        let mut tutor = Tutor::new(String::default());
+        assert!(tutor.check(&buffer).is_empty());
+        let line_of = |buffer: &Buffer, needle: &str| {
+            buffer
+                .get_text()
+                .lines()
+                .position(|line| line.contains(needle))
+                .unwrap()
+        };
+        // the second lesson done first waits for the first one
+        let line_number = line_of(&buffer, "Thhe ccow");
+        buffer.goto(line_number, 9);
+        buffer.delete_chars(1);
+        buffer.goto(line_number, 13);
+        buffer.delete_chars(1);
+        buffer.goto(line_number, 22);
+        buffer.delete_chars(1);
+        assert!(tutor.check(&buffer).is_empty());
+        buffer.goto(line_of(&buffer, "----@"), 12);
+        let done: Vec<&str> = tutor.check(&buffer).iter().map(|l| l.title).collect();
+        assert_eq!(done, vec!["moving the cursor", "deleting characters"]);
+        buffer.goto(line_of(&buffer, "DOES NOT BELONG"), 0);
+        buffer.delete_lines(1);
+        assert_eq!(tutor.check(&buffer).len(), 1);
+        assert_eq!(tutor.lesson, 3);
+        assert!(!tutor.is_done());
+    }
+}
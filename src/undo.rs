@@ -0,0 +1,482 @@
+//! Every state a buffer went through, as a tree: editing after an undo starts a new branch
+//! instead of dropping the undone states, which `undo_tree` can still jump back to.
+//!
+//! The tree is saved as JSON in a file named after a hash of the buffer's path, each state
+//! stored as the edit from its parent. It is rebuilt from the file's content, which must be the
+//! one of the state current when saved, walking edits back to the root then forward.
+
+use ropey::Rope;
+use serde_json::{json, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// how far `earlier` and `later` go
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Step {
+    Changes(usize),
+    Time(Duration),
+}
+
+impl Step {
+    /// `3` changes, or a time like `30s`, `2m`, `1h` or `1d`
+    pub fn parse(text: &str) -> Option<Step> {
+        let text = text.trim();
+        let unit = match text.chars().last()? {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return text.parse().ok().map(Step::Changes),
+        };
+        let n: u64 = text[..text.len() - 1].parse().ok()?;
+        Some(Step::Time(Duration::from_secs(n.checked_mul(unit)?)))
+    }
+}
+
+pub struct State {
+    pub content: Rope,
+    /// where the cursor was when the state was recorded
+    pub cursor: usize,
+    pub taken: SystemTime,
+    parent: Option<usize>,
+    /// (start, removed, inserted) taking the parent's content to this one
+    edit: Option<(usize, String, String)>,
+    children: Vec<usize>,
+    /// the child redo goes to, the last one visited
+    redo: Option<usize>,
+}
+
+pub struct UndoTree {
+    states: Vec<State>,
+    current: usize,
+}
+
+impl UndoTree {
+    /// a tree of the initial content only
+    pub fn new(content: Rope) -> Self {
+        Self {
+            states: vec![State {
+                content,
+                cursor: 0,
+                taken: SystemTime::now(),
+                parent: None,
+                edit: None,
+                children: vec![],
+                redo: None,
+            }],
+            current: 0,
+        }
+    }
+
+    pub fn current(&self) -> &State {
+        &self.states[self.current]
+    }
+
+    /// the index of the current state
+    pub fn index(&self) -> usize {
+        self.current
+    }
+
+    pub fn get(&self, index: usize) -> Option<&State> {
+        self.states.get(index)
+    }
+
+    /// the state before the current one in the order they were recorded, whatever the branch
+    pub fn earlier(&self, step: Step) -> usize {
+        match step {
+            Step::Changes(n) => self.current.saturating_sub(n),
+            Step::Time(duration) => {
+                let time = self.current().taken.checked_sub(duration);
+                time.map(|time| self.at(time)).unwrap_or(0)
+            }
+        }
+    }
+
+    /// the state after the current one in the order they were recorded, whatever the branch
+    pub fn later(&self, step: Step) -> usize {
+        match step {
+            Step::Changes(n) => self.current.saturating_add(n).min(self.states.len() - 1),
+            Step::Time(duration) => match self.current().taken.checked_add(duration) {
+                Some(time) => self.at(time),
+                None => self.states.len() - 1,
+            },
+        }
+    }
+
+    /// the last state recorded by time, states being recorded in order
+    fn at(&self, time: SystemTime) -> usize {
+        self.states
+            .partition_point(|state| state.taken <= time)
+            .saturating_sub(1)
+    }
+
+    /// adds a state after the current one, which it becomes
+    pub fn record(&mut self, content: Rope, cursor: usize) {
+        let index = self.states.len();
+        let edit = edit(&self.current().content, &content);
+        self.states.push(State {
+            content,
+            cursor,
+            taken: SystemTime::now(),
+            parent: Some(self.current),
+            edit: Some(edit),
+            children: vec![],
+            redo: None,
+        });
+        let parent = &mut self.states[self.current];
+        parent.children.push(index);
+        parent.redo = Some(index);
+        self.current = index;
+    }
+
+    /// goes back to the parent state, returns it along with where the undone change was
+    pub fn undo(&mut self) -> Option<(&State, usize)> {
+        let undone = self.current;
+        self.current = self.states[undone].parent?;
+        self.states[self.current].redo = Some(undone);
+        Some((&self.states[self.current], self.states[undone].cursor))
+    }
+
+    /// goes forward to the child state last visited
+    pub fn redo(&mut self) -> Option<&State> {
+        self.current = self.states[self.current].redo?;
+        Some(&self.states[self.current])
+    }
+
+    /// goes to any state, redo then following the branch leading to it
+    pub fn jump(&mut self, index: usize) -> Option<&State> {
+        self.states.get(index)?;
+        let mut child = index;
+        while let Some(parent) = self.states[child].parent {
+            self.states[parent].redo = Some(child);
+            child = parent;
+        }
+        self.current = index;
+        Some(&self.states[index])
+    }
+
+    /// the tree as JSON, at most `limit` states: past it only the current branch is kept, the
+    /// oldest of its states becoming the root
+    pub fn to_json(&self, limit: usize) -> Value {
+        let kept: Vec<usize> = if self.states.len() <= limit {
+            (0..self.states.len()).collect()
+        } else {
+            let mut branch = vec![self.current];
+            while let Some(parent) = self.states[branch[branch.len() - 1]].parent {
+                branch.push(parent);
+            }
+            branch.truncate(limit.max(1));
+            branch.reverse();
+            branch
+        };
+        let mut renumbered = vec![None; self.states.len()];
+        for (i, &index) in kept.iter().enumerate() {
+            renumbered[index] = Some(i);
+        }
+        let states: Vec<Value> = kept
+            .iter()
+            .map(|&index| {
+                let state = &self.states[index];
+                let time = state.taken.duration_since(UNIX_EPOCH).unwrap_or_default();
+                let mut value = json!({ "cursor": state.cursor, "time": time.as_secs() });
+                let parent = state.parent.filter(|&p| renumbered[p].is_some());
+                if let (Some(parent), Some((start, removed, inserted))) = (parent, &state.edit) {
+                    value["parent"] = json!(renumbered[parent]);
+                    value["edit"] = json!([start, removed, inserted]);
+                }
+                value
+            })
+            .collect();
+        json!({
+            "current": renumbered[self.current],
+            "checksum": checksum(&self.current().content),
+            "states": states,
+        })
+    }
+
+    /// the tree saved by `to_json`, None when malformed or saved for another content
+    pub fn from_json(value: &Value, content: Rope) -> Option<Self> {
+        if value["checksum"].as_str()? != checksum(&content) {
+            return None;
+        }
+        let current = value["current"].as_u64()? as usize;
+        let mut states = vec![];
+        for (i, state) in value["states"].as_array()?.iter().enumerate() {
+            let parent = match &state["parent"] {
+                Value::Null if i == 0 => None,
+                parent => Some(parent.as_u64().map(|p| p as usize).filter(|&p| p < i)?),
+            };
+            let edit = match &state["edit"] {
+                Value::Null if parent.is_none() => None,
+                edit => Some((
+                    edit[0].as_u64()? as usize,
+                    edit[1].as_str()?.to_string(),
+                    edit[2].as_str()?.to_string(),
+                )),
+            };
+            states.push(State {
+                content: Rope::new(),
+                cursor: state["cursor"].as_u64()? as usize,
+                taken: UNIX_EPOCH.checked_add(Duration::from_secs(state["time"].as_u64()?))?,
+                parent,
+                edit,
+                children: vec![],
+                redo: None,
+            });
+        }
+        states.get(current)?;
+        // back from the current content to the root, then forward to every other state
+        let mut contents: Vec<Option<Rope>> = vec![None; states.len()];
+        let mut index = current;
+        contents[index] = Some(content);
+        while let (Some(parent), Some((start, removed, inserted))) =
+            (states[index].parent, &states[index].edit)
+        {
+            let mut content = contents[index].clone()?;
+            apply(&mut content, *start, inserted, removed)?;
+            contents[parent] = Some(content);
+            index = parent;
+        }
+        for i in 0..states.len() {
+            if contents[i].is_none() {
+                let (start, removed, inserted) = states[i].edit.as_ref()?;
+                let mut content = contents[states[i].parent?].clone()?;
+                apply(&mut content, *start, removed, inserted)?;
+                contents[i] = Some(content);
+            }
+            if let Some(parent) = states[i].parent {
+                states[parent].children.push(i);
+                states[parent].redo = Some(i);
+            }
+        }
+        for (state, content) in states.iter_mut().zip(contents) {
+            state.content = content?;
+        }
+        let mut tree = Self { states, current };
+        tree.jump(current);
+        Some(tree)
+    }
+
+    /// a line per state like `@ #4 12:03:09 +5 chars`, `@` marking the current one,
+    /// each branch listed under the state it forked from and indented
+    pub fn render(&self) -> Vec<String> {
+        let mut lines = vec![];
+        let mut stack = vec![(0, 0)];
+        while let Some((index, depth)) = stack.pop() {
+            let state = &self.states[index];
+            let marker = if index == self.current { '@' } else { ' ' };
+            let indent = match depth {
+                0 => String::new(),
+                _ => format!("{}└ ", "  ".repeat(depth - 1)),
+            };
+            let change = match state.parent {
+                Some(parent) => {
+                    let before = self.states[parent].content.len_chars() as isize;
+                    match state.content.len_chars() as isize - before {
+                        n if n.abs() == 1 => format!("{:+} char", n),
+                        n => format!("{:+} chars", n),
+                    }
+                }
+                None => String::from("original"),
+            };
+            lines.push(format!(
+                "{} {}#{} {} {}",
+                marker,
+                indent,
+                index,
+                clock(state.taken),
+                change
+            ));
+            // the first child continues the branch, the others fork from it and come first
+            if let Some((first, forks)) = state.children.split_first() {
+                stack.push((*first, depth));
+                stack.extend(forks.iter().rev().map(|fork| (*fork, depth + 1)));
+            }
+        }
+        lines
+    }
+}
+
+/// (start, removed, inserted) taking old to new, around their common start and end
+fn edit(old: &Rope, new: &Rope) -> (usize, String, String) {
+    let start = old
+        .chars()
+        .zip(new.chars())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let end = old
+        .chars_at(old.len_chars())
+        .reversed()
+        .zip(new.chars_at(new.len_chars()).reversed())
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(old.len_chars() - start)
+        .min(new.len_chars() - start);
+    (
+        start,
+        old.slice(start..old.len_chars() - end).to_string(),
+        new.slice(start..new.len_chars() - end).to_string(),
+    )
+}
+
+/// replaces `removed` at start by `inserted`, None when it isn't there
+fn apply(content: &mut Rope, start: usize, removed: &str, inserted: &str) -> Option<()> {
+    let end = start + removed.chars().count();
+    if end > content.len_chars() || content.slice(start..end) != removed {
+        return None;
+    }
+    content.remove(start..end);
+    content.insert(start, inserted);
+    Some(())
+}
+
+/// FNV-1a hash of the text, in hexadecimal
+pub fn checksum(content: &Rope) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in content.bytes() {
+        hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// the name of the file the undo tree of a path is saved to
+pub fn file_name(path: &str) -> String {
+    checksum(&Rope::from(path))
+}
+
+/// the index of the state a line of `render` stands for
+pub fn parse_index(line: &str) -> Option<usize> {
+    let (_, rest) = line.split_once('#')?;
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+/// HH:MM:SS (UTC) of a time
+pub fn clock(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format!(
+        "{:02}:{:02}:{:02}",
+        (secs / 3600) % 24,
+        (secs / 60) % 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(state: &State) -> String {
+        state.content.to_string()
+    }
+
+    #[test]
+    fn branch_after_undo() {
+        let mut tree = UndoTree::new(Rope::from("a"));
+        tree.record(Rope::from("ab"), 1);
+        tree.record(Rope::from("abc"), 2);
+        let (state, cursor) = tree.undo().unwrap();
+        assert_eq!((text(state), cursor), ("ab".to_string(), 2));
+        tree.record(Rope::from("abd"), 2);
+        assert_eq!(text(tree.undo().unwrap().0), "ab");
+        assert_eq!(text(tree.redo().unwrap()), "abd");
+        assert!(tree.redo().is_none());
+        assert_eq!(text(tree.jump(2).unwrap()), "abc");
+        tree.undo();
+        assert_eq!(text(tree.redo().unwrap()), "abc");
+        tree.undo();
+        tree.undo();
+        assert!(tree.undo().is_none());
+        assert!(tree.jump(9).is_none());
+        let lines = tree.render();
+        let lines: Vec<String> = lines.iter().map(|line| clock_free(line)).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "@ #0 original",
+                "  #1 +1 char",
+                "  └ #3 +1 char",
+                "  #2 +1 char"
+            ]
+        );
+        assert_eq!(parse_index("  └ #3 12:00:00 +1 char"), Some(3));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut tree = UndoTree::new(Rope::from("one\n"));
+        tree.record(Rope::from("one\ntwo\n"), 4);
+        tree.record(Rope::from("one\ntwo\nthree\n"), 8);
+        tree.undo();
+        tree.record(Rope::from("zero\none\ntwo\n"), 0);
+        let json = tree.to_json(10);
+        let content = Rope::from("zero\none\ntwo\n");
+        let mut loaded = UndoTree::from_json(&json, content.clone()).unwrap();
+        // the edits recorded are saved again as loaded
+        assert_eq!(loaded.to_json(10), json);
+        assert_eq!(loaded.render().len(), 4);
+        assert_eq!(text(loaded.undo().unwrap().0), "one\ntwo\n");
+        assert_eq!(text(loaded.undo().unwrap().0), "one\n");
+        assert_eq!(text(loaded.jump(2).unwrap()), "one\ntwo\nthree\n");
+        assert!(UndoTree::from_json(&json, Rope::from("changed")).is_none());
+        let mut corrupt = json.clone();
+        corrupt["states"][3]["edit"][1] = json!("nothing like it");
+        assert!(UndoTree::from_json(&corrupt, content.clone()).is_none());
+        let mut corrupt = json.clone();
+        corrupt["states"][1]["time"] = json!(u64::MAX);
+        assert!(UndoTree::from_json(&corrupt, content.clone()).is_none());
+        assert!(UndoTree::from_json(&json!("garbage"), content.clone()).is_none());
+        // past the limit, only the last states of the current branch are kept
+        let mut trimmed = UndoTree::from_json(&tree.to_json(2), content).unwrap();
+        assert_eq!(trimmed.render().len(), 2);
+        assert_eq!(text(trimmed.undo().unwrap().0), "one\ntwo\n");
+        assert!(trimmed.undo().is_none());
+        assert_eq!(file_name("/a"), file_name("/a"));
+        assert_ne!(file_name("/a"), file_name("/b"));
+    }
+
+    #[test]
+    fn earlier_and_later() {
+        assert_eq!(Step::parse("3"), Some(Step::Changes(3)));
+        assert_eq!(
+            Step::parse("2m"),
+            Some(Step::Time(Duration::from_secs(120)))
+        );
+        assert_eq!(
+            Step::parse(" 1h "),
+            Some(Step::Time(Duration::from_secs(3600)))
+        );
+        assert_eq!(Step::parse("s"), None);
+        assert_eq!(Step::parse("2w"), None);
+        let mut tree = UndoTree::new(Rope::from("a"));
+        tree.record(Rope::from("ab"), 1);
+        tree.record(Rope::from("abc"), 2);
+        tree.undo();
+        tree.record(Rope::from("abd"), 2);
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        for (i, state) in tree.states.iter_mut().enumerate() {
+            state.taken = start + Duration::from_secs(10 * i as u64);
+        }
+        // recorded at 0s, 10s, 20s and 30s
+        assert_eq!(tree.earlier(Step::Changes(1)), 2);
+        assert_eq!(tree.earlier(Step::Time(Duration::from_secs(15))), 1);
+        assert_eq!(tree.earlier(Step::Time(Duration::from_secs(60))), 0);
+        tree.jump(0);
+        assert_eq!(tree.later(Step::Changes(2)), 2);
+        assert_eq!(tree.later(Step::Changes(9)), 3);
+        assert_eq!(tree.later(Step::Time(Duration::from_secs(25))), 2);
+        // too far to count goes to the newest state
+        assert_eq!(Step::parse("999999999999999999d"), None);
+        let far = Step::parse("106751991167300d").unwrap();
+        assert_eq!(tree.later(far), 3);
+        assert_eq!(tree.later(Step::Changes(usize::MAX)), 3);
+    }
+
+    /// the line without its time
+    fn clock_free(line: &str) -> String {
+        let mut words: Vec<&str> = line.split(' ').collect();
+        words.retain(|word| word.len() != 8 || !word.contains(':'));
+        words.join(" ")
+    }
+}
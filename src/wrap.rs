@@ -0,0 +1,91 @@
+//! Soft wrap: the screen rows a long line is drawn on, broken after the last whitespace that
+//! fits in the view's width, or within a word longer than a row
+
+use std::ops::Range;
+use unicode_width::UnicodeWidthChar;
+
+/// columns a char takes on screen, as tui draws it
+pub fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+/// char ranges of the rows a line without its line break is drawn on, at least one
+pub fn rows(line: &str, width: usize) -> Vec<Range<usize>> {
+    let width = width.max(1);
+    let mut rows = vec![];
+    let mut start = 0;
+    let mut used = 0;
+    // the char after the last whitespace of the row being laid out
+    let mut after_space = None;
+    for (i, c) in line.chars().enumerate() {
+        let w = char_width(c);
+        if used + w > width && i > start {
+            let end = after_space
+                .filter(|end| *end > start && *end < i)
+                .unwrap_or(i);
+            rows.push(start..end);
+            used = line.chars().take(i).skip(end).map(char_width).sum();
+            start = end;
+            after_space = None;
+        }
+        used += w;
+        if c.is_whitespace() {
+            after_space = Some(i + 1);
+        }
+    }
+    rows.push(start..line.chars().count());
+    rows
+}
+
+/// the row of rows holding column, the last one for a column past the end
+pub fn row_of(rows: &[Range<usize>], column: usize) -> usize {
+    rows.iter()
+        .position(|row| column < row.end)
+        .unwrap_or(rows.len() - 1)
+}
+
+/// columns taken on screen by the chars of line within range
+pub fn width_of(line: &str, range: Range<usize>) -> usize {
+    line.chars()
+        .take(range.end)
+        .skip(range.start)
+        .map(char_width)
+        .sum()
+}
+
+/// the char of line within row drawn at x columns from its start, its last one when shorter
+pub fn column_at(line: &str, row: &Range<usize>, x: usize) -> usize {
+    let mut used = 0;
+    for (i, c) in line.chars().enumerate().take(row.end).skip(row.start) {
+        used += char_width(c);
+        if used > x {
+            return i;
+        }
+    }
+    row.end.saturating_sub(1).max(row.start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn break_after_spaces() {
+        assert_eq!(rows("", 10), vec![0..0]);
+        assert_eq!(rows("one two three", 8), vec![0..8, 8..13]);
+        assert_eq!(rows("one two three", 7), vec![0..4, 4..8, 8..13]);
+        // a word longer than a row is cut
+        assert_eq!(rows("abcdefgh ij", 3), vec![0..3, 3..6, 6..9, 9..11]);
+        // wide chars take two columns
+        assert_eq!(rows("漢字漢字", 5), vec![0..2, 2..4]);
+        let line = "one two three";
+        let layout = rows(line, 8);
+        assert_eq!(row_of(&layout, 7), 0);
+        assert_eq!(row_of(&layout, 8), 1);
+        assert_eq!(row_of(&layout, 13), 1);
+        assert_eq!(width_of("漢字 a", 1..3), 3);
+        assert_eq!(column_at(line, &layout[1], 2), 10);
+        assert_eq!(column_at(line, &layout[1], 9), 12);
+        assert_eq!(column_at("漢字", &(0..2), 3), 1);
+    }
+}